@@ -0,0 +1,99 @@
+//! Evaluation of synthetic definitions with 100, 1,000 and 10,000 options
+//!
+//! Run with `cargo bench`, e.g. `cargo bench -- resolve/10000` for a single group and size.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rconfig::{
+    check_config_str, evaluate_config, fuse_config_str, generate_config, parse_definition_str,
+    resolve, EvalContext,
+};
+
+const CRATE_NAME: &str = "bench";
+const SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+/// Menus of ten options - a bool enabling the menu's other options, which are `u32` options
+/// with a validation and enums
+fn definition(options: usize) -> String {
+    let mut out = String::new();
+    for menu in 0..options.div_ceil(10) {
+        out.push_str(&format!(
+            "[menu{menu}]\ndescription = \"Menu {menu}\"\ndepends = 'feature(\"f{}\")'\n\n",
+            menu % 4
+        ));
+        out.push_str(&format!(
+            "[menu{menu}.options.enable]\ndescription = \"Enable\"\ntype = \"bool\"\ndefault = false\n\n"
+        ));
+        for option in 1..10.min(options - menu * 10) {
+            out.push_str(&format!(
+                "[menu{menu}.options.option{option}]\ndescription = \"Option {option}\"\ndepends = 'enabled(\"menu{menu}.enable\")'\n"
+            ));
+            if option % 2 == 0 {
+                out.push_str(
+                    "type = \"enum\"\nvalues = [\n    { description = \"A\", value = \"a\" },\n    { description = \"B\", value = \"b\" },\n]\ndefault = \"a\"\n\n",
+                );
+            } else {
+                out.push_str(&format!(
+                    "type = \"u32\"\nvalid = \"value >= 0 && value <= 100000\"\ndefault = {option}\n\n"
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Enable every other menu and set a value in each of them
+fn user_config(options: usize) -> String {
+    let mut out = format!("[{CRATE_NAME}]\n");
+    for menu in (0..options.div_ceil(10)).step_by(2) {
+        out.push_str(&format!(
+            "menu{menu}.enable = true\nmenu{menu}.option1 = 42\n"
+        ));
+    }
+    out
+}
+
+const FEATURES: [&str; 2] = ["f0", "f2"];
+
+fn bench_evaluation(c: &mut Criterion) {
+    for size in SIZES {
+        let definition = definition(size);
+        let user_config = user_config(size);
+        let parsed = parse_definition_str(&definition).unwrap();
+        let ctx = EvalContext::new(&FEATURES).with_crate_name(CRATE_NAME);
+
+        c.bench_with_input(
+            BenchmarkId::new("parse", size),
+            &definition,
+            |b, definition| b.iter(|| parse_definition_str(definition).unwrap()),
+        );
+
+        c.bench_with_input(BenchmarkId::new("fuse", size), &parsed, |b, parsed| {
+            b.iter(|| fuse_config_str(&user_config, CRATE_NAME, parsed.clone()).unwrap())
+        });
+
+        c.bench_with_input(BenchmarkId::new("validate", size), &parsed, |b, parsed| {
+            b.iter(|| {
+                check_config_str(&user_config, CRATE_NAME, parsed, FEATURES.to_vec()).unwrap()
+            })
+        });
+
+        c.bench_with_input(
+            BenchmarkId::new("resolve", size),
+            &definition,
+            |b, definition| b.iter(|| resolve(definition, &user_config, &ctx).unwrap()),
+        );
+
+        let effective_config =
+            evaluate_config(&user_config, CRATE_NAME, parsed.clone(), FEATURES.to_vec()).unwrap();
+        c.bench_with_input(BenchmarkId::new("codegen", size), &parsed, |b, parsed| {
+            b.iter(|| generate_config(parsed, &effective_config))
+        });
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_evaluation
+}
+criterion_main!(benches);
@@ -2,6 +2,10 @@ rconfig::include_config!();
 
 pub fn awesome(){
     println!("Heapsize={}", HEAP_SIZE);
+    println!(
+        "config fingerprint = {:#x}",
+        rconfig_build_info::CONFIG_FINGERPRINT
+    );
 
     #[cfg(psram_enable)]
     {
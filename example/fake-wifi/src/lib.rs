@@ -1,9 +1,9 @@
 rconfig::include_config!();
 
 pub fn awesome(){
-    #[cfg(options_ble)]
+    #[cfg(rcfg_fake_wifi_options_ble)]
     println!("BLE ENABLED");
 
-    #[cfg(has_options_buffer)]
+    #[cfg(rcfg_fake_wifi_has_options_buffer)]
     println!("BLE_BUFFER {:?}", OPTIONS_BUFFER);
 }
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|definition: &str| {
+    let ctx = rconfig::EvalContext::new(&[]).with_crate_name("fuzz");
+    let _ = rconfig::resolve(definition, "", &ctx);
+
+    if let Ok(parsed) = rconfig::parse_definition_str(definition) {
+        let _ = rconfig::definition_to_toml_string(&parsed);
+        let _ = rconfig::kconfig::definition_to_kconfig(&parsed);
+    }
+});
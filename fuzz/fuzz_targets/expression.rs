@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|expression: &str| {
+    // JSON strings are valid TOML strings
+    let expression = rconfig::Value::String(expression.to_string()).to_string();
+    let definition = format!(
+        "[a]\ndescription = \"A\"\ntype = \"bool\"\n\n[b]\ndescription = \"B\"\ntype = \"u32\"\ndepends = {expression}\nvalid = {expression}\n"
+    );
+    let ctx = rconfig::EvalContext::new(&["x"]).with_crate_name("fuzz");
+    let _ = rconfig::resolve(&definition, "[fuzz]\na = true\nb = 1\n", &ctx);
+});
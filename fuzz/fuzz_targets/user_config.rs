@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+const DEFINITION: &str = include_str!("../../example/fake-hal/config/rconfig.toml");
+
+fuzz_target!(|user_config: &str| {
+    let ctx = rconfig::EvalContext::new(&["esp32"]).with_crate_name("fake-hal");
+    let _ = rconfig::resolve(DEFINITION, user_config, &ctx);
+
+    if let Ok(definition) = rconfig::parse_definition_str(DEFINITION) {
+        let _ = rconfig::check_config_str(user_config, "fake-hal", &definition, vec!["esp32"]);
+    }
+});
@@ -0,0 +1,87 @@
+//! The configuration of all crates of a build as used by rconfig's frontends
+//!
+//! A [Repository] combines the config-definitions and features of the crates using rconfig with
+//! the user's `config.toml`. It evaluates which options are available, validates and changes
+//! values and creates the `config.toml` to save. Frontends (like the TUI) only present it.
+
+use linked_hash_map::LinkedHashMap as Map;
+use rconfig::{ConfigOption, GlobalConstraint, Migration, MigrationApplied};
+
+mod repository;
+
+pub use repository::{Repository, SortMode};
+
+/// A crate using rconfig - as told by its build script
+pub struct CrateDefinition {
+    pub crate_name: String,
+    /// Path of the config-definition, more than one are separated like in `PATH`
+    pub definition: String,
+    /// The features the crate is built with, comma separated
+    pub features: String,
+}
+
+impl CrateDefinition {
+    /// Read and parse the config-definition, see [rconfig::parse_definitions_str]
+    pub fn parse(&self) -> Result<Map<String, ConfigOption>, String> {
+        let definitions = self.read()?;
+        let definitions: Vec<&str> = definitions.iter().map(String::as_str).collect();
+        rconfig::parse_definitions_str(&definitions)
+            .map_err(|err| format!("Invalid config-definition {}: {:?}", self.definition, err))
+    }
+
+    /// The `[[migrations]]` of the config-definition, see [rconfig::parse_migrations_str]
+    pub fn migrations(&self) -> Result<Vec<Migration>, String> {
+        let mut migrations = Vec::new();
+        for definition in self.read()? {
+            migrations.extend(rconfig::parse_migrations_str(&definition).map_err(|err| {
+                format!("Invalid config-definition {}: {:?}", self.definition, err)
+            })?);
+        }
+        Ok(migrations)
+    }
+
+    /// The `[constraints]` of the config-definition, see [rconfig::parse_constraints_str]
+    pub fn constraints(&self) -> Result<Map<String, GlobalConstraint>, String> {
+        let mut constraints = Map::new();
+        for definition in self.read()? {
+            constraints.extend(rconfig::parse_constraints_str(&definition).map_err(|err| {
+                format!("Invalid config-definition {}: {:?}", self.definition, err)
+            })?);
+        }
+        Ok(constraints)
+    }
+
+    /// The content of the files, including what their `include` refers to, see
+    /// [rconfig::read_definitions]
+    fn read(&self) -> Result<Vec<String>, String> {
+        let paths: Vec<_> = std::env::split_paths(&self.definition).collect();
+        if let Some(missing) = paths.iter().find(|path| !path.is_file()) {
+            return Err(format!("Can't read {}", missing.display()));
+        }
+        rconfig::read_definitions(&paths)
+            .map_err(|err| format!("Invalid config-definition {}: {:?}", self.definition, err))
+    }
+}
+
+/// Apply the migrations of all crates to the user's config (the content of `config.toml`), e.g.
+/// after upgrading a crate to a new major version - the changes are returned with the crate name
+pub fn upgrade_config(
+    crates: &[CrateDefinition],
+    input: &str,
+) -> Result<(String, Vec<(String, MigrationApplied)>), String> {
+    let mut upgraded = input.to_string();
+    let mut applied = Vec::new();
+    for crate_definition in crates {
+        let migrations = crate_definition.migrations()?;
+        let (migrated, crate_applied) =
+            rconfig::migrate_user_config(&upgraded, &crate_definition.crate_name, &migrations)
+                .map_err(|err| format!("`config.toml` can't be upgraded: {:?}", err))?;
+        upgraded = migrated;
+        applied.extend(
+            crate_applied
+                .into_iter()
+                .map(|change| (crate_definition.crate_name.clone(), change)),
+        );
+    }
+    Ok((upgraded, applied))
+}
@@ -0,0 +1,2070 @@
+use std::{cell::RefCell, rc::Rc};
+
+use linked_hash_map::LinkedHashMap as Map;
+use rconfig::{ConfigOption, Value, ValueType};
+use serde_json::Map as JsonMap;
+
+use crate::CrateDefinition;
+
+/// The definitions and the user's config of all crates of a build - frontends only deal with presenting it
+pub struct Repository {
+    data: Map<String, (Map<String, ConfigOption>, Vec<String>)>,
+    user_cfg: String,
+    // the config as read from disk - sections of crates not in the current build are kept verbatim
+    pub original_cfg: String,
+    /// The crate and the keys of the menus to the current menu
+    pub path: Vec<String>,
+    // features toggled in the UI - only used to preview the effect, never for saving
+    preview_features: Map<String, Vec<String>>,
+    // hex display of numeric values toggled per option (by full key) or globally
+    hex_display: Map<String, bool>,
+    hex_global: Option<bool>,
+    // integers written in hex, octal or binary (by full key) - written back the same way
+    radixes: Map<String, rconfig::Radix>,
+    // (crate, entry) of the user's config not matching the definition - kept out of `user_cfg`
+    // and written back verbatim unless deleted or fixed
+    pub kept_entries: Vec<(String, rconfig::ConfigProblem)>,
+    // the evaluated config per crate - updated when a single value changes, dropped when
+    // `user_cfg` is replaced or the features change. `None` if the crate's config can't be evaluated
+    evaluated: RefCell<Map<String, Option<rconfig::IncrementalConfig>>>,
+    // the `[constraints]` per crate and the ones not met - dropped like `evaluated`
+    constraints: Map<String, Map<String, rconfig::GlobalConstraint>>,
+    unmet: RefCell<Map<String, Vec<rconfig::UnmetConstraint>>>,
+    sort_mode: SortMode,
+    // show the `hidden` options
+    expert_mode: bool,
+}
+
+/// The entries of a table of the user's config
+fn table_mut<'a>(
+    value: &'a mut Value,
+    key: &str,
+) -> core::result::Result<&'a mut JsonMap<String, Value>, String> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| format!("`{}` in `config.toml` needs to be a table", key))
+}
+
+/// Run `f` for every crate in its own thread, the results are in the order of the crates - the
+/// evaluation of a crate doesn't depend on the others
+fn per_crate<T: Send>(
+    data: &Map<String, (Map<String, ConfigOption>, Vec<String>)>,
+    f: impl Fn(&str, &Map<String, ConfigOption>, Vec<&str>) -> T + Sync,
+) -> Vec<T> {
+    std::thread::scope(|scope| {
+        let f = &f;
+        let handles: Vec<_> = data
+            .iter()
+            .map(|(crate_name, (crate_config, features))| {
+                scope.spawn(move || {
+                    f(
+                        crate_name,
+                        crate_config,
+                        features.iter().map(|v| v.as_str()).collect(),
+                    )
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+/// A value of the user's config as TOML - a table becomes an inline table
+fn toml_value(value: &Value) -> toml_edit::Value {
+    match value {
+        Value::Bool(value) => toml_edit::Value::from(*value),
+        Value::Number(number) => match (number.as_i64(), number.as_f64()) {
+            (Some(number), _) => toml_edit::Value::from(number),
+            (None, Some(number)) => toml_edit::Value::from(number),
+            _ => toml_edit::Value::from(number.to_string()),
+        },
+        Value::String(value) => toml_edit::Value::from(value.as_str()),
+        Value::Array(values) => toml_edit::Value::Array(values.iter().map(toml_value).collect()),
+        Value::Object(entries) => toml_edit::Value::InlineTable(
+            entries
+                .iter()
+                .map(|(key, value)| (key.as_str(), toml_value(value)))
+                .collect(),
+        ),
+        // there is no null in TOML
+        Value::Null => toml_edit::Value::from(""),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    /// By weight, otherwise as defined
+    Definition,
+    /// By description
+    Alphabetical,
+    /// Options with a value set in `config.toml` (or menus containing them) first
+    ModifiedFirst,
+}
+
+impl SortMode {
+    fn next(self) -> Self {
+        match self {
+            SortMode::Definition => SortMode::Alphabetical,
+            SortMode::Alphabetical => SortMode::ModifiedFirst,
+            SortMode::ModifiedFirst => SortMode::Definition,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            SortMode::Definition => "definition order",
+            SortMode::Alphabetical => "alphabetical",
+            SortMode::ModifiedFirst => "modified first",
+        }
+    }
+}
+
+impl Repository {
+    /// Read the crates' config-definitions, `input` is the content of the user's `config.toml`
+    pub fn load(crates: Vec<CrateDefinition>, input: String) -> Result<Self, String> {
+        let crates = crates
+            .into_iter()
+            .map(|cfg| cfg.parse().map(|definition| (cfg, definition)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::load_parsed(crates, input)
+    }
+
+    /// Like [Repository::load] with the config-definitions already parsed (see
+    /// [CrateDefinition::parse])
+    pub fn load_parsed(
+        crates: Vec<(CrateDefinition, Map<String, ConfigOption>)>,
+        input: String,
+    ) -> Result<Self, String> {
+        // to avoid the need to check things everywhere just make sure the input contains entries for all contained crates
+        let mut input_toml = basic_toml::from_str::<JsonMap<String, Value>>(&input)
+            .map_err(|err| format!("`config.toml` isn't valid: {}", err))?;
+        for (cfg, _) in &crates {
+            match input_toml.get(&cfg.crate_name) {
+                None => {
+                    input_toml.insert(cfg.crate_name.clone(), Value::Object(JsonMap::new()));
+                }
+                Some(Value::Object(_)) => (),
+                Some(_) => {
+                    return Err(format!(
+                        "`{}` in `config.toml` needs to be a table",
+                        cfg.crate_name
+                    ))
+                }
+            }
+        }
+        let original_cfg = input;
+        let input = basic_toml::to_string(&input_toml).map_err(|err| err.to_string())?;
+
+        let mut all_data: Map<String, (Map<String, ConfigOption>, Vec<String>)> = Map::new();
+        let mut constraints = Map::new();
+        for (cfg, config) in crates {
+            let crate_constraints = cfg.constraints()?;
+            if !crate_constraints.is_empty() {
+                constraints.insert(cfg.crate_name.clone(), crate_constraints);
+            }
+            all_data.insert(
+                cfg.crate_name,
+                (
+                    config,
+                    cfg.features.split(",").map(|v| v.to_string()).collect(),
+                ),
+            );
+        }
+        let mut repository = Self::new(all_data, input, original_cfg)?;
+        repository.constraints = constraints;
+        Ok(repository)
+    }
+
+    /// `data` holds the parsed definition and the features per crate, `user_cfg` needs to contain a
+    /// section for every crate
+    pub fn new(
+        data: Map<String, (Map<String, ConfigOption>, Vec<String>)>,
+        user_cfg: String,
+        original_cfg: String,
+    ) -> Result<Self, String> {
+        let mut repository = Self {
+            data,
+            user_cfg,
+            original_cfg,
+            path: Vec::new(),
+            preview_features: Map::new(),
+            hex_display: Map::new(),
+            hex_global: None,
+            radixes: Map::new(),
+            kept_entries: Vec::new(),
+            evaluated: RefCell::new(Map::new()),
+            constraints: Map::new(),
+            unmet: RefCell::new(Map::new()),
+            sort_mode: SortMode::Definition,
+            expert_mode: false,
+        };
+
+        repository.take_problems()?;
+        repository.read_radixes(&repository.original_cfg.clone());
+        Ok(repository)
+    }
+
+    /// Remember which integers of the config are written in hex, octal or binary
+    fn read_radixes(&mut self, cfg: &str) {
+        for crate_name in self.data.keys() {
+            for (key, radix) in rconfig::integer_radixes(cfg, crate_name) {
+                self.radixes
+                    .insert(format!("{}.{}", crate_name, key), radix);
+            }
+        }
+    }
+
+    /// Move the invalid entries out of the user's config, they would make the evaluation fail everywhere
+    fn take_problems(&mut self) -> Result<(), String> {
+        let user_cfg = &self.user_cfg;
+        let problems = per_crate(&self.data, |crate_name, crate_config, features| {
+            rconfig::check_config_str(user_cfg, crate_name, crate_config, features)
+                .unwrap_or_default()
+        });
+
+        let crate_names: Vec<String> = self.data.keys().cloned().collect();
+        for (crate_name, problems) in crate_names.into_iter().zip(problems) {
+            for problem in problems {
+                self.set_user_value(&crate_name, &problem.key, None)?;
+                self.kept_entries.push((crate_name.clone(), problem));
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the config by the one changed on disk, keeping the changes made in this session.
+    /// Returns the number of values changed both on disk and in this session.
+    pub fn reload(&mut self, disk_cfg: String) -> Result<usize, String> {
+        fn leaves(out: &mut Map<String, Value>, value: &Value, prefix: &str) {
+            match value.as_object() {
+                Some(object) if !object.is_empty() || prefix.is_empty() => {
+                    for (name, value) in object {
+                        let key = if prefix.is_empty() {
+                            name.to_string()
+                        } else {
+                            format!("{}.{}", prefix, name)
+                        };
+                        leaves(out, value, &key);
+                    }
+                }
+                _ => {
+                    out.insert(prefix.to_string(), value.clone());
+                }
+            }
+        }
+
+        let mut base = Map::new();
+        let original_toml = basic_toml::from_str(&self.original_cfg)
+            .map_err(|err| format!("`config.toml` isn't valid: {}", err))?;
+        leaves(&mut base, &original_toml, "");
+        let disk_toml = basic_toml::from_str::<JsonMap<String, Value>>(&disk_cfg)
+            .map_err(|err| format!("`config.toml` isn't valid: {}", err))?;
+        if let Some(crate_name) = self
+            .data
+            .keys()
+            .find(|crate_name| disk_toml.get(*crate_name).is_some_and(|v| !v.is_object()))
+        {
+            return Err(format!(
+                "`{}` in `config.toml` needs to be a table",
+                crate_name
+            ));
+        }
+        let mut theirs = Map::new();
+        leaves(&mut theirs, &Value::Object(disk_toml), "");
+        let mut mine = Map::new();
+        leaves(&mut mine, &Value::Object(self.user_toml()?), "");
+        for (crate_name, entry) in &self.kept_entries {
+            mine.insert(format!("{}.{}", crate_name, entry.key), entry.value.clone());
+        }
+
+        let session_changes: Vec<(String, Option<Value>)> = base
+            .keys()
+            .chain(mine.keys())
+            .filter(|key| base.get(*key) != mine.get(*key))
+            .map(|key| (key.clone(), mine.get(key).cloned()))
+            .collect();
+
+        self.set_user_cfg(disk_cfg.clone());
+        self.read_radixes(&disk_cfg);
+        self.original_cfg = disk_cfg;
+        self.kept_entries.clear();
+        for crate_name in self.data.keys().cloned().collect::<Vec<_>>() {
+            if self.user_value(&crate_name, "").is_none() {
+                let mut cfg = self.user_toml()?;
+                cfg.insert(crate_name, Value::Object(JsonMap::new()));
+                self.set_user_cfg(basic_toml::to_string(&cfg).map_err(|err| err.to_string())?);
+            }
+        }
+
+        let mut conflicts = 0;
+        let mut applied: Vec<&String> = Vec::new();
+        for (key, value) in &session_changes {
+            if applied.contains(&key) {
+                continue;
+            }
+            applied.push(key);
+
+            let their_value = theirs.get(key);
+            if their_value != base.get(key) && their_value != value.as_ref() {
+                conflicts += 1;
+            }
+
+            let (crate_name, key) = key.split_once('.').unwrap_or((key, ""));
+            if !key.is_empty() {
+                self.set_user_value(crate_name, key, value.clone())?;
+            }
+        }
+
+        self.take_problems()?;
+        Ok(conflicts)
+    }
+
+    fn set_user_cfg(&mut self, user_cfg: String) {
+        self.user_cfg = user_cfg;
+        self.evaluated.borrow_mut().clear();
+        self.unmet.borrow_mut().clear();
+    }
+
+    /// The crate's config with the user's values and without the options not available
+    fn evaluated(&self, crate_name: &str) -> Rc<Map<String, ConfigOption>> {
+        let mut evaluated = self.evaluated.borrow_mut();
+        let config = evaluated.entry(crate_name.to_string()).or_insert_with(|| {
+            let features = self.crate_features(crate_name);
+            let features = features.iter().map(|v| v.as_str()).collect();
+            rconfig::IncrementalConfig::new(
+                &self.user_cfg,
+                crate_name,
+                self.data[crate_name].0.clone(),
+                features,
+            )
+            .ok()
+        });
+
+        match config {
+            Some(config) => config.config(),
+            // the entries which would make it fail are kept out of `user_cfg`
+            None => Rc::new(self.definition(crate_name)),
+        }
+    }
+
+    /// Only the options depending on the changed value are re-evaluated
+    fn update_evaluated(&self, crate_name: &str, key: &str, value: Option<Value>) {
+        self.unmet.borrow_mut().remove(crate_name);
+        let mut evaluated = self.evaluated.borrow_mut();
+        let updated = match evaluated.get_mut(crate_name) {
+            Some(Some(config)) => config.set_value(key, value).is_ok(),
+            _ => false,
+        };
+        if !updated {
+            evaluated.remove(crate_name);
+        }
+    }
+
+    fn problems(&self, crate_name: &str) -> Vec<rconfig::ConfigProblem> {
+        let features = self.data[crate_name].1.iter().map(|v| v.as_str()).collect();
+        // `user_cfg` is always valid TOML with a table per crate
+        rconfig::check_config_str(
+            &self.user_cfg,
+            crate_name,
+            &self.data[crate_name].0,
+            features,
+        )
+        .unwrap_or_default()
+    }
+
+    /// The user's config, always a table per crate
+    fn user_toml(&self) -> Result<JsonMap<String, Value>, String> {
+        basic_toml::from_str(&self.user_cfg)
+            .map_err(|err| format!("`config.toml` isn't valid: {}", err))
+    }
+
+    /// Set or remove (`None`) a dotted key in the user's config
+    fn set_user_value(
+        &mut self,
+        crate_name: &str,
+        key: &str,
+        value: Option<Value>,
+    ) -> Result<(), String> {
+        fn set(item: &mut JsonMap<String, Value>, path: &[&str], value: Option<Value>) {
+            if path.len() == 1 {
+                match value {
+                    Some(value) => item.insert(path[0].to_string(), value),
+                    None => item.remove(path[0]),
+                };
+                return;
+            }
+
+            let child = item
+                .entry(path[0].to_string())
+                .or_insert_with(|| Value::Object(JsonMap::new()));
+            if !child.is_object() {
+                *child = Value::Object(JsonMap::new());
+            }
+            if let Value::Object(child) = child {
+                set(child, &path[1..], value);
+
+                // don't leave empty tables behind, they would be unknown keys again
+                if child.is_empty() {
+                    item.remove(path[0]);
+                }
+            }
+        }
+
+        let mut cfg = self.user_toml()?;
+        let crate_cfg = cfg
+            .entry(crate_name.to_string())
+            .or_insert_with(|| Value::Object(JsonMap::new()));
+        let path: Vec<&str> = key.split('.').collect();
+        set(table_mut(crate_cfg, crate_name)?, &path, value.clone());
+        self.user_cfg = basic_toml::to_string(&cfg).map_err(|err| err.to_string())?;
+        self.update_evaluated(crate_name, key, value);
+        Ok(())
+    }
+
+    /// Whether the kept entry is (or contains) the value of a `secret` option - it's not shown then
+    pub fn is_secret_entry(&self, index: usize) -> bool {
+        let Some((crate_name, entry)) = self.kept_entries.get(index) else {
+            return false;
+        };
+        let Some((definition, _)) = self.data.get(crate_name) else {
+            return false;
+        };
+        let table = format!("{}.", entry.key);
+        rconfig::OptionIndex::new(definition)
+            .iter()
+            .any(|(key, option)| option.secret && (key == entry.key || key.starts_with(&table)))
+    }
+
+    /// Replace a kept entry by `key = value` given as TOML
+    pub fn fix_entry(&mut self, index: usize, fixed: &str) -> core::result::Result<(), String> {
+        let (key, value) = match basic_toml::from_str::<Value>(fixed) {
+            Ok(value) => {
+                let mut key = Vec::new();
+                let mut value = &value;
+                while let Some(object) = value.as_object() {
+                    let Some((name, inner)) = object.iter().next().filter(|_| object.len() == 1)
+                    else {
+                        return Err("Expected a single `key = value`".to_string());
+                    };
+                    key.push(name.as_str());
+                    value = inner;
+                }
+                (key.join("."), value.clone())
+            }
+            Err(_) => return Err("Expected a single `key = value`".to_string()),
+        };
+
+        let crate_name = self.kept_entries[index].0.clone();
+        self.set_key(&crate_name, &key, Some(value))?;
+
+        self.kept_entries.remove(index);
+        Ok(())
+    }
+
+    /// Set (or reset to the default with `None`) the option given by its dotted key - the
+    /// config stays unchanged if the result isn't valid
+    pub fn set_key(
+        &mut self,
+        crate_name: &str,
+        key: &str,
+        value: Option<Value>,
+    ) -> core::result::Result<(), String> {
+        if !self.data.contains_key(crate_name) {
+            return Err(format!("Unknown crate `{}`", crate_name));
+        }
+
+        let previous = self.user_value(crate_name, key);
+        self.set_user_value(crate_name, key, value)?;
+        if let Some(problem) = self
+            .problems(crate_name)
+            .into_iter()
+            .find(|problem| problem.key == key)
+        {
+            self.set_user_value(crate_name, key, previous)?;
+            return Err(match problem.kind {
+                rconfig::ConfigProblemKind::UnknownKey => format!("Unknown key `{}`", key),
+                rconfig::ConfigProblemKind::NotApplicable => format!("`{}` is not available", key),
+                rconfig::ConfigProblemKind::InvalidValue => {
+                    let constraints = rconfig::OptionIndex::new(&self.data[crate_name].0)
+                        .get(key)
+                        .map(rconfig::describe_constraints)
+                        .unwrap_or_default();
+                    if constraints.is_empty() {
+                        format!("Invalid value for `{}`", key)
+                    } else {
+                        format!("Invalid value for `{}` ({})", key, constraints.join(", "))
+                    }
+                }
+            });
+        }
+
+        if self.user_value(crate_name, key) == Some(Value::Bool(true)) {
+            self.deselect_other_members(crate_name, key)?;
+        }
+        Ok(())
+    }
+
+    /// Check if the value would be valid for the option without changing anything
+    pub fn check_key(
+        &mut self,
+        crate_name: &str,
+        key: &str,
+        value: Option<Value>,
+    ) -> core::result::Result<(), String> {
+        let previous = self.user_value(crate_name, key);
+        self.set_key(crate_name, key, value)?;
+        self.set_user_value(crate_name, key, previous)
+    }
+
+    /// All crates with their available options as JSON
+    pub fn to_json(&self) -> Value {
+        fn options(
+            repository: &Repository,
+            level: &Map<String, ConfigOption>,
+            prefix: &str,
+        ) -> Value {
+            let entries = repository
+                .sorted(level)
+                .into_iter()
+                .map(|(name, option)| {
+                    let key = format!("{}{}", prefix, name);
+                    let value_type = match &option.value_type {
+                        Some(value_type) => Value::from(value_type.to_string()),
+                        None => Value::Null,
+                    };
+                    let values: Vec<Value> = option
+                        .values
+                        .iter()
+                        .flatten()
+                        .map(|item| {
+                            serde_json::json!({
+                                "value": item.value,
+                                "description": item.description,
+                            })
+                        })
+                        .collect();
+                    let children = match option.options.as_ref() {
+                        Some(children) => options(repository, children, &format!("{}.", key)),
+                        None => Value::Null,
+                    };
+
+                    serde_json::json!({
+                        "key": key,
+                        "description": option.description,
+                        "type": value_type,
+                        "value": option.__value.as_ref().filter(|_| !option.secret),
+                        "default": option.default_value.as_ref().filter(|_| !option.secret),
+                        "secret": option.secret,
+                        "env": option.env,
+                        "values": values,
+                        "min_length": option.min_length,
+                        "max_length": option.max_length,
+                        "charset": option.charset,
+                        "step": option.step,
+                        "options": children,
+                    })
+                })
+                .collect();
+            Value::Array(entries)
+        }
+
+        Value::Array(
+            self.data
+                .keys()
+                .map(|crate_name| {
+                    serde_json::json!({
+                        "crate": crate_name,
+                        "features": self.crate_features(crate_name),
+                        "options": options(self, &self.evaluated(crate_name), ""),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn user_value(&self, crate_name: &str, key: &str) -> Option<Value> {
+        let cfg = self.user_toml().ok()?;
+        let mut item = cfg.get(crate_name)?;
+        for part in key.split('.').filter(|part| !part.is_empty()) {
+            item = item.get(part)?;
+        }
+        Some(item.clone())
+    }
+
+    /// The `config.toml` containing the values of the options available with the build's features
+    pub fn create_config(&self) -> String {
+        let mut out = String::new();
+
+        let sections = rconfig::split_sections(&self.original_cfg);
+        if let Some((_, preamble)) = sections.iter().find(|(name, _)| name.is_empty()) {
+            out.push_str(preamble);
+        }
+
+        let user_cfg = &self.user_cfg;
+        let values = per_crate(&self.data, |crate_name, crate_config, features| {
+            rconfig::evaluate_config_str_to_cfg(
+                user_cfg,
+                crate_name,
+                crate_config.clone(),
+                features.clone(),
+            )
+            .and_then(|crate_config| rconfig::current_config_values(crate_config, features))
+            // the entries which would make it fail are kept out of `user_cfg`
+            .ok()
+        });
+
+        for (crate_name, cfgs) in self.data.keys().zip(values) {
+            // keep the crate's sections as they are rather than dropping its values
+            let Some(cfgs) = cfgs else {
+                for (_, text) in sections.iter().filter(|(name, _)| name == crate_name) {
+                    out.push_str(text);
+                }
+                continue;
+            };
+
+            out.push_str(&format!("[{crate_name}]"));
+            out.push('\n');
+
+            for (name, value) in cfgs {
+                match (
+                    self.radixes.get(&format!("{crate_name}.{name}")),
+                    value.as_u64(),
+                ) {
+                    (Some(radix), Some(number)) => {
+                        out.push_str(&format!("{name}={}", radix.format(number)))
+                    }
+                    _ => out.push_str(&format!("{name}={value}")),
+                }
+                out.push('\n');
+            }
+
+            for (_, entry) in self
+                .kept_entries
+                .iter()
+                .filter(|(kept_crate, _)| kept_crate == crate_name)
+            {
+                out.push_str(&format!("{}={}", entry.key, toml_value(&entry.value)));
+                out.push('\n');
+            }
+        }
+
+        // keep the sections of crates which are not part of the current build (e.g. other targets)
+        for (name, text) in &sections {
+            if !name.is_empty() && !self.data.contains_key(name) {
+                if !out.is_empty() && !out.ends_with("\n\n") {
+                    out.push('\n');
+                }
+                out.push_str(text);
+            }
+        }
+
+        out
+    }
+
+    /// A config (e.g. [Repository::create_config]) with the values of `secret` options masked - for
+    /// showing it, never for saving
+    pub fn redact(&self, cfg: &str) -> Result<String, String> {
+        self.data
+            .iter()
+            .try_fold(cfg.to_string(), |cfg, (crate_name, (definition, _))| {
+                rconfig::redact_config_str(&cfg, crate_name, definition)
+            })
+            .map_err(|err| format!("`config.toml` can't be shown: {:?}", err))
+    }
+
+    /// Write the config and remember it as the saved state
+    pub fn save(&mut self, path: &std::path::Path) -> std::io::Result<()> {
+        let cfg = self.create_config();
+        std::fs::write(path, &cfg)?;
+        self.original_cfg = cfg;
+        Ok(())
+    }
+
+    /// The whole option hierarchy as indented text, `*` marks modified and `-` disabled options
+    pub fn tree(&self) -> Result<String, String> {
+        let mut out = String::from("# * = modified, - = disabled\n");
+
+        for crate_name in self.data.keys() {
+            let features = self.crate_features(crate_name);
+            let features: Vec<&str> = features.iter().map(|v| v.as_str()).collect();
+            let config =
+                rconfig::fuse_config_str(&self.user_cfg, crate_name, self.definition(crate_name))
+                    .map_err(|err| format!("Invalid configuration: {:?}", err))?;
+
+            out.push_str(&format!(
+                "  {} (features: {})\n",
+                crate_name,
+                features.join(", ")
+            ));
+            Self::tree_level(&mut out, &config, &config, &features, 1, true);
+        }
+
+        Ok(out)
+    }
+
+    /// The `[constraints]` of the crates' config-definitions which the current config doesn't
+    /// meet, with the crate name
+    pub fn unmet_constraints(&self) -> Vec<(String, rconfig::UnmetConstraint)> {
+        let mut unmet = self.unmet.borrow_mut();
+        let mut result = Vec::new();
+        for (crate_name, constraints) in &self.constraints {
+            let crate_unmet = unmet.entry(crate_name.clone()).or_insert_with(|| {
+                let features = self.crate_features(crate_name);
+                let features: Vec<&str> = features.iter().map(|v| v.as_str()).collect();
+                rconfig::evaluate_config(
+                    &self.user_cfg,
+                    crate_name,
+                    self.data[crate_name].0.clone(),
+                    features.clone(),
+                )
+                .map(|cfg| rconfig::unmet_constraints(constraints, &cfg, &features))
+                .unwrap_or_default()
+            });
+            result.extend(
+                crate_unmet
+                    .iter()
+                    .map(|constraint| (crate_name.clone(), constraint.clone())),
+            );
+        }
+        result
+    }
+
+    /// The sections of `config.toml` which don't belong to any of the crates, e.g. named after a
+    /// renamed dependency
+    pub fn unknown_sections(&self) -> Vec<String> {
+        let crate_names: Vec<&str> = self.data.keys().map(|v| v.as_str()).collect();
+        rconfig::unknown_sections(&self.original_cfg, &crate_names)
+    }
+
+    /// How the option with the dotted key (including the crate name, e.g. `mycrate.psram.size`) is
+    /// evaluated - the `depends` of the option and its menus, the validation and the value
+    pub fn explain(&self, key: &str) -> core::result::Result<String, String> {
+        let (crate_name, option_key) = self
+            .data
+            .keys()
+            .find_map(|crate_name| {
+                let option_key = key.strip_prefix(crate_name.as_str())?.strip_prefix('.')?;
+                Some((crate_name, option_key))
+            })
+            .ok_or_else(|| format!("Unknown crate in `{}`", key))?;
+
+        let config =
+            rconfig::fuse_config_str(&self.user_cfg, crate_name, self.definition(crate_name))
+                .map_err(|err| format!("Invalid configuration: {:?}", err))?;
+        let features = self.crate_features(crate_name);
+        let feature_names: Vec<&str> = features.iter().map(|v| v.as_str()).collect();
+        let info = rconfig::get_option_info(
+            &config,
+            option_key,
+            &rconfig::EvalContext::new(&feature_names),
+        )
+        .ok_or_else(|| format!("Unknown key `{}`", key))?;
+
+        let mut out = format!("{} - {}\n", key, info.option.description);
+        out.push_str(&format!("  features: {}\n", features.join(", ")));
+
+        // the menus on the way can have a `depends`, too
+        let mut level = Some(&config);
+        let mut path = Vec::new();
+        for part in option_key.split('.') {
+            let Some(item) = level.and_then(|level| level.get(part)) else {
+                break;
+            };
+            path.push(part);
+            let path_key = path.join(".");
+
+            if let Some(depends) = &item.depends {
+                match info.unmet.iter().find(|unmet| unmet.key == path_key) {
+                    None => {
+                        out.push_str(&format!("  depends of {}: {} - met\n", path_key, depends))
+                    }
+                    Some(unmet) => {
+                        out.push_str(&format!(
+                            "  depends of {}: {} - NOT met\n",
+                            path_key, depends
+                        ));
+                        for feature in &unmet.missing_features {
+                            out.push_str(&format!("    feature `{}` is not enabled\n", feature));
+                        }
+                        for option in &unmet.disabled_options {
+                            out.push_str(&format!("    option `{}` is not enabled\n", option));
+                        }
+                    }
+                }
+            }
+            level = item.options.as_ref();
+        }
+
+        if let Some(valid) = &info.option.valid {
+            match &info.value {
+                Some(value)
+                    if rconfig::is_option_value_valid(
+                        &info.option,
+                        value,
+                        &config,
+                        &feature_names,
+                    ) =>
+                {
+                    out.push_str(&format!("  valid: {} - passes\n", valid))
+                }
+                Some(_) => out.push_str(&format!("  valid: {} - FAILS\n", valid)),
+                None => out.push_str(&format!("  valid: {} - not checked\n", valid)),
+            }
+        }
+
+        match (&info.value, info.provenance) {
+            (Some(value), Some(provenance)) => {
+                let value = if info.option.secret {
+                    rconfig::REDACTED.to_string()
+                } else {
+                    value.to_string()
+                };
+                let provenance = match provenance {
+                    rconfig::Provenance::User => "set in config.toml",
+                    rconfig::Provenance::Default => "default",
+                    rconfig::Provenance::Env => "from the environment",
+                };
+                out.push_str(&format!("  value: {} ({})\n", value, provenance));
+            }
+            _ if !info.active => out.push_str("  not available, it isn't part of the build\n"),
+            _ => out.push_str("  no value\n"),
+        }
+
+        Ok(out)
+    }
+
+    fn tree_level(
+        out: &mut String,
+        level: &Map<String, ConfigOption>,
+        all_config: &Map<String, ConfigOption>,
+        features: &Vec<&str>,
+        depth: usize,
+        parent_active: bool,
+    ) {
+        for (name, option) in level {
+            let active = parent_active
+                && rconfig::is_valid_depends(option.depends.clone(), all_config, features);
+
+            let marker = if !active {
+                '-'
+            } else if option.__value.is_some() {
+                '*'
+            } else {
+                ' '
+            };
+
+            let entry = match option.__value.as_ref().or(option.default_value.as_ref()) {
+                Some(_) if option.secret => format!("{} = {}", name, rconfig::REDACTED),
+                Some(value) => format!("{} = {}", name, value),
+                None => name.to_string(),
+            };
+
+            out.push_str(&format!(
+                "{} {}{:<width$} {}\n",
+                marker,
+                "  ".repeat(depth),
+                entry,
+                option.description,
+                width = 40usize.saturating_sub(depth * 2)
+            ));
+
+            if let Some(options) = option.options.as_ref() {
+                Self::tree_level(out, options, all_config, features, depth + 1, active);
+            }
+        }
+    }
+
+    /// The options of the current menu in the order to show them - `None` if the menu isn't
+    /// available anymore
+    fn current(&self) -> Option<Map<String, ConfigOption>> {
+        self.current_menu().map(|current| self.sorted(&current))
+    }
+
+    /// The options of a menu in the order to show them
+    fn sorted(&self, level: &Map<String, ConfigOption>) -> Map<String, ConfigOption> {
+        fn is_modified(option: &ConfigOption) -> bool {
+            option.__value.is_some()
+                || option
+                    .options
+                    .as_ref()
+                    .is_some_and(|options| options.values().any(is_modified))
+        }
+
+        let mut entries: Vec<(&String, &ConfigOption)> = level
+            .iter()
+            .filter(|(_, option)| self.expert_mode || !option.hidden)
+            .collect();
+        // the sorts are stable - equal entries keep the definition's order
+        entries.sort_by_key(|(_, option)| option.weight.unwrap_or_default());
+        match self.sort_mode {
+            SortMode::Definition => (),
+            SortMode::Alphabetical => {
+                entries.sort_by_key(|(_, option)| option.description.to_lowercase())
+            }
+            SortMode::ModifiedFirst => entries.sort_by_key(|(_, option)| !is_modified(option)),
+        }
+
+        entries
+            .into_iter()
+            .map(|(name, option)| (name.clone(), option.clone()))
+            .collect()
+    }
+
+    /// Switch to the next sort mode, returns its description
+    pub fn toggle_sort_mode(&mut self) -> &'static str {
+        self.sort_mode = self.sort_mode.next();
+        self.sort_mode.description()
+    }
+
+    /// Show or hide the `hidden` options, returns whether they are shown now
+    ///
+    /// Hiding them leaves a hidden menu.
+    pub fn toggle_expert_mode(&mut self) -> bool {
+        self.expert_mode = !self.expert_mode;
+        if !self.expert_mode && self.path.len() > 1 {
+            let visible = {
+                let config = self.evaluated(&self.path[0]);
+                let mut level = &*config;
+                let mut visible = 1;
+                for path_elem in &self.path[1..] {
+                    match level.get(path_elem) {
+                        Some(option) if !option.hidden => visible += 1,
+                        _ => break,
+                    }
+                    match level[path_elem].options.as_ref() {
+                        Some(options) => level = options,
+                        None => break,
+                    }
+                }
+                visible
+            };
+            self.path.truncate(visible);
+        }
+        self.expert_mode
+    }
+
+    fn current_features(&self) -> &Vec<String> {
+        self.crate_features(&self.path[0])
+    }
+
+    /// The features used for evaluation - the previewed ones if any
+    pub fn crate_features(&self, crate_name: &str) -> &Vec<String> {
+        self.preview_features
+            .get(crate_name)
+            .unwrap_or(&(self.data[crate_name]).1)
+    }
+
+    /// The crate's definition with the defaults for the features used for evaluation
+    fn definition(&self, crate_name: &str) -> Map<String, ConfigOption> {
+        let mut definition = self.data[crate_name].0.clone();
+        let features = self.crate_features(crate_name);
+        let features: Vec<&str> = features.iter().map(|v| v.as_str()).collect();
+        rconfig::select_defaults(&mut definition, &features);
+        definition
+    }
+
+    /// The features the crate was actually built with plus all features the definition checks
+    pub fn available_features(&self, crate_name: &str) -> Vec<String> {
+        let (config, build_features) = &self.data[crate_name];
+        let mut features: Vec<String> = build_features
+            .iter()
+            .filter(|feature| !feature.is_empty())
+            .cloned()
+            .collect();
+        for feature in rconfig::referenced_features(config) {
+            if !features.contains(&feature) {
+                features.push(feature);
+            }
+        }
+        features
+    }
+
+    /// Are the crate's features changed for a preview?
+    pub fn is_preview(&self, crate_name: &str) -> bool {
+        self.preview_features.contains_key(crate_name)
+    }
+
+    /// Enable or disable a feature for previewing its effect
+    pub fn toggle_preview_feature(&mut self, crate_name: &str, feature: &str) {
+        let mut features = self.crate_features(crate_name).clone();
+        if let Some(index) = features.iter().position(|f| f == feature) {
+            features.remove(index);
+        } else {
+            features.push(feature.to_string());
+        }
+
+        let mut build_features = self.data[crate_name].1.clone();
+        build_features.sort();
+        let mut sorted = features.clone();
+        sorted.sort();
+
+        if sorted == build_features {
+            self.preview_features.remove(crate_name);
+        } else {
+            self.preview_features
+                .insert(crate_name.to_string(), features);
+        }
+        self.evaluated.borrow_mut().remove(crate_name);
+        self.unmet.borrow_mut().remove(crate_name);
+    }
+
+    /// The crate the user is currently looking at
+    pub fn current_crate(&self, selected: usize) -> Option<String> {
+        if self.path.is_empty() {
+            self.get_current_level().get(selected).cloned()
+        } else {
+            Some(self.path[0].clone())
+        }
+    }
+
+    /// The names of the crates or the options of the current menu
+    pub fn get_current_level(&self) -> Vec<String> {
+        let mut res = Vec::new();
+
+        if self.path.is_empty() {
+            for (item, _) in &self.data {
+                res.push(item.to_string());
+            }
+        } else {
+            for (item, _) in self.current().unwrap_or_default() {
+                res.push(item.to_string());
+            }
+        }
+
+        res
+    }
+
+    /// The descriptions including the values of the crates or the options of the current menu
+    pub fn get_current_level_desc(&self) -> Vec<String> {
+        let mut res = Vec::new();
+
+        if self.path.is_empty() {
+            for (item, _) in &self.data {
+                res.push(item.to_string());
+            }
+        } else {
+            let choice = self.current_choice().is_some();
+            for (item, option) in self.current().unwrap_or_default() {
+                // entries without a type and options are just comments
+                if option.value_type.is_none() && option.options.is_none() {
+                    res.push(format!("--- {} ---", option.description));
+                    continue;
+                }
+
+                let values = &option.values;
+                let radix = self.display_radix(&self.full_key(&item), &option);
+                let current_value = if choice {
+                    // a radio group
+                    match option.__value.as_ref().or(option.default_value.as_ref()) {
+                        Some(Value::Bool(true)) => "(*)".to_string(),
+                        _ => "( )".to_string(),
+                    }
+                } else if let Some(var) = &option.env {
+                    format!("(ENV = {})", var)
+                } else if option.secret
+                    && (option.__value.is_some() || option.default_value.is_some())
+                {
+                    format!("({})", rconfig::REDACTED)
+                } else if let Some(value) = &option.__value {
+                    format!("({})", Self::display_value(value, values, radix))
+                } else if let Some(value) = &option.default_value {
+                    format!("(DEFAULT = {})", Self::display_value(value, values, radix))
+                } else {
+                    String::new()
+                };
+
+                let deprecated = if option.deprecated {
+                    " (DEPRECATED)"
+                } else {
+                    ""
+                };
+                res.push(format!(
+                    "{}{} {}",
+                    option.description, deprecated, current_value
+                ));
+            }
+        }
+
+        res
+    }
+
+    fn display_value(
+        value: &rconfig::Value,
+        values: &Option<Vec<rconfig::ValueItem>>,
+        radix: rconfig::Radix,
+    ) -> String {
+        match values.iter().flatten().find(|v| v.value == *value) {
+            Some(display) => display.description.to_string(),
+            None => match value.as_u64() {
+                Some(number) => radix.format(number),
+                None => value.to_string(),
+            },
+        }
+    }
+
+    /// The members of the current menu if it's a `choice`
+    fn current_choice(&self) -> Option<Vec<String>> {
+        let (crate_name, menu) = self.path.split_first()?;
+        self.choice_members(crate_name, menu)
+    }
+
+    /// The members of the menu given by the names of the menus leading to it if it's a `choice`
+    fn choice_members(&self, crate_name: &str, menu_path: &[String]) -> Option<Vec<String>> {
+        if menu_path.is_empty() {
+            return None;
+        }
+
+        let config = self.evaluated(crate_name);
+        let mut level = &*config;
+        let mut menu = None;
+        for path_elem in menu_path {
+            let item = level.get(path_elem)?;
+            level = item.options.as_ref()?;
+            menu = Some(item);
+        }
+        menu.filter(|menu| menu.choice).map(|menu| {
+            menu.options
+                .iter()
+                .flatten()
+                .map(|(name, _)| name.clone())
+                .collect()
+        })
+    }
+
+    /// Selecting a member of a `choice` (by its dotted key) unselects the others
+    fn deselect_other_members(&mut self, crate_name: &str, key: &str) -> Result<(), String> {
+        let menu_path: Vec<String> = key.split('.').map(str::to_string).collect();
+        let Some((selected, menu_path)) = menu_path.split_last() else {
+            return Ok(());
+        };
+        let Some(members) = self.choice_members(crate_name, menu_path) else {
+            return Ok(());
+        };
+
+        for member in members.iter().filter(|member| *member != selected) {
+            let key = menu_path
+                .iter()
+                .chain([member])
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(".");
+            if self.user_value(crate_name, &key).is_some() {
+                self.set_user_value(crate_name, &key, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The dotted key (including the crate name) of the selected entry
+    pub fn selected_key(&self, which: usize) -> Option<String> {
+        let item = self.get_current_level().get(which)?.clone();
+        if self.path.is_empty() {
+            Some(item)
+        } else {
+            Some(self.full_key(&item))
+        }
+    }
+
+    fn full_key(&self, item: &str) -> String {
+        format!("{}.{}", self.path.join("."), item)
+    }
+
+    /// The radix to show the numeric option in - toggling hex display wins over the radix the
+    /// value was entered with, which wins over the definition's `display` hint
+    fn display_radix(&self, key: &str, option: &ConfigOption) -> rconfig::Radix {
+        let hex = self.hex_display.get(key).copied().or(self.hex_global);
+        match (hex, self.radixes.get(key)) {
+            (Some(true), _) => rconfig::Radix::Hex,
+            (Some(false), _) => rconfig::Radix::Decimal,
+            (None, Some(radix @ (rconfig::Radix::Hex | rconfig::Radix::Binary))) => *radix,
+            (None, Some(_)) => rconfig::Radix::Decimal,
+            (None, None) => option
+                .display
+                .map_or(rconfig::Radix::Decimal, rconfig::DisplayHint::radix),
+        }
+    }
+
+    /// Should the numeric option be shown in hex?
+    fn is_hex(&self, key: &str, option: &ConfigOption) -> bool {
+        self.display_radix(key, option) == rconfig::Radix::Hex
+    }
+
+    /// The radix to show the selected option in
+    pub fn value_radix(&self, which: usize) -> rconfig::Radix {
+        match (self.get_current_level().get(which), self.get_option(which)) {
+            (Some(item), Some(option)) => self.display_radix(&self.full_key(item), &option),
+            _ => rconfig::Radix::Decimal,
+        }
+    }
+
+    /// Write the value of the selected option in the radix it was entered with
+    pub fn set_radix(&mut self, which: usize, radix: rconfig::Radix) {
+        if let Some(item) = self.get_current_level().get(which) {
+            let key = self.full_key(item);
+            self.radixes.insert(key, radix);
+        }
+    }
+
+    /// Toggle hex display of the selected option
+    pub fn toggle_hex(&mut self, which: usize) {
+        if let (Some(item), Some(option)) =
+            (self.get_current_level().get(which), self.get_option(which))
+        {
+            let key = self.full_key(item);
+            let hex = !self.is_hex(&key, &option);
+            self.hex_display.insert(key, hex);
+        }
+    }
+
+    /// Cycles through all hex, all decimal and as defined by the definitions
+    pub fn toggle_hex_global(&mut self) {
+        self.hex_global = match self.hex_global {
+            None => Some(true),
+            Some(true) => Some(false),
+            Some(false) => None,
+        };
+        self.hex_display.clear();
+    }
+
+    /// The number of entries in the current menu
+    pub fn get_count(&self) -> usize {
+        if self.path.is_empty() {
+            self.data.len()
+        } else {
+            self.current().map_or(0, |current| current.len())
+        }
+    }
+
+    /// The description of the current menu
+    pub fn current_title(&self) -> String {
+        if self.path.is_empty() {
+            String::from("Root")
+        } else {
+            let mut title = self.path[0].clone();
+            let mut current = &(self.data[&self.path[0]]).0;
+            for path_elem in &self.path[1..] {
+                let Some(menu) = current.get(path_elem) else {
+                    break;
+                };
+                title = menu.description.clone();
+                match menu.options.as_ref() {
+                    Some(options) => current = options,
+                    None => break,
+                }
+            }
+            title
+        }
+    }
+
+    /// Enter the selected menu
+    pub fn select(&mut self, select: usize) -> core::result::Result<(), rconfig::Error> {
+        let next = self
+            .get_current_level()
+            .into_iter()
+            .nth(select)
+            .ok_or(rconfig::Error::InvalidKey)?;
+        self.path.push(next);
+        Ok(())
+    }
+
+    /// All dotted keys (including the crate name) of the currently available options and menus
+    pub fn all_keys(&self) -> Vec<String> {
+        fn collect(
+            keys: &mut Vec<String>,
+            level: &Map<String, ConfigOption>,
+            prefix: &str,
+            expert_mode: bool,
+        ) {
+            for (name, option) in level {
+                if option.hidden && !expert_mode {
+                    continue;
+                }
+                let key = format!("{}.{}", prefix, name);
+                keys.push(key.clone());
+                if let Some(options) = option.options.as_ref() {
+                    collect(keys, options, &key, expert_mode);
+                }
+            }
+        }
+
+        let mut keys = Vec::new();
+        for crate_name in self.data.keys() {
+            keys.push(crate_name.clone());
+            collect(
+                &mut keys,
+                &self.evaluated(crate_name),
+                crate_name,
+                self.expert_mode,
+            );
+        }
+        keys
+    }
+
+    /// Navigate to the given dotted key - returns the index of the option to select
+    pub fn goto(&mut self, key: &str) -> Option<usize> {
+        let (crate_name, option_key) = key.split_once('.').unwrap_or((key, ""));
+        if !self.data.contains_key(crate_name) {
+            return None;
+        }
+        if !option_key.is_empty() {
+            let config = self.evaluated(crate_name);
+            let index = rconfig::OptionIndex::new(&config);
+            let id = index.id(option_key)?;
+            // hidden options and the ones of hidden menus are only reachable in expert mode
+            if !self.expert_mode && index.path(id).iter().any(|id| index.option(*id).hidden) {
+                return None;
+            }
+        }
+
+        let parts: Vec<String> = key.split('.').map(|p| p.to_string()).collect();
+        self.path = parts.clone();
+
+        // menus are entered, for values the containing menu is shown
+        let is_menu = parts.len() == 1 || self.current_menu().is_some();
+        if is_menu {
+            Some(0)
+        } else {
+            let last = self.path.pop()?;
+            self.get_current_level()
+                .iter()
+                .position(|item| *item == last)
+        }
+    }
+
+    /// The options of the current path if it points to a menu
+    fn current_menu(&self) -> Option<Map<String, ConfigOption>> {
+        let config = self.evaluated(&self.path[0]);
+
+        let mut current = &*config;
+        for path_elem in &self.path[1..] {
+            current = current.get(path_elem)?.options.as_ref()?;
+        }
+        Some(current.clone())
+    }
+
+    /// (description, key, unmet depends) of the options in the current menu which are not available
+    pub fn hidden_options(
+        &self,
+    ) -> Result<Vec<(String, String, Vec<rconfig::UnmetDepends>)>, String> {
+        if self.path.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let crate_name = &self.path[0];
+        let config =
+            rconfig::fuse_config_str(&self.user_cfg, crate_name, self.definition(crate_name))
+                .map_err(|err| format!("Invalid configuration: {:?}", err))?;
+        let mut level = &config;
+        for path_elem in &self.path[1..] {
+            level = level
+                .get(path_elem)
+                .and_then(|menu| menu.options.as_ref())
+                .ok_or_else(|| format!("Unknown menu `{}`", self.path.join(".")))?;
+        }
+
+        let available = self.current().unwrap_or_default();
+        let features = self.current_features().iter().map(|v| v.as_str()).collect();
+        Ok(level
+            .iter()
+            .filter(|(name, option)| {
+                !available.contains_key(*name) && (self.expert_mode || !option.hidden)
+            })
+            .map(|(name, option)| {
+                let mut key = self.path[1..].to_vec();
+                key.push(name.to_string());
+                let key = key.join(".");
+                let unmet = rconfig::explain_unavailable(&key, &config, &features);
+                (option.description.clone(), key, unmet)
+            })
+            .collect())
+    }
+
+    /// Leave the current menu
+    pub fn up(&mut self) {
+        if !self.path.is_empty() {
+            self.path.remove(self.path.len() - 1);
+        }
+    }
+
+    /// The selected option or menu - also includes options which are not available
+    pub fn option_info(&self, which: usize) -> Option<rconfig::OptionInfo> {
+        if self.path.is_empty() {
+            return None;
+        }
+
+        let crate_name = &self.path[0];
+        let key = self.selected_key(which)?;
+        let key = key.strip_prefix(crate_name.as_str())?.strip_prefix('.')?;
+        let config =
+            rconfig::fuse_config_str(&self.user_cfg, crate_name, self.definition(crate_name))
+                .ok()?;
+        let features: Vec<&str> = self.current_features().iter().map(|v| v.as_str()).collect();
+        rconfig::get_option_info(&config, key, &rconfig::EvalContext::new(&features))
+    }
+
+    /// Is the selected entry an option (not a menu)?
+    pub fn is_value(&self, which: usize) -> bool {
+        self.get_option(which)
+            .is_some_and(|option| option.options.is_none())
+    }
+
+    /// The selected option or menu with the user's value
+    pub fn get_option(&self, which: usize) -> Option<ConfigOption> {
+        self.option_info(which).map(|info| info.option)
+    }
+
+    /// Change the value of the selected numeric option by `steps` times its `step` (`1` without
+    /// one), clamped to the range its `valid` tells (see [rconfig::numeric_range])
+    pub fn step_value(
+        &mut self,
+        which: usize,
+        steps: i64,
+    ) -> core::result::Result<i128, rconfig::Error> {
+        let option = self.get_option(which).ok_or(rconfig::Error::InvalidKey)?;
+        if !option
+            .value_type
+            .as_ref()
+            .is_some_and(ValueType::is_integer)
+        {
+            return Err(rconfig::Error::InvalidKey);
+        }
+
+        // definitions read by the TUI aren't checked, `step = 0` would never change the value
+        let step = i128::from(option.step.filter(|step| *step > 0).unwrap_or(1));
+        let (min, max) = rconfig::numeric_range(&option);
+        // the bounds might not be multiples of the step
+        let (min, max) = (-(-min).div_euclid(step) * step, max.div_euclid(step) * step);
+        let current = option
+            .__value
+            .as_ref()
+            .or(option.default_value.as_ref())
+            .and_then(|value| {
+                value
+                    .as_i64()
+                    .map(i128::from)
+                    .or_else(|| value.as_u64().map(i128::from))
+            })
+            .unwrap_or(min);
+        let value = (current + i128::from(steps) * step).clamp(min, max.max(min));
+
+        self.set_value(which, rconfig::integer_value(value))?;
+        Ok(value)
+    }
+
+    /// Set the value of the selected option
+    pub fn set_value(
+        &mut self,
+        which: usize,
+        value: rconfig::Value,
+    ) -> core::result::Result<(), rconfig::Error> {
+        // check value against validation rule
+        let current = self.get_option(which).ok_or(rconfig::Error::InvalidKey)?;
+        if current.env.is_some() {
+            return Err(rconfig::Error::InvalidConfiguration(self.current_title()));
+        }
+        let crate_cfg = &(self.data[&self.path[0]]).0;
+        let features = self.current_features().iter().map(|s| s.as_str()).collect();
+        if !rconfig::is_option_value_valid(&current, &value, crate_cfg, &features) {
+            return Err(rconfig::Error::InvalidConfigurationValue(
+                self.current_title(),
+            ));
+        }
+
+        // find where to insert/update
+        let next = self
+            .get_current_level()
+            .into_iter()
+            .enumerate()
+            .find(|(index, _value)| *index == which)
+            .ok_or(rconfig::Error::InvalidKey)?
+            .1;
+
+        // selecting a member of a choice unselects the others, the selected one stays selected
+        if self.current_choice().is_some() {
+            if value != Value::Bool(true) {
+                return Ok(());
+            }
+            let crate_name = self.path[0].clone();
+            let key = self.path[1..]
+                .iter()
+                .chain([&next])
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(".");
+            self.deselect_other_members(&crate_name, &key)
+                .map_err(rconfig::Error::InvalidConfiguration)?;
+        }
+
+        let mut cfg = self
+            .user_toml()
+            .map_err(rconfig::Error::InvalidConfiguration)?;
+        let crate_cfg = cfg
+            .get_mut(&self.path[0])
+            .ok_or(rconfig::Error::InvalidKey)?;
+        let mut item =
+            table_mut(crate_cfg, &self.path[0]).map_err(rconfig::Error::InvalidConfiguration)?;
+        for path_elem in &self.path[1..] {
+            let child = item
+                .entry(path_elem.to_string())
+                .or_insert_with(|| rconfig::Value::Object(Default::default()));
+            item = table_mut(child, path_elem).map_err(rconfig::Error::InvalidConfiguration)?;
+        }
+
+        item.remove(&next);
+        let key = self.path[1..]
+            .iter()
+            .chain([&next])
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(".");
+        item.insert(next, value.clone());
+
+        self.user_cfg = basic_toml::to_string(&cfg)
+            .map_err(|err| rconfig::Error::InvalidConfiguration(err.to_string()))?;
+        self.update_evaluated(&self.path[0], &key, Some(value));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = "feature(\"esp32\")"
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    depends = "enabled(\"psram.enable\")"
+    type = "u32"
+    default = 2
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    valid = "value >= 0 && value <= 80000"
+    "#;
+
+    fn repository(definition: &str, user_cfg: &str) -> Repository {
+        let mut data = Map::new();
+        data.insert(
+            "mycrate".to_string(),
+            (
+                rconfig::parse_definition_str(definition).unwrap(),
+                vec!["esp32".to_string()],
+            ),
+        );
+
+        let mut input = basic_toml::from_str::<Value>(user_cfg).unwrap();
+        input
+            .as_object_mut()
+            .unwrap()
+            .entry("mycrate")
+            .or_insert_with(|| Value::Object(JsonMap::new()));
+        Repository::new(
+            data,
+            basic_toml::to_string(&input).unwrap(),
+            user_cfg.to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn set_key_validates() {
+        let mut repository = repository(DEFINITION, "");
+
+        assert!(repository
+            .set_key("mycrate", "heap.size", Some(Value::from(90000)))
+            .is_err());
+        assert!(repository
+            .set_key("mycrate", "heap.sise", Some(Value::from(100)))
+            .is_err());
+        repository
+            .set_key("mycrate", "heap.size", Some(Value::from(30000)))
+            .unwrap();
+
+        assert_eq!("[mycrate]\nheap.size=30000\n", repository.create_config());
+    }
+
+    #[test]
+    fn changed_values_update_the_config() {
+        let mut repository = repository(DEFINITION, "");
+        assert!(!repository
+            .all_keys()
+            .contains(&"mycrate.psram.size".to_string()));
+
+        repository
+            .set_key("mycrate", "psram.enable", Some(Value::Bool(true)))
+            .unwrap();
+        assert_eq!(
+            vec![
+                "mycrate",
+                "mycrate.psram",
+                "mycrate.psram.enable",
+                "mycrate.psram.size",
+                "mycrate.heap",
+                "mycrate.heap.size"
+            ],
+            repository.all_keys()
+        );
+
+        repository.set_key("mycrate", "psram.enable", None).unwrap();
+        assert!(!repository
+            .all_keys()
+            .contains(&"mycrate.psram.size".to_string()));
+    }
+
+    #[test]
+    fn unavailable_current_menu() {
+        let mut repository = repository(DEFINITION, "");
+        repository.select(0).unwrap();
+        repository.select(0).unwrap();
+        assert_eq!("PSRAM", repository.current_title());
+
+        // the menu depends on the feature
+        repository.toggle_preview_feature("mycrate", "esp32");
+        assert_eq!(0, repository.get_count());
+        assert!(repository.get_current_level_desc().is_empty());
+        assert!(repository.select(0).is_err());
+        assert_eq!(2, repository.hidden_options().unwrap().len());
+        assert_eq!("PSRAM", repository.current_title());
+    }
+
+    #[test]
+    fn unmet_constraints() {
+        let mut repository = repository(DEFINITION, "");
+        repository.constraints.insert(
+            "mycrate".to_string(),
+            rconfig::parse_constraints_str(
+                "[constraints]\nheap = 'value_of(\"heap.size\") >= 1024'\n",
+            )
+            .unwrap(),
+        );
+        // there is no heap size
+        assert_eq!(1, repository.unmet_constraints().len());
+
+        repository
+            .set_key("mycrate", "heap.size", Some(Value::from(4096)))
+            .unwrap();
+        assert!(repository.unmet_constraints().is_empty());
+
+        repository
+            .set_key("mycrate", "heap.size", Some(Value::from(512)))
+            .unwrap();
+        assert_eq!(
+            vec![(
+                "mycrate".to_string(),
+                rconfig::UnmetConstraint {
+                    name: "heap".to_string(),
+                    message: "`value_of(\"heap.size\") >= 1024` doesn't hold".to_string(),
+                }
+            )],
+            repository.unmet_constraints()
+        );
+    }
+
+    #[test]
+    fn crates_in_order() {
+        let mut data = Map::new();
+        let mut user_cfg = String::new();
+        let mut expected = String::new();
+        for i in 0..12 {
+            let crate_name = format!("crate{}", i);
+            data.insert(
+                crate_name.clone(),
+                (
+                    rconfig::parse_definition_str(DEFINITION).unwrap(),
+                    vec!["esp32".to_string()],
+                ),
+            );
+            user_cfg.push_str(&format!("[{}]\nheap.size = {}\n", crate_name, i * 100_000));
+            expected.push_str(&format!("[{}]\nheap.size={}\n", crate_name, i * 100_000));
+        }
+
+        let repository = Repository::new(data, user_cfg.clone(), user_cfg).unwrap();
+        assert_eq!(11, repository.kept_entries.len());
+        assert_eq!("crate1", repository.kept_entries[0].0);
+        assert_eq!(expected, repository.create_config());
+    }
+
+    #[test]
+    fn keeps_invalid_entries() {
+        let repository = repository(
+            DEFINITION,
+            "[mycrate]\nheap.size = 90000\nwifi.enable = true\n",
+        );
+
+        let keys: Vec<&str> = repository
+            .kept_entries
+            .iter()
+            .map(|(_, problem)| problem.key.as_str())
+            .collect();
+        assert_eq!(vec!["wifi.enable", "heap.size"], keys);
+
+        assert_eq!(
+            "[mycrate]\nwifi.enable=true\nheap.size=90000\n",
+            repository.create_config()
+        );
+    }
+
+    #[test]
+    fn keeps_invalid_entries_as_toml() {
+        let repository = repository(
+            DEFINITION,
+            "[mycrate]\npins = [{ gpio = 1 }, { gpio = 2 }]\nled = { pin = \"a\" }\n",
+        );
+        assert_eq!(
+            "[mycrate]\nled.pin=\"a\"\npins=[{ gpio = 1 }, { gpio = 2 }]\n",
+            repository.create_config()
+        );
+    }
+
+    #[test]
+    fn keeps_sections_of_other_crates() {
+        let mut repository = repository(
+            DEFINITION,
+            "# comment\n[mycrate]\n\n[other]\nsome.key = 1\n",
+        );
+        repository
+            .set_key("mycrate", "psram.enable", Some(Value::Bool(true)))
+            .unwrap();
+
+        assert_eq!(
+            "# comment\n[mycrate]\npsram.enable=true\n\n[other]\nsome.key = 1\n",
+            repository.create_config()
+        );
+    }
+
+    #[test]
+    fn keeps_the_section_of_a_crate_failing_to_evaluate() {
+        let mut data = Map::new();
+        data.insert(
+            "mycrate".to_string(),
+            (rconfig::parse_definition_str(DEFINITION).unwrap(), vec![]),
+        );
+        let original = "# comment\n[mycrate]\nheap.size = 100 # bytes\n";
+        let repository =
+            Repository::new(data, "[mycrate".to_string(), original.to_string()).unwrap();
+
+        assert_eq!(original, repository.create_config());
+    }
+
+    #[test]
+    fn reload_keeps_session_changes() {
+        let mut repository = repository(DEFINITION, "[mycrate]\nheap.size = 100\n");
+        repository
+            .set_key("mycrate", "psram.enable", Some(Value::Bool(true)))
+            .unwrap();
+
+        let conflicts = repository
+            .reload("[mycrate]\nheap.size = 200\n".to_string())
+            .unwrap();
+
+        assert_eq!(0, conflicts);
+        assert_eq!(
+            "[mycrate]\npsram.enable=true\nheap.size=200\n",
+            repository.create_config()
+        );
+    }
+
+    #[test]
+    fn keeps_the_radix_of_integers() {
+        let mut repository = repository(DEFINITION, "[mycrate]\nheap.size = 0x8000\n");
+        repository
+            .set_key("mycrate", "heap.size", Some(Value::from(0x4000)))
+            .unwrap();
+        repository
+            .set_key("mycrate", "psram.enable", Some(Value::Bool(true)))
+            .unwrap();
+        repository
+            .set_key("mycrate", "psram.size", Some(Value::from(4)))
+            .unwrap();
+
+        assert_eq!(
+            "[mycrate]\npsram.enable=true\npsram.size=4\nheap.size=0x4000\n",
+            repository.create_config()
+        );
+    }
+
+    #[test]
+    fn shows_values_in_the_display_radix() {
+        let definition = r#"
+        [mask]
+        description = "Interrupt mask"
+        type = "u8"
+        default = 0b1010
+        display = "binary"
+
+        [base]
+        description = "Base address"
+        type = "u32"
+        default = 0x4000
+        display = "hex"
+        "#;
+        let mut repository = repository(definition, "[mycrate]\n");
+        repository.select(0).unwrap();
+
+        assert_eq!(
+            vec![
+                "Interrupt mask (DEFAULT = 0b1010)",
+                "Base address (DEFAULT = 0x4000)"
+            ],
+            repository.get_current_level_desc()
+        );
+        assert_eq!(rconfig::Radix::Binary, repository.value_radix(0));
+        repository.toggle_hex(0);
+        assert_eq!(rconfig::Radix::Hex, repository.value_radix(0));
+    }
+
+    #[test]
+    fn steps_values() {
+        let definition = r#"
+        [buffer]
+        description = "DMA buffer"
+        type = "u32"
+        step = 1024
+        default = 4096
+        valid = "value >= 1000 && value <= 10000"
+
+        [retries]
+        description = "Retries"
+        type = "u8"
+        step = 0
+        default = 3
+        "#;
+        let mut repository = repository(definition, "[mycrate]\n");
+        repository.select(0).unwrap();
+
+        assert_eq!(5120, repository.step_value(0, 1).unwrap());
+        // clamped to the multiples of the step in the range
+        assert_eq!(9216, repository.step_value(0, 10).unwrap());
+        assert_eq!(2048, repository.step_value(0, -7).unwrap());
+        assert_eq!(1024, repository.step_value(0, -1).unwrap());
+        assert_eq!(1024, repository.step_value(0, -1).unwrap());
+        assert_eq!("[mycrate]\nbuffer=1024\n", repository.create_config());
+
+        assert_eq!(4, repository.step_value(1, 1).unwrap());
+    }
+
+    #[test]
+    fn hides_secret_values() {
+        let definition = r#"
+        [token]
+        description = "API token"
+        type = "string"
+        secret = true
+
+        [password]
+        description = "Password"
+        type = "string"
+        secret = true
+        env = "PASSWORD"
+        "#;
+        let mut repository = repository(definition, "[mycrate]\n");
+
+        repository
+            .set_key("mycrate", "token", Some(Value::from("abc")))
+            .unwrap();
+        // only from the environment
+        assert!(repository
+            .set_key("mycrate", "password", Some(Value::from("abc")))
+            .is_err());
+
+        let tree = repository.tree().unwrap();
+        assert!(tree.contains("token = ********"));
+        assert!(!tree.contains("abc"));
+        assert_eq!(Value::Null, repository.to_json()[0]["options"][0]["value"]);
+        assert_eq!("[mycrate]\ntoken=\"abc\"\n", repository.create_config());
+        assert_eq!(
+            "[mycrate]\ntoken=\"********\"\n",
+            repository.redact(&repository.create_config()).unwrap()
+        );
+    }
+
+    #[test]
+    fn hides_secret_entries() {
+        let definition = r#"
+        [password]
+        description = "Password"
+        type = "string"
+        secret = true
+        env = "PASSWORD"
+        "#;
+        // only from the environment, kept as an invalid entry
+        let repository = repository(definition, "[mycrate]\npassword = \"hunter2\"\nother = 1\n");
+
+        let secret: Vec<(&str, bool)> = (0..repository.kept_entries.len())
+            .map(|index| {
+                (
+                    repository.kept_entries[index].1.key.as_str(),
+                    repository.is_secret_entry(index),
+                )
+            })
+            .collect();
+        assert!(secret.contains(&("password", true)));
+        assert!(secret.contains(&("other", false)));
+    }
+
+    #[test]
+    fn shows_hidden_options_in_expert_mode() {
+        let definition = r#"
+        [tuning]
+        description = "Tuning"
+
+        [tuning.options.rx_threshold]
+        description = "RX threshold"
+        type = "u32"
+        default = 4
+        hidden = true
+
+        [tuning.options.tx_power]
+        description = "TX power"
+        type = "u32"
+        default = 20
+        "#;
+        let mut repository = repository(definition, "");
+
+        assert!(!repository
+            .all_keys()
+            .contains(&"mycrate.tuning.rx_threshold".to_string()));
+        assert_eq!(None, repository.goto("mycrate.tuning.rx_threshold"));
+        repository.goto("mycrate.tuning");
+        assert_eq!(vec!["tx_power"], repository.get_current_level());
+        assert!(repository.hidden_options().unwrap().is_empty());
+
+        assert!(repository.toggle_expert_mode());
+        assert_eq!(
+            vec!["rx_threshold", "tx_power"],
+            repository.get_current_level()
+        );
+        assert_eq!(Some(0), repository.goto("mycrate.tuning.rx_threshold"));
+        // still part of the config
+        assert!(repository.tree().unwrap().contains("rx_threshold = 4"));
+    }
+
+    #[test]
+    fn leaves_hidden_menus_in_expert_mode() {
+        let definition = r#"
+        [tuning]
+        description = "Tuning"
+        hidden = true
+
+        [tuning.options.tx_power]
+        description = "TX power"
+        type = "u32"
+        default = 20
+        "#;
+        let mut repository = repository(definition, "");
+        repository.toggle_expert_mode();
+        repository.goto("mycrate.tuning");
+        // leaves the hidden menu
+        assert!(!repository.toggle_expert_mode());
+        assert_eq!(vec!["mycrate"], repository.path);
+        assert!(repository.get_current_level().is_empty());
+    }
+
+    #[test]
+    fn selects_one_member_of_a_choice() {
+        let definition = r#"
+        [mode]
+        description = "PSRAM mode"
+        choice = true
+
+        [mode.options.quad]
+        description = "Quad SPI"
+        type = "bool"
+        default = true
+
+        [mode.options.octal]
+        description = "Octal SPI"
+        type = "bool"
+
+        [mode.options.opi]
+        description = "OPI"
+        type = "bool"
+        "#;
+        let mut repository = repository(definition, "[mycrate]\n");
+        repository.goto("mycrate.mode");
+        assert_eq!(
+            vec!["Quad SPI (*)", "Octal SPI ( )", "OPI ( )"],
+            repository.get_current_level_desc()
+        );
+
+        repository.set_value(1, Value::Bool(true)).unwrap();
+        repository.set_value(2, Value::Bool(true)).unwrap();
+        // only selecting changes anything
+        repository.set_value(2, Value::Bool(false)).unwrap();
+        assert_eq!(
+            vec!["Quad SPI ( )", "Octal SPI ( )", "OPI (*)"],
+            repository.get_current_level_desc()
+        );
+        assert_eq!("[mycrate]\nmode.opi=true\n", repository.create_config());
+
+        // the same for frontends setting keys
+        repository
+            .set_key("mycrate", "mode.octal", Some(Value::Bool(true)))
+            .unwrap();
+        assert_eq!("[mycrate]\nmode.octal=true\n", repository.create_config());
+    }
+
+    #[test]
+    fn marks_deprecated_options() {
+        let definition = r#"
+        [legacy]
+        description = "Legacy mode"
+        type = "bool"
+        default = false
+        deprecated = true
+        "#;
+        let mut repository = repository(definition, "");
+        repository.select(0).unwrap();
+
+        assert_eq!(
+            vec!["Legacy mode (DEPRECATED) (DEFAULT = false)"],
+            repository.get_current_level_desc()
+        );
+    }
+
+    #[test]
+    fn explains_options() {
+        let repository = repository(DEFINITION, "[mycrate]\nheap.size = 30000\n");
+
+        assert_eq!(
+            "mycrate.psram.size - PSRAM Size\n  \
+             features: esp32\n  \
+             depends of psram: feature(\"esp32\") - met\n  \
+             depends of psram.size: enabled(\"psram.enable\") - NOT met\n    \
+             option `psram.enable` is not enabled\n  \
+             not available, it isn't part of the build\n",
+            repository.explain("mycrate.psram.size").unwrap()
+        );
+        assert_eq!(
+            "mycrate.heap.size - Bytes to allocate\n  \
+             features: esp32\n  \
+             valid: value >= 0 && value <= 80000 - passes\n  \
+             value: 30000 (set in config.toml)\n",
+            repository.explain("mycrate.heap.size").unwrap()
+        );
+        assert!(repository.explain("mycrate.heap.missing").is_err());
+        assert!(repository.explain("other.heap.size").is_err());
+    }
+}
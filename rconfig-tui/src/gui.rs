@@ -0,0 +1,266 @@
+//! The configurator as a desktop window - the same [Repository] as the terminal UI, for users who
+//! prefer a windowed configurator or for screenshots in the documentation
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use rconfig::{Value, ValueType};
+use rconfig_core::Repository;
+
+/// How many matches of the search are listed
+const MAX_MATCHES: usize = 50;
+
+pub fn run(repository: Repository, config_path: &Path, read_only: bool) -> eframe::Result {
+    let app = GuiApp {
+        repository,
+        config_path: config_path.to_path_buf(),
+        read_only,
+        expert_mode: false,
+        editing: None,
+        search: String::new(),
+        selected: None,
+        status: None,
+    };
+    eframe::run_native(
+        "rconfig",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(app))),
+    )
+}
+
+struct GuiApp {
+    repository: Repository,
+    config_path: PathBuf,
+    read_only: bool,
+    expert_mode: bool,
+    /// The option of the current menu being edited and the text entered for it
+    editing: Option<(usize, String)>,
+    search: String,
+    /// The option of the current menu the search went to
+    selected: Option<usize>,
+    status: Option<String>,
+}
+
+impl eframe::App for GuiApp {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        egui::Panel::top("toolbar").show(ui, |ui| self.toolbar(ui));
+        egui::Panel::bottom("status").show(ui, |ui| self.status_bar(ui));
+        egui::Panel::left("search")
+            .resizable(true)
+            .show(ui, |ui| self.search(ui));
+        egui::CentralPanel::default_margins().show(ui, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| self.level(ui));
+        });
+    }
+}
+
+impl GuiApp {
+    fn toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.repository.path.is_empty(), egui::Button::new("Up"))
+                .clicked()
+            {
+                self.repository.up();
+                self.editing = None;
+                self.selected = None;
+            }
+            ui.heading(self.repository.current_title());
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let modified = self.repository.create_config() != self.repository.original_cfg;
+                if ui
+                    .add_enabled(!self.read_only && modified, egui::Button::new("Save"))
+                    .clicked()
+                {
+                    self.status = Some(match self.repository.save(&self.config_path) {
+                        Ok(()) => "Saved `config.toml`".to_string(),
+                        Err(err) => format!("Saving failed: {}", err),
+                    });
+                }
+                if ui
+                    .checkbox(&mut self.expert_mode, "Expert mode")
+                    .on_hover_text("Show the hidden options")
+                    .changed()
+                {
+                    self.expert_mode = self.repository.toggle_expert_mode();
+                    self.editing = None;
+                    self.selected = None;
+                }
+                if self.read_only {
+                    ui.label("Read-only");
+                }
+            });
+        });
+    }
+
+    fn status_bar(&mut self, ui: &mut egui::Ui) {
+        for (crate_name, unmet) in self.repository.unmet_constraints() {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "`{}` doesn't meet the constraint `{}`: {}",
+                    crate_name, unmet.name, unmet.message
+                ),
+            );
+        }
+        if !self.repository.kept_entries.is_empty() {
+            ui.label(format!(
+                "{} invalid entries of `config.toml` are kept as they are, use `--fix` to remove \
+                 them",
+                self.repository.kept_entries.len()
+            ));
+        }
+        ui.label(self.status.as_deref().unwrap_or_default());
+    }
+
+    fn search(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("Search keys"));
+        if self.search.is_empty() {
+            return;
+        }
+
+        let search = self.search.to_lowercase();
+        let matches: Vec<String> = self
+            .repository
+            .all_keys()
+            .into_iter()
+            .filter(|key| key.to_lowercase().contains(&search))
+            .take(MAX_MATCHES)
+            .collect();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for key in matches {
+                if ui.selectable_label(false, &key).clicked() {
+                    self.selected = self.repository.goto(&key);
+                    self.editing = None;
+                }
+            }
+        });
+    }
+
+    fn level(&mut self, ui: &mut egui::Ui) {
+        let items = self.repository.get_current_level_desc();
+        for (which, item) in items.iter().enumerate() {
+            let item = item.trim_end();
+            if !self.repository.is_value(which) {
+                if ui.button(format!("{} >", item)).clicked() {
+                    self.repository.select(which).ok();
+                    self.editing = None;
+                    self.selected = None;
+                    return;
+                }
+                continue;
+            }
+
+            ui.horizontal(|ui| {
+                let label = ui.selectable_label(self.selected == Some(which), item);
+                if let Some(key) = self.repository.selected_key(which) {
+                    label.on_hover_text(key);
+                }
+                if !self.read_only {
+                    self.editor(ui, which);
+                }
+            });
+        }
+    }
+
+    /// A checkbox for bools, a drop-down for enums and a text field for the other types
+    fn editor(&mut self, ui: &mut egui::Ui, which: usize) {
+        let Some(option) = self.repository.get_option(which) else {
+            return;
+        };
+        if option.env.is_some() {
+            return;
+        }
+        let current = option.__value.clone().or(option.default_value.clone());
+
+        let changed = match option.value_type {
+            Some(ValueType::Bool) => {
+                let mut enabled = current
+                    .and_then(|value| value.as_bool())
+                    .unwrap_or_default();
+                ui.checkbox(&mut enabled, "")
+                    .changed()
+                    .then_some(Value::Bool(enabled))
+            }
+            Some(ValueType::Enum) => {
+                let values = option.values.unwrap_or_default();
+                let current = current.as_ref().and_then(|value| value.as_str());
+                let selected_text = values
+                    .iter()
+                    .find(|value| Some(value.value.as_str()) == current)
+                    .map(|value| value.description.clone())
+                    .unwrap_or_default();
+                let mut changed = None;
+                egui::ComboBox::from_id_salt(("enum", which))
+                    .selected_text(selected_text)
+                    .show_ui(ui, |ui| {
+                        for value in &values {
+                            if ui
+                                .selectable_label(
+                                    Some(value.value.as_str()) == current,
+                                    &value.description,
+                                )
+                                .clicked()
+                            {
+                                changed = Some(Value::String(value.value.clone()));
+                            }
+                        }
+                    });
+                changed
+            }
+            _ => {
+                self.text_editor(ui, which, &option, current);
+                None
+            }
+        };
+
+        if let Some(value) = changed {
+            self.status = self
+                .repository
+                .set_value(which, value)
+                .err()
+                .map(|_| "The value isn't valid".to_string());
+        }
+    }
+
+    fn text_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        which: usize,
+        option: &rconfig::ConfigOption,
+        current: Option<Value>,
+    ) {
+        match &mut self.editing {
+            Some((editing, text)) if *editing == which => {
+                let response = ui.add(egui::TextEdit::singleline(text).password(option.secret));
+                let entered =
+                    response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                if entered || ui.button("Apply").clicked() {
+                    let text = text.clone();
+                    match super::plain::change(&mut self.repository, which, Some(&text)) {
+                        Ok(()) => {
+                            self.editing = None;
+                            self.status = None;
+                        }
+                        Err(err) => self.status = Some(err),
+                    }
+                } else if ui.button("Cancel").clicked() {
+                    self.editing = None;
+                }
+            }
+            _ => {
+                if ui.button("Edit").clicked() {
+                    // secrets aren't shown, they are entered again
+                    let text = match current {
+                        _ if option.secret => String::new(),
+                        Some(Value::String(text)) => text,
+                        Some(value) => value.to_string(),
+                        None => String::new(),
+                    };
+                    self.editing = Some((which, text));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// A single line text input - the cursor moves by graphemes, not bytes
+#[derive(Debug, Default, Clone)]
+pub struct TextInput {
+    value: String,
+    // byte offset into `value`, always on a grapheme boundary
+    cursor: usize,
+    // the maximum number of characters
+    max_chars: Option<usize>,
+}
+
+impl TextInput {
+    /// An input containing `value` with the cursor at the end
+    pub fn new(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            cursor: value.len(),
+            max_chars: None,
+        }
+    }
+
+    /// Don't accept more than `max_chars` characters, e.g. the `max_length` of a string option
+    pub fn with_max_chars(mut self, max_chars: Option<usize>) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    fn remaining_chars(&self) -> usize {
+        self.max_chars.map_or(usize::MAX, |max| {
+            max.saturating_sub(self.value.chars().count())
+        })
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The terminal column of the cursor relative to the start of the input
+    pub fn cursor_column(&self) -> u16 {
+        self.value[..self.cursor].width() as u16
+    }
+
+    /// Insert text (e.g. pasted) at the cursor - line breaks are dropped since the input is a single line
+    pub fn insert_str(&mut self, text: &str, accept: impl Fn(char) -> bool) {
+        let text: String = text
+            .chars()
+            .filter(|c| *c != '\n' && *c != '\r')
+            .filter(|c| accept(*c))
+            .take(self.remaining_chars())
+            .collect();
+        self.value.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    /// Handle editing keys, returns `false` if the key isn't handled by the input.
+    /// `accept` decides which characters can be typed.
+    pub fn handle_key(&mut self, key: KeyEvent, accept: impl Fn(char) -> bool) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+        match key.code {
+            KeyCode::Left if ctrl || alt => self.cursor = self.previous_word(),
+            KeyCode::Right if ctrl || alt => self.cursor = self.next_word(),
+            KeyCode::Left => self.cursor = self.previous_grapheme(),
+            KeyCode::Right => self.cursor = self.next_grapheme(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.len(),
+            KeyCode::Backspace if ctrl || alt => self.delete_to(self.previous_word()),
+            KeyCode::Backspace => self.delete_to(self.previous_grapheme()),
+            KeyCode::Delete => self.delete_to(self.next_grapheme()),
+            KeyCode::Char(c) if ctrl => match c {
+                'a' => self.cursor = 0,
+                'e' => self.cursor = self.value.len(),
+                'w' => self.delete_to(self.previous_word()),
+                'u' => self.delete_to(0),
+                'k' => self.delete_to(self.value.len()),
+                _ => return false,
+            },
+            KeyCode::Char(c) if accept(c) && self.remaining_chars() > 0 => {
+                self.value.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Delete between the cursor and `to`
+    fn delete_to(&mut self, to: usize) {
+        let (from, to) = (self.cursor.min(to), self.cursor.max(to));
+        self.value.replace_range(from..to, "");
+        self.cursor = from;
+    }
+
+    fn previous_grapheme(&self) -> usize {
+        self.value[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    }
+
+    fn next_grapheme(&self) -> usize {
+        self.value[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map(|grapheme| self.cursor + grapheme.len())
+            .unwrap_or(self.cursor)
+    }
+
+    fn previous_word(&self) -> usize {
+        self.value[..self.cursor]
+            .split_word_bound_indices()
+            .rev()
+            .find(|(_, word)| !word.trim().is_empty())
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    }
+
+    fn next_word(&self) -> usize {
+        self.value[self.cursor..]
+            .split_word_bound_indices()
+            .find(|(_, word)| !word.trim().is_empty())
+            .map(|(index, word)| self.cursor + index + word.len())
+            .unwrap_or(self.value.len())
+    }
+}
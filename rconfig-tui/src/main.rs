@@ -1,7 +1,7 @@
 use cargo_metadata::Message;
 use clap::Parser;
 use linked_hash_map::LinkedHashMap as Map;
-use rconfig::{ConfigOption, JsonMap, Value, ValueType};
+use rconfig::{Value, ValueType};
 use std::{
     io::*,
     process::{exit, Command, Stdio},
@@ -11,21 +11,27 @@ use std::io;
 
 use crossterm::ExecutableCommand;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind,
+    },
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{prelude::*, style::palette::tailwind, widgets::*};
 
-struct Rconfig {
-    crate_name: String,
-    definition: String,
-    features: String,
-}
+mod clipboard;
+#[cfg(feature = "gui")]
+mod gui;
+mod input;
+mod plain;
+mod rpc;
+mod serve;
+use input::TextInput;
+use rconfig_core::{CrateDefinition, Repository};
 
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Ignore invalid configuration keys
+    /// Remove invalid configuration entries instead of asking what to do with them
     #[arg(long)]
     fix: bool,
 
@@ -38,18 +44,171 @@ struct Args {
     init: bool,
 
     /// Features to be passed to the build
-    #[arg(long)]
+    #[arg(long, short = 'F', global = true)]
     features: Option<String>,
 
+    /// Activate all available features
+    #[arg(long, global = true)]
+    all_features: bool,
+
     /// Don't activate default features
-    #[arg(long)]
+    #[arg(long, global = true)]
     no_default_features: bool,
+
+    /// Path to the binary crate's `Cargo.toml`, `config.toml` is expected next to it
+    #[arg(long, global = true)]
+    manifest_path: Option<std::path::PathBuf>,
+
+    /// Package to build
+    #[arg(long, short, global = true)]
+    package: Option<String>,
+
+    /// Build for the target triple
+    #[arg(long, global = true)]
+    target: Option<String>,
+
+    /// Build with the release profile
+    #[arg(long, short, global = true)]
+    release: bool,
+
+    /// Build with the given profile
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Find the crates using rconfig via `cargo metadata` (`package.metadata.rconfig`) instead of
+    /// building
+    #[arg(long, global = true)]
+    no_build: bool,
+
+    /// Browse the configuration without being able to change or save it
+    #[arg(long, conflicts_with_all = ["fix", "init"])]
+    read_only: bool,
+
+    /// Only use black and white, also used if `NO_COLOR` is set
+    #[arg(long)]
+    high_contrast: bool,
+
+    /// Print the menus and read commands line by line instead of using the full screen, e.g. for
+    /// screen readers or to capture the session
+    #[arg(long)]
+    plain: bool,
+
+    /// Open a desktop window instead of using the terminal
+    #[cfg(feature = "gui")]
+    #[arg(long, conflicts_with = "plain")]
+    gui: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Commands {
+    /// Export a config-definition to Kconfig syntax
+    ExportKconfig {
+        /// The config-definition (e.g. `config/rconfig.toml`)
+        definition: std::path::PathBuf,
+
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Print a graph of the dependencies between options and features of a config-definition
+    Graph {
+        /// The config-definition (e.g. `config/rconfig.toml`)
+        definition: std::path::PathBuf,
+
+        /// Print a Mermaid graph instead of Graphviz's DOT
+        #[arg(long)]
+        mermaid: bool,
+
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Enumerate the distinct valid configurations of a config-definition, e.g. to check them all in CI
+    Matrix {
+        /// The config-definition (e.g. `config/rconfig.toml`)
+        definition: std::path::PathBuf,
+
+        /// The name of the crate (the section in `config.toml`)
+        #[arg(long)]
+        crate_name: String,
+
+        /// The features to evaluate the definition with, comma separated
+        #[arg(long, default_value = "")]
+        features: String,
+
+        /// Stop after this many configurations
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+
+        /// Write every configuration to `config-<n>.toml` in this directory instead of stdout
+        #[arg(long)]
+        output_dir: Option<std::path::PathBuf>,
+    },
+
+    /// Print the option hierarchy with the current values
+    Tree,
+
+    /// Explain how an option is evaluated - its depends, its validation and its value
+    Explain {
+        /// The crate name and the dotted key of the option, e.g. `fake-hal.psram.size`
+        key: String,
+    },
+
+    /// Serve a web UI to edit the configuration on localhost
+    Serve {
+        /// The port to listen on
+        #[arg(long, default_value_t = 7878)]
+        port: u16,
+    },
+
+    /// Handle JSON-RPC requests (one per line) on stdin, e.g. for editor integrations
+    Rpc,
+
+    /// Create a JSON Schema for `config.toml`, e.g. for editor validation
+    Schema {
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Apply the crates' migrations to `config.toml` (renamed keys, converted values, removed
+    /// keys) - the previous version is kept as `config.toml.bak`
+    Upgrade {
+        /// Only print what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Import a Kconfig file as a config-definition
+    ImportKconfig {
+        /// The Kconfig file
+        kconfig: std::path::PathBuf,
+
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
 }
 
 fn main() {
-    let args = Args::parse();
+    // when invoked as `cargo rconfig` cargo passes the subcommand name as the first argument
+    let mut cli_args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    if cli_args.get(1).and_then(|arg| arg.to_str()) == Some("rconfig") {
+        cli_args.remove(1);
+    }
+    let mut args = Args::parse_from(cli_args);
+
+    if let Some(command) = args.command.take() {
+        run_command(command, &args);
+        return;
+    }
 
-    let cfg_path = std::path::PathBuf::from("./config.toml");
+    let cfg_path = config_path(&args);
 
     let cfg_exists = if let Ok(metadata) = std::fs::metadata(&cfg_path) {
         if metadata.is_dir() {
@@ -61,22 +220,141 @@ fn main() {
         false
     };
 
-    // "fix" things by temporarily removing the config for the build - we need to restore the config before running the TUI
-    // to keep the valid values
+    if args.fix && !cfg_exists {
+        println!("No `config.toml` found. use `--init` to create a new one.");
+        exit(1);
+    }
+
+    let per_crate_configs = discover_crates(&args);
+
+    if args.init
+        && (!cfg_exists || args.force || ask_confirm("Overwrite the current `config.toml`? (Y/N)"))
+    {
+        std::fs::write(&cfg_path, "").expect("Unable to create `config.toml`");
+    }
+
+    let input = std::fs::read_to_string(&cfg_path).expect("`config.toml` missing or not readable");
+
+    // renamed and removed keys are only rewritten on request
+    let outdated = per_crate_configs.iter().any(|(crate_definition, _)| {
+        crate_definition.migrations().is_ok_and(|migrations| {
+            rconfig::migrate_user_config(&input, &crate_definition.crate_name, &migrations)
+                .is_ok_and(|(_, applied)| !applied.is_empty())
+        })
+    });
+    let outdated_hint =
+        "`config.toml` has outdated keys, run `cargo rconfig upgrade` to rewrite them";
+
+    let mut repository = load_repository(per_crate_configs, input);
     if args.fix {
-        if !cfg_exists {
-            println!("No `config.toml` found. use `--init` to create a new one.");
-            exit(1);
+        repository.kept_entries.clear();
+    }
+
+    let unknown_sections = repository.unknown_sections();
+
+    if args.plain {
+        if !unknown_sections.is_empty() {
+            println!(
+                "Not used by any crate: [{}] in `config.toml`",
+                unknown_sections.join("], [")
+            );
+        }
+        if outdated {
+            println!("{}", outdated_hint);
         }
+        plain::run(repository, &cfg_path, args.read_only).unwrap();
+        return;
+    }
+
+    #[cfg(feature = "gui")]
+    if args.gui {
+        if !unknown_sections.is_empty() {
+            eprintln!(
+                "Not used by any crate: [{}] in `config.toml`",
+                unknown_sections.join("], [")
+            );
+        }
+        if outdated {
+            eprintln!("{}", outdated_hint);
+        }
+        gui::run(repository, &cfg_path, args.read_only).unwrap_or_else(|err| {
+            eprintln!("Can't open the window: {}", err);
+            exit(1);
+        });
+        return;
+    }
+
+    let theme = if args.high_contrast || std::env::var_os("NO_COLOR").is_some() {
+        &HIGH_CONTRAST_THEME
+    } else {
+        &DEFAULT_THEME
+    };
+
+    // TUI stuff ahead
+    let terminal = init_terminal().unwrap();
+
+    // create app and run it
+    let mut app = App::new(repository, args.read_only, cfg_path, theme);
+    if !unknown_sections.is_empty() {
+        app.set_status(&format!(
+            "Not used by any crate: [{}] in `config.toml`",
+            unknown_sections.join("], [")
+        ));
+    } else if outdated {
+        app.set_status(outdated_hint);
+    }
+    app.run(terminal).unwrap();
+
+    restore_terminal().unwrap();
+}
+
+/// The user's `config.toml` - next to the manifest if one is given
+fn config_path(args: &Args) -> std::path::PathBuf {
+    match &args.manifest_path {
+        Some(manifest_path) => manifest_path
+            .parent()
+            .map(|dir| dir.join("config.toml"))
+            .unwrap_or_else(|| std::path::PathBuf::from("./config.toml")),
+        None => std::path::PathBuf::from("./config.toml"),
+    }
+}
+
+/// The crates using rconfig with their parsed config-definitions
+type DiscoveredCrates = Vec<(CrateDefinition, Map<String, rconfig::ConfigOption>)>;
 
-        let mut new_file = cfg_path.clone();
-        new_file.set_extension(".toml.old");
-        std::fs::rename(&cfg_path, &new_file).unwrap();
+/// Build the binary crate to learn about the crates using rconfig
+///
+/// The config-definitions are parsed as soon as a build script tells about them, while cargo
+/// builds the rest.
+fn discover_crates(args: &Args) -> DiscoveredCrates {
+    if args.no_build {
+        return discover_crates_from_metadata(args);
     }
 
     let mut cargo_args = vec!["build".to_string(), "--message-format=json".to_string()];
+    for (arg, value) in [
+        (
+            "--manifest-path",
+            args.manifest_path.as_ref().map(|p| p.display().to_string()),
+        ),
+        ("--package", args.package.clone()),
+        ("--target", args.target.clone()),
+        ("--profile", args.profile.clone()),
+    ] {
+        if let Some(value) = value {
+            cargo_args.push(format!("{}={}", arg, value));
+        }
+    }
+
+    if args.all_features {
+        cargo_args.push("--all-features".to_string());
+    }
+
+    if args.release {
+        cargo_args.push("--release".to_string());
+    }
 
-    if let Some(features) = args.features {
+    if let Some(features) = &args.features {
         let features = format!("--features={}", features);
         cargo_args.push(features);
     }
@@ -85,15 +363,26 @@ fn main() {
         cargo_args.push("--no-default-features".to_string());
     }
 
-    let mut command = Command::new("cargo")
+    // show our own progress instead of cargo's - cargo still prints errors
+    let progress = stderr().is_terminal();
+    if progress {
+        cargo_args.push("--quiet".to_string());
+    }
+
+    // use the cargo we got invoked by (as a cargo subcommand), this also keeps the toolchain
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    // an invalid `config.toml` shouldn't fail the build, we want to repair it
+    let mut command = Command::new(cargo)
         .args(&cargo_args)
+        .env("RCONFIG_TOOLING", "1")
         .stdout(Stdio::piped())
         .spawn()
         .unwrap();
 
     let reader = std::io::BufReader::new(command.stdout.take().unwrap());
 
-    let mut per_crate_configs: Vec<Rconfig> = Vec::new();
+    let mut parsing = Vec::new();
+    let mut compiled = 0;
     for message in cargo_metadata::Message::parse_stream(reader) {
         match message.unwrap() {
             Message::BuildScriptExecuted(script) => {
@@ -105,360 +394,439 @@ fn main() {
                     let crate_name = env_map.get("__RCONFIG_CRATE").unwrap().to_string();
                     let features = env_map.get("__RCONFIG_FEATURES").unwrap().to_string();
 
-                    per_crate_configs.push(Rconfig {
+                    let crate_definition = CrateDefinition {
                         crate_name,
                         definition,
                         features,
-                    });
+                    };
+                    parsing.push(std::thread::spawn(move || {
+                        let definition = crate_definition.parse();
+                        (crate_definition, definition)
+                    }));
                 }
             }
-            _ => (), // don't care
+            Message::CompilerArtifact(artifact) if progress => {
+                compiled += 1;
+                eprint!(
+                    "\r\x1b[K{} Building - {} compiled, {} using rconfig: {}",
+                    ['|', '/', '-', '\\'][compiled % 4],
+                    compiled,
+                    parsing.len(),
+                    artifact.target.name
+                );
+            }
+            _ => (),
         }
     }
+    if progress {
+        eprint!("\r\x1b[K");
+    }
 
+    let per_crate_configs: DiscoveredCrates = parsing
+        .into_iter()
+        .map(|handle| {
+            let (crate_definition, definition) = handle.join().unwrap();
+            let definition = definition.unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                exit(1);
+            });
+            (crate_definition, definition)
+        })
+        .collect();
+
+    // an invalid config might break compiling the crates - their build scripts already told us everything we need
     let exit_status = command.wait().expect("Couldn't get cargo's exit status");
-    if !exit_status.success() {
+    if !exit_status.success() && per_crate_configs.is_empty() {
         eprintln!("\n\nA successful build is needed");
         exit(1);
     }
 
-    if args.fix {
-        let mut new_file = cfg_path.clone();
-        new_file.set_extension(".toml.old");
-        std::fs::rename(&new_file, &cfg_path).unwrap();
-    }
+    per_crate_configs
+}
 
-    if args.init {
-        if (cfg_exists && (args.force || ask_confirm("Overwrite the current `config.toml`? (Y/N)")))
-            || !cfg_exists
-        {
-            std::fs::write(&cfg_path, "").expect("Unable to create `config.toml`");
-        }
+/// Find the crates using rconfig without building - they have to tell where their
+/// config-definition is in their `Cargo.toml`
+///
+/// ```toml
+/// [package.metadata.rconfig]
+/// definition = "config/rconfig.toml"
+/// ```
+///
+/// The features are the ones cargo resolves for the build.
+fn discover_crates_from_metadata(args: &Args) -> DiscoveredCrates {
+    let mut command = cargo_metadata::MetadataCommand::new();
+    if let Some(manifest_path) = &args.manifest_path {
+        command.manifest_path(manifest_path);
     }
-
-    let input = std::fs::read_to_string(cfg_path).expect("`config.toml` missing or not readable");
-
-    // to avoid the need to check things everywhere just make sure the input contains entries for all contained crates
-    let mut input_toml = basic_toml::from_str::<Value>(&input).unwrap();
-    let input_toml = input_toml.as_object_mut().unwrap();
-    for cfg in &per_crate_configs {
-        if !input_toml.contains_key(&cfg.crate_name) {
-            input_toml.insert(
-                cfg.crate_name.clone(),
-                rconfig::Value::Object(JsonMap::new()),
-            );
-        }
+    if args.all_features {
+        command.features(cargo_metadata::CargoOpt::AllFeatures);
     }
-    let input = basic_toml::to_string(input_toml).unwrap();
-
-    // prepare repository
-    let mut all_data: Map<String, (Map<String, ConfigOption>, Vec<String>)> = Map::new();
-    for cfg in per_crate_configs {
-        let definition = std::fs::read_to_string(cfg.definition).unwrap();
-        let config = rconfig::parse_definition_str(&definition);
-        all_data.insert(
-            cfg.crate_name,
-            (
-                config,
-                cfg.features.split(",").map(|v| v.to_string()).collect(),
-            ),
-        );
+    if args.no_default_features {
+        command.features(cargo_metadata::CargoOpt::NoDefaultFeatures);
     }
-    let repository = Repository::new(all_data, input);
-
-    // TUI stuff ahead
-    let terminal = init_terminal().unwrap();
-
-    // create app and run it
-    App::new(repository).run(terminal).unwrap();
-
-    restore_terminal().unwrap();
-}
-
-fn ask_confirm(question: &str) -> bool {
-    println!("{}", question);
-    loop {
-        let mut input = [0];
-        let _ = std::io::stdin().read(&mut input);
-        match input[0] as char {
-            'y' | 'Y' => return true,
-            'n' | 'N' => return false,
-            _ => (),
-        }
+    if let Some(features) = &args.features {
+        command.features(cargo_metadata::CargoOpt::SomeFeatures(
+            features.split(',').map(|v| v.trim().to_string()).collect(),
+        ));
     }
-}
-
-struct Repository {
-    data: Map<String, (Map<String, ConfigOption>, Vec<String>)>,
-    user_cfg: String,
-    path: Vec<String>,
-}
-
-impl Repository {
-    pub fn new(
-        data: Map<String, (Map<String, ConfigOption>, Vec<String>)>,
-        user_cfg: String,
-    ) -> Self {
-        Self {
-            data,
-            user_cfg,
-            path: Vec::new(),
-        }
+    if let Some(target) = &args.target {
+        command.other_options(vec![format!("--filter-platform={}", target)]);
     }
 
-    fn create_config(&self) -> String {
-        let mut out = String::new();
-
-        for (crate_name, (crate_config, crate_features)) in &self.data {
-            let crate_features: Vec<&str> =
-                crate_features.into_iter().map(|v| v.as_str()).collect();
-
-            let crate_config = rconfig::evaluate_config_str_to_cfg(
-                &self.user_cfg,
-                &crate_name,
-                crate_config.clone(),
-                crate_features.clone(),
-            )
-            .unwrap();
-
-            out.push_str(&format!("[{crate_name}]"));
-            out.push_str("\n");
-
-            let cfgs =
-                rconfig::current_config_values(crate_config, crate_features.clone()).unwrap();
-            for (name, value) in cfgs {
-                out.push_str(&format!("{name}={value}"));
-                out.push_str("\n");
-            }
-        }
-        out
-    }
+    let metadata = command.exec().unwrap_or_else(|err| {
+        eprintln!("`cargo metadata` failed: {}", err);
+        exit(1);
+    });
+    let Some(resolve) = metadata.resolve.as_ref() else {
+        eprintln!("`cargo metadata` didn't resolve the dependencies");
+        exit(1);
+    };
 
-    fn current(&self) -> Map<String, ConfigOption> {
-        let crate_name = &self.path[0];
-        let current = &(self.data[crate_name]).0;
-        let features = self.current_features();
-        let features = features.into_iter().map(|v| v.as_str()).collect();
-        let config = rconfig::evaluate_config_str_to_cfg(
-            &self.user_cfg,
-            &crate_name,
-            current.clone(),
+    let mut per_crate_configs: DiscoveredCrates = Vec::new();
+    for node in &resolve.nodes {
+        let package = &metadata[&node.id];
+        let definition = match &package.metadata["rconfig"]["definition"] {
+            serde_json::Value::String(definition) => package
+                .manifest_path
+                .parent()
+                .map(|dir| dir.join(definition))
+                .unwrap_or_else(|| definition.into()),
+            // inline in `Cargo.toml`
+            serde_json::Value::Object(_) => package.manifest_path.clone(),
+            _ => continue,
+        };
+
+        let text = rconfig::read_definitions(&[definition.as_std_path().to_path_buf()])
+            .map(|mut definitions| definitions.swap_remove(0))
+            .unwrap_or_else(|err| {
+                eprintln!("Can't read {}: {:?}", definition, err);
+                exit(1);
+            });
+        // the same as the build script tells
+        let crate_name =
+            rconfig::parse_section_name_str(&text, &package.name).unwrap_or_else(|err| {
+                eprintln!("Invalid config-definition {}: {:?}", definition, err);
+                exit(1);
+            });
+        let features = node
+            .features
+            .iter()
+            .map(|feature| feature.to_ascii_lowercase().replace('_', "-"))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let crate_definition = CrateDefinition {
+            crate_name,
+            definition: definition.to_string(),
             features,
-        )
-        .unwrap();
-
-        let mut current = &config;
-
-        for path_elem in &self.path[1..] {
-            current = current.get(path_elem).unwrap().options.as_ref().unwrap();
-        }
-        current.clone()
+        };
+        let parsed = crate_definition.parse().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        });
+        per_crate_configs.push((crate_definition, parsed));
     }
 
-    fn current_features(&self) -> &Vec<String> {
-        &(self.data[&self.path[0]]).1
+    if per_crate_configs.is_empty() {
+        eprintln!("No crate has `package.metadata.rconfig.definition` in its `Cargo.toml`");
+        exit(1);
     }
 
-    pub fn get_current_level(&self) -> Vec<String> {
-        let mut res = Vec::new();
+    per_crate_configs
+}
 
-        if self.path.is_empty() {
-            for (item, _) in &self.data {
-                res.push(item.to_string());
-            }
-        } else {
-            let current = self.current();
-            for (item, _) in current {
-                res.push(item.to_string());
-            }
-        }
+fn load_repository(per_crate_configs: DiscoveredCrates, input: String) -> Repository {
+    Repository::load_parsed(per_crate_configs, input).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    })
+}
 
-        res
-    }
+/// Read and parse a config-definition, exits if that fails
+fn read_definition(path: &std::path::Path) -> Map<String, rconfig::ConfigOption> {
+    let definitions = rconfig::read_definitions(&[path.to_path_buf()]).unwrap_or_else(|err| {
+        eprintln!("Can't read {}: {:?}", path.display(), err);
+        exit(1);
+    });
+    let definitions: Vec<&str> = definitions.iter().map(String::as_str).collect();
+    rconfig::parse_definitions_str(&definitions).unwrap_or_else(|err| {
+        eprintln!("Invalid config-definition {}: {:?}", path.display(), err);
+        exit(1);
+    })
+}
 
-    pub fn get_current_level_desc(&self) -> Vec<String> {
-        let mut res = Vec::new();
+/// The `[constraints]` of a config-definition, exits if reading or parsing fails
+fn read_constraints(path: &std::path::Path) -> Map<String, rconfig::GlobalConstraint> {
+    let definition = std::fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("Can't read {}: {}", path.display(), err);
+        exit(1);
+    });
+    rconfig::parse_constraints_str(&definition).unwrap_or_else(|err| {
+        eprintln!("Invalid config-definition {}: {:?}", path.display(), err);
+        exit(1);
+    })
+}
 
-        if self.path.is_empty() {
-            for (item, _) in &self.data {
-                res.push(item.to_string());
+fn run_command(command: Commands, args: &Args) {
+    let (result, output) = match command {
+        Commands::Tree => {
+            let per_crate_configs = discover_crates(args);
+            let input = std::fs::read_to_string(config_path(args)).unwrap_or_default();
+            let repository = load_repository(per_crate_configs, input);
+            for section in repository.unknown_sections() {
+                eprintln!("`[{}]` in `config.toml` isn't used by any crate", section);
             }
-        } else {
-            let current = self.current();
-            for (_item, option) in current {
-                let values = &option.values;
-                let current_value = if let Some(value) = &option.__value {
-                    format!("({})", Self::display_value(value, values))
-                } else if let Some(value) = &option.default_value {
-                    format!("(DEFAULT = {})", Self::display_value(value, values))
-                } else {
-                    String::new()
-                };
-
-                res.push(
-                    format!("{} {}", option.description.to_string(), current_value).to_string(),
+            for (crate_name, unmet) in repository.unmet_constraints() {
+                eprintln!(
+                    "`{}` doesn't meet the constraint `{}`: {}",
+                    crate_name, unmet.name, unmet.message
                 );
             }
+            match repository.tree() {
+                Ok(tree) => (Ok(tree), None),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    exit(1);
+                }
+            }
         }
-
-        res
-    }
-
-    fn display_value(value: &rconfig::Value, values: &Option<Vec<rconfig::ValueItem>>) -> String {
-        if values.is_none() {
-            return value.to_string();
-        } else {
-            let display = values
-                .as_ref()
-                .unwrap()
-                .iter()
-                .find(|v| v.value == *value)
-                .unwrap();
-            return display.description.to_string();
+        Commands::Explain { key } => {
+            let per_crate_configs = discover_crates(args);
+            let input = std::fs::read_to_string(config_path(args)).unwrap_or_default();
+            let repository = load_repository(per_crate_configs, input);
+            match repository.explain(&key) {
+                Ok(explanation) => (Ok(explanation), None),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    exit(1);
+                }
+            }
         }
-    }
-
-    pub fn get_count(&self) -> usize {
-        if self.path.is_empty() {
-            self.data.len()
-        } else {
-            self.current().len()
+        Commands::Serve { port } => {
+            let per_crate_configs = discover_crates(args);
+            let cfg_path = config_path(args);
+            let input = std::fs::read_to_string(&cfg_path).unwrap_or_default();
+            let repository = load_repository(per_crate_configs, input);
+            if let Err(err) = serve::serve(repository, cfg_path, port) {
+                eprintln!("{}", err);
+                exit(1);
+            }
+            (Ok(String::new()), None)
         }
-    }
-
-    pub fn current_title(&self) -> String {
-        if self.path.is_empty() {
-            String::from("Root")
-        } else {
-            let mut title = self.path[0].clone();
-            let mut current = &(self.data[&self.path[0]]).0;
-            for path_elem in &self.path[1..] {
-                title = current.get(path_elem).unwrap().description.clone();
-                current = current.get(path_elem).unwrap().options.as_ref().unwrap();
+        Commands::Rpc => {
+            let per_crate_configs = discover_crates(args);
+            let cfg_path = config_path(args);
+            let input = std::fs::read_to_string(&cfg_path).unwrap_or_default();
+            let repository = load_repository(per_crate_configs, input);
+            if let Err(err) = rpc::run(repository, &cfg_path) {
+                eprintln!("{}", err);
+                exit(1);
             }
-            title
+            (Ok(String::new()), None)
         }
-    }
-
-    pub fn select(&mut self, select: usize) {
-        let next = self
-            .get_current_level()
-            .into_iter()
-            .enumerate()
-            .find(|(index, _value)| *index == select)
-            .unwrap()
-            .1;
-        self.path.push(next);
-    }
-
-    pub fn up(&mut self) {
-        if !self.path.is_empty() {
-            self.path.remove(self.path.len() - 1);
+        Commands::Schema { output } => {
+            let definitions = discover_crates(args);
+            let definitions: Vec<(&str, &Map<String, rconfig::ConfigOption>)> = definitions
+                .iter()
+                .map(|(crate_definition, definition)| {
+                    (crate_definition.crate_name.as_str(), definition)
+                })
+                .collect();
+            let schema = rconfig::generate_json_schema(&definitions);
+            (Ok(serde_json::to_string_pretty(&schema).unwrap()), output)
         }
-    }
-
-    pub fn is_value(&self, which: usize) -> bool {
-        if self.path.is_empty() {
-            false
-        } else {
-            let next = self
-                .get_current_level()
+        Commands::ExportKconfig { definition, output } => {
+            let definition = read_definition(&definition);
+            (
+                Ok(rconfig::kconfig::definition_to_kconfig(&definition)),
+                output,
+            )
+        }
+        Commands::Graph {
+            definition,
+            mermaid,
+            output,
+        } => {
+            let definition = read_definition(&definition);
+            let format = if mermaid {
+                rconfig::GraphFormat::Mermaid
+            } else {
+                rconfig::GraphFormat::Dot
+            };
+            (Ok(rconfig::dependency_graph(&definition, format)), output)
+        }
+        Commands::Matrix {
+            definition,
+            crate_name,
+            features,
+            limit,
+            output_dir,
+        } => {
+            let constraints = read_constraints(&definition);
+            let definition = read_definition(&definition);
+            let features: Vec<&str> = features
+                .split(',')
+                .map(str::trim)
+                .filter(|feature| !feature.is_empty())
+                .collect();
+            // the build fails for configurations not meeting the constraints
+            let configs: Vec<_> = rconfig::enumerate_configs(&definition, &features, limit)
                 .into_iter()
-                .enumerate()
-                .find(|(index, _value)| *index == which)
-                .unwrap()
-                .1;
-
-            self.current()
-                .get(&next)
-                .as_ref()
-                .unwrap()
-                .options
-                .is_none()
+                .filter(|config| {
+                    rconfig::unmet_constraints(&constraints, config, &features).is_empty()
+                })
+                .collect();
+
+            let mut out = String::new();
+            for (i, config) in configs.iter().enumerate() {
+                let toml = rconfig::config_to_toml_string(&crate_name, config);
+                if let Some(output_dir) = &output_dir {
+                    let path = output_dir.join(format!("config-{}.toml", i + 1));
+                    std::fs::create_dir_all(output_dir)
+                        .and_then(|_| std::fs::write(&path, toml))
+                        .unwrap_or_else(|err| {
+                            eprintln!("Can't write {}: {}", path.display(), err);
+                            exit(1);
+                        });
+                } else {
+                    if i > 0 {
+                        out.push('\n');
+                    }
+                    out.push_str(&format!("# configuration {} of {}\n", i + 1, configs.len()));
+                    out.push_str(&toml);
+                }
+            }
+            (Ok(out), None)
         }
-    }
-
-    pub fn get_option(&self, which: usize) -> Option<ConfigOption> {
-        if self.path.is_empty() {
-            None
-        } else {
-            let next = self
-                .get_current_level()
+        Commands::Upgrade { dry_run } => {
+            let crates: Vec<CrateDefinition> = discover_crates(args)
                 .into_iter()
-                .enumerate()
-                .find(|(index, _value)| *index == which)
-                .unwrap()
-                .1;
+                .map(|(crate_definition, _)| crate_definition)
+                .collect();
+            let cfg_path = config_path(args);
+            let input = std::fs::read_to_string(&cfg_path).unwrap_or_else(|err| {
+                eprintln!("Can't read {}: {}", cfg_path.display(), err);
+                exit(1);
+            });
+            let (upgraded, applied) =
+                rconfig_core::upgrade_config(&crates, &input).unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    exit(1);
+                });
+
+            let mut out = String::new();
+            for (crate_name, change) in &applied {
+                match (&change.to, &change.new_value) {
+                    (Some(to), Some(new_value)) => out.push_str(&format!(
+                        "{}.{} = {} -> {}.{} = {}\n",
+                        crate_name, change.from, change.old_value, crate_name, to, new_value
+                    )),
+                    _ => out.push_str(&format!(
+                        "{}.{} = {} removed\n",
+                        crate_name, change.from, change.old_value
+                    )),
+                }
+            }
 
-            Some((*self.current().get(&next).as_ref().unwrap()).clone())
+            if applied.is_empty() {
+                out.push_str("`config.toml` is up to date\n");
+            } else if !dry_run {
+                let backup = cfg_path.with_extension("toml.bak");
+                std::fs::copy(&cfg_path, &backup).expect("Unable to back up `config.toml`");
+                std::fs::write(&cfg_path, upgraded).expect("Unable to write `config.toml`");
+                out.push_str(&format!(
+                    "Upgraded `config.toml`, the previous version is in {}\n",
+                    backup.display()
+                ));
+            }
+            (Ok(out), None)
         }
-    }
-
-    pub fn set_value(
-        &mut self,
-        which: usize,
-        value: rconfig::Value,
-    ) -> core::result::Result<(), rconfig::Error> {
-        // check value against validation rule
-        let current = self.get_option(which).unwrap();
-        let crate_cfg = &(self.data[&self.path[0]]).0;
-        let features = self.current_features().iter().map(|s| s.as_str()).collect();
-        if !rconfig::is_value_valid(current.valid.clone(), &value, &crate_cfg, &features) {
-            return Err(rconfig::Error::InvalidConfigurationValue(
-                self.current_title(),
-            ));
+        Commands::ImportKconfig { kconfig, output } => {
+            let kconfig = std::fs::read_to_string(&kconfig).unwrap_or_else(|err| {
+                eprintln!("Can't read {}: {}", kconfig.display(), err);
+                exit(1);
+            });
+            (rconfig::kconfig::kconfig_to_definition(&kconfig), output)
         }
+    };
 
-        // find where to insert/update
-        let next = self
-            .get_current_level()
-            .into_iter()
-            .enumerate()
-            .find(|(index, _value)| *index == which)
-            .unwrap()
-            .1;
-
-        let mut cfg = basic_toml::from_str::<rconfig::Value>(&self.user_cfg).unwrap();
-
-        let crate_cfg = cfg.as_object_mut().unwrap().get_mut(&self.path[0]).unwrap();
-        let mut item = crate_cfg;
-        for path_elem in &self.path[1..] {
-            if !item
-                .as_object_mut()
-                .unwrap()
-                .contains_key(path_elem.as_str())
-            {
-                item.as_object_mut().unwrap().insert(
-                    path_elem.to_string(),
-                    rconfig::Value::Object(Default::default()),
-                );
+    match result {
+        Ok(result) => {
+            if let Some(output) = output {
+                std::fs::write(&output, result).unwrap_or_else(|err| {
+                    eprintln!("Can't write {}: {}", output.display(), err);
+                    exit(1);
+                });
+            } else {
+                print!("{}", result);
             }
-            item = item
-                .as_object_mut()
-                .unwrap()
-                .get_mut(path_elem.as_str())
-                .unwrap();
         }
-
-        if item.as_object_mut().unwrap().contains_key(&next) {
-            item.as_object_mut().unwrap().remove(&next);
+        Err(err) => {
+            eprintln!("{:?}", err);
+            exit(1);
         }
-        item.as_object_mut().unwrap().insert(next, value);
-
-        self.user_cfg = basic_toml::to_string(&cfg).unwrap();
+    }
+}
 
-        Ok(())
+fn ask_confirm(question: &str) -> bool {
+    println!("{}", question);
+    loop {
+        let mut input = [0];
+        let _ = std::io::stdin().read(&mut input);
+        match input[0] as char {
+            'y' | 'Y' => return true,
+            'n' | 'N' => return false,
+            _ => (),
+        }
     }
 }
 
-const TODO_HEADER_BG: Color = tailwind::BLUE.c950;
-const NORMAL_ROW_COLOR: Color = tailwind::SLATE.c950;
-const SELECTED_STYLE_FG: Color = tailwind::BLUE.c300;
-const TEXT_COLOR: Color = tailwind::SLATE.c200;
+const READ_ONLY_STATUS: &str = "Read-only mode - editing and saving is disabled";
+
+const STATUS_DURATION: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// The colors of the TUI
+struct Theme {
+    header_bg: Color,
+    row_bg: Color,
+    selected_fg: Color,
+    text: Color,
+    added: Color,
+    removed: Color,
+    error: Color,
+    input_fg: Color,
+    input_bg: Color,
+}
+
+const DEFAULT_THEME: Theme = Theme {
+    header_bg: tailwind::BLUE.c950,
+    row_bg: tailwind::SLATE.c950,
+    selected_fg: tailwind::BLUE.c300,
+    text: tailwind::SLATE.c200,
+    added: Color::Green,
+    removed: Color::Red,
+    error: Color::Red,
+    input_fg: Color::Black,
+    input_bg: Color::Gray,
+};
+
+/// Black and white only - diffs are still marked by `+` and `-`, invalid input is underlined
+const HIGH_CONTRAST_THEME: Theme = Theme {
+    header_bg: Color::Black,
+    row_bg: Color::Black,
+    selected_fg: Color::White,
+    text: Color::White,
+    added: Color::White,
+    removed: Color::White,
+    error: Color::Black,
+    input_fg: Color::Black,
+    input_bg: Color::White,
+};
 
 fn init_terminal() -> Result<Terminal<impl Backend>> {
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
+    stdout().execute(EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout());
     let terminal = Terminal::new(backend)?;
     Ok(terminal)
@@ -466,6 +834,7 @@ fn init_terminal() -> Result<Terminal<impl Backend>> {
 
 fn restore_terminal() -> Result<()> {
     disable_raw_mode()?;
+    stdout().execute(DisableBracketedPaste)?;
     stdout().execute(LeaveAlternateScreen)?;
     Ok(())
 }
@@ -473,7 +842,26 @@ fn restore_terminal() -> Result<()> {
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum InputMode {
     Number,
+    /// a number which can be negative
+    Signed,
+    /// a number with a unit like `KB`
+    Size,
+    Hex,
     Chars,
+    Charset(rconfig::Charset),
+}
+
+impl InputMode {
+    fn accepts(self, c: char) -> bool {
+        match self {
+            InputMode::Number => c.is_ascii_digit() || c == '_',
+            InputMode::Signed => c.is_ascii_digit() || c == '_' || c == '-',
+            InputMode::Size => c.is_ascii_digit() || "_ KkMGiB".contains(c),
+            InputMode::Hex => c.is_ascii_hexdigit() || c == 'x' || c == '_',
+            InputMode::Chars => true,
+            InputMode::Charset(charset) => charset.accepts(c),
+        }
+    }
 }
 
 struct App {
@@ -482,28 +870,80 @@ struct App {
 
     show_input: bool,
 
-    input: String,
+    input: TextInput,
     input_mode: InputMode,
-    cursor_position: usize,
 
     cursor: Option<(u16, u16)>,
 
     show_error: bool,
+
+    // (crate, state) of the feature preview popup
+    features_popup: Option<(String, ListState)>,
+
+    show_help: bool,
+
+    // transient message shown in the footer
+    status: Option<(String, std::time::Instant)>,
+
+    read_only: bool,
+
+    // the input of the goto prompt
+    goto: Option<String>,
+
+    config_path: std::path::PathBuf,
+
+    // (diff lines, scroll offset) of the pending changes popup
+    diff_popup: Option<(Vec<DiffLine>, usize)>,
+
+    // index of the kept entry currently asked about and the input when fixing it
+    repair: Option<(usize, Option<TextInput>)>,
+    repair_error: Option<String>,
+
+    // the options hidden in the current menu and why
+    why_popup: Option<Vec<(String, String, Vec<rconfig::UnmetDepends>)>>,
+
+    // modification time of `config.toml` as last seen and its content if it changed on disk
+    config_modified: Option<std::time::SystemTime>,
+    changed_on_disk: Option<String>,
+
+    theme: &'static Theme,
 }
 
 impl App {
-    fn new(repository: Repository) -> Self {
+    fn new(
+        repository: Repository,
+        read_only: bool,
+        config_path: std::path::PathBuf,
+        theme: &'static Theme,
+    ) -> Self {
         let mut initial_state = ListState::default();
         initial_state.select(Some(0));
+        let repair = if !read_only && !repository.kept_entries.is_empty() {
+            Some((0, None))
+        } else {
+            None
+        };
         Self {
             repository,
             state: initial_state,
             show_input: false,
-            input: "".to_string(),
+            input: TextInput::default(),
             input_mode: InputMode::Chars,
-            cursor_position: 0,
             cursor: None,
             show_error: false,
+            features_popup: None,
+            show_help: false,
+            status: None,
+            read_only,
+            goto: None,
+            diff_popup: None,
+            repair,
+            repair_error: None,
+            why_popup: None,
+            config_modified: modified_time(&config_path),
+            changed_on_disk: None,
+            config_path,
+            theme,
         }
     }
 }
@@ -513,13 +953,56 @@ impl App {
         loop {
             self.draw(&mut terminal)?;
 
-            if let Event::Key(key) = event::read()? {
+            // wake up from time to time to let transient status messages disappear
+            if !event::poll(std::time::Duration::from_millis(250))? {
+                if let Some((_, since)) = &self.status {
+                    if since.elapsed() > STATUS_DURATION {
+                        self.status = None;
+                    }
+                }
+                self.check_changed_on_disk();
+                continue;
+            }
+
+            let event = event::read()?;
+            if let Event::Key(key) = event {
                 if key.kind == KeyEventKind::Press {
                     use KeyCode::*;
 
-                    if !self.show_input {
+                    if self.changed_on_disk.is_some() {
+                        self.handle_changed_on_disk_key(key.code);
+                    } else if self.repair.is_some() {
+                        self.handle_repair_key(key);
+                    } else if let Some((lines, scroll)) = self.diff_popup.as_mut() {
+                        match key.code {
+                            Char('j') | Down if *scroll + 1 < lines.len() => *scroll += 1,
+                            Char('k') | Up => *scroll = scroll.saturating_sub(1),
+                            Esc | Char('q') | Char('d') => self.diff_popup = None,
+                            _ => (),
+                        }
+                    } else if self.goto.is_some() {
+                        self.handle_goto_key(key.code);
+                    } else if self.show_help {
+                        // any key closes the help
+                        self.show_help = false;
+                    } else if self.why_popup.is_some() {
+                        self.why_popup = None;
+                    } else if self.features_popup.is_some() {
+                        self.handle_features_popup_key(key.code);
+                    } else if !self.show_input {
                         match key.code {
                             Char('q') | Esc => return Ok(()),
+                            Char('s') | Char('S') if self.read_only => {
+                                self.set_status(READ_ONLY_STATUS);
+                            }
+                            Char('l') | Right | Enter
+                                if self.read_only
+                                    && self
+                                        .repository
+                                        .is_value(self.state.selected().unwrap_or_default()) =>
+                            {
+                                self.set_status(READ_ONLY_STATUS);
+                            }
                             Char('h') | Left => {
                                 self.repository.up();
                                 self.state.select(Some(0));
@@ -531,12 +1014,14 @@ impl App {
                                     let option = self.repository.get_option(selected);
                                     if let Some(option) = option {
                                         if let Some(value_type) = option.value_type {
+                                            let current = option
+                                                .__value
+                                                .clone()
+                                                .or_else(|| option.default_value.clone());
                                             if value_type == ValueType::Bool {
-                                                let current_value = option
-                                                    .__value
-                                                    .unwrap_or(option.default_value.unwrap())
-                                                    .as_bool()
-                                                    .unwrap();
+                                                let current_value = current
+                                                    .and_then(|value| value.as_bool())
+                                                    .unwrap_or_default();
                                                 self.repository
                                                     .set_value(
                                                         selected,
@@ -544,108 +1029,209 @@ impl App {
                                                     )
                                                     .ok();
                                             } else if value_type == ValueType::Enum {
-                                                let current_value = option
-                                                    .__value
-                                                    .unwrap_or(option.default_value.unwrap())
-                                                    .as_str()
-                                                    .unwrap()
-                                                    .to_owned();
-
-                                                let values = option.values.as_ref().unwrap();
-                                                let index = &values
-                                                    .into_iter()
-                                                    .enumerate()
-                                                    .find(|v| v.1.value == current_value)
-                                                    .unwrap()
-                                                    .0;
-                                                let index = (index + 1) % &values.len();
-
-                                                self.repository
-                                                    .set_value(
-                                                        selected,
-                                                        rconfig::Value::String(
-                                                            values[index].value.to_string(),
-                                                        ),
-                                                    )
-                                                    .ok();
+                                                let values =
+                                                    option.values.clone().unwrap_or_default();
+                                                // the first value if none is set yet
+                                                let index = values
+                                                    .iter()
+                                                    .position(|v| {
+                                                        current.as_ref().and_then(|c| c.as_str())
+                                                            == Some(&v.value)
+                                                    })
+                                                    .map_or(0, |index| (index + 1) % values.len());
+
+                                                if let Some(next) = values.get(index) {
+                                                    self.repository
+                                                        .set_value(
+                                                            selected,
+                                                            rconfig::Value::String(
+                                                                next.value.to_string(),
+                                                            ),
+                                                        )
+                                                        .ok();
+                                                }
                                             } else {
-                                                self.input_mode = if value_type == ValueType::U32 {
-                                                    InputMode::Number
-                                                } else {
-                                                    InputMode::Chars
+                                                let radix = self.repository.value_radix(selected);
+                                                // `b` is a hex digit, binary can be typed as well
+                                                let hex = radix != rconfig::Radix::Decimal;
+                                                self.input_mode = match value_type.integer_range() {
+                                                    None => option.charset.map_or(
+                                                        InputMode::Chars,
+                                                        InputMode::Charset,
+                                                    ),
+                                                    Some(_) if value_type == ValueType::Size => {
+                                                        InputMode::Size
+                                                    }
+                                                    Some(_) if hex => InputMode::Hex,
+                                                    Some((min, _)) if min < 0 => InputMode::Signed,
+                                                    Some(_) => InputMode::Number,
                                                 };
 
-                                                let default = if value_type == ValueType::U32 {
+                                                let default = if value_type.is_integer() {
                                                     Value::Number(0.into())
                                                 } else {
                                                     Value::String("".to_string())
                                                 };
 
                                                 self.show_input = true;
-                                                self.input = option
-                                                    .__value
-                                                    .as_ref()
-                                                    .unwrap_or(&default)
-                                                    .to_string(); // TODO: this formats strings as \"str\"
-                                                self.cursor_position = self.input.len()
+                                                let value =
+                                                    option.__value.as_ref().unwrap_or(&default);
+                                                self.input = TextInput::new(&match value {
+                                                    Value::Number(number)
+                                                        if hex && number.is_u64() =>
+                                                    {
+                                                        radix.format(number.as_u64().unwrap())
+                                                    }
+                                                    Value::String(text) => text.clone(),
+                                                    _ => value.to_string(),
+                                                })
+                                                .with_max_chars(option.max_length);
                                             }
                                         }
                                     }
-                                } else {
-                                    self.repository
-                                        .select(self.state.selected().unwrap_or_default());
+                                } else if self
+                                    .repository
+                                    .select(self.state.selected().unwrap_or_default())
+                                    .is_ok()
+                                {
                                     self.state.select(Some(0));
                                 }
                             }
-                            Char('j') | Down => {
-                                if self.state.selected().unwrap_or_default()
-                                    < self.repository.get_count() - 1
-                                {
-                                    self.state.select(Some(
-                                        self.state.selected().unwrap_or_default() + 1,
-                                    ));
+                            Char('j') | Down
+                                if self.state.selected().unwrap_or_default() + 1
+                                    < self.repository.get_count() =>
+                            {
+                                self.state
+                                    .select(Some(self.state.selected().unwrap_or_default() + 1));
+                            }
+                            Char('k') | Up if self.state.selected().unwrap_or_default() > 0 => {
+                                self.state
+                                    .select(Some(self.state.selected().unwrap_or_default() - 1));
+                            }
+                            Char('f') => {
+                                let selected = self.state.selected().unwrap_or_default();
+                                if let Some(crate_name) = self.repository.current_crate(selected) {
+                                    let mut state = ListState::default();
+                                    state.select(Some(0));
+                                    self.features_popup = Some((crate_name, state));
+                                }
+                            }
+                            Char('?') => self.show_help = true,
+                            Char('w') => match self.repository.hidden_options() {
+                                Ok(hidden) if hidden.is_empty() => {
+                                    self.set_status("No hidden options in this menu")
+                                }
+                                Ok(hidden) => self.why_popup = Some(hidden),
+                                Err(err) => self.set_status(&err),
+                            },
+                            Char('r') if !self.read_only => {
+                                if self.repository.kept_entries.is_empty() {
+                                    self.set_status("No invalid entries in `config.toml`");
+                                } else {
+                                    self.repair = Some((0, None));
+                                }
+                            }
+                            Char('g') => self.goto = Some(String::new()),
+                            Char('d') => {
+                                let old = self.repository.redact(&self.repository.original_cfg);
+                                let new = self.repository.redact(&self.repository.create_config());
+                                match old.and_then(|old| Ok((old, new?))) {
+                                    Ok((old, new)) => {
+                                        self.diff_popup = Some((diff_lines(&old, &new), 0))
+                                    }
+                                    Err(err) => self.set_status(&err),
+                                }
+                            }
+                            Char('+' | '-') | PageUp | PageDown if self.read_only => {
+                                self.set_status(READ_ONLY_STATUS);
+                            }
+                            code @ (Char('+' | '-') | PageUp | PageDown) => {
+                                let selected = self.state.selected().unwrap_or_default();
+                                let steps = match code {
+                                    Char('+') => 1,
+                                    Char('-') => -1,
+                                    PageUp => 10,
+                                    _ => -10,
+                                };
+                                match self.repository.step_value(selected, steps) {
+                                    Ok(_) => (),
+                                    Err(rconfig::Error::InvalidKey) => {
+                                        self.set_status("Only numbers can be stepped")
+                                    }
+                                    Err(_) => self.set_status("The value isn't valid"),
+                                }
+                            }
+                            Char('x') => {
+                                let selected = self.state.selected().unwrap_or_default();
+                                self.repository.toggle_hex(selected);
+                            }
+                            Char('X') => self.repository.toggle_hex_global(),
+                            Char('y') => {
+                                let selected = self.state.selected().unwrap_or_default();
+                                if let Some(key) = self.repository.selected_key(selected) {
+                                    self.copy(&key);
                                 }
                             }
-                            Char('k') | Up => {
-                                if self.state.selected().unwrap_or_default() > 0 {
-                                    self.state.select(Some(
-                                        self.state.selected().unwrap_or_default() - 1,
-                                    ));
+                            Char('Y') => {
+                                let selected = self.state.selected().unwrap_or_default();
+                                let option = self.repository.get_option(selected);
+                                let secret = option.as_ref().is_some_and(|option| option.secret);
+                                let value = option
+                                    .and_then(|option| option.__value.or(option.default_value));
+                                match value {
+                                    Some(_) if secret => {
+                                        self.set_status("Secret values aren't copied")
+                                    }
+                                    Some(Value::String(value)) => self.copy(&value),
+                                    Some(value) => self.copy(&value.to_string()),
+                                    None => self.set_status("No value to copy"),
                                 }
                             }
-                            Char('s') => {
-                                let cfg = self.repository.create_config();
-                                std::fs::write("./config.toml", cfg).unwrap();
-                                return Ok(());
+                            Char('o') => {
+                                let selected = self.state.selected().unwrap_or_default();
+                                let key =
+                                    self.repository.get_current_level().get(selected).cloned();
+                                let sort_mode = self.repository.toggle_sort_mode();
+
+                                // keep the selection on the same option
+                                if let Some(key) = key {
+                                    let selected = self
+                                        .repository
+                                        .get_current_level()
+                                        .iter()
+                                        .position(|item| *item == key);
+                                    self.state.select(selected.or(Some(0)));
+                                }
+                                self.set_status(&format!("Sort order: {}", sort_mode));
+                            }
+                            Char('e') => {
+                                let shown = self.repository.toggle_expert_mode();
+                                self.state.select(Some(0));
+                                self.set_status(if shown {
+                                    "Expert mode: hidden options are shown"
+                                } else {
+                                    "Hidden options are not shown"
+                                });
                             }
+                            Char('s') => match self.save() {
+                                Ok(()) => self.set_status("Saved `config.toml`"),
+                                Err(err) => self.set_status(&format!("Saving failed: {}", err)),
+                            },
+                            Char('S') => match self.save() {
+                                Ok(()) => return Ok(()),
+                                Err(err) => self.set_status(&format!("Saving failed: {}", err)),
+                            },
                             _ => {}
                         }
                     } else {
                         // input mode key handling
-                        // TODO can we use something like https://crates.io/crates/ratatui_input/ instead ?
-
                         self.show_error = false;
+                        let input_mode = self.input_mode;
                         match key.code {
                             Esc => {
                                 self.show_input = false;
                                 self.cursor = None;
                             }
-                            Backspace => {
-                                if self.cursor_position > 0 {
-                                    self.input.remove(self.cursor_position - 1);
-                                    self.cursor_position -= 1;
-                                }
-                            }
-                            Left => {
-                                if self.cursor_position > 0 {
-                                    self.cursor_position -= 1;
-                                }
-                            }
-                            Right => {
-                                if self.cursor_position < self.input.len() {
-                                    self.cursor_position += 1;
-                                }
-                            }
                             Enter => {
                                 let selected = self.state.selected().unwrap_or_default();
                                 if self.repository.is_value(selected) {
@@ -653,22 +1239,33 @@ impl App {
 
                                     let mut error = false;
                                     if let Some(option) = option {
-                                        match option.value_type {
-                                            Some(vt) => match vt {
-                                                ValueType::U32 => {
-                                                    let val = (self.input.parse::<u32>())
-                                                        .unwrap_or(u32::MAX);
-                                                    self.repository
-                                                        .set_value(
-                                                            selected,
-                                                            rconfig::Value::Number(val.into()),
-                                                        )
-                                                        .unwrap_or_else(|_| {
-                                                            error = true;
-                                                        });
+                                        if let Some(vt) = option.value_type {
+                                            match vt {
+                                                integer if integer.is_integer() => {
+                                                    match parse_value(&integer, self.input.value())
+                                                    {
+                                                        Some((val, radix)) => {
+                                                            self.repository
+                                                                .set_value(
+                                                                    selected,
+                                                                    rconfig::integer_value(val),
+                                                                )
+                                                                .unwrap_or_else(|_| {
+                                                                    error = true;
+                                                                });
+                                                            if !error {
+                                                                self.repository
+                                                                    .set_radix(selected, radix);
+                                                            }
+                                                        }
+                                                        None => error = true,
+                                                    }
                                                 }
-                                                ValueType::String => {
-                                                    let val = self.input.clone();
+                                                ValueType::String
+                                                | ValueType::Ipv4
+                                                | ValueType::Ipv6
+                                                | ValueType::Mac => {
+                                                    let val = self.input.value().to_string();
                                                     self.repository
                                                         .set_value(
                                                             selected,
@@ -679,8 +1276,7 @@ impl App {
                                                         });
                                                 }
                                                 _ => (),
-                                            },
-                                            None => (),
+                                            }
                                         }
                                     }
                                     if !error {
@@ -691,23 +1287,234 @@ impl App {
                                     }
                                 }
                             }
-                            KeyCode::Char(to_insert) => {
-                                if self.input_mode == InputMode::Chars {
-                                    self.input.insert(self.cursor_position, to_insert);
-                                    self.cursor_position += 1;
-                                } else if to_insert.is_numeric() {
-                                    self.input.insert(self.cursor_position, to_insert);
-                                    self.cursor_position += 1;
-                                }
+                            _ => {
+                                self.input.handle_key(key, |c| input_mode.accepts(c));
                             }
-                            _ => (),
                         }
                     }
                 }
+            } else if let Event::Paste(text) = event {
+                self.handle_paste(&text);
             }
         }
     }
 
+    fn handle_paste(&mut self, text: &str) {
+        if let Some((_, Some(input))) = self.repair.as_mut() {
+            input.insert_str(text, |_| true);
+        } else if let Some(goto) = self.goto.as_mut() {
+            goto.extend(text.chars().filter(|c| !c.is_whitespace()));
+        } else if self.show_input {
+            let input_mode = self.input_mode;
+            self.input.insert_str(text, |c| input_mode.accepts(c));
+        }
+    }
+
+    fn goto_candidates(&self) -> Vec<String> {
+        let input = self.goto.as_deref().unwrap_or_default();
+        self.repository
+            .all_keys()
+            .into_iter()
+            .filter(|key| key.starts_with(input))
+            .collect()
+    }
+
+    fn handle_goto_key(&mut self, key: KeyCode) {
+        let Some(input) = self.goto.as_mut() else {
+            return;
+        };
+
+        match key {
+            KeyCode::Esc => self.goto = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Tab => {
+                // complete to the longest common prefix of all candidates
+                let candidates = self.goto_candidates();
+                if let Some(common) = common_prefix(&candidates) {
+                    self.goto = Some(common.to_string());
+                }
+            }
+            KeyCode::Enter => {
+                let key = input.clone();
+                let path = self.repository.path.clone();
+                match self.repository.goto(&key) {
+                    Some(selected) => {
+                        self.state.select(Some(selected));
+                        self.goto = None;
+                    }
+                    None => {
+                        self.repository.path = path;
+                        self.set_status(&format!("Unknown key `{}`", key));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_repair_key(&mut self, key: KeyEvent) {
+        let Some((index, input)) = self.repair.as_mut() else {
+            return;
+        };
+        let index = *index;
+
+        if let Some(text) = input {
+            self.repair_error = None;
+            match key.code {
+                KeyCode::Esc => *input = None,
+                KeyCode::Enter => {
+                    let text = text.value().to_string();
+                    match self.repository.fix_entry(index, &text) {
+                        Ok(()) => self.repair = Some((index, None)),
+                        Err(err) => self.repair_error = Some(err),
+                    }
+                }
+                _ => {
+                    text.handle_key(key, |_| true);
+                }
+            }
+        } else {
+            match key.code {
+                KeyCode::Char('k') => self.repair = Some((index + 1, None)),
+                KeyCode::Char('K') | KeyCode::Esc => {
+                    self.repair = Some((self.repository.kept_entries.len(), None))
+                }
+                KeyCode::Char('d') => {
+                    self.repository.kept_entries.remove(index);
+                }
+                KeyCode::Char('f') => {
+                    let entry = &self.repository.kept_entries[index].1;
+                    // secrets aren't shown, they are entered again
+                    *input = Some(TextInput::new(&if self.repository.is_secret_entry(index) {
+                        format!("{} = ", entry.key)
+                    } else {
+                        format!("{} = {}", entry.key, entry.value)
+                    }));
+                }
+                _ => (),
+            }
+        }
+
+        if matches!(self.repair, Some((index, _)) if index >= self.repository.kept_entries.len()) {
+            self.repair = None;
+        }
+    }
+
+    fn save(&mut self) -> std::io::Result<()> {
+        self.repository.save(&self.config_path)?;
+        self.config_modified = modified_time(&self.config_path);
+        Ok(())
+    }
+
+    fn check_changed_on_disk(&mut self) {
+        let modified = modified_time(&self.config_path);
+        if modified == self.config_modified {
+            return;
+        }
+        self.config_modified = modified;
+
+        // e.g. a `git checkout` touching but not changing the file
+        let Ok(disk_cfg) = std::fs::read_to_string(&self.config_path) else {
+            return;
+        };
+        if disk_cfg == self.repository.original_cfg {
+            return;
+        }
+
+        if basic_toml::from_str::<Value>(&disk_cfg).is_err() {
+            self.set_status("`config.toml` changed on disk but isn't valid TOML");
+            return;
+        }
+        self.changed_on_disk = Some(disk_cfg);
+    }
+
+    fn handle_changed_on_disk_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char('r') => {
+                let disk_cfg = self.changed_on_disk.take().unwrap();
+                let conflicts = match self.repository.reload(disk_cfg) {
+                    Ok(conflicts) => conflicts,
+                    Err(err) => {
+                        self.set_status(&err);
+                        return;
+                    }
+                };
+
+                // the current menu might not exist anymore
+                self.repository
+                    .path
+                    .truncate(1.min(self.repository.path.len()));
+                self.state.select(Some(0));
+                self.show_input = false;
+                self.goto = None;
+                self.features_popup = None;
+                self.repair = if !self.read_only && !self.repository.kept_entries.is_empty() {
+                    Some((0, None))
+                } else {
+                    None
+                };
+
+                if conflicts == 0 {
+                    self.set_status("Reloaded `config.toml`");
+                } else {
+                    self.set_status(&format!(
+                        "Reloaded `config.toml`, kept this session's values for {} options changed on disk",
+                        conflicts
+                    ));
+                }
+            }
+            KeyCode::Char('i') | KeyCode::Esc => self.changed_on_disk = None,
+            _ => (),
+        }
+    }
+
+    fn copy(&mut self, text: &str) {
+        match clipboard::copy(text) {
+            Ok(()) => self.set_status(&format!("Copied `{}`", text)),
+            Err(err) => self.set_status(&format!("Copying failed: {}", err)),
+        }
+    }
+
+    fn set_status(&mut self, status: &str) {
+        self.status = Some((status.to_string(), std::time::Instant::now()));
+    }
+
+    fn handle_features_popup_key(&mut self, key: KeyCode) {
+        let Some((crate_name, state)) = self.features_popup.as_mut() else {
+            return;
+        };
+        let features = self.repository.available_features(crate_name);
+        let selected = state.selected().unwrap_or_default();
+
+        match key {
+            KeyCode::Esc | KeyCode::Char('f') | KeyCode::Char('q') => {
+                self.features_popup = None;
+            }
+            KeyCode::Char('j') | KeyCode::Down if selected + 1 < features.len() => {
+                state.select(Some(selected + 1));
+            }
+            KeyCode::Char('k') | KeyCode::Up if selected > 0 => {
+                state.select(Some(selected - 1));
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                if let Some(feature) = features.get(selected) {
+                    let crate_name = crate_name.clone();
+                    self.repository.toggle_preview_feature(&crate_name, feature);
+
+                    // the current menu might not be available with the new features
+                    self.repository
+                        .path
+                        .truncate(1.min(self.repository.path.len()));
+                    self.state.select(Some(0));
+                }
+            }
+            _ => (),
+        }
+    }
+
     fn draw(&mut self, terminal: &mut Terminal<impl Backend>) -> io::Result<()> {
         let cursor = self.cursor;
 
@@ -738,30 +1545,282 @@ impl Widget for &mut App {
         let vertical = Layout::vertical([Constraint::Percentage(100)]);
         let [upper_item_list_area] = vertical.areas(rest_area);
 
-        render_title(header_area, buf);
+        let selected = self.state.selected().unwrap_or_default();
+        let features_line = match self.repository.current_crate(selected) {
+            Some(crate_name) => {
+                let features = self
+                    .repository
+                    .crate_features(&crate_name)
+                    .iter()
+                    .filter(|f| !f.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if self.repository.is_preview(&crate_name) {
+                    format!("features: {} (PREVIEW - not used for the build)", features)
+                } else {
+                    format!("features: {}", features)
+                }
+            }
+            None => String::new(),
+        };
+
+        let features_line = if self.read_only {
+            format!("READ-ONLY  {}", features_line)
+        } else {
+            features_line
+        };
+        render_title(header_area, buf, features_line);
         self.render_item(upper_item_list_area, buf);
-        render_footer(footer_area, buf);
+        // unmet constraints are shown until they are met
+        let unmet = self
+            .repository
+            .unmet_constraints()
+            .first()
+            .map(|(crate_name, unmet)| {
+                format!(
+                    "`{}` doesn't meet the constraint `{}`: {}",
+                    crate_name, unmet.name, unmet.message
+                )
+            });
+        render_footer(
+            footer_area,
+            buf,
+            self.status
+                .as_ref()
+                .map(|(status, _)| status.as_str())
+                .or(unmet.as_deref()),
+        );
+
+        if let Some((crate_name, state)) = self.features_popup.as_mut() {
+            let active = self.repository.crate_features(crate_name);
+            let items: Vec<ListItem> = self
+                .repository
+                .available_features(crate_name)
+                .into_iter()
+                .map(|feature| {
+                    let mark = if active.contains(&feature) { "x" } else { " " };
+                    ListItem::new(format!("[{}] {}", mark, feature))
+                })
+                .collect();
+
+            let area = centered_rect(50, 60, area);
+            Clear.render(area, buf);
+            let list = List::new(items)
+                .block(
+                    Block::bordered()
+                        .title(format!("Preview features of {}", crate_name))
+                        .title_bottom("space: toggle, esc: close"),
+                )
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .highlight_symbol(">");
+            StatefulWidget::render(list, area, buf, state);
+        }
+
+        if let Some(input) = &self.goto {
+            let candidates = self.goto_candidates();
+
+            let mut area = centered_rect(60, 60, area);
+            let inner_height = area.height.saturating_sub(3) as usize;
+            let mut lines = vec![Line::from(format!("> {}", input)).bold()];
+            lines.extend(
+                candidates
+                    .iter()
+                    .take(inner_height)
+                    .map(|c| Line::from(c.to_string())),
+            );
+            area.height = area.height.min(lines.len() as u16 + 2);
+
+            Clear.render(area, buf);
+            Paragraph::new(lines)
+                .block(
+                    Block::bordered()
+                        .title("Go to")
+                        .title_bottom("tab: complete, enter: go, esc: cancel"),
+                )
+                .render(area, buf);
+            self.cursor = Some((area.x + 3 + input.chars().count() as u16, area.y + 1));
+        } else if !self.show_input {
+            self.cursor = None;
+        }
+
+        if let Some((lines, scroll)) = &self.diff_popup {
+            let changed = lines.iter().any(|line| !matches!(line, DiffLine::Same(_)));
+            let lines: Vec<Line> = if changed {
+                lines
+                    .iter()
+                    .skip(*scroll)
+                    .map(|line| match line {
+                        DiffLine::Same(text) => Line::from(format!("  {}", text)),
+                        DiffLine::Added(text) => {
+                            Line::from(format!("+ {}", text)).fg(self.theme.added)
+                        }
+                        DiffLine::Removed(text) => {
+                            Line::from(format!("- {}", text)).fg(self.theme.removed)
+                        }
+                    })
+                    .collect()
+            } else {
+                vec![Line::from("No changes")]
+            };
+
+            let area = centered_rect(80, 80, area);
+            Clear.render(area, buf);
+            Paragraph::new(lines)
+                .block(
+                    Block::bordered()
+                        .title(format!("Pending changes to {}", self.config_path.display()))
+                        .title_bottom("↓↑: scroll, esc: close"),
+                )
+                .render(area, buf);
+        }
+
+        if self.show_help {
+            render_help(area, buf);
+        }
+
+        if let Some(hidden) = &self.why_popup {
+            let mut lines = Vec::new();
+            for (description, key, unmet) in hidden {
+                lines.push(Line::from(format!("{} ({})", description, key)).bold());
+                for unmet in unmet {
+                    lines.push(Line::from(format!(
+                        "  `{}` needs `{}`",
+                        unmet.key, unmet.depends
+                    )));
+                    if !unmet.missing_features.is_empty() {
+                        lines.push(Line::from(format!(
+                            "    features not active: {}",
+                            unmet.missing_features.join(", ")
+                        )));
+                    }
+                    if !unmet.disabled_options.is_empty() {
+                        lines.push(Line::from(format!(
+                            "    options not enabled: {}",
+                            unmet.disabled_options.join(", ")
+                        )));
+                    }
+                }
+            }
+
+            let mut area = centered_rect(80, 80, area);
+            area.height = area.height.min(lines.len() as u16 + 2);
+            Clear.render(area, buf);
+            Paragraph::new(lines)
+                .block(
+                    Block::bordered()
+                        .title("Hidden options")
+                        .title_bottom("press any key to close"),
+                )
+                .render(area, buf);
+        }
+
+        if let Some((index, input)) = &self.repair {
+            let (crate_name, entry) = &self.repository.kept_entries[*index];
+            let reason = match entry.kind {
+                rconfig::ConfigProblemKind::UnknownKey => "The key doesn't exist",
+                rconfig::ConfigProblemKind::NotApplicable => {
+                    "The option isn't available with the current features and values"
+                }
+                rconfig::ConfigProblemKind::InvalidValue => "The value isn't valid",
+            };
+
+            let value = if self.repository.is_secret_entry(*index) {
+                rconfig::REDACTED.to_string()
+            } else {
+                entry.value.to_string()
+            };
+            let mut lines = vec![
+                Line::from(format!("[{}]", crate_name)),
+                Line::from(format!("{} = {}", entry.key, value)).bold(),
+                Line::from(""),
+                Line::from(reason),
+            ];
+            if let Some(text) = input {
+                lines.push(Line::from(""));
+                lines.push(Line::from(format!("> {}", text.value())).bold());
+            }
+            if let Some(err) = &self.repair_error {
+                lines.push(
+                    Line::from(err.to_string())
+                        .fg(self.theme.error)
+                        .underlined(),
+                );
+            }
+
+            let mut area = centered_rect(70, 60, area);
+            area.height = area.height.min(lines.len() as u16 + 2);
+            Clear.render(area, buf);
+            Paragraph::new(lines)
+                .block(
+                    Block::bordered()
+                        .title(format!(
+                            "Invalid entry in `config.toml` ({}/{})",
+                            index + 1,
+                            self.repository.kept_entries.len()
+                        ))
+                        .title_bottom(if input.is_some() {
+                            "enter: apply, esc: cancel"
+                        } else {
+                            "k: keep, K: keep all, f: fix, d: delete"
+                        }),
+                )
+                .render(area, buf);
+
+            if let Some(text) = input {
+                self.cursor = Some((area.x + 3 + text.cursor_column(), area.y + 6));
+            }
+        }
 
         if self.show_input {
-            let block = Block::bordered().title("Value");
+            let option = self
+                .repository
+                .get_option(self.state.selected().unwrap_or_default());
+            let block = Block::bordered().title(value_title(option.as_ref()));
             let mut area = centered_rect(60, 20, area);
             area.height = 3;
             block.render(area, buf);
 
-            let text = Text::from(Line::from(self.input.clone())).patch_style(
-                Style::default().bg(Color::Gray).fg(if self.show_error {
-                    Color::Red
-                } else {
-                    Color::Black
-                }),
-            );
-            area.y = area.y + area.height / 2;
-            area.x = area.x + 2;
-            area.width = area.width - 4;
+            let shown = if option.as_ref().is_some_and(|option| option.secret) {
+                "*".repeat(self.input.value().chars().count())
+            } else {
+                self.input.value().to_string()
+            };
+            let style = if self.show_error {
+                Style::default()
+                    .fg(self.theme.error)
+                    .add_modifier(Modifier::UNDERLINED)
+            } else {
+                Style::default().fg(self.theme.input_fg)
+            };
+            let text = Text::from(Line::from(shown)).patch_style(style.bg(self.theme.input_bg));
+            area.y += area.height / 2;
+            area.x += 2;
+            area.width -= 4;
             area.height = 1;
             text.render(area, buf);
 
-            self.cursor = Some((area.x + self.cursor_position as u16, area.y));
+            self.cursor = Some((area.x + self.input.cursor_column(), area.y));
+        }
+
+        if self.changed_on_disk.is_some() {
+            let lines = vec![
+                Line::from("`config.toml` was changed outside of the TUI."),
+                Line::from(""),
+                Line::from("r: reload it, keeping the changes made in this session"),
+                Line::from("i: ignore, saving overwrites the file"),
+            ];
+
+            let mut area = centered_rect(60, 60, area);
+            area.height = area.height.min(lines.len() as u16 + 2);
+            Clear.render(area, buf);
+            Paragraph::new(lines)
+                .block(
+                    Block::bordered()
+                        .title("Changed on disk")
+                        .title_bottom("r: reload, i: ignore"),
+                )
+                .render(area, buf);
         }
     }
 }
@@ -771,14 +1830,14 @@ impl App {
         // We create two blocks, one is for the header (outer) and the other is for list (inner).
         let outer_block = Block::default()
             .borders(Borders::NONE)
-            .fg(TEXT_COLOR)
-            .bg(TODO_HEADER_BG)
+            .fg(self.theme.text)
+            .bg(self.theme.header_bg)
             .title(self.repository.current_title())
             .title_alignment(Alignment::Center);
         let inner_block = Block::default()
             .borders(Borders::NONE)
-            .fg(TEXT_COLOR)
-            .bg(NORMAL_ROW_COLOR);
+            .fg(self.theme.text)
+            .bg(self.theme.row_bg);
 
         // We get the inner area from outer_block. We'll use this area later to render the table.
         let outer_area = area;
@@ -792,7 +1851,7 @@ impl App {
             .repository
             .get_current_level_desc()
             .into_iter()
-            .map(|v| ListItem::new(v))
+            .map(ListItem::new)
             .collect();
 
         // Create a List from all list items and highlight the currently selected one
@@ -802,7 +1861,7 @@ impl App {
                 Style::default()
                     .add_modifier(Modifier::BOLD)
                     .add_modifier(Modifier::REVERSED)
-                    .fg(SELECTED_STYLE_FG),
+                    .fg(self.theme.selected_fg),
             )
             .highlight_symbol(">")
             .highlight_spacing(HighlightSpacing::Always);
@@ -814,21 +1873,187 @@ impl App {
     }
 }
 
-fn render_title(area: Rect, buf: &mut Buffer) {
-    Paragraph::new("rconfig")
-        .bold()
+fn render_title(area: Rect, buf: &mut Buffer, features: String) {
+    Paragraph::new(vec![Line::from("rconfig").bold(), Line::from(features)])
         .centered()
         .render(area, buf);
 }
 
-fn render_footer(area: Rect, buf: &mut Buffer) {
-    Paragraph::new(
-        "\nUse ↓↑ to move, ← to go up, → to go deeper or change the value, s/S to save and exit",
-    )
+fn render_footer(area: Rect, buf: &mut Buffer, status: Option<&str>) {
+    Paragraph::new(vec![
+        Line::from(status.unwrap_or_default().to_string()).bold(),
+        Line::from(
+            "Use ↓↑ to move, ← to go up, → to go deeper or change the value, s to save, S to save and exit, ? for help",
+        ),
+    ])
     .centered()
     .render(area, buf);
 }
 
+const KEYBINDINGS: &[(&str, &str)] = &[
+    ("Navigation", ""),
+    ("↓ / j", "move down"),
+    ("↑ / k", "move up"),
+    ("← / h", "go up one level"),
+    ("→ / l / Enter", "go deeper or change the value"),
+    ("w", "explain why options of this menu are hidden"),
+    (
+        "o",
+        "sort by definition order, alphabetical or modified first",
+    ),
+    ("Editing", ""),
+    (
+        "→ / l / Enter",
+        "toggle bools, cycle enums, edit numbers and strings",
+    ),
+    ("+ / -", "increase / decrease a number by its step"),
+    ("PgUp / PgDn", "the same by 10 steps"),
+    ("e", "expert mode - show the hidden options"),
+    ("x", "toggle hex display of the selected option"),
+    ("X", "toggle hex display of all options"),
+    ("f", "preview the options with different features"),
+    ("g", "go to a dotted key (tab completes)"),
+    ("y", "copy the dotted key of the selected option"),
+    ("Y", "copy the value of the selected option"),
+    ("Value input", ""),
+    ("Home / End", "jump to the start / end"),
+    ("Ctrl-← / Ctrl-→", "move by words"),
+    ("Ctrl-W", "delete the previous word"),
+    ("Enter", "accept the value"),
+    ("Esc", "cancel"),
+    ("Saving", ""),
+    ("d", "show the pending changes to `config.toml`"),
+    ("r", "repair the invalid entries of `config.toml`"),
+    ("s", "save"),
+    ("S", "save and exit"),
+    ("q / Esc", "exit without saving"),
+    ("", ""),
+    ("?", "show this help"),
+];
+
+fn render_help(area: Rect, buf: &mut Buffer) {
+    let lines: Vec<Line> = KEYBINDINGS
+        .iter()
+        .map(|(keys, description)| {
+            if description.is_empty() {
+                Line::from(keys.to_string()).bold()
+            } else {
+                Line::from(format!("  {:<16} {}", keys, description))
+            }
+        })
+        .collect();
+
+    let mut area = centered_rect(70, 80, area);
+    area.height = area.height.min(lines.len() as u16 + 2);
+    Clear.render(area, buf);
+    Paragraph::new(lines)
+        .block(
+            Block::bordered()
+                .title("Help")
+                .title_bottom("press any key to close"),
+        )
+        .render(area, buf);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// The longest common prefix of the strings - `None` if there are none
+fn common_prefix(candidates: &[String]) -> Option<&str> {
+    let first = candidates.first()?;
+    let mut common = first.len();
+    for candidate in &candidates[1..] {
+        // a byte offset, the keys and descriptions aren't necessarily ASCII
+        let differs_at = first
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(first.len().min(candidate.len()), |((index, _), _)| index);
+        common = common.min(differs_at);
+    }
+    Some(&first[..common])
+}
+
+/// line based diff (longest common subsequence) - config files are small enough
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            result.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            result.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        } else {
+            result.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        }
+    }
+    result
+}
+
+/// "Value" with the constraints of the option, e.g. "Value (1-32 characters, ident)"
+fn value_title(option: Option<&rconfig::ConfigOption>) -> String {
+    let Some(option) = option else {
+        return "Value".to_string();
+    };
+
+    let constraints = rconfig::describe_constraints(option);
+
+    if constraints.is_empty() {
+        "Value".to_string()
+    } else {
+        format!("Value ({})", constraints.join(", "))
+    }
+}
+
+/// parse the input for an integer option, sizes can have a unit
+fn parse_value(value_type: &ValueType, input: &str) -> Option<(i128, rconfig::Radix)> {
+    match value_type {
+        ValueType::Size => {
+            rconfig::parse_size(input).map(|bytes| (bytes.into(), rconfig::Radix::Decimal))
+        }
+        _ => parse_number(input),
+    }
+}
+
+/// parse a (possibly negative) decimal or `0x` prefixed hexadecimal number, `_` can separate the
+/// digits - the range is checked when setting the value
+fn parse_number(input: &str) -> Option<(i128, rconfig::Radix)> {
+    let input = input.trim();
+    let input = match input.strip_prefix("0X") {
+        Some(hex) => format!("0x{}", hex),
+        None => input.to_string(),
+    };
+    rconfig::parse_signed_integer_literal(&input)
+}
+
+/// The modification time of `path` - `None` if it can't be read
+fn modified_time(path: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::vertical([
@@ -845,3 +2070,29 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     ])
     .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_prefixes() {
+        let candidates = |candidates: &[&str]| -> Vec<String> {
+            candidates.iter().map(|c| c.to_string()).collect()
+        };
+
+        assert_eq!(None, common_prefix(&[]));
+        assert_eq!(
+            Some("hal.psram."),
+            common_prefix(&candidates(&["hal.psram.size", "hal.psram.enable"]))
+        );
+        assert_eq!(
+            Some("hal.gr"),
+            common_prefix(&candidates(&["hal.größe", "hal.grün"]))
+        );
+        assert_eq!(
+            Some("hal.ä"),
+            common_prefix(&candidates(&["hal.äb", "hal.ä"]))
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! The configurator without the alternate screen and styling - it prints the current menu and
+//! reads one command per line, e.g. for screen readers, limited terminals or CI logs
+
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use rconfig::{Value, ValueType};
+use rconfig_core::Repository;
+
+const HELP: &str = "\
+Commands:
+  <n>          go into menu <n>, toggle a bool, cycle an enum or enter a new value
+  <n> <value>  set the value of option <n>
+  u            go up one level
+  e            show or hide the hidden options (expert mode)
+  l            list the current menu again
+  s            save `config.toml`
+  q            quit
+  ?            show this help";
+
+pub fn run(mut repository: Repository, config_path: &Path, read_only: bool) -> std::io::Result<()> {
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut stdout = std::io::stdout();
+
+    if !repository.kept_entries.is_empty() {
+        println!(
+            "{} invalid entries of `config.toml` are kept as they are, use `--fix` to remove them",
+            repository.kept_entries.len()
+        );
+    }
+    if read_only {
+        println!("Read-only mode - changing and saving is disabled");
+    }
+    println!("Enter ? for help");
+
+    print_level(&repository);
+    loop {
+        for (crate_name, unmet) in repository.unmet_constraints() {
+            println!(
+                "`{}` doesn't meet the constraint `{}`: {}",
+                crate_name, unmet.name, unmet.message
+            );
+        }
+        print!("> ");
+        stdout.flush()?;
+
+        let Some(line) = lines.next().transpose()? else {
+            // stdin was closed
+            println!();
+            return Ok(());
+        };
+        let line = line.trim();
+        let (command, argument) = match line.split_once(' ') {
+            Some((command, argument)) => (command, Some(argument.trim())),
+            None => (line, None),
+        };
+
+        match command {
+            "" => (),
+            "?" | "h" | "help" => println!("{}", HELP),
+            "l" => print_level(&repository),
+            "e" => {
+                if repository.toggle_expert_mode() {
+                    println!("Expert mode: hidden options are shown");
+                } else {
+                    println!("Hidden options are not shown");
+                }
+                print_level(&repository);
+            }
+            "u" | ".." => {
+                repository.up();
+                print_level(&repository);
+            }
+            "s" if read_only => println!("Read-only mode - saving is disabled"),
+            "s" => {
+                repository.save(config_path)?;
+                println!("Saved `config.toml`");
+            }
+            "q" => {
+                if !read_only
+                    && repository.create_config() != repository.original_cfg
+                    && ask(&mut lines, "Save the changes? (y/n)")?
+                {
+                    repository.save(config_path)?;
+                    println!("Saved `config.toml`");
+                }
+                return Ok(());
+            }
+            number => match number.parse::<usize>() {
+                Ok(n) if (1..=repository.get_count()).contains(&n) => {
+                    let which = n - 1;
+                    if !repository.is_value(which) {
+                        if repository.select(which).is_ok() {
+                            print_level(&repository);
+                        }
+                    } else if read_only {
+                        println!("Read-only mode - changing is disabled");
+                    } else {
+                        let argument = match argument {
+                            Some(argument) => Some(argument.to_string()),
+                            None => match prompt_value(&repository, which, &mut lines)? {
+                                // an empty line keeps the value
+                                Some(value) if value.is_empty() => continue,
+                                value => value,
+                            },
+                        };
+                        match change(&mut repository, which, argument.as_deref()) {
+                            Ok(()) => print_level(&repository),
+                            Err(err) => println!("{}", err),
+                        }
+                    }
+                }
+                _ => println!("Unknown command `{}`, enter ? for help", line),
+            },
+        }
+    }
+}
+
+fn print_level(repository: &Repository) {
+    println!();
+    println!("{}", repository.current_title());
+    for (i, item) in repository.get_current_level_desc().iter().enumerate() {
+        println!("{:>3}) {}", i + 1, item.trim_end());
+    }
+}
+
+/// The new value of a number or string option - `None` for the other types, empty if the user
+/// didn't enter anything
+fn prompt_value(
+    repository: &Repository,
+    which: usize,
+    lines: &mut impl Iterator<Item = std::io::Result<String>>,
+) -> std::io::Result<Option<String>> {
+    let Some(option) = repository.get_option(which) else {
+        return Ok(None);
+    };
+    match &option.value_type {
+        Some(ValueType::String | ValueType::Ipv4 | ValueType::Ipv6 | ValueType::Mac) => (),
+        Some(value_type) if value_type.is_integer() => (),
+        _ => return Ok(None),
+    }
+
+    print!("{}: ", super::value_title(Some(&option)));
+    std::io::stdout().flush()?;
+    Ok(Some(
+        lines
+            .next()
+            .transpose()?
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    ))
+}
+
+/// Set the value of the option - without a value bools are toggled and enums cycled
+pub(crate) fn change(
+    repository: &mut Repository,
+    which: usize,
+    value: Option<&str>,
+) -> Result<(), String> {
+    let option = repository
+        .get_option(which)
+        .ok_or_else(|| "Not an option".to_string())?;
+    let current = option.__value.clone().or(option.default_value.clone());
+
+    let value = match (option.value_type, value) {
+        (Some(ValueType::Bool), None) => Value::Bool(
+            !current
+                .and_then(|value| value.as_bool())
+                .unwrap_or_default(),
+        ),
+        (Some(ValueType::Bool), Some(value)) => Value::Bool(
+            value
+                .parse()
+                .map_err(|_| "Expected `true` or `false`".to_string())?,
+        ),
+        (Some(ValueType::Enum), None) => {
+            let values = option.values.unwrap_or_default();
+            let index = values
+                .iter()
+                .position(|v| current.as_ref().and_then(|c| c.as_str()) == Some(&v.value))
+                .map_or(0, |index| (index + 1) % values.len());
+            let Some(next) = values.get(index) else {
+                return Err("The option has no values".to_string());
+            };
+            Value::String(next.value.clone())
+        }
+        (Some(value_type), Some(value)) if value_type.is_integer() => {
+            let (number, radix) = super::parse_value(&value_type, value)
+                .ok_or_else(|| "Expected a number".to_string())?;
+            repository
+                .set_value(which, rconfig::integer_value(number))
+                .map_err(|_| "The value isn't valid".to_string())?;
+            repository.set_radix(which, radix);
+            return Ok(());
+        }
+        (
+            Some(
+                ValueType::Enum
+                | ValueType::String
+                | ValueType::Ipv4
+                | ValueType::Ipv6
+                | ValueType::Mac,
+            ),
+            Some(value),
+        ) => Value::String(value.to_string()),
+        _ => return Err("Enter a value".to_string()),
+    };
+
+    repository
+        .set_value(which, value)
+        .map_err(|_| "The value isn't valid".to_string())
+}
+
+fn ask(
+    lines: &mut impl Iterator<Item = std::io::Result<String>>,
+    question: &str,
+) -> std::io::Result<bool> {
+    loop {
+        print!("{} ", question);
+        std::io::stdout().flush()?;
+        match lines.next().transpose()?.as_deref().map(str::trim) {
+            Some("y" | "Y") => return Ok(true),
+            Some("n" | "N") | None => return Ok(false),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use rconfig::Value;
+
+use rconfig_core::Repository;
+
+/// Handle JSON-RPC 2.0 requests on stdin (one per line) until stdin is closed
+///
+/// Methods:
+/// - `config` - all crates with their available options
+/// - `option` `{crate, key}` - a single option or menu
+/// - `validate` `{crate, key, value}` - check a value without changing anything
+/// - `set` `{crate, key, value}` - set a value, `null` resets to the default
+/// - `pending` - the `config.toml` which would be saved, `secret` values masked
+/// - `save` - write `config.toml`
+pub fn run(mut repository: Repository, config_path: &Path) -> std::io::Result<()> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some(response) = respond(&mut repository, config_path, &line) else {
+            continue;
+        };
+        writeln!(stdout, "{}", response)?;
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
+
+/// The response to a line of input - `None` for notifications
+fn respond(repository: &mut Repository, config_path: &Path, line: &str) -> Option<Value> {
+    let request = match serde_json::from_str::<Value>(line) {
+        Ok(request) => request,
+        Err(err) => {
+            return Some(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": err.to_string() },
+            }))
+        }
+    };
+
+    let method = request["method"].as_str().unwrap_or_default();
+    let result = handle(repository, config_path, method, &request["params"]);
+
+    // notifications (without an id) don't get a response, `"id": null` does
+    let id = request.get("id")?;
+    Some(match result {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    })
+}
+
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+// implementation defined server error
+const INVALID_VALUE: i64 = -32000;
+
+fn handle(
+    repository: &mut Repository,
+    config_path: &Path,
+    method: &str,
+    params: &Value,
+) -> Result<Value, (i64, String)> {
+    match method {
+        "config" => Ok(repository.to_json()),
+        "option" => {
+            let (crate_name, key, _) = key_params(params)?;
+            option_json(&repository.to_json(), crate_name, key).ok_or((
+                INVALID_PARAMS,
+                format!("Unknown option `{}.{}`", crate_name, key),
+            ))
+        }
+        "validate" => {
+            let (crate_name, key, value) = key_params(params)?;
+            repository
+                .check_key(crate_name, key, value)
+                .map(|_| Value::Null)
+                .map_err(|err| (INVALID_VALUE, err))
+        }
+        "set" => {
+            let (crate_name, key, value) = key_params(params)?;
+            repository
+                .set_key(crate_name, key, value)
+                .map(|_| Value::Null)
+                .map_err(|err| (INVALID_VALUE, err))
+        }
+        "pending" => repository
+            .redact(&repository.create_config())
+            .map(Value::String)
+            .map_err(|err| (INVALID_VALUE, err)),
+        "save" => repository
+            .save(config_path)
+            .map(|_| Value::Null)
+            .map_err(|err| (INVALID_VALUE, err.to_string())),
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method `{}`", method))),
+    }
+}
+
+fn key_params(params: &Value) -> Result<(&str, &str, Option<Value>), (i64, String)> {
+    let (Some(crate_name), Some(key)) = (params["crate"].as_str(), params["key"].as_str()) else {
+        return Err((INVALID_PARAMS, "Expected `crate` and `key`".to_string()));
+    };
+
+    let value = match &params["value"] {
+        Value::Null => None,
+        value => Some(value.clone()),
+    };
+    Ok((crate_name, key, value))
+}
+
+fn option_json(config: &Value, crate_name: &str, key: &str) -> Option<Value> {
+    fn find(options: &Value, key: &str) -> Option<Value> {
+        for option in options.as_array()? {
+            if option["key"] == key {
+                return Some(option.clone());
+            }
+            if let Some(found) = find(&option["options"], key) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    let crate_config = config
+        .as_array()?
+        .iter()
+        .find(|config| config["crate"] == crate_name)?;
+    find(&crate_config["options"], key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    valid = "value <= 80000"
+    default = 4096
+    "#;
+
+    fn repository() -> Repository {
+        let mut data = linked_hash_map::LinkedHashMap::new();
+        data.insert(
+            "mycrate".to_string(),
+            (rconfig::parse_definition_str(DEFINITION).unwrap(), vec![]),
+        );
+        Repository::new(data, "[mycrate]\n".to_string(), "[mycrate]\n".to_string()).unwrap()
+    }
+
+    fn call(repository: &mut Repository, request: Value) -> Option<Value> {
+        respond(repository, Path::new("config.toml"), &request.to_string())
+    }
+
+    fn result(repository: &mut Repository, method: &str, params: Value) -> Value {
+        let request =
+            serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        call(repository, request).unwrap()
+    }
+
+    #[test]
+    fn config_and_option() {
+        let mut repository = repository();
+
+        let response = result(&mut repository, "config", Value::Null);
+        assert_eq!(1, response["id"]);
+        assert_eq!("mycrate", response["result"][0]["crate"]);
+
+        let option = serde_json::json!({ "crate": "mycrate", "key": "heap.size" });
+        let response = result(&mut repository, "option", option);
+        assert_eq!("heap.size", response["result"]["key"]);
+        assert_eq!(4096, response["result"]["default"]);
+    }
+
+    #[test]
+    fn validate_and_set() {
+        let mut repository = repository();
+
+        let invalid = serde_json::json!({ "crate": "mycrate", "key": "heap.size", "value": 90000 });
+        let response = result(&mut repository, "validate", invalid.clone());
+        assert_eq!(INVALID_VALUE, response["error"]["code"]);
+        let response = result(&mut repository, "set", invalid);
+        assert_eq!(INVALID_VALUE, response["error"]["code"]);
+
+        let valid = serde_json::json!({ "crate": "mycrate", "key": "heap.size", "value": 1024 });
+        let response = result(&mut repository, "validate", valid.clone());
+        assert_eq!(Value::Null, response["result"]);
+        assert_eq!("[mycrate]\n", repository.create_config());
+
+        let response = result(&mut repository, "set", valid);
+        assert_eq!(Value::Null, response["result"]);
+        assert_eq!("[mycrate]\nheap.size=1024\n", repository.create_config());
+    }
+
+    #[test]
+    fn errors() {
+        let mut repository = repository();
+
+        let response = result(&mut repository, "frobnicate", Value::Null);
+        assert_eq!(METHOD_NOT_FOUND, response["error"]["code"]);
+
+        let response = result(
+            &mut repository,
+            "set",
+            serde_json::json!({ "crate": "mycrate" }),
+        );
+        assert_eq!(INVALID_PARAMS, response["error"]["code"]);
+
+        let unknown = serde_json::json!({ "crate": "mycrate", "key": "heap.sise" });
+        let response = result(&mut repository, "option", unknown);
+        assert_eq!(INVALID_PARAMS, response["error"]["code"]);
+
+        let response = respond(&mut repository, Path::new("config.toml"), "{").unwrap();
+        assert_eq!(-32700, response["error"]["code"]);
+    }
+
+    #[test]
+    fn notifications() {
+        let mut repository = repository();
+
+        let set = serde_json::json!({ "crate": "mycrate", "key": "heap.size", "value": 1024 });
+        let notification = serde_json::json!({ "jsonrpc": "2.0", "method": "set", "params": set });
+        assert_eq!(None, call(&mut repository, notification));
+        assert_eq!("[mycrate]\nheap.size=1024\n", repository.create_config());
+
+        let null_id = serde_json::json!({ "jsonrpc": "2.0", "id": null, "method": "config" });
+        let response = call(&mut repository, null_id).unwrap();
+        assert_eq!(Value::Null, response["id"]);
+        assert_eq!("mycrate", response["result"][0]["crate"]);
+    }
+}
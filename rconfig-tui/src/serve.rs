@@ -0,0 +1,424 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rconfig::Value;
+
+use rconfig_core::Repository;
+
+const INDEX_HTML: &str = include_str!("serve.html");
+
+/// Requests (headers and body) larger than this are rejected
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+/// A client sending nothing doesn't block the server for longer than this
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve the web UI and its JSON API on localhost - handles one request at a time
+///
+/// Only requests for `127.0.0.1`, `localhost` or `[::1]` are answered (against DNS rebinding), on
+/// any port so the port can be forwarded to another one. Changes need the JSON content type and the token of the page (against other
+/// pages posting to the server).
+pub fn serve(mut repository: Repository, config_path: PathBuf, port: u16) -> std::io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Serving the configuration on http://127.0.0.1:{}/", port);
+
+    let server = Server {
+        token: session_token(),
+    };
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        // a broken connection shouldn't stop the server
+        if let Err(err) = server.handle_connection(&mut stream, &mut repository, &config_path) {
+            eprintln!("{}", err);
+        }
+    }
+
+    Ok(())
+}
+
+/// A random token for the session - changes need it
+fn session_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+
+    let random = || {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        hasher.write_u32(std::process::id());
+        hasher.finish()
+    };
+    format!("{:016x}{:016x}", random(), random())
+}
+
+struct Request {
+    method: String,
+    path: String,
+    /// The headers with lower-case names
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn read_request(stream: impl Read) -> std::io::Result<Request> {
+    let too_large =
+        || std::io::Error::new(std::io::ErrorKind::InvalidData, "The request is too large");
+
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_SIZE as u64));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            // the limit ends the request before the empty line
+            return Err(too_large());
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let name = name.trim().to_ascii_lowercase();
+            let value = value.trim().to_string();
+            if name == "content-length" {
+                content_length = value.parse().unwrap_or_default();
+            }
+            headers.push((name, value));
+        }
+    }
+
+    if content_length > MAX_REQUEST_SIZE {
+        return Err(too_large());
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+/// What the `Host` header can name, with any port
+const LOCAL_HOSTS: [&str; 3] = ["127.0.0.1", "localhost", "[::1]"];
+
+/// The host of a `Host` header without the port
+fn host_name(host: &str) -> &str {
+    match host.find(']') {
+        // `[::1]:7878`
+        Some(end) if host.starts_with('[') => &host[..=end],
+        _ => host.split(':').next().unwrap_or_default(),
+    }
+}
+
+struct Server {
+    token: String,
+}
+
+impl Server {
+    fn handle_connection(
+        &self,
+        stream: &mut TcpStream,
+        repository: &mut Repository,
+        config_path: &Path,
+    ) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let request = match read_request(&mut *stream) {
+            Ok(request) => request,
+            Err(err) => {
+                respond(
+                    stream,
+                    "400 Bad Request",
+                    "text/plain",
+                    &format!("Invalid request: {}", err),
+                )?;
+                return Err(err);
+            }
+        };
+
+        let (status, content_type, body) = self.response(&request, repository, config_path);
+        respond(stream, status, content_type, &body)
+    }
+
+    fn response(
+        &self,
+        request: &Request,
+        repository: &mut Repository,
+        config_path: &Path,
+    ) -> (&'static str, &'static str, String) {
+        if !request
+            .header("host")
+            .is_some_and(|host| LOCAL_HOSTS.contains(&host_name(host)))
+        {
+            return ("403 Forbidden", "text/plain", "Unknown host".to_string());
+        }
+        if request.method == "POST" {
+            let json = request.header("content-type").is_some_and(|content_type| {
+                content_type
+                    .split(';')
+                    .next()
+                    .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+            });
+            if !json || request.header("x-rconfig-token") != Some(self.token.as_str()) {
+                return (
+                    "403 Forbidden",
+                    "application/json",
+                    serde_json::json!({ "error": "Changes are only accepted from the page" })
+                        .to_string(),
+                );
+            }
+        }
+
+        handle_request(request, repository, config_path, &self.token)
+    }
+}
+
+fn handle_request(
+    request: &Request,
+    repository: &mut Repository,
+    config_path: &Path,
+    token: &str,
+) -> (&'static str, &'static str, String) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => (
+            "200 OK",
+            "text/html; charset=utf-8",
+            INDEX_HTML.replace("__RCONFIG_TOKEN__", token),
+        ),
+        ("GET", "/api/config") => (
+            "200 OK",
+            "application/json",
+            repository.to_json().to_string(),
+        ),
+        ("GET", "/api/pending") => match repository
+            .redact(&repository.original_cfg)
+            .and_then(|saved| Ok((saved, repository.redact(&repository.create_config())?)))
+        {
+            Ok((saved, pending)) => (
+                "200 OK",
+                "application/json",
+                serde_json::json!({
+                    "path": config_path.display().to_string(),
+                    "saved": saved,
+                    "pending": pending,
+                })
+                .to_string(),
+            ),
+            Err(err) => (
+                "500 Internal Server Error",
+                "application/json",
+                serde_json::json!({ "error": err }).to_string(),
+            ),
+        },
+        ("POST", "/api/set") => match set_value(repository, &request.body) {
+            Ok(()) => ("200 OK", "application/json", "{}".to_string()),
+            Err(err) => (
+                "400 Bad Request",
+                "application/json",
+                serde_json::json!({ "error": err }).to_string(),
+            ),
+        },
+        ("POST", "/api/save") => match repository.save(config_path) {
+            Ok(()) => ("200 OK", "application/json", "{}".to_string()),
+            Err(err) => (
+                "500 Internal Server Error",
+                "application/json",
+                serde_json::json!({ "error": err.to_string() }).to_string(),
+            ),
+        },
+        _ => ("404 Not Found", "text/plain", "Not found".to_string()),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Body is `{"crate": .., "key": .., "value": ..}`, a `null` value resets to the default
+fn set_value(repository: &mut Repository, body: &str) -> Result<(), String> {
+    let request: Value = serde_json::from_str(body).map_err(|err| err.to_string())?;
+    let (Some(crate_name), Some(key)) = (request["crate"].as_str(), request["key"].as_str()) else {
+        return Err("Expected `crate` and `key`".to_string());
+    };
+
+    let value = match &request["value"] {
+        Value::Null => None,
+        value => Some(value.clone()),
+    };
+    repository.set_key(crate_name, key, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    valid = "value <= 80000"
+    default = 4096
+    "#;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn repository() -> Repository {
+        let mut data = linked_hash_map::LinkedHashMap::new();
+        data.insert(
+            "mycrate".to_string(),
+            (rconfig::parse_definition_str(DEFINITION).unwrap(), vec![]),
+        );
+        Repository::new(data, "[mycrate]\n".to_string(), "[mycrate]\n".to_string()).unwrap()
+    }
+
+    fn post(host: &str, headers: &str, body: &str) -> String {
+        format!(
+            "POST /api/set HTTP/1.1\r\nHost: {}\r\n{}Content-Length: {}\r\n\r\n{}",
+            host,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    fn respond_to(request: &str, repository: &mut Repository) -> &'static str {
+        let server = Server {
+            token: TOKEN.to_string(),
+        };
+        let request = read_request(request.as_bytes()).unwrap();
+        server
+            .response(&request, repository, Path::new("config.toml"))
+            .0
+    }
+
+    const SET: &str = r#"{"crate": "mycrate", "key": "heap.size", "value": 1024}"#;
+
+    #[test]
+    fn sets_values() {
+        let mut repository = repository();
+        let headers = format!(
+            "Content-Type: application/json; charset=utf-8\r\nX-Rconfig-Token: {}\r\n",
+            TOKEN
+        );
+
+        for host in [
+            "127.0.0.1:7878",
+            "localhost:9000",
+            "[::1]:9000",
+            "localhost",
+        ] {
+            assert_eq!(
+                "200 OK",
+                respond_to(&post(host, &headers, SET), &mut repository),
+                "{}",
+                host
+            );
+        }
+        assert_eq!("[mycrate]\nheap.size=1024\n", repository.create_config());
+
+        let invalid = r#"{"crate": "mycrate", "key": "heap.size", "value": 90000}"#;
+        assert_eq!(
+            "400 Bad Request",
+            respond_to(&post("localhost:7878", &headers, invalid), &mut repository)
+        );
+    }
+
+    #[test]
+    fn rejects_other_hosts() {
+        let mut repository = repository();
+        let headers = format!(
+            "Content-Type: application/json\r\nX-Rconfig-Token: {}\r\n",
+            TOKEN
+        );
+
+        for host in [
+            "attacker.example:7878",
+            "localhost.attacker.example",
+            "[::2]:7878",
+        ] {
+            assert_eq!(
+                "403 Forbidden",
+                respond_to(&post(host, &headers, SET), &mut repository),
+                "{}",
+                host
+            );
+        }
+        assert_eq!(
+            "403 Forbidden",
+            respond_to("GET /api/config HTTP/1.1\r\n\r\n", &mut repository)
+        );
+        assert_eq!("[mycrate]\n", repository.create_config());
+    }
+
+    #[test]
+    fn changes_need_the_token_and_json() {
+        let mut repository = repository();
+
+        for headers in [
+            "Content-Type: application/json\r\n".to_string(),
+            "Content-Type: application/json\r\nX-Rconfig-Token: wrong\r\n".to_string(),
+            format!("Content-Type: text/plain\r\nX-Rconfig-Token: {}\r\n", TOKEN),
+            format!("X-Rconfig-Token: {}\r\n", TOKEN),
+        ] {
+            assert_eq!(
+                "403 Forbidden",
+                respond_to(&post("localhost:7878", &headers, SET), &mut repository),
+                "{}",
+                headers
+            );
+        }
+        assert_eq!("[mycrate]\n", repository.create_config());
+    }
+
+    #[test]
+    fn rejects_large_requests() {
+        let body = "x".repeat(MAX_REQUEST_SIZE + 1);
+        assert!(read_request(post("localhost:7878", "", &body).as_bytes()).is_err());
+
+        let headers = "X-Padding: x\r\n".repeat(MAX_REQUEST_SIZE / 14 + 1);
+        assert!(read_request(post("localhost:7878", &headers, "").as_bytes()).is_err());
+
+        let request = read_request(post("localhost:7878", "", SET).as_bytes()).unwrap();
+        assert_eq!("POST", request.method);
+        assert_eq!("/api/set", request.path);
+        assert_eq!(Some("localhost:7878"), request.header("host"));
+        assert_eq!(SET, request.body);
+    }
+}
@@ -0,0 +1,412 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::{ConfigOption, Map, Value};
+
+/// Expressions are rejected if they don't finish after this many operations
+#[cfg(feature = "rhai")]
+const MAX_EXPRESSION_OPERATIONS: u64 = 100_000;
+
+/// Evaluates `depends` and `valid` expressions for one state of the config (the definition with
+/// the user's values fused in) and features
+///
+/// Create it once per pass over the config - creating the engine and finding the enabled options
+/// is what's expensive, not evaluating an expression. Results are cached - many options share
+/// the same `depends`, e.g. all options of a peripheral.
+///
+/// With the `rhai` feature the expressions are Rhai scripts, otherwise the built-in evaluator
+/// handles them.
+pub(crate) struct Expressions {
+    #[cfg(feature = "rhai")]
+    engine: rhai::Engine,
+    #[cfg(not(feature = "rhai"))]
+    features: Vec<String>,
+    enabled: Rc<RefCell<HashSet<String>>>,
+    target: Rc<RefCell<Option<String>>>,
+    values: Rc<RefCell<HashMap<String, Value>>>,
+    // expression -> result, only valid as long as `enabled` doesn't change
+    depends_results: RefCell<HashMap<String, bool>>,
+    // (expression, value) -> result
+    valid_results: RefCell<HashMap<(String, String), bool>>,
+}
+
+impl Expressions {
+    pub(crate) fn new(all_config: &Map<String, ConfigOption>, features: &[&str]) -> Self {
+        let features: Vec<String> = features.iter().map(|s| s.to_string()).collect();
+
+        let mut enabled = HashSet::new();
+        collect_enabled(&mut enabled, all_config, "");
+        let enabled = Rc::new(RefCell::new(enabled));
+        let target = Rc::new(RefCell::new(None));
+        let values = Rc::new(RefCell::new(HashMap::new()));
+
+        #[cfg(feature = "rhai")]
+        let engine = {
+            let mut engine = rhai::Engine::new();
+            engine.set_max_operations(MAX_EXPRESSION_OPERATIONS);
+
+            engine.register_fn("feature", move |what: String| features.contains(&what));
+
+            let script_enabled = enabled.clone();
+            engine.register_fn("enabled", move |what: &str| {
+                script_enabled.borrow().contains(what)
+            });
+
+            let script_target: Rc<RefCell<Option<String>>> = target.clone();
+            engine.register_fn("target", move |what: &str| {
+                script_target.borrow().as_deref() == Some(what)
+            });
+
+            let script_values: Rc<RefCell<HashMap<String, Value>>> = values.clone();
+            engine.register_fn(
+                "value_of",
+                move |what: &str| -> Result<rhai::Dynamic, Box<rhai::EvalAltResult>> {
+                    match script_values.borrow().get(what) {
+                        Some(value) => Ok(to_dynamic(value)),
+                        None => Err(format!("No value for `{}`", what).into()),
+                    }
+                },
+            );
+            engine
+        };
+
+        Self {
+            #[cfg(feature = "rhai")]
+            engine,
+            #[cfg(not(feature = "rhai"))]
+            features,
+            enabled,
+            target,
+            values,
+            depends_results: RefCell::new(HashMap::new()),
+            valid_results: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// What `target("...")` compares to - without a target it's always `false`
+    pub(crate) fn with_target(self, target: Option<&str>) -> Self {
+        *self.target.borrow_mut() = target.map(str::to_string);
+        self
+    }
+
+    /// What `value_of("...")` returns - and `enabled("...")` with it, values which aren't known
+    /// are neither
+    pub(crate) fn with_values(self, values: HashMap<String, Value>) -> Self {
+        *self.enabled.borrow_mut() = values
+            .iter()
+            .filter(|(_, value)| is_set(value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        *self.values.borrow_mut() = values;
+        self
+    }
+
+    pub(crate) fn is_enabled(&self, key: &str) -> bool {
+        self.enabled.borrow().contains(key)
+    }
+
+    /// Update what `enabled("...")` returns after the value of an option changed
+    pub(crate) fn set_enabled(&self, key: &str, enabled: bool) {
+        if enabled {
+            self.enabled.borrow_mut().insert(key.to_string());
+        } else {
+            self.enabled.borrow_mut().remove(key);
+        }
+        self.depends_results.borrow_mut().clear();
+        self.valid_results.borrow_mut().clear();
+    }
+
+    pub(crate) fn depends(&self, depends: Option<&str>) -> Result<bool, String> {
+        let Some(depends) = depends else {
+            return Ok(true);
+        };
+
+        if let Some(result) = self.depends_results.borrow().get(depends) {
+            return Ok(*result);
+        }
+        let result = self.eval(depends, None)?;
+        self.depends_results
+            .borrow_mut()
+            .insert(depends.to_string(), result);
+        Ok(result)
+    }
+
+    pub(crate) fn valid(&self, validation: Option<&str>, value: &Value) -> Result<bool, String> {
+        let Some(validation) = validation else {
+            return Ok(true);
+        };
+
+        let cache_key = (validation.to_string(), value.to_string());
+        if let Some(result) = self.valid_results.borrow().get(&cache_key) {
+            return Ok(*result);
+        }
+
+        let result = self.eval(validation, Some(value))?;
+        self.valid_results.borrow_mut().insert(cache_key, result);
+        Ok(result)
+    }
+
+    #[cfg(feature = "rhai")]
+    fn eval(&self, expression: &str, value: Option<&Value>) -> Result<bool, String> {
+        let mut scope = rhai::Scope::new();
+        if let Some(value) = value {
+            scope.push_dynamic("value", to_dynamic(value));
+        }
+
+        self.engine
+            .eval_with_scope::<bool>(&mut scope, expression)
+            .map_err(|err| err.to_string())
+    }
+
+    #[cfg(not(feature = "rhai"))]
+    fn eval(&self, expression: &str, value: Option<&Value>) -> Result<bool, String> {
+        let enabled = self.enabled.borrow();
+        let target = self.target.borrow();
+        let values = self.values.borrow();
+        let env = crate::expression::Env {
+            features: &self.features,
+            enabled: &enabled,
+            value,
+            target: target.as_deref(),
+            values: &values,
+        };
+        crate::expression::parse(expression)?.eval_bool(&env)
+    }
+}
+
+#[cfg(feature = "rhai")]
+fn to_dynamic(value: &Value) -> rhai::Dynamic {
+    match value {
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => n.into(),
+            None => n.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(s) => s.clone().into(),
+        _ => false.into(),
+    }
+}
+
+/// Whether the expression parses - it can still fail to evaluate
+#[cfg(feature = "rhai")]
+pub(crate) fn check_syntax(expression: &str) -> Result<(), String> {
+    rhai::Engine::new()
+        .compile(expression)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Whether the expression parses - it can still fail to evaluate
+#[cfg(not(feature = "rhai"))]
+pub(crate) fn check_syntax(expression: &str) -> Result<(), String> {
+    crate::expression::parse(expression).map(|_| ())
+}
+
+/// Whether `enabled("...")` is true for an option with the value
+pub(crate) fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(value) => value.as_f64() != Some(0.0),
+        Value::String(value) => !value.is_empty(),
+        Value::Array(_) => false,
+        Value::Object(_) => false,
+    }
+}
+
+fn collect_enabled(
+    enabled: &mut HashSet<String>,
+    config: &Map<String, ConfigOption>,
+    prefix: &str,
+) {
+    for (name, item) in config {
+        let key = format!("{}{}", prefix, name);
+        if item
+            .__value
+            .as_ref()
+            .or(item.default_value.as_ref())
+            .is_some_and(is_set)
+        {
+            enabled.insert(key.clone());
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            collect_enabled(enabled, options, &format!("{}.", key));
+        }
+    }
+}
+
+/// The dotted keys of the menus and options which are available - their `depends` and the
+/// `depends` of all menus containing them are met
+///
+/// A `depends` which can't be evaluated isn't met.
+pub(crate) struct ActiveSet(HashSet<String>);
+
+impl ActiveSet {
+    pub(crate) fn new(config: &Map<String, ConfigOption>, expressions: &Expressions) -> Self {
+        let mut active = HashSet::new();
+        collect_active(&mut active, config, expressions, "");
+        Self(active)
+    }
+
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        self.0.contains(key)
+    }
+
+    /// Add a menu or option which became available and everything in it which is available
+    pub(crate) fn insert(&mut self, key: &str, option: &ConfigOption, expressions: &Expressions) {
+        if let Some(options) = option.options.as_ref() {
+            collect_active(&mut self.0, options, expressions, &format!("{}.", key));
+        }
+        self.0.insert(key.to_string());
+    }
+
+    /// Remove a menu or option which isn't available anymore and everything in it
+    pub(crate) fn remove(&mut self, key: &str, option: &ConfigOption) {
+        if let Some(options) = option.options.as_ref() {
+            for (name, item) in options {
+                self.remove(&format!("{}.{}", key, name), item);
+            }
+        }
+        self.0.remove(key);
+    }
+}
+
+fn collect_active(
+    active: &mut HashSet<String>,
+    config: &Map<String, ConfigOption>,
+    expressions: &Expressions,
+    prefix: &str,
+) {
+    for (name, item) in config {
+        if !expressions
+            .depends(item.depends.as_deref())
+            .unwrap_or(false)
+        {
+            continue;
+        }
+
+        let key = format!("{}{}", prefix, name);
+        if let Some(options) = item.options.as_ref() {
+            collect_active(active, options, expressions, &format!("{}.", key));
+        }
+        active.insert(key);
+    }
+}
+
+/// Remove the menus and options which are not active
+pub(crate) fn prune(config: &mut Map<String, ConfigOption>, active: &ActiveSet, prefix: &str) {
+    let inactive: Vec<String> = config
+        .keys()
+        .filter(|name| !active.contains(&format!("{}{}", prefix, name)))
+        .cloned()
+        .collect();
+    for name in inactive {
+        config.remove(&name);
+    }
+
+    for (name, item) in config.iter_mut() {
+        if let Some(options) = item.options.as_mut() {
+            prune(options, active, &format!("{}{}.", prefix, name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fuse_config_str, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    depends = 'enabled("psram.enable")'
+    type = "u32"
+    default = 2
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    valid = "value <= 80000"
+    "#;
+
+    #[test]
+    fn active_set() {
+        let config = fuse_config_str(
+            "[mycrate]\npsram.enable = true\n",
+            "mycrate",
+            parse_definition_str(DEFINITION).unwrap(),
+        )
+        .unwrap();
+
+        let expressions = Expressions::new(&config, &["esp32"]);
+        let active = ActiveSet::new(&config, &expressions);
+        for key in ["psram", "psram.enable", "psram.size", "heap", "heap.size"] {
+            assert!(active.contains(key), "{}", key);
+        }
+        assert!(expressions
+            .valid(Some("value <= 80000"), &Value::from(100))
+            .unwrap());
+        assert!(!expressions
+            .valid(Some("value <= 80000"), &Value::from(90000))
+            .unwrap());
+
+        // the options of a menu which isn't available aren't either
+        let active = ActiveSet::new(&config, &Expressions::new(&config, &[]));
+        assert!(!active.contains("psram"));
+        assert!(!active.contains("psram.enable"));
+        assert!(active.contains("heap.size"));
+
+        let mut pruned = config.clone();
+        prune(&mut pruned, &active, "");
+        assert_eq!(vec!["heap"], pruned.keys().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn memoized_results() {
+        let config = fuse_config_str(
+            "[mycrate]\npsram.enable = true\n",
+            "mycrate",
+            parse_definition_str(DEFINITION).unwrap(),
+        )
+        .unwrap();
+        let expressions = Expressions::new(&config, &["esp32"]);
+
+        for _ in 0..3 {
+            assert!(expressions
+                .depends(Some(r#"enabled("psram.enable")"#))
+                .unwrap());
+            assert!(expressions
+                .valid(Some("value <= 80000"), &Value::from(100))
+                .unwrap());
+        }
+        assert_eq!(1, expressions.depends_results.borrow().len());
+        assert_eq!(1, expressions.valid_results.borrow().len());
+
+        // a string isn't the same input as a number
+        assert!(expressions
+            .valid(Some(r#"value == "100""#), &Value::from("100"))
+            .unwrap());
+        assert!(!expressions
+            .valid(Some(r#"value == "100""#), &Value::from(100))
+            .unwrap());
+
+        expressions.set_enabled("psram.enable", false);
+        assert!(!expressions
+            .depends(Some(r#"enabled("psram.enable")"#))
+            .unwrap());
+    }
+}
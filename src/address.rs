@@ -0,0 +1,138 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::ValueType;
+
+/// The bytes of a network address written the usual way - `192.168.0.1` for `ipv4`, `fe80::1`
+/// for `ipv6` and `02:00:00:00:00:01` (or with `-`) for `mac` options
+///
+/// `None` if the text isn't an address of the type or the type isn't an address type.
+pub fn parse_address(value_type: &ValueType, text: &str) -> Option<Vec<u8>> {
+    match value_type {
+        ValueType::Ipv4 => text.parse::<Ipv4Addr>().ok().map(|ip| ip.octets().to_vec()),
+        ValueType::Ipv6 => text.parse::<Ipv6Addr>().ok().map(|ip| ip.octets().to_vec()),
+        ValueType::Mac => parse_mac(text),
+        _ => None,
+    }
+}
+
+fn parse_mac(text: &str) -> Option<Vec<u8>> {
+    let separator = if text.contains('-') { '-' } else { ':' };
+    let bytes = text
+        .split(separator)
+        .map(|byte| {
+            if byte.len() == 2 {
+                u8::from_str_radix(byte, 16).ok()
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    (bytes.len() == 6).then_some(bytes)
+}
+
+/// The number of bytes of an address type, `None` for the other types
+pub(crate) fn address_len(value_type: &ValueType) -> Option<usize> {
+    match value_type {
+        ValueType::Ipv4 => Some(4),
+        ValueType::Ipv6 => Some(16),
+        ValueType::Mac => Some(6),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config, generate_config, parse_definition_str};
+
+    #[test]
+    fn addresses() {
+        assert_eq!(
+            Some(vec![192, 168, 0, 1]),
+            parse_address(&ValueType::Ipv4, "192.168.0.1")
+        );
+        assert_eq!(
+            Some(vec![0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]),
+            parse_address(&ValueType::Ipv6, "fe80::1")
+        );
+        for mac in ["02:00:00:ab:cd:ef", "02-00-00-AB-CD-EF"] {
+            assert_eq!(
+                Some(vec![2, 0, 0, 0xab, 0xcd, 0xef]),
+                parse_address(&ValueType::Mac, mac),
+                "{}",
+                mac
+            );
+        }
+        for (value_type, invalid) in [
+            (ValueType::Ipv4, "192.168.0"),
+            (ValueType::Ipv4, "fe80::1"),
+            (ValueType::Ipv6, "192.168.0.1"),
+            (ValueType::Mac, "02:00:00:ab:cd"),
+            (ValueType::Mac, "02:00:00:ab:cd:e"),
+            (ValueType::Mac, "02:00-00:ab:cd:ef"),
+            (ValueType::String, "192.168.0.1"),
+        ] {
+            assert_eq!(None, parse_address(&value_type, invalid), "{}", invalid);
+        }
+    }
+
+    const DEFINITION: &str = r#"
+    [net]
+    description = "Network"
+
+    [net.options.ip]
+    description = "Static IP"
+    type = "ipv4"
+    default = "192.168.0.10"
+
+    [net.options.mac]
+    description = "MAC address"
+    type = "mac"
+    default = "02:00:00:00:00:01"
+    "#;
+
+    #[test]
+    fn address_options() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let effective_config = evaluate_config(
+            "[mycrate]\nnet.ip = \"10.0.0.2\"\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some("10.0.0.2"), effective_config.get_str("net.ip"));
+
+        let config_rs = generate_config(&definition, &effective_config).config_rs;
+        assert!(config_rs.contains("pub const NET_IP: [u8; 4] = [10, 0, 0, 2];\n"));
+        assert!(config_rs
+            .contains("pub const NET_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];\n"));
+
+        for invalid in [
+            "net.ip = \"10.0.0.256\"",
+            "net.mac = \"02:00\"",
+            "net.ip = 10",
+        ] {
+            assert!(
+                evaluate_config(
+                    &format!("[mycrate]\n{}\n", invalid),
+                    "mycrate",
+                    definition.clone(),
+                    vec![],
+                )
+                .is_err(),
+                "{}",
+                invalid
+            );
+        }
+
+        let invalid_default = DEFINITION.replace("192.168.0.10", "192.168.0");
+        assert!(evaluate_config(
+            "",
+            "mycrate",
+            parse_definition_str(&invalid_default).unwrap(),
+            vec![]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use std::io::Write;
+
+use crate::cache::fnv1a;
+use crate::EffectiveConfig;
+
+/// What the `rconfig_build_info` module reports about a build, see [write_build_info]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildInfo {
+    /// The enabled features of the crate
+    pub features: Vec<String>,
+    /// FNV-1a of the config-definition as written
+    pub definition_digest: u64,
+    /// See [config_fingerprint]
+    pub config_fingerprint: u64,
+    /// FNV-1a of the user's config (`config.toml`) as read - the same for all crates of a build,
+    /// see [crate::assert_consistent_config]
+    pub user_config_digest: u64,
+    /// When the config was resolved, in seconds since the Unix epoch
+    pub resolved_at: u64,
+}
+
+impl BuildInfo {
+    pub fn new(definition: &str, cfg: &EffectiveConfig, features: &[String]) -> Self {
+        Self {
+            features: features.to_vec(),
+            definition_digest: fnv1a(definition.bytes()),
+            config_fingerprint: config_fingerprint(cfg),
+            user_config_digest: fnv1a("".bytes()),
+            resolved_at: 0,
+        }
+    }
+
+    pub fn with_user_config(mut self, user_config: &str) -> Self {
+        self.user_config_digest = fnv1a(user_config.bytes());
+        self
+    }
+
+    pub fn with_resolved_at(mut self, resolved_at: u64) -> Self {
+        self.resolved_at = resolved_at;
+        self
+    }
+}
+
+/// FNV-1a of the keys and values of the effective config - the same values give the same
+/// fingerprint, whether they are defaults or set by the user
+pub fn config_fingerprint(cfg: &EffectiveConfig) -> u64 {
+    fnv1a(
+        cfg.iter()
+            .flat_map(|entry| format!("{}={}\n", entry.key, entry.value).into_bytes()),
+    )
+}
+
+/// Write the `rconfig_build_info` module, enabled by `build_info = true` in the config-definition
+///
+/// ```text
+/// pub mod rconfig_build_info {
+///     pub const FEATURES: &[&str] = &["esp32"];
+///     pub const DEFINITION_DIGEST: u64 = 0x4a1c_..;
+///     pub const CONFIG_FINGERPRINT: u64 = 0x91f0_..;
+///     pub const USER_CONFIG_DIGEST: u64 = 0x2c7e_..;
+///     pub const RESOLVED_AT: u64 = 1760400000;
+/// }
+/// ```
+pub fn write_build_info(out: &mut impl Write, info: &BuildInfo) -> std::io::Result<()> {
+    writeln!(out, "pub mod rconfig_build_info {{")?;
+    writeln!(out, "/// The enabled features of the crate")?;
+    writeln!(
+        out,
+        "pub const FEATURES: &[&str] = &[{}];",
+        info.features
+            .iter()
+            .map(|feature| format!("{:?}", feature))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )?;
+    writeln!(out, "/// FNV-1a of the config-definition")?;
+    writeln!(
+        out,
+        "pub const DEFINITION_DIGEST: u64 = {:#018x};",
+        info.definition_digest
+    )?;
+    writeln!(out, "/// FNV-1a of the keys and values of the config")?;
+    writeln!(
+        out,
+        "pub const CONFIG_FINGERPRINT: u64 = {:#018x};",
+        info.config_fingerprint
+    )?;
+    writeln!(
+        out,
+        "/// FNV-1a of the config.toml the crate was built with"
+    )?;
+    writeln!(
+        out,
+        "pub const USER_CONFIG_DIGEST: u64 = {:#018x};",
+        info.user_config_digest
+    )?;
+    writeln!(
+        out,
+        "/// When the config was resolved, in seconds since the Unix epoch"
+    )?;
+    writeln!(out, "pub const RESOLVED_AT: u64 = {};", info.resolved_at)?;
+    writeln!(out, "}}")
+}
+
+/// `SOURCE_DATE_EPOCH` for reproducible builds, the current time otherwise
+pub(crate) fn resolved_at() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config, parse_definition_str, testing};
+
+    const DEFINITION: &str = r#"
+    build_info = true
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    default = 4096
+    "#;
+
+    #[test]
+    fn build_info() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let defaults = evaluate_config("", "mycrate", definition.clone(), vec![]).unwrap();
+        let same = evaluate_config(
+            "[mycrate]\nheap.size = 4096\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        let other = evaluate_config(
+            "[mycrate]\nheap.size = 1024\n",
+            "mycrate",
+            definition,
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(config_fingerprint(&defaults), config_fingerprint(&same));
+        assert_ne!(config_fingerprint(&defaults), config_fingerprint(&other));
+
+        let info = BuildInfo::new(DEFINITION, &defaults, &["esp32".to_string()])
+            .with_user_config("[mycrate]\nheap.size = 4096\n")
+            .with_resolved_at(1760400000);
+        let mut out = Vec::new();
+        write_build_info(&mut out, &info).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("pub mod rconfig_build_info {\n"));
+        assert!(out.contains("pub const FEATURES: &[&str] = &[\"esp32\"];\n"));
+        assert!(out.contains(&format!(
+            "pub const CONFIG_FINGERPRINT: u64 = {:#018x};\n",
+            config_fingerprint(&defaults)
+        )));
+        assert!(out.contains("pub const RESOLVED_AT: u64 = 1760400000;\n"));
+        assert!(out.contains(&format!(
+            "pub const USER_CONFIG_DIGEST: u64 = {:#018x};\n",
+            fnv1a("[mycrate]\nheap.size = 4096\n".bytes())
+        )));
+        assert_ne!(
+            info.user_config_digest,
+            info.clone().with_user_config("").user_config_digest
+        );
+
+        // only with `build_info = true`
+        let generated = testing::generate(DEFINITION, "", &["esp32"]).unwrap();
+        assert!(generated
+            .config_rs
+            .contains("pub mod rconfig_build_info {\n"));
+        let generated =
+            testing::generate(&DEFINITION.replace("build_info = true", ""), "", &[]).unwrap();
+        assert!(!generated.config_rs.contains("rconfig_build_info"));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::size::normalize_number_defaults;
+use crate::unless::fold_unless;
+use crate::{
+    Charset, ConfigOption, ConfigValue, Definition, DisplayHint, Error, Map, ValueItem, ValueType,
+};
+
+/// Builds the options of one level of a config-definition, see [Definition::builder]
+///
+/// Modifiers like [DefinitionBuilder::depends] apply to the option or menu added last.
+#[derive(Default)]
+pub struct DefinitionBuilder {
+    options: Map<String, ConfigOption>,
+    last: Option<String>,
+    // the first modifier used before adding an option, reported by `build`
+    misuse: Option<String>,
+}
+
+impl DefinitionBuilder {
+    /// A menu containing the options added by `build`
+    pub fn menu(
+        mut self,
+        name: &str,
+        description: &str,
+        build: impl FnOnce(DefinitionBuilder) -> DefinitionBuilder,
+    ) -> Self {
+        let menu = build(DefinitionBuilder::default());
+        if let Some(modifier) = menu.misuse {
+            self.misuse
+                .get_or_insert_with(|| format!("{}.{}", name, modifier));
+        }
+        let options = menu.options;
+        self.add(
+            name,
+            ConfigOption {
+                description: description.to_string(),
+                options: Some(options),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// A menu item without a value
+    pub fn comment(self, name: &str, description: &str) -> Self {
+        self.add(
+            name,
+            ConfigOption {
+                description: description.to_string(),
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn bool(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::Bool)
+    }
+
+    pub fn u8(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::U8)
+    }
+
+    pub fn u16(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::U16)
+    }
+
+    pub fn u32(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::U32)
+    }
+
+    pub fn u64(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::U64)
+    }
+
+    pub fn usize(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::Usize)
+    }
+
+    pub fn i8(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::I8)
+    }
+
+    pub fn i16(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::I16)
+    }
+
+    pub fn i32(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::I32)
+    }
+
+    pub fn i64(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::I64)
+    }
+
+    /// A number of bytes, see [crate::parse_size]
+    pub fn size(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::Size)
+    }
+
+    pub fn string(self, name: &str, description: &str) -> Self {
+        self.typed(name, description, ValueType::String)
+    }
+
+    /// An enum option, `values` are `(value, description)` pairs
+    pub fn enumeration(self, name: &str, description: &str, values: &[(&str, &str)]) -> Self {
+        let values = values
+            .iter()
+            .map(|(value, description)| ValueItem {
+                description: description.to_string(),
+                value: value.to_string(),
+                ..Default::default()
+            })
+            .collect();
+
+        self.add(
+            name,
+            ConfigOption {
+                description: description.to_string(),
+                value_type: Some(ValueType::Enum),
+                values: Some(values),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Add an option which was created by other means
+    pub fn option(self, name: &str, option: ConfigOption) -> Self {
+        self.add(name, option)
+    }
+
+    pub fn default_value(self, value: impl Into<ConfigValue>) -> Self {
+        self.modify("default_value", |option| {
+            option.default_value = Some(value.into().to_json())
+        })
+    }
+
+    pub fn depends(self, expression: &str) -> Self {
+        self.modify("depends", |option| {
+            option.depends = Some(expression.to_string())
+        })
+    }
+
+    /// Not available if the expression is true, combined with the `depends`
+    pub fn unless(self, expression: &str) -> Self {
+        self.modify("unless", |option| {
+            option.unless = Some(expression.to_string())
+        })
+    }
+
+    pub fn valid(self, expression: &str) -> Self {
+        self.modify("valid", |option| {
+            option.valid = Some(expression.to_string())
+        })
+    }
+
+    /// The minimum and maximum number of characters of a string option
+    pub fn length(self, min: Option<usize>, max: Option<usize>) -> Self {
+        self.modify("length", |option| {
+            option.min_length = min;
+            option.max_length = max;
+        })
+    }
+
+    pub fn charset(self, charset: Charset) -> Self {
+        self.modify("charset", |option| option.charset = Some(charset))
+    }
+
+    /// Numeric values have to be a multiple of it
+    pub fn step(self, step: u64) -> Self {
+        self.modify("step", |option| option.step = Some(step))
+    }
+
+    /// The user has to set the value, see [ConfigOption::required]
+    pub fn required(self) -> Self {
+        self.modify("required", |option| option.required = true)
+    }
+
+    /// Setting the option makes the build warn, see [ConfigOption::deprecated]
+    pub fn deprecated(self) -> Self {
+        self.modify("deprecated", |option| option.deprecated = true)
+    }
+
+    /// The key the option had before, see [ConfigOption::renamed_from]
+    pub fn renamed_from(self, key: &str) -> Self {
+        self.modify("renamed_from", |option| {
+            option.renamed_from = Some(key.to_string())
+        })
+    }
+
+    /// The smallest and the largest value of an integer option
+    pub fn range(self, min: i64, max: i64) -> Self {
+        self.modify("range", |option| {
+            option.min = Some(min);
+            option.max = Some(max);
+        })
+    }
+
+    /// Exactly one bool option of the menu is `true`, see [crate::ConfigOption::choice]
+    pub fn choice(self) -> Self {
+        self.modify("choice", |option| option.choice = true)
+    }
+
+    /// Only one option of the group can claim a resource, see [crate::ConfigOption::exclusive_group]
+    pub fn exclusive_group(self, group: &str) -> Self {
+        self.modify("exclusive_group", |option| {
+            option.exclusive_group = Some(group.to_string())
+        })
+    }
+
+    /// The bool options defaulting to `true` while the option is enabled, see
+    /// [crate::ConfigOption::imply]
+    pub fn imply(self, keys: &[&str]) -> Self {
+        self.modify("imply", |option| {
+            option.imply = Some(keys.iter().map(|key| key.to_string()).collect())
+        })
+    }
+
+    /// The bool options forced to `true` while the option is enabled, see
+    /// [crate::ConfigOption::selects]
+    pub fn selects(self, keys: &[&str]) -> Self {
+        self.modify("selects", |option| {
+            option.selects = Some(keys.iter().map(|key| key.to_string()).collect())
+        })
+    }
+
+    /// The bool options which can't be enabled together with the option, see
+    /// [crate::ConfigOption::conflicts]
+    pub fn conflicts(self, keys: &[&str]) -> Self {
+        self.modify("conflicts", |option| {
+            option.conflicts = Some(keys.iter().map(|key| key.to_string()).collect())
+        })
+    }
+
+    /// Only generate code for the option if the expression is true, see [crate::emitted_config]
+    pub fn emit_if(self, expression: &str) -> Self {
+        self.modify("emit_if", |option| {
+            option.emit_if = Some(expression.to_string())
+        })
+    }
+
+    /// Tooling doesn't show the value
+    pub fn secret(self) -> Self {
+        self.modify("secret", |option| option.secret = true)
+    }
+
+    /// Read the value from the environment variable instead of `config.toml`
+    pub fn env(self, var: &str) -> Self {
+        self.modify("env", |option| option.env = Some(var.to_string()))
+    }
+
+    pub fn display(self, display: DisplayHint) -> Self {
+        self.modify("display", |option| option.display = Some(display))
+    }
+
+    pub fn weight(self, weight: i64) -> Self {
+        self.modify("weight", |option| option.weight = Some(weight))
+    }
+
+    /// Only shown in expert mode, see [ConfigOption::hidden]
+    pub fn hidden(self) -> Self {
+        self.modify("hidden", |option| option.hidden = true)
+    }
+
+    /// Fails with [Error::InvalidDefinition] if a modifier is used before adding an option (e.g.
+    /// `psram.depends` for a `depends` first in the menu `psram`)
+    pub fn build(mut self) -> Result<Definition, Error> {
+        if let Some(modifier) = self.misuse {
+            return Err(Error::InvalidDefinition(modifier));
+        }
+        fold_unless(&mut self.options);
+        normalize_number_defaults(&mut self.options);
+        Ok(Definition::from(self.options))
+    }
+
+    fn typed(self, name: &str, description: &str, value_type: ValueType) -> Self {
+        self.add(
+            name,
+            ConfigOption {
+                description: description.to_string(),
+                value_type: Some(value_type),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn add(mut self, name: &str, option: ConfigOption) -> Self {
+        self.options.insert(name.to_string(), option);
+        self.last = Some(name.to_string());
+        self
+    }
+
+    fn modify(mut self, modifier: &str, modify: impl FnOnce(&mut ConfigOption)) -> Self {
+        match self
+            .last
+            .as_ref()
+            .and_then(|name| self.options.get_mut(name))
+        {
+            Some(option) => modify(option),
+            None => {
+                self.misuse.get_or_insert_with(|| modifier.to_string());
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config_str, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    depends = 'enabled("psram.enable")'
+    type = "enum"
+    values = [
+        { description = "1MB", value = "1" },
+        { description = "2MB", value = "2" },
+    ]
+    default = "2"
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    valid = "value >= 0 && value <= 80000"
+    default = 4096
+    "#;
+
+    #[test]
+    fn builder_matches_toml() {
+        let built = Definition::builder()
+            .menu("psram", "PSRAM", |psram| {
+                psram
+                    .bool("enable", "Enable PSRAM")
+                    .default_value(false)
+                    .enumeration("size", "PSRAM Size", &[("1", "1MB"), ("2", "2MB")])
+                    .depends(r#"enabled("psram.enable")"#)
+                    .default_value("2")
+            })
+            .depends(r#"feature("esp32")"#)
+            .menu("heap", "Heapsize", |heap| {
+                heap.u32("size", "Bytes to allocate")
+                    .valid("value >= 0 && value <= 80000")
+                    .default_value(4096)
+            })
+            .build()
+            .unwrap()
+            .into_map();
+
+        let cfg = r#"
+        [mycrate]
+        psram.enable = true
+        "#;
+
+        assert_eq!(
+            evaluate_config_str(
+                cfg,
+                "mycrate",
+                parse_definition_str(DEFINITION).unwrap(),
+                vec!["esp32"]
+            )
+            .unwrap(),
+            evaluate_config_str(cfg, "mycrate", built, vec!["esp32"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn modifiers_without_an_option() {
+        assert!(matches!(
+            Definition::builder().depends("true").build(),
+            Err(Error::InvalidDefinition(key)) if key == "depends"
+        ));
+        assert!(matches!(
+            Definition::builder()
+                .menu("psram", "PSRAM", |psram| psram.secret().bool("enable", "Enable"))
+                .build(),
+            Err(Error::InvalidDefinition(key)) if key == "psram.secret"
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use crate::definition_file::DefinitionFile;
+use crate::{check_definition, Error};
+
+/// Parse a config-definition and check it, see [crate::check_definition]
+pub(crate) fn compile_definition(definition: &str) -> Result<DefinitionFile, Error> {
+    let compiled = DefinitionFile::parse(definition)?;
+    check_definition(&compiled.options, "")?;
+    crate::imply::check_implies(&compiled.options)?;
+    crate::deprecated::check_renamed(&compiled.options)?;
+    crate::exclusive::check_conflicts_definition(&compiled.options)?;
+    crate::global_constraints::check_constraints_syntax(&compiled.constraints)?;
+    Ok(compiled)
+}
+
+/// Like [compile_definition] but reuses what an earlier build stored in `cache_dir`, keyed by the
+/// hash of the definition
+///
+/// The cache is shared by all crates of a workspace and all builds using the same target dir.
+/// Failing to read or write it only costs compiling the definition again.
+pub(crate) fn compile_definition_cached(
+    definition: &str,
+    cache_dir: &Path,
+) -> Result<DefinitionFile, Error> {
+    let path = cache_dir.join(format!("{:016x}.json", hash(definition)));
+    if let Some(compiled) = std::fs::read(&path)
+        .ok()
+        .and_then(|cached| serde_json::from_slice(&cached).ok())
+    {
+        return Ok(compiled);
+    }
+
+    let compiled = compile_definition(definition)?;
+    if let Ok(content) = serde_json::to_vec(&compiled) {
+        write_atomically(&path, &content).ok();
+    }
+    Ok(compiled)
+}
+
+// build scripts run in parallel - never let another one read a partially written file
+fn write_atomically(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temp, content)?;
+    std::fs::rename(&temp, path)
+}
+
+/// FNV-1a of the definition and the version of rconfig
+fn hash(definition: &str) -> u64 {
+    fnv1a(
+        env!("CARGO_PKG_VERSION")
+            .bytes()
+            .chain([0])
+            .chain(definition.bytes()),
+    )
+}
+
+/// Stable between builds and platforms, unlike [std::hash::DefaultHasher]
+pub(crate) fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    valid = "value <= 80000"
+    default = 4096
+    "#;
+
+    #[test]
+    fn cached_definition() {
+        let cache_dir = std::env::temp_dir().join(format!("rconfig-cache-{}", std::process::id()));
+        let path = cache_dir.join(format!("{:016x}.json", hash(DEFINITION)));
+
+        let compiled = compile_definition_cached(DEFINITION, &cache_dir).unwrap();
+        assert!(path.exists());
+        let cached = compile_definition_cached(DEFINITION, &cache_dir).unwrap();
+        assert_eq!(
+            serde_json::to_value(&compiled).unwrap(),
+            serde_json::to_value(&cached).unwrap()
+        );
+        assert_eq!(
+            Some(4096),
+            cached.options["heap"].options.as_ref().unwrap()["size"]
+                .default_value
+                .as_ref()
+                .and_then(|value| value.as_u64())
+        );
+
+        // a broken cache entry is replaced
+        std::fs::write(&path, "{").unwrap();
+        assert!(compile_definition_cached(DEFINITION, &cache_dir).is_ok());
+        assert!(serde_json::from_slice::<DefinitionFile>(&std::fs::read(&path).unwrap()).is_ok());
+
+        // errors are never cached
+        assert!(compile_definition_cached(
+            "[heap]\ndescription = \"Heapsize\"\ndepends = \"feature(\"\n",
+            &cache_dir
+        )
+        .is_err());
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+}
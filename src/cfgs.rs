@@ -0,0 +1,318 @@
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::definition_file::DefinitionFile;
+use crate::{is_enabled_value, ConfigOption, EffectiveConfig, Error, Map, ValueType};
+
+/// `cfg_namespace` of a config-definition
+///
+/// ```toml
+/// # `rcfg_fake_wifi_options_ble` instead of `options_ble`
+/// cfg_namespace = true
+/// # or `rcfg_wifi_options_ble`
+/// cfg_namespace = "wifi"
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum CfgNamespace {
+    /// Namespaced by the crate name if `true`
+    Crate(bool),
+    Name(String),
+}
+
+/// How the cfgs of a crate's options are named
+///
+/// Without a namespace the option `psram.size` sets `has_psram_size` and `psram_size`, which
+/// collide with the cfgs of another crate having the same option. With the namespace `fake_hal`
+/// they are `rcfg_fake_hal_has_psram_size` and `rcfg_fake_hal_psram_size`.
+///
+/// With `negated_cfgs = true` in the config-definition bool options set to `false` also set
+/// `not_psram_enable`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CfgNames {
+    prefix: String,
+    negated: bool,
+}
+
+impl CfgNames {
+    /// `has_psram_size` and `psram_size`, the same as [CfgNames::default]
+    pub const fn plain() -> Self {
+        Self {
+            prefix: String::new(),
+            negated: false,
+        }
+    }
+
+    /// The cfgs are prefixed with `rcfg_<namespace>_`
+    pub fn namespaced(namespace: &str) -> Self {
+        Self {
+            prefix: format!("rcfg_{}_", to_cfg_name(namespace)),
+            negated: false,
+        }
+    }
+
+    /// Also set [CfgNames::not] for bool options which are `false`
+    pub fn with_negated(mut self, negated: bool) -> Self {
+        self.negated = negated;
+        self
+    }
+
+    pub(crate) fn new(namespace: Option<&CfgNamespace>, crate_name: &str) -> Self {
+        match namespace {
+            None | Some(CfgNamespace::Crate(false)) => Self::plain(),
+            Some(CfgNamespace::Crate(true)) => Self::namespaced(crate_name),
+            Some(CfgNamespace::Name(name)) => Self::namespaced(name),
+        }
+    }
+
+    /// The cfg names of a parsed config-definition
+    pub(crate) fn of(definition: &DefinitionFile, crate_name: &str) -> Self {
+        Self::new(definition.cfg_namespace.as_ref(), crate_name)
+            .with_negated(definition.negated_cfgs)
+    }
+
+    /// The cfg set if the value of the option with the dotted key isn't `false` or `0`
+    pub fn value(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, to_cfg_name(key))
+    }
+
+    /// The cfg set if the option with the dotted key has a value
+    pub fn has(&self, key: &str) -> String {
+        format!("{}has_{}", self.prefix, to_cfg_name(key))
+    }
+
+    /// The cfg set if the bool option with the dotted key is `false` - only with
+    /// [CfgNames::with_negated]
+    pub fn not(&self, key: &str) -> String {
+        format!("{}not_{}", self.prefix, to_cfg_name(key))
+    }
+}
+
+fn to_cfg_name(name: &str) -> String {
+    name.replace(['.', '-'], "_")
+}
+
+/// The cfg names the config-definition (its `cfg_namespace` and `negated_cfgs`) asks for
+pub fn parse_cfg_names_str(definition: &str, crate_name: &str) -> Result<CfgNames, Error> {
+    Ok(CfgNames::of(
+        &DefinitionFile::parse(definition)?,
+        crate_name,
+    ))
+}
+
+/// The cfgs to set for the effective config, see [crate::GeneratedConfig::cfgs]
+pub fn generate_cfgs_with<'a>(
+    cfg: &'a EffectiveConfig,
+    names: &'a CfgNames,
+) -> impl Iterator<Item = String> + 'a {
+    cfg.iter().flat_map(|entry| {
+        let enabled = is_enabled_value(&entry.value);
+        let negated = names.negated && entry.value_type == ValueType::Bool && !enabled;
+        std::iter::once(names.has(&entry.key))
+            .chain(enabled.then(|| names.value(&entry.key)))
+            .chain(negated.then(|| names.not(&entry.key)))
+    })
+}
+
+/// The `cfg(...)` to pass to `cargo::rustc-check-cfg` - all cfgs the options of the definition
+/// can set, whether they are available or not
+pub fn generate_check_cfgs(
+    definition: &Map<String, ConfigOption>,
+    names: &CfgNames,
+) -> Vec<String> {
+    let mut check_cfgs = Vec::new();
+    collect_check_cfgs(&mut check_cfgs, definition, names, "");
+    check_cfgs
+}
+
+fn collect_check_cfgs(
+    check_cfgs: &mut Vec<String>,
+    config: &Map<String, ConfigOption>,
+    names: &CfgNames,
+    prefix: &str,
+) {
+    for (name, item) in config {
+        let key = format!("{}{}", prefix, name);
+        if names.negated && item.value_type == Some(ValueType::Bool) {
+            check_cfgs.push(format!(
+                "cfg({}, {}, {})",
+                names.has(&key),
+                names.value(&key),
+                names.not(&key)
+            ));
+        } else if item.value_type.is_some() {
+            check_cfgs.push(format!("cfg({}, {})", names.has(&key), names.value(&key)));
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            collect_check_cfgs(check_cfgs, options, names, &format!("{}.", key));
+        }
+    }
+}
+
+/// Write a macro per bool option to `config.rs` gating items on the option's cfg - rustdoc shows
+/// them as "Available with config `psram.enable`", enabled by `cfg_macros = true` in the
+/// config-definition
+///
+/// ```ignore
+/// cfg_psram_enable! {
+///     /// Allocate in PSRAM
+///     pub fn alloc_psram(size: usize) -> *mut u8 { todo!() }
+/// }
+/// ```
+pub fn write_cfg_macros(
+    out: &mut impl Write,
+    definition: &Map<String, ConfigOption>,
+    names: &CfgNames,
+) -> std::io::Result<()> {
+    write_cfg_macros_of(out, definition, names, "")
+}
+
+fn write_cfg_macros_of(
+    out: &mut impl Write,
+    config: &Map<String, ConfigOption>,
+    names: &CfgNames,
+    prefix: &str,
+) -> std::io::Result<()> {
+    for (name, item) in config {
+        let key = format!("{}{}", prefix, name);
+        if item.value_type == Some(ValueType::Bool) {
+            writeln!(out, "#[allow(unused_macros)]")?;
+            writeln!(out, "macro_rules! cfg_{} {{", to_cfg_name(&key))?;
+            writeln!(out, "($($item:item)*) => {{ $(")?;
+            writeln!(out, "#[cfg({})]", names.value(&key))?;
+            writeln!(
+                out,
+                "#[doc = \"\\n\\n**Available with config `{}`**\"]",
+                key
+            )?;
+            writeln!(out, "$item")?;
+            writeln!(out, ")* }};")?;
+            writeln!(out, "}}")?;
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            write_cfg_macros_of(out, options, names, &format!("{}.", key))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    cfg_namespace = true
+
+    [options]
+    description = "Options"
+
+    [options.options.ble]
+    description = "Enable Bluetooth Low Energy"
+    type = "bool"
+    default = false
+
+    [options.options.buffer]
+    description = "BLE Buffer Size"
+    depends = 'enabled("options.ble")'
+    type = "u32"
+    default = 1
+    "#;
+
+    #[test]
+    fn namespaced_cfgs() {
+        let names = parse_cfg_names_str(DEFINITION, "fake-wifi").unwrap();
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        assert_eq!(vec!["options"], definition.keys().collect::<Vec<_>>());
+
+        let effective_config = evaluate_config(
+            "[fake-wifi]\noptions.ble = true\n",
+            "fake-wifi",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "rcfg_fake_wifi_has_options_ble",
+                "rcfg_fake_wifi_options_ble",
+                "rcfg_fake_wifi_has_options_buffer",
+                "rcfg_fake_wifi_options_buffer",
+            ],
+            generate_cfgs_with(&effective_config, &names).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                "cfg(rcfg_fake_wifi_has_options_ble, rcfg_fake_wifi_options_ble)",
+                "cfg(rcfg_fake_wifi_has_options_buffer, rcfg_fake_wifi_options_buffer)",
+            ],
+            generate_check_cfgs(&definition, &names)
+        );
+
+        assert_eq!(
+            CfgNames::namespaced("wifi"),
+            parse_cfg_names_str("cfg_namespace = \"wifi\"", "fake-wifi").unwrap()
+        );
+        let plain =
+            parse_cfg_names_str("[options]\ndescription = \"Options\"", "fake-wifi").unwrap();
+        assert_eq!("has_options_ble", plain.has("options.ble"));
+        assert!(parse_cfg_names_str("cfg_namespace = 1", "fake-wifi").is_err());
+    }
+
+    #[test]
+    fn negated_cfgs() {
+        let definition = DEFINITION.replace("cfg_namespace = true", "negated_cfgs = true");
+        let names = parse_cfg_names_str(&definition, "fake-wifi").unwrap();
+        assert_eq!("not_options_ble", names.not("options.ble"));
+        let definition = parse_definition_str(&definition).unwrap();
+
+        let effective_config =
+            evaluate_config("", "fake-wifi", definition.clone(), vec![]).unwrap();
+        assert_eq!(
+            vec!["has_options_ble", "not_options_ble"],
+            generate_cfgs_with(&effective_config, &names).collect::<Vec<_>>()
+        );
+        let effective_config = evaluate_config(
+            "[fake-wifi]\noptions.ble = true\noptions.buffer = 0\n",
+            "fake-wifi",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["has_options_ble", "options_ble", "has_options_buffer"],
+            generate_cfgs_with(&effective_config, &names).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                "cfg(has_options_ble, options_ble, not_options_ble)",
+                "cfg(has_options_buffer, options_buffer)",
+            ],
+            generate_check_cfgs(&definition, &names)
+        );
+
+        // only if asked for
+        let plain = CfgNames::plain();
+        assert_eq!(
+            vec!["has_options_ble", "options_ble", "has_options_buffer"],
+            generate_cfgs_with(&effective_config, &plain).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn cfg_macros() {
+        let names = parse_cfg_names_str(DEFINITION, "fake-wifi").unwrap();
+        let mut out = Vec::new();
+        write_cfg_macros(&mut out, &parse_definition_str(DEFINITION).unwrap(), &names).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("macro_rules! cfg_options_ble {\n"));
+        assert!(out.contains("#[cfg(rcfg_fake_wifi_options_ble)]\n"));
+        assert!(out.contains("**Available with config `options.ble`**"));
+        // only bool options
+        assert!(!out.contains("options_buffer"));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::active::{ActiveSet, Expressions};
+use crate::{ConfigOption, Error, Map, OptionIndex, Value, ValueType};
+
+/// Select one member of every available `choice` menu - the bool options of the menu are
+/// mutually exclusive, exactly one of them is `true`
+///
+/// ```toml
+/// [psram.options.mode]
+/// description = "PSRAM mode"
+/// choice = true
+///
+/// [psram.options.mode.options.quad]
+/// description = "Quad SPI"
+/// type = "bool"
+/// default = true
+///
+/// [psram.options.mode.options.octal]
+/// description = "Octal SPI"
+/// type = "bool"
+/// ```
+///
+/// The member the user enabled is selected, otherwise the first available member defaulting to
+/// `true` or just the first available one the user didn't disable. The other members default to
+/// `false`.
+pub(crate) fn apply_choices(config: &mut Map<String, ConfigOption>, features: &[&str]) {
+    let expressions = Expressions::new(config, features);
+    let active = ActiveSet::new(config, &expressions);
+    apply_level(config, &active, "");
+}
+
+fn apply_level(level: &mut Map<String, ConfigOption>, active: &ActiveSet, prefix: &str) {
+    for (name, option) in level.iter_mut() {
+        let key = format!("{}{}", prefix, name);
+        let choice = option.choice;
+        let Some(options) = option.options.as_mut() else {
+            continue;
+        };
+        if choice && active.contains(&key) {
+            select(options, active, &key);
+        }
+        apply_level(options, active, &format!("{}.", key));
+    }
+}
+
+fn select(members: &mut Map<String, ConfigOption>, active: &ActiveSet, menu_key: &str) {
+    let available: Vec<(&String, &ConfigOption)> = members
+        .iter()
+        .filter(|(name, _)| active.contains(&format!("{}.{}", menu_key, name)))
+        .collect();
+    let selected = available
+        .iter()
+        .find(|(_, member)| member.__value == Some(Value::Bool(true)))
+        .or_else(|| {
+            available.iter().find(|(_, member)| {
+                member.__value.is_none() && member.default_value == Some(Value::Bool(true))
+            })
+        })
+        .or_else(|| {
+            available
+                .iter()
+                .find(|(_, member)| member.__value.is_none())
+        })
+        .map(|(name, _)| name.to_string());
+
+    for (name, member) in members.iter_mut() {
+        member.default_value = Some(Value::Bool(selected.as_ref() == Some(name)));
+    }
+}
+
+/// The keys of the available choices without a selected member and of the members enabled in
+/// addition to the first one
+pub(crate) fn clashes(config: &Map<String, ConfigOption>, active: &ActiveSet) -> Vec<String> {
+    let mut clashes = Vec::new();
+    for (key, option) in OptionIndex::new(config).iter() {
+        if !option.choice || !active.contains(key) {
+            continue;
+        }
+
+        let enabled: Vec<String> = option
+            .options
+            .iter()
+            .flatten()
+            .map(|(name, member)| (format!("{}.{}", key, name), member))
+            .filter(|(member_key, member)| {
+                active.contains(member_key)
+                    && member.__value.as_ref().or(member.default_value.as_ref())
+                        == Some(&Value::Bool(true))
+            })
+            .map(|(member_key, _)| member_key)
+            .collect();
+        match enabled.len() {
+            0 => clashes.push(key.to_string()),
+            _ => clashes.extend(enabled.into_iter().skip(1)),
+        }
+    }
+    clashes
+}
+
+/// The first clash makes the config invalid, see [clashes]
+pub(crate) fn check_choices(
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+) -> Result<(), Error> {
+    match clashes(config, active).into_iter().next() {
+        Some(key) => Err(Error::InvalidConfigurationValue(key)),
+        None => Ok(()),
+    }
+}
+
+/// Only menus of bool options can be a choice
+pub(crate) fn check_choice(key: &str, option: &ConfigOption) -> Result<(), Error> {
+    let is_bool_menu = option.value_type.is_none()
+        && option.options.as_ref().is_some_and(|members| {
+            members
+                .values()
+                .all(|member| member.value_type == Some(ValueType::Bool))
+        });
+    if option.choice && !is_bool_menu {
+        return Err(Error::InvalidDefinition(format!("{}.choice", key)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_config_str, evaluate_config, parse_definition_str, ConfigProblemKind};
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+
+    [psram.options.mode]
+    description = "PSRAM mode"
+    choice = true
+
+    [psram.options.mode.options.quad]
+    description = "Quad SPI"
+    type = "bool"
+    default = true
+
+    [psram.options.mode.options.octal]
+    description = "Octal SPI"
+    type = "bool"
+
+    [psram.options.mode.options.opi]
+    description = "OPI"
+    type = "bool"
+    depends = 'feature("esp32s3")'
+    "#;
+
+    #[test]
+    fn choices() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let selected = |user_config: &str, features: Vec<&str>| {
+            let effective_config = evaluate_config(
+                &format!("[mycrate]\n{}\n", user_config),
+                "mycrate",
+                definition.clone(),
+                features,
+            )
+            .unwrap();
+            effective_config
+                .iter()
+                .filter(|entry| entry.value == Value::Bool(true))
+                .map(|entry| entry.key.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["psram.mode.quad"], selected("", vec![]));
+        assert_eq!(
+            vec!["psram.mode.octal"],
+            selected("psram.mode.octal = true", vec![])
+        );
+        assert_eq!(
+            vec!["psram.mode.octal"],
+            selected("psram.mode.quad = false", vec![])
+        );
+        assert_eq!(
+            vec!["psram.mode.opi"],
+            selected("psram.mode.opi = true", vec!["esp32s3"])
+        );
+
+        for (user_config, clash) in [
+            (
+                "psram.mode.quad = true\npsram.mode.octal = true",
+                "psram.mode.octal",
+            ),
+            (
+                "psram.mode.quad = false\npsram.mode.octal = false",
+                "psram.mode",
+            ),
+        ] {
+            match evaluate_config(
+                &format!("[mycrate]\n{}\n", user_config),
+                "mycrate",
+                definition.clone(),
+                vec![],
+            ) {
+                Err(Error::InvalidConfigurationValue(key)) => assert_eq!(clash, key),
+                other => panic!("{}: {:?}", user_config, other.map(|_| ())),
+            }
+        }
+
+        let problems = check_config_str(
+            "[mycrate]\npsram.mode.quad = true\npsram.mode.octal = true\n",
+            "mycrate",
+            &definition,
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(1, problems.len());
+        assert_eq!("psram.mode.octal", problems[0].key);
+        assert_eq!(ConfigProblemKind::InvalidValue, problems[0].kind);
+
+        let invalid = DEFINITION.replace(
+            "type = \"bool\"\n    depends",
+            "type = \"u32\"\n    depends",
+        );
+        let invalid = parse_definition_str(&invalid).unwrap();
+        let mode = &invalid["psram"].options.as_ref().unwrap()["mode"];
+        assert!(check_choice("psram.mode", mode).is_err());
+    }
+}
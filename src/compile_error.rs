@@ -0,0 +1,99 @@
+use std::io::Write;
+
+use crate::Error;
+
+/// What the user can do about the invalid configuration of the crate's section, for
+/// [write_compile_error]
+///
+/// ```text
+/// set a valid `esp-hal.heap.size` in config.toml or run `cargo rconfig`
+/// ```
+pub fn compile_error_message(section: &str, err: &Error) -> String {
+    match err {
+        Error::InvalidConfigurationValue(key) => format!(
+            "set a valid `{}.{}` in config.toml or run `cargo rconfig`",
+            section, key
+        ),
+        Error::InvalidConfiguration(key) => format!(
+            "remove or fix `{}` of `[{}]` in config.toml or run `cargo rconfig`",
+            key, section
+        ),
+        Error::InvalidKey => format!(
+            "`[{}]` in config.toml has an unknown key, fix it or run `cargo rconfig`",
+            section
+        ),
+        Error::InvalidToml(err) => format!("config.toml isn't valid: {}", err),
+        Error::UnmetConstraint(name, message) => format!(
+            "`[{}]` in config.toml doesn't meet the constraint `{}`: {}, fix it or run `cargo rconfig`",
+            section, name, message
+        ),
+        Error::ConflictingOptions(first, second) => format!(
+            "`{}.{}` and `{}.{}` can't be enabled together, change config.toml or run `cargo rconfig`",
+            section, first, section, second
+        ),
+        Error::MissingRequired(keys) => format!(
+            "set {} in config.toml or run `cargo rconfig`",
+            keys.iter()
+                .map(|key| format!("`{}.{}`", section, key))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        err => format!(
+            "the configuration of `[{}]` in config.toml is invalid ({:?}), run `cargo rconfig`",
+            section, err
+        ),
+    }
+}
+
+/// Write a `config.rs` failing the build with the message, enabled by `compile_errors = true` in
+/// the config-definition - the build script doesn't panic then and the message reaches the user
+/// as a regular compiler error
+///
+/// ```text
+/// compile_error!("set a valid `esp-hal.heap.size` in config.toml or run `cargo rconfig`");
+/// ```
+pub fn write_compile_error(out: &mut impl Write, message: &str) -> std::io::Result<()> {
+    writeln!(out, "compile_error!({:?});", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config, parse_definition_str};
+
+    #[test]
+    fn compile_errors() {
+        let definition = parse_definition_str(
+            r#"
+            [heap]
+            description = "Heapsize"
+
+            [heap.options.size]
+            description = "Bytes to allocate"
+            type = "u32"
+            valid = "value >= 1024"
+            "#,
+        )
+        .unwrap();
+        let err = evaluate_config(
+            "[esp-hal]\nheap.size = 512\n",
+            "esp-hal",
+            definition,
+            vec![],
+        )
+        .map(|_| ())
+        .unwrap_err();
+        let message = compile_error_message("esp-hal", &err);
+        assert_eq!(
+            "set a valid `esp-hal.heap.size` in config.toml or run `cargo rconfig`",
+            message
+        );
+
+        let mut out = Vec::new();
+        write_compile_error(&mut out, "a \"quoted\" key").unwrap();
+        assert_eq!(
+            "compile_error!(\"a \\\"quoted\\\" key\");\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
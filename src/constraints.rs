@@ -0,0 +1,377 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{parse_signed_integer_literal, ConfigOption, Error, Value, ValueType};
+
+/// The characters a string option accepts, e.g. `charset = "ident"`
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Charset {
+    #[serde(rename = "ascii")]
+    Ascii,
+    /// ASCII without control characters
+    #[serde(rename = "printable")]
+    Printable,
+    /// ASCII letters and digits
+    #[serde(rename = "alphanumeric")]
+    Alphanumeric,
+    /// ASCII letters, digits and `_`, not starting with a digit
+    #[serde(rename = "ident")]
+    Ident,
+}
+
+impl Charset {
+    /// Whether the character can be part of a value - e.g. to filter what's typed
+    pub fn accepts(self, c: char) -> bool {
+        match self {
+            Charset::Ascii => c.is_ascii(),
+            Charset::Printable => c.is_ascii() && !c.is_ascii_control(),
+            Charset::Alphanumeric => c.is_ascii_alphanumeric(),
+            Charset::Ident => c.is_ascii_alphanumeric() || c == '_',
+        }
+    }
+
+    pub fn matches(self, value: &str) -> bool {
+        if self == Charset::Ident && value.starts_with(|c: char| c.is_ascii_digit()) {
+            return false;
+        }
+        value.chars().all(|c| self.accepts(c))
+    }
+}
+
+/// Whether the value meets the `min_length`, `max_length`, `charset`, `step`, `min` and `max` of
+/// the option
+///
+/// Lengths are counted in characters.
+pub(crate) fn meets_constraints(option: &ConfigOption, value: &Value) -> bool {
+    if let Some(number) = crate::literal::json_integer(value) {
+        return option
+            .step
+            // `step = 0` is rejected by `check_constraints`, unchecked definitions mustn't panic
+            .is_none_or(|step| {
+                number
+                    .checked_rem(i128::from(step))
+                    .is_none_or(|rest| rest == 0)
+            })
+            && option.min.is_none_or(|min| number >= min.into())
+            && option.max.is_none_or(|max| number <= max.into());
+    }
+    let Some(value) = value.as_str() else {
+        return true;
+    };
+
+    let length = value.chars().count();
+    option.min_length.is_none_or(|min| length >= min)
+        && option.max_length.is_none_or(|max| length <= max)
+        && option.charset.is_none_or(|charset| charset.matches(value))
+}
+
+/// The constraints of the option as text, e.g. for tooling to show what's accepted
+///
+/// ```text
+/// ["1-32 characters", "printable"]
+/// ["1024 to 65536", "multiple of 1024"]
+/// ```
+pub fn describe_constraints(option: &ConfigOption) -> Vec<String> {
+    let mut constraints = Vec::new();
+    match (option.min_length, option.max_length) {
+        (Some(min), Some(max)) => constraints.push(format!("{}-{} characters", min, max)),
+        (Some(min), None) => constraints.push(format!("at least {} characters", min)),
+        (None, Some(max)) => constraints.push(format!("at most {} characters", max)),
+        (None, None) => (),
+    }
+    if let Some(charset) = option.charset {
+        constraints.push(format!("{:?}", charset).to_lowercase());
+    }
+    match (option.min, option.max) {
+        (Some(min), Some(max)) => constraints.push(format!("{} to {}", min, max)),
+        (Some(min), None) => constraints.push(format!("at least {}", min)),
+        (None, Some(max)) => constraints.push(format!("at most {}", max)),
+        (None, None) => (),
+    }
+    if let Some(step) = option.step {
+        constraints.push(format!("multiple of {}", step));
+    }
+    constraints
+}
+
+/// The smallest and the largest value of a numeric option as far as its `min`, `max` and `valid`
+/// tell - bounds like `value >= 1024 && value <= 0x8000` are recognized, the range of its type
+/// otherwise
+pub fn numeric_range(option: &ConfigOption) -> (i128, i128) {
+    let mut range = option
+        .value_type
+        .as_ref()
+        .and_then(ValueType::integer_range)
+        .unwrap_or((0, u32::MAX.into()));
+    if let Some(min) = option.min {
+        range.0 = range.0.max(min.into());
+    }
+    if let Some(max) = option.max {
+        range.1 = range.1.min(max.into());
+    }
+    let Some(valid) = option
+        .valid
+        .as_deref()
+        .filter(|valid| !valid.contains("||"))
+    else {
+        return range;
+    };
+
+    for bound in valid.split("&&") {
+        let Some(bound) = bound.trim().strip_prefix("value") else {
+            continue;
+        };
+        let bound = bound.trim_start();
+        let (operator, literal) = match bound.find(|c: char| !"<>=".contains(c)) {
+            Some(index) => bound.split_at(index),
+            None => continue,
+        };
+        let Some((number, _)) = parse_signed_integer_literal(literal) else {
+            continue;
+        };
+        match operator {
+            ">=" => range.0 = range.0.max(number),
+            ">" => range.0 = range.0.max(number + 1),
+            "<=" => range.1 = range.1.min(number),
+            "<" => range.1 = range.1.min(number - 1),
+            _ => (),
+        }
+    }
+
+    range
+}
+
+/// The length constraints are only allowed for string options and the length range can't be
+/// empty, `step` only for numeric options and not `0`, `min` and `max` only for numeric options
+/// and within the range of the type
+pub(crate) fn check_constraints(key: &str, option: &ConfigOption) -> Result<(), Error> {
+    let constrained =
+        option.min_length.is_some() || option.max_length.is_some() || option.charset.is_some();
+    if constrained && option.value_type != Some(ValueType::String) {
+        return Err(Error::InvalidDefinition(key.to_string()));
+    }
+
+    if let (Some(min), Some(max)) = (option.min_length, option.max_length) {
+        if min > max {
+            return Err(Error::InvalidDefinition(format!("{}.min_length", key)));
+        }
+    }
+
+    if let Some(step) = option.step {
+        if step == 0
+            || !option
+                .value_type
+                .as_ref()
+                .is_some_and(ValueType::is_integer)
+        {
+            return Err(Error::InvalidDefinition(format!("{}.step", key)));
+        }
+    }
+
+    if option.min.is_some() || option.max.is_some() {
+        let Some((type_min, type_max)) = option
+            .value_type
+            .as_ref()
+            .and_then(ValueType::integer_range)
+        else {
+            return Err(Error::InvalidDefinition(key.to_string()));
+        };
+        let (min, max) = (
+            option.min.map_or(type_min, i128::from),
+            option.max.map_or(type_max, i128::from),
+        );
+        if min > max || min < type_min || max > type_max {
+            return Err(Error::InvalidDefinition(format!("{}.min", key)));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    const DEFINITION: &str = r#"
+    [wifi]
+    description = "WiFi"
+
+    [wifi.options.ssid]
+    description = "SSID"
+    type = "string"
+    min_length = 1
+    max_length = 32
+    charset = "printable"
+
+    [wifi.options.hostname]
+    description = "Hostname"
+    type = "string"
+    max_length = 8
+    charset = "ident"
+    "#;
+
+    #[test]
+    fn string_constraints() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let options = definition["wifi"].options.as_ref().unwrap();
+        let ssid = &options["ssid"];
+        let hostname = &options["hostname"];
+
+        assert!(meets_constraints(ssid, &Value::from("my network")));
+        assert!(!meets_constraints(ssid, &Value::from("")));
+        assert!(!meets_constraints(ssid, &Value::from("x".repeat(33))));
+        assert!(!meets_constraints(ssid, &Value::from("tab\t")));
+        assert!(!meets_constraints(ssid, &Value::from("café")));
+
+        assert!(meets_constraints(hostname, &Value::from("esp_32")));
+        assert!(meets_constraints(hostname, &Value::from("")));
+        assert!(!meets_constraints(hostname, &Value::from("32esp")));
+        assert!(!meets_constraints(hostname, &Value::from("esp-32")));
+        assert!(!meets_constraints(hostname, &Value::from("esp32_hostname")));
+
+        assert!(check_constraints("wifi.ssid", ssid).is_ok());
+        assert_eq!(
+            vec!["1-32 characters", "printable"],
+            describe_constraints(ssid)
+        );
+
+        // the short names
+        let aliased = parse_definition_str(
+            &DEFINITION
+                .replace("min_length", "min_len")
+                .replace("max_length", "max_len"),
+        )
+        .unwrap();
+        let aliased = &aliased["wifi"].options.as_ref().unwrap()["ssid"];
+        assert_eq!(
+            (Some(1), Some(32)),
+            (aliased.min_length, aliased.max_length)
+        );
+        assert!(crate::evaluate_config_str(
+            &format!("[mycrate]\nwifi.ssid = \"{}\"\n", "x".repeat(33)),
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn step() {
+        let definition = parse_definition_str(
+            "[buffer]\ndescription = \"Buffer\"\ntype = \"u32\"\nmultiple_of = 1024\n",
+        )
+        .unwrap();
+        let buffer = &definition["buffer"];
+        assert_eq!(Some(1024), buffer.step);
+        assert!(meets_constraints(buffer, &Value::from(0)));
+        assert!(meets_constraints(buffer, &Value::from(4096)));
+        assert!(!meets_constraints(buffer, &Value::from(4000)));
+        assert_eq!(vec!["multiple of 1024"], describe_constraints(buffer));
+        assert_eq!((0, u32::MAX.into()), numeric_range(buffer));
+
+        let effective_config = crate::evaluate_config(
+            "[mycrate]\nbuffer = 2048\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(2048), effective_config.get_u32("buffer"));
+        assert!(crate::evaluate_config(
+            "[mycrate]\nbuffer = 2000\n",
+            "mycrate",
+            definition,
+            vec![]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn min_max() {
+        let definition = parse_definition_str(
+            "[heap]\ndescription = \"Heap\"\ntype = \"u32\"\nmin = 1024\nmax = 80000\ndefault = 4096\n",
+        )
+        .unwrap();
+        let heap = &definition["heap"];
+        assert_eq!(vec!["1024 to 80000"], describe_constraints(heap));
+        assert_eq!((1024, 80000), numeric_range(heap));
+        assert!(check_constraints("heap", heap).is_ok());
+
+        for (input, valid) in [
+            ("1024", true),
+            ("80000", true),
+            ("1000", false),
+            ("80001", false),
+        ] {
+            assert_eq!(
+                valid,
+                crate::evaluate_config(
+                    &format!("[mycrate]\nheap = {}\n", input),
+                    "mycrate",
+                    definition.clone(),
+                    vec![],
+                )
+                .is_ok(),
+                "{}",
+                input
+            );
+        }
+
+        for (definition, key) in [
+            ("type = \"u32\"\nmin = 10\nmax = 5\n", "heap.min"),
+            ("type = \"u8\"\nmax = 256\n", "heap.min"),
+            ("type = \"string\"\nmin = 1\n", "heap"),
+        ] {
+            let parsed =
+                parse_definition_str(&format!("[heap]\ndescription = \"Heap\"\n{}", definition))
+                    .unwrap();
+            match check_constraints("heap", &parsed["heap"]) {
+                Err(Error::InvalidDefinition(invalid)) => assert_eq!(key, invalid),
+                other => panic!("{}: {:?}", definition, other),
+            }
+        }
+    }
+
+    #[test]
+    fn numeric_ranges() {
+        let option = |valid: &str| ConfigOption {
+            value_type: Some(ValueType::U32),
+            valid: Some(valid.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            (1024, 0x8000),
+            numeric_range(&option("value >= 1024 && value <= 0x8000"))
+        );
+        assert_eq!((1, 99), numeric_range(&option("value > 0 && value < 100")));
+        assert_eq!(
+            (0, 13),
+            numeric_range(&option("value % 2 == 0 && value <= 13"))
+        );
+        assert_eq!(
+            (0, u32::MAX.into()),
+            numeric_range(&option("value < 10 || value > 20"))
+        );
+    }
+
+    #[test]
+    fn invalid_constraints() {
+        for definition in [
+            "[size]\ndescription = \"Size\"\ntype = \"u32\"\nmax_length = 4\n",
+            "[name]\ndescription = \"Name\"\ntype = \"string\"\nmin_length = 4\nmax_length = 2\n",
+            "[name]\ndescription = \"Name\"\ntype = \"string\"\nstep = 2\n",
+            "[size]\ndescription = \"Size\"\ntype = \"u32\"\nstep = 0\n",
+        ] {
+            let parsed = parse_definition_str(definition).unwrap();
+            let (key, option) = parsed.iter().next().unwrap();
+            assert!(check_constraints(key, option).is_err(), "{}", definition);
+            meets_constraints(option, &Value::from(3));
+        }
+
+        assert!(parse_definition_str(
+            "[name]\ndescription = \"Name\"\ntype = \"string\"\ncharset = \"emoji\"\n"
+        )
+        .is_err());
+    }
+}
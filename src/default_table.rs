@@ -0,0 +1,314 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+
+use crate::active::{check_syntax, Expressions};
+use crate::{is_value_of_type, ConfigOption, Error, Map, Value};
+
+/// The key of a default table used if none of the features is enabled
+pub const OTHER: &str = "other";
+
+/// Replace the default tables of the options by the default for the features - the first
+/// enabled feature of the table wins, `other` is used if there is none
+///
+/// ```toml
+/// [heap.options.size]
+/// description = "Bytes to allocate"
+/// type = "u32"
+/// default = { esp32 = 4096, esp32s3 = 16384, other = 1024 }
+/// ```
+///
+/// An option without a matching entry and without `other` has no default.
+///
+/// A list of conditional defaults is evaluated in order, the first entry whose `if` is true (or
+/// which has no `if`) wins - only `feature("...")` can be used in the conditions.
+///
+/// ```toml
+/// [psram.options.size]
+/// description = "PSRAM size in MB"
+/// type = "u32"
+/// default = [
+///     { if = 'feature("esp32s3")', value = 8 },
+///     { if = 'feature("esp32")', value = 4 },
+///     { value = 2 },
+/// ]
+/// ```
+pub fn select_defaults(config: &mut Map<String, ConfigOption>, features: &[&str]) {
+    select_level(config, features, &OnceCell::new());
+}
+
+fn select_level(
+    config: &mut Map<String, ConfigOption>,
+    features: &[&str],
+    expressions: &OnceCell<Expressions>,
+) {
+    for (_, option) in config.iter_mut() {
+        match &option.default_value {
+            Some(Value::Object(table)) => {
+                option.default_value = table
+                    .iter()
+                    .find(|(name, _)| features.contains(&name.as_str()))
+                    .or_else(|| table.iter().find(|(name, _)| *name == OTHER))
+                    .map(|(_, value)| value.clone());
+            }
+            Some(Value::Array(entries)) => {
+                let expressions =
+                    expressions.get_or_init(|| Expressions::new(&Map::new(), features));
+                option.default_value = entries
+                    .iter()
+                    .find(|entry| {
+                        let condition = entry.get("if").and_then(Value::as_str);
+                        expressions.depends(condition).unwrap_or(false)
+                    })
+                    .and_then(|entry| entry.get("value"))
+                    .cloned();
+            }
+            _ => (),
+        }
+
+        if let Some(options) = option.options.as_mut() {
+            select_level(options, features, expressions);
+        }
+    }
+}
+
+/// The config with the defaults for the features, only cloned if it has default tables
+pub(crate) fn with_defaults<'a>(
+    config: &'a Map<String, ConfigOption>,
+    features: &[&str],
+) -> Cow<'a, Map<String, ConfigOption>> {
+    if has_default_tables(config) {
+        let mut config = config.clone();
+        select_defaults(&mut config, features);
+        Cow::Owned(config)
+    } else {
+        Cow::Borrowed(config)
+    }
+}
+
+fn has_default_tables(config: &Map<String, ConfigOption>) -> bool {
+    config.values().any(|option| {
+        matches!(
+            option.default_value,
+            Some(Value::Object(_) | Value::Array(_))
+        ) || option.options.as_ref().is_some_and(has_default_tables)
+    })
+}
+
+/// The entries of the option's default table, `None` if the default isn't a table
+pub(crate) fn default_table(option: &ConfigOption) -> Option<&serde_json::Map<String, Value>> {
+    match &option.default_value {
+        Some(Value::Object(table)) => Some(table),
+        _ => None,
+    }
+}
+
+/// The entries of the option's conditional defaults, `None` if the default isn't a list
+pub(crate) fn conditional_defaults(option: &ConfigOption) -> Option<&[Value]> {
+    match &option.default_value {
+        Some(Value::Array(entries)) => Some(entries),
+        _ => None,
+    }
+}
+
+/// The `if` expressions of the option's conditional defaults
+pub(crate) fn default_conditions(option: &ConfigOption) -> impl Iterator<Item = &str> {
+    conditional_defaults(option)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.get("if").and_then(Value::as_str))
+}
+
+/// Only options with a type can have a default table, every entry has to be a value of the type
+///
+/// The same for conditional defaults - every entry needs a `value` and its `if` (if any) has to
+/// parse.
+pub(crate) fn check_default_table(key: &str, option: &ConfigOption) -> Result<(), Error> {
+    if let Some(entries) = conditional_defaults(option) {
+        for (index, entry) in entries.iter().enumerate() {
+            let valid = entry.as_object().is_some_and(|entry| {
+                entry.keys().all(|name| name == "if" || name == "value")
+                    && entry.get("if").is_none_or(Value::is_string)
+            }) && entry
+                .get("value")
+                .is_some_and(|value| is_value_of_type(option, value));
+            if !valid {
+                return Err(Error::InvalidDefinition(format!(
+                    "{}.default.{}",
+                    key, index
+                )));
+            }
+            if let Some(condition) = entry.get("if").and_then(Value::as_str) {
+                if check_syntax(condition).is_err() {
+                    return Err(Error::InvalidExpression(format!(
+                        "{}.default.{}.if",
+                        key, index
+                    )));
+                }
+            }
+        }
+    }
+
+    let Some(table) = default_table(option) else {
+        return Ok(());
+    };
+
+    for (name, value) in table {
+        if !is_value_of_type(option, value) {
+            return Err(Error::InvalidDefinition(format!(
+                "{}.default.{}",
+                key, name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{evaluate_config, parse_definition_str, referenced_features, ConfigOption, Map};
+
+    const DEFINITION: &str = r#"
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    default = { esp32 = 4096, esp32s3 = 16384, other = 1024 }
+    valid = "value >= 1024"
+
+    [heap.options.placement]
+    description = "Placement"
+    type = "enum"
+    values = [
+        { description = "Internal RAM", value = "internal" },
+        { description = "PSRAM", value = "psram" },
+    ]
+    default = { esp32s3 = "psram" }
+    "#;
+
+    #[test]
+    fn default_tables() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        for (features, size, placement) in [
+            (vec!["esp32"], 4096, None),
+            (vec!["esp32s3"], 16384, Some("psram")),
+            (vec!["esp32c6"], 1024, None),
+            (vec![], 1024, None),
+        ] {
+            let effective_config =
+                evaluate_config("", "mycrate", definition.clone(), features.clone()).unwrap();
+            assert_eq!(
+                Some(size),
+                effective_config.get_u32("heap.size"),
+                "{:?}",
+                features
+            );
+            assert_eq!(placement, effective_config.get_str("heap.placement"));
+        }
+
+        // the user's value still wins
+        let effective_config = evaluate_config(
+            "[mycrate]\nheap.size = 2048\n",
+            "mycrate",
+            definition.clone(),
+            vec!["esp32"],
+        )
+        .unwrap();
+        assert_eq!(Some(2048), effective_config.get_u32("heap.size"));
+
+        assert_eq!(vec!["esp32", "esp32s3"], referenced_features(&definition));
+    }
+
+    const CONDITIONAL: &str = r#"
+    [psram]
+    description = "PSRAM"
+
+    [psram.options.size]
+    description = "PSRAM size in MB"
+    type = "u32"
+    default = [
+        { if = 'feature("esp32s3")', value = 8 },
+        { if = 'feature("esp32") || feature("esp32s2")', value = 4 },
+        { value = 2 },
+    ]
+
+    [psram.options.mode]
+    description = "Mode"
+    type = "string"
+    default = [{ if = 'feature("esp32s3")', value = "octal" }]
+    "#;
+
+    #[test]
+    fn conditional_defaults() {
+        let definition = parse_definition_str(CONDITIONAL).unwrap();
+        for (features, size, mode) in [
+            (vec!["esp32s3"], 8, Some("octal")),
+            (vec!["esp32s2"], 4, None),
+            (vec!["esp32", "esp32s3"], 8, Some("octal")),
+            (vec![], 2, None),
+        ] {
+            let effective_config =
+                evaluate_config("", "mycrate", definition.clone(), features.clone()).unwrap();
+            assert_eq!(
+                Some(size),
+                effective_config.get_u32("psram.size"),
+                "{:?}",
+                features
+            );
+            assert_eq!(mode, effective_config.get_str("psram.mode"));
+        }
+        assert_eq!(
+            vec!["esp32s3", "esp32", "esp32s2"],
+            referenced_features(&definition)
+        );
+
+        let written = crate::definition_to_toml_string(&definition);
+        let size = |definition: &Map<String, ConfigOption>| {
+            definition["psram"].options.as_ref().unwrap()["size"]
+                .default_value
+                .clone()
+        };
+        assert_eq!(
+            size(&definition),
+            size(&parse_definition_str(&written).unwrap())
+        );
+
+        for (invalid, error) in [
+            (
+                CONDITIONAL.replace("value = 8", "value = \"eight\""),
+                "InvalidDefinition(\"psram.size.default.0\")",
+            ),
+            (
+                CONDITIONAL.replace("{ value = 2 }", "{ default = 2 }"),
+                "InvalidDefinition(\"psram.size.default.2\")",
+            ),
+            (
+                CONDITIONAL.replace("feature(\"esp32s3\")', value = 8", "feature(', value = 8"),
+                "InvalidExpression(\"psram.size.default.0.if\")",
+            ),
+        ] {
+            let definition = parse_definition_str(&invalid).unwrap();
+            let err = evaluate_config("", "mycrate", definition, vec![])
+                .map(|_| ())
+                .unwrap_err();
+            assert_eq!(error, format!("{:?}", err), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn invalid_default_tables() {
+        for invalid in [
+            DEFINITION.replace("esp32 = 4096", "esp32 = \"4 KB\""),
+            DEFINITION.replace("esp32s3 = \"psram\"", "esp32s3 = \"flash\""),
+        ] {
+            let definition = parse_definition_str(&invalid).unwrap();
+            assert!(
+                evaluate_config("", "mycrate", definition, vec![]).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+}
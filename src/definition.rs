@@ -0,0 +1,317 @@
+use crate::{
+    definition_to_toml_string, evaluate_config, parse_definition_str, Charset, ConfigOption,
+    ConfigValue, DefinitionBuilder, EffectiveConfig, Error, EvalContext, Map, OptionIndex,
+    ValueType,
+};
+
+/// A config-definition
+///
+/// Prefer this (and [OptionNode]) over the `Map<String, ConfigOption>` most functions take - the
+/// collection types used internally might change.
+#[derive(Debug, Clone, Default)]
+pub struct Definition(Map<String, ConfigOption>);
+
+impl Definition {
+    /// Parse a config-definition in TOML
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        parse_definition_str(input).map(Self)
+    }
+
+    /// Create a config-definition in code instead of TOML
+    ///
+    /// ```
+    /// use rconfig::Definition;
+    ///
+    /// let definition = Definition::builder()
+    ///     .menu("psram", "PSRAM", |psram| {
+    ///         psram
+    ///             .bool("enable", "Enable PSRAM")
+    ///             .default_value(false)
+    ///             .enumeration("size", "PSRAM Size", &[("1", "1MB"), ("2", "2MB")])
+    ///             .default_value("2")
+    ///             .depends("enabled(\"psram.enable\")")
+    ///     })
+    ///     .depends("feature(\"esp32\")")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert!(definition.get("psram.size").is_some());
+    /// ```
+    pub fn builder() -> DefinitionBuilder {
+        DefinitionBuilder::default()
+    }
+
+    /// The top-level menus and options
+    pub fn iter(&self) -> impl Iterator<Item = OptionNode<'_>> {
+        nodes(&self.0, "")
+    }
+
+    /// The option or menu with the dotted key, e.g. `psram.size`
+    pub fn get(&self, key: &str) -> Option<OptionNode<'_>> {
+        let mut parts = key.split('.');
+        let mut option = self.0.get(parts.next()?)?;
+        for part in parts {
+            option = option.options.as_ref()?.get(part)?;
+        }
+        Some(OptionNode {
+            key: key.to_string(),
+            option,
+        })
+    }
+
+    /// Index all menus and options by their dotted key, for looking up many of them
+    pub fn index(&self) -> OptionIndex<'_> {
+        OptionIndex::new(&self.0)
+    }
+
+    pub fn to_toml_string(&self) -> String {
+        definition_to_toml_string(&self.0)
+    }
+
+    /// Evaluate the user's config (the content of `config.toml`) for the crate
+    pub fn evaluate(
+        &self,
+        input: &str,
+        crate_name: &str,
+        ctx: &EvalContext,
+    ) -> Result<EffectiveConfig, Error> {
+        let features = ctx
+            .features
+            .iter()
+            .map(|feature| feature.as_str())
+            .collect();
+        evaluate_config(input, crate_name, self.0.clone(), features)
+    }
+
+    /// The underlying collection, for the functions not taking a [Definition] yet
+    pub fn as_map(&self) -> &Map<String, ConfigOption> {
+        &self.0
+    }
+
+    pub fn into_map(self) -> Map<String, ConfigOption> {
+        self.0
+    }
+}
+
+impl From<Map<String, ConfigOption>> for Definition {
+    fn from(options: Map<String, ConfigOption>) -> Self {
+        Self(options)
+    }
+}
+
+/// A menu, option or comment of a [Definition]
+#[derive(Debug, Clone)]
+pub struct OptionNode<'a> {
+    key: String,
+    option: &'a ConfigOption,
+}
+
+fn nodes<'a>(
+    options: &'a Map<String, ConfigOption>,
+    prefix: &str,
+) -> impl Iterator<Item = OptionNode<'a>> {
+    let prefix = prefix.to_string();
+    options.iter().map(move |(name, option)| OptionNode {
+        key: format!("{}{}", prefix, name),
+        option,
+    })
+}
+
+impl<'a> OptionNode<'a> {
+    /// The dotted key, e.g. `psram.size`
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn description(&self) -> &'a str {
+        &self.option.description
+    }
+
+    /// `None` for menus and comments
+    pub fn value_type(&self) -> Option<ValueType> {
+        self.option.value_type.clone()
+    }
+
+    pub fn default_value(&self) -> Option<ConfigValue> {
+        ConfigValue::from_json(self.option.default_value.as_ref()?)
+    }
+
+    /// The `(value, description)` pairs of an enum
+    pub fn values(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.option
+            .values
+            .iter()
+            .flatten()
+            .map(|item| (item.value.as_str(), item.description.as_str()))
+    }
+
+    pub fn depends(&self) -> Option<&'a str> {
+        self.option.depends.as_deref()
+    }
+
+    pub fn valid(&self) -> Option<&'a str> {
+        self.option.valid.as_deref()
+    }
+
+    /// The `(min_length, max_length)` of a string option
+    pub fn length(&self) -> (Option<usize>, Option<usize>) {
+        (self.option.min_length, self.option.max_length)
+    }
+
+    pub fn charset(&self) -> Option<Charset> {
+        self.option.charset
+    }
+
+    /// Numeric values have to be a multiple of it
+    pub fn step(&self) -> Option<u64> {
+        self.option.step
+    }
+
+    /// The user has to set the value
+    pub fn is_required(&self) -> bool {
+        self.option.required
+    }
+
+    /// Tooling only shows the option in expert mode
+    pub fn is_hidden(&self) -> bool {
+        self.option.hidden
+    }
+
+    /// Setting the option makes the build warn
+    pub fn is_deprecated(&self) -> bool {
+        self.option.deprecated
+    }
+
+    /// The key the option had before
+    pub fn renamed_from(&self) -> Option<&str> {
+        self.option.renamed_from.as_deref()
+    }
+
+    /// The `(min, max)` of an integer option
+    pub fn range(&self) -> (Option<i64>, Option<i64>) {
+        (self.option.min, self.option.max)
+    }
+
+    /// The expression deciding whether code is generated for the option
+    pub fn emit_if(&self) -> Option<&'a str> {
+        self.option.emit_if.as_deref()
+    }
+
+    /// Exactly one bool option of the menu is `true`
+    pub fn is_choice(&self) -> bool {
+        self.option.choice
+    }
+
+    /// The group of options claiming the same resources
+    pub fn exclusive_group(&self) -> Option<&'a str> {
+        self.option.exclusive_group.as_deref()
+    }
+
+    /// The options defaulting to `true` while the option is enabled
+    pub fn imply(&self) -> &'a [String] {
+        self.option.imply.as_deref().unwrap_or_default()
+    }
+
+    /// The options which can't be enabled together with the option
+    pub fn conflicts(&self) -> &'a [String] {
+        self.option.conflicts.as_deref().unwrap_or_default()
+    }
+
+    /// The options forced to `true` while the option is enabled
+    pub fn selects(&self) -> &'a [String] {
+        self.option.selects.as_deref().unwrap_or_default()
+    }
+
+    pub fn is_secret(&self) -> bool {
+        self.option.secret
+    }
+
+    /// The environment variable the value comes from
+    pub fn env(&self) -> Option<&'a str> {
+        self.option.env.as_deref()
+    }
+
+    pub fn is_menu(&self) -> bool {
+        self.option.options.is_some()
+    }
+
+    /// An entry without a type and options is shown as a comment between the options
+    pub fn is_comment(&self) -> bool {
+        self.option.options.is_none() && self.option.value_type.is_none()
+    }
+
+    /// The options and menus of a menu
+    pub fn children(&self) -> impl Iterator<Item = OptionNode<'a>> {
+        let prefix = format!("{}.", self.key);
+        self.option
+            .options
+            .iter()
+            .flat_map(move |options| nodes(options, &prefix))
+    }
+
+    /// The underlying definition of the option
+    pub fn as_option(&self) -> &'a ConfigOption {
+        self.option
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    type = "enum"
+    values = [
+        { description = "1MB", value = "1" },
+        { description = "2MB", value = "2" },
+    ]
+    default = "2"
+    "#;
+
+    #[test]
+    fn facade() {
+        let definition = Definition::parse(DEFINITION).unwrap();
+
+        let psram = definition.iter().next().unwrap();
+        assert!(psram.is_menu());
+        assert_eq!(Some("feature(\"esp32\")"), psram.depends());
+        assert_eq!(
+            vec!["psram.enable", "psram.size"],
+            psram
+                .children()
+                .map(|child| child.key().to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let size = definition.get("psram.size").unwrap();
+        assert_eq!(Some(ValueType::Enum), size.value_type());
+        assert_eq!(Some(ConfigValue::from("2")), size.default_value());
+        assert_eq!(
+            vec![("1", "1MB"), ("2", "2MB")],
+            size.values().collect::<Vec<_>>()
+        );
+
+        let config = definition
+            .evaluate(
+                "[mycrate]\npsram.enable = true\n",
+                "mycrate",
+                &EvalContext::new(&["esp32"]),
+            )
+            .unwrap();
+        assert_eq!(
+            Some(ConfigValue::Bool(true)),
+            config.get_config_value("psram.enable")
+        );
+    }
+}
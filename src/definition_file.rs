@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+use crate::cfgs::CfgNamespace;
+use crate::deprecated::rename_migrations;
+use crate::format::DefinitionFormat;
+use crate::global_constraints::GlobalConstraint;
+use crate::instances::expand_instances;
+use crate::migrate::Migration;
+use crate::size::normalize_number_defaults;
+use crate::templates::RawOption;
+use crate::unless::fold_unless;
+use crate::{ConfigOption, Error, Map, Value};
+
+/// A config-definition file - the options and the settings for the whole definition
+///
+/// ```toml
+/// section_name = "hal"
+/// include = ["psram.toml"]
+///
+/// [[migrations]]
+/// from = "psram.mode"
+/// to = "psram.type"
+///
+/// [psram]
+/// description = "PSRAM"
+/// ```
+#[derive(Deserialize, Serialize, Default)]
+pub(crate) struct DefinitionFile<O = ConfigOption> {
+    /// More files of the config-definition, see [crate::definition_files]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub migrations: Vec<Migration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfg_namespace: Option<CfgNamespace>,
+    /// The crate's section in `config.toml` if it's not the package name
+    #[serde(
+        default,
+        alias = "section-name",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub section_name: Option<String>,
+    /// Also set `not_<key>` cfgs for bool options which are `false`, see [crate::CfgNames]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub negated_cfgs: bool,
+    /// Generate the `rconfig_build_info` module, see [crate::write_build_info]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub build_info: bool,
+    /// Fail the build via `compile_error!` in `config.rs` instead of panicking if the user's
+    /// config is invalid, see [crate::write_compile_error]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compile_errors: bool,
+    /// Only generate `config.rs` - no `rustc-cfg` (and `rustc-check-cfg`) for the options
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub consts_only: bool,
+    /// Also generate a `cfg_<key>!` macro per bool option, see [crate::write_cfg_macros]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cfg_macros: bool,
+    /// All features the expressions can check, see [crate::parse_declared_features_str]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// Expressions over all options which have to be true, see [crate::GlobalConstraint]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub constraints: Map<String, GlobalConstraint>,
+    /// The bases of options with `extends`, see [DefinitionFile::expand_templates]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub templates: Map<String, serde_json::Map<String, Value>>,
+    #[serde(flatten)]
+    pub options: Map<String, O>,
+}
+
+impl DefinitionFile {
+    pub(crate) fn parse(definition: &str) -> Result<Self, Error> {
+        Self::parse_as(definition, DefinitionFormat::detect(definition))
+    }
+
+    pub(crate) fn parse_as(definition: &str, format: DefinitionFormat) -> Result<Self, Error> {
+        // the options might be incomplete without their templates
+        let mut parsed = if format
+            .parse::<Value>(definition)?
+            .get("templates")
+            .is_some()
+        {
+            format
+                .parse::<DefinitionFile<RawOption>>(definition)?
+                .expand_templates()?
+        } else {
+            format.parse::<DefinitionFile>(definition)?
+        };
+        fold_unless(&mut parsed.options);
+        normalize_number_defaults(&mut parsed.options);
+        expand_instances(&mut parsed.options)?;
+        let renames = rename_migrations(&parsed.options);
+        parsed.migrations.extend(renames);
+        Ok(parsed)
+    }
+}
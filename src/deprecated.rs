@@ -0,0 +1,117 @@
+use crate::migrate::collect_entries;
+use crate::{parse_toml, ConfigOption, Error, Map, Migration, OptionIndex, Value};
+
+/// The migrations of the options' `renamed_from` - the old keys still work, the build warns about
+/// them and `cargo rconfig upgrade` rewrites them
+///
+/// ```toml
+/// [wifi.options.rx_buffers]
+/// description = "RX buffers"
+/// type = "u32"
+/// default = 10
+/// renamed_from = "wifi.rx_queue"
+/// ```
+pub(crate) fn rename_migrations(config: &Map<String, ConfigOption>) -> Vec<Migration> {
+    OptionIndex::new(config)
+        .iter()
+        .filter_map(|(key, option)| {
+            option.renamed_from.as_ref().map(|from| Migration {
+                from: from.clone(),
+                to: Some(key.to_string()),
+                values: None,
+            })
+        })
+        .collect()
+}
+
+/// An option can't be renamed from a key which is still in use
+pub(crate) fn check_renamed(config: &Map<String, ConfigOption>) -> Result<(), Error> {
+    let index = OptionIndex::new(config);
+    for (key, option) in index.iter() {
+        if let Some(from) = &option.renamed_from {
+            if index.get(from).is_some() {
+                return Err(Error::InvalidDefinition(format!("{}.renamed_from", key)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Warnings for the `deprecated` options set in the crate's section of the user's config
+pub(crate) fn deprecated_warnings(
+    config: &Map<String, ConfigOption>,
+    user_config: &str,
+    crate_name: &str,
+) -> Result<Vec<String>, Error> {
+    let mut entries = Vec::new();
+    if let Some(section) = parse_toml::<Value>(user_config)?.get(crate_name) {
+        collect_entries(&mut entries, section, "");
+    }
+
+    let index = OptionIndex::new(config);
+    Ok(entries
+        .into_iter()
+        .filter(|(key, _)| index.get(key).is_some_and(|option| option.deprecated))
+        .map(|(key, _)| format!("`{}` in `config.toml` is deprecated", key))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{resolve, Error, EvalContext};
+
+    const DEFINITION: &str = r#"
+    [wifi]
+    description = "WiFi"
+
+    [wifi.options.rx_buffers]
+    description = "RX buffers"
+    type = "u32"
+    default = 10
+    renamed_from = "wifi.rx_queue"
+
+    [wifi.options.legacy_power_save]
+    description = "Legacy power save"
+    type = "bool"
+    default = false
+    deprecated = true
+    "#;
+
+    #[test]
+    fn deprecated_and_renamed() {
+        let ctx = EvalContext::new(&[]).with_crate_name("mycrate");
+        let effective_config = resolve(
+            DEFINITION,
+            "[mycrate]\nwifi.rx_queue = 16\nwifi.legacy_power_save = true\n",
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(Some(16), effective_config.get_u64("wifi.rx_buffers"));
+        assert_eq!(
+            vec![
+                "`wifi.rx_queue` in `config.toml` is outdated, use `wifi.rx_buffers` instead",
+                "`wifi.legacy_power_save` in `config.toml` is deprecated",
+            ],
+            effective_config.warnings()
+        );
+
+        // tooling rewrites the old key
+        let (upgraded, applied) = crate::migrate_user_config(
+            "[mycrate]\nwifi.rx_queue = 16\n",
+            "mycrate",
+            &crate::parse_migrations_str(DEFINITION).unwrap(),
+        )
+        .unwrap();
+        assert_eq!("[mycrate]\nwifi.rx_buffers=16\n", upgraded);
+        assert_eq!(1, applied.len());
+
+        assert!(resolve(DEFINITION, "", &ctx).unwrap().warnings().is_empty());
+
+        let still_used = DEFINITION.replace("wifi.rx_queue", "wifi.legacy_power_save");
+        match resolve(&still_used, "", &ctx) {
+            Err(Error::InvalidDefinition(key)) => assert_eq!("wifi.rx_buffers.renamed_from", key),
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+    }
+}
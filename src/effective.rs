@@ -0,0 +1,135 @@
+use crate::{ConfigValue, Value, ValueType};
+
+/// Where the value of an option comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Provenance {
+    /// Set in the user's `config.toml`
+    User,
+    /// The default of the config-definition
+    Default,
+    /// The environment variable of the option, see [crate::ConfigOption::env]
+    Env,
+}
+
+/// A single option of the effective config
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    /// The dotted key, e.g. `psram.size`
+    pub key: String,
+    pub value: Value,
+    pub value_type: ValueType,
+    pub provenance: Provenance,
+    /// Don't show the value, see [crate::ConfigOption::secret]
+    pub secret: bool,
+}
+
+/// The values of all options available with the user's config and the features
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EffectiveConfig {
+    entries: Vec<ConfigEntry>,
+    warnings: Vec<String>,
+}
+
+impl EffectiveConfig {
+    pub fn new(entries: Vec<ConfigEntry>) -> Self {
+        Self {
+            entries,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Things the user should know about, e.g. outdated keys in `config.toml`
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn entries(&self) -> &[ConfigEntry] {
+        &self.entries
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ConfigEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfigEntry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+
+    pub fn get_value(&self, key: &str) -> Option<&Value> {
+        self.get(key).map(|entry| &entry.value)
+    }
+
+    pub fn get_config_value(&self, key: &str) -> Option<ConfigValue> {
+        ConfigValue::from_json(self.get_value(key)?)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get_value(key)?.as_bool()
+    }
+
+    pub fn get_u32(&self, key: &str) -> Option<u32> {
+        self.get_value(key)?
+            .as_u64()
+            .and_then(|value| u32::try_from(value).ok())
+    }
+
+    /// The value of the other unsigned integer options
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        self.get_value(key)?.as_u64()
+    }
+
+    /// The value of signed integer options
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get_value(key)?.as_i64()
+    }
+
+    /// The value of string and enum options
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get_value(key)?.as_str()
+    }
+
+    /// The `(key, value, type)` tuples with stringified values as returned by [crate::evaluate_config_str]
+    pub fn to_tuples(&self) -> Vec<(String, String, ValueType)> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.key.clone(),
+                    entry.value.to_string(),
+                    entry.value_type.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a EffectiveConfig {
+    type Item = &'a ConfigEntry;
+    type IntoIter = std::slice::Iter<'a, ConfigEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl IntoIterator for EffectiveConfig {
+    type Item = ConfigEntry;
+    type IntoIter = std::vec::IntoIter<ConfigEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
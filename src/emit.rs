@@ -0,0 +1,111 @@
+use crate::active::Expressions;
+use crate::{ConfigOption, EffectiveConfig, Error, Map, OptionIndex};
+
+/// The part of the definition and of the effective config the code is generated for - without
+/// the options (and their sub-options) whose `emit_if` is false
+///
+/// ```toml
+/// [unstable]
+/// description = "Unstable API"
+/// emit_if = 'feature("unstable-api")'
+/// ```
+///
+/// The options are still evaluated and validated, only `feature("...")` and `target("...")` can
+/// be used in `emit_if`.
+pub fn emitted_config(
+    definition: &Map<String, ConfigOption>,
+    cfg: &EffectiveConfig,
+    features: &[&str],
+    target: Option<&str>,
+) -> Result<(Map<String, ConfigOption>, EffectiveConfig), Error> {
+    let expressions = Expressions::new(&Map::new(), features).with_target(target);
+    let definition = emitted_options(definition, &expressions, "")?;
+
+    let index = OptionIndex::new(&definition);
+    let entries = cfg
+        .iter()
+        .filter(|entry| index.get(&entry.key).is_some())
+        .cloned()
+        .collect();
+    let cfg = EffectiveConfig::new(entries).with_warnings(cfg.warnings().to_vec());
+    Ok((definition, cfg))
+}
+
+fn emitted_options(
+    config: &Map<String, ConfigOption>,
+    expressions: &Expressions,
+    prefix: &str,
+) -> Result<Map<String, ConfigOption>, Error> {
+    let mut emitted = Map::new();
+    for (name, option) in config {
+        let key = format!("{}{}", prefix, name);
+        let emit = expressions
+            .depends(option.emit_if.as_deref())
+            .map_err(|_| Error::InvalidExpression(format!("{}.emit_if", key)))?;
+        if !emit {
+            continue;
+        }
+
+        let mut option = option.clone();
+        if let Some(options) = option.options.as_ref() {
+            option.options = Some(emitted_options(options, expressions, &format!("{}.", key))?);
+        }
+        emitted.insert(name.clone(), option);
+    }
+
+    Ok(emitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    const DEFINITION: &str = r#"
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    default = 4096
+
+    [unstable]
+    description = "Unstable API"
+    emit_if = 'feature("unstable-api")'
+
+    [unstable.options.enable]
+    description = "Enable"
+    type = "bool"
+    default = true
+    "#;
+
+    #[test]
+    fn emit_if() {
+        let generated = testing::generate(DEFINITION, "", &[]).unwrap();
+        assert!(generated
+            .config_rs
+            .contains("pub const HEAP_SIZE: u32 = 4096;"));
+        assert!(!generated.config_rs.contains("UNSTABLE_ENABLE"));
+        assert!(!generated.cfgs.iter().any(|cfg| cfg.contains("unstable")));
+        // still declared
+        assert!(generated
+            .check_cfgs
+            .iter()
+            .any(|cfg| cfg.contains("unstable_enable")));
+
+        let generated = testing::generate(DEFINITION, "", &["unstable-api"]).unwrap();
+        assert!(generated
+            .config_rs
+            .contains("pub const UNSTABLE_ENABLE: bool = true;"));
+        assert!(generated.cfgs.contains(&"unstable_enable".to_string()));
+
+        // still evaluated
+        assert!(testing::generate(DEFINITION, "unstable.missing = true", &[]).is_err());
+        assert!(testing::generate(
+            &DEFINITION.replace("feature(\"unstable-api\")", "feature("),
+            "",
+            &[]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{evaluate_config, ConfigOption, EffectiveConfig, Map, Provenance, Value, ValueType};
+
+const CRATE_NAME: &str = "crate-under-test";
+
+/// Enumerate the distinct valid configurations with the given features, e.g. to check a crate
+/// with all of them in CI
+///
+/// All combinations of the `bool` and `enum` options are tried (each also left unset), other
+/// options keep their defaults. Combinations which are invalid or result in the same effective
+/// config as an earlier one are skipped. Stops after `limit` configurations.
+pub fn enumerate_configs(
+    definition: &Map<String, ConfigOption>,
+    features: &[&str],
+    limit: usize,
+) -> Vec<EffectiveConfig> {
+    let mut choices = Vec::new();
+    collect_choices(&mut choices, definition, "");
+
+    let mut result: Vec<EffectiveConfig> = Vec::new();
+    let mut selected = vec![0; choices.len()];
+    loop {
+        let mut input = format!("[{}]\n", CRATE_NAME);
+        for ((key, values), index) in choices.iter().zip(&selected) {
+            if let Some(value) = &values[*index] {
+                input.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+
+        if let Ok(config) =
+            evaluate_config(&input, CRATE_NAME, definition.clone(), features.to_vec())
+        {
+            let tuples = config.to_tuples();
+            if !result.iter().any(|existing| existing.to_tuples() == tuples) {
+                result.push(config);
+                if result.len() >= limit {
+                    break;
+                }
+            }
+        }
+
+        // next combination - the first option changes fastest
+        let mut position = 0;
+        while position < selected.len() {
+            selected[position] += 1;
+            if selected[position] < choices[position].1.len() {
+                break;
+            }
+            selected[position] = 0;
+            position += 1;
+        }
+        if position == selected.len() {
+            break;
+        }
+    }
+
+    result
+}
+
+/// The user's `config.toml` for a configuration - only the values which differ from the defaults
+pub fn config_to_toml_string(crate_name: &str, config: &EffectiveConfig) -> String {
+    let mut out = format!("[{}]\n", crate_name);
+    for entry in config {
+        if entry.provenance == Provenance::User {
+            out.push_str(&format!("{}={}\n", entry.key, entry.value));
+        }
+    }
+    out
+}
+
+fn collect_choices(
+    choices: &mut Vec<(String, Vec<Option<Value>>)>,
+    config: &Map<String, ConfigOption>,
+    prefix: &str,
+) {
+    for (name, item) in config {
+        let key = format!("{}{}", prefix, name);
+
+        let values: Vec<Value> = match item.value_type {
+            Some(ValueType::Bool) => vec![Value::Bool(true), Value::Bool(false)],
+            Some(ValueType::Enum) => item
+                .values
+                .iter()
+                .flatten()
+                .map(|item| Value::String(item.value.clone()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        if !values.is_empty() {
+            choices.push((
+                key.clone(),
+                std::iter::once(None)
+                    .chain(values.into_iter().map(Some))
+                    .collect(),
+            ));
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            collect_choices(choices, options, &format!("{}.", key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32") || feature("esp32s3")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    depends = 'enabled("psram.enable")'
+    type = "enum"
+    values = [
+        { description = "1MB", value = "1" },
+        { description = "2MB", value = "2" },
+    ]
+    default = "2"
+
+    [psram.options.type]
+    description = "PSRAM Type"
+    depends = 'feature("esp32s3")'
+    type = "enum"
+    values = [
+        { description = "Quad", value = "quad" },
+        { description = "Octal", value = "octal" },
+    ]
+    default = "quad"
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    default = 4096
+    "#;
+
+    #[test]
+    fn enumerate() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let configs = enumerate_configs(&definition, &["esp32"], 100);
+
+        assert_eq!(
+            vec![
+                "[mycrate]\n",
+                "[mycrate]\npsram.enable=true\n",
+                "[mycrate]\npsram.enable=true\npsram.size=\"1\"\n",
+            ],
+            configs
+                .iter()
+                .map(|config| config_to_toml_string("mycrate", config))
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(6, enumerate_configs(&definition, &["esp32s3"], 100).len());
+        assert_eq!(2, enumerate_configs(&definition, &["esp32s3"], 2).len());
+    }
+}
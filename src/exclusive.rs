@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+use crate::active::ActiveSet;
+use crate::{ConfigOption, Error, Map, OptionIndex, Value, ValueType};
+
+/// Two available options of the same `exclusive_group` claiming the same resource - the first
+/// and the second one in the order of the definition
+///
+/// ```toml
+/// [spi.options.dma]
+/// description = "Use DMA channel 2"
+/// type = "bool"
+/// exclusive_group = "dma_channel_2"
+///
+/// [i2s.options.dma]
+/// description = "Use DMA channel 2"
+/// type = "bool"
+/// exclusive_group = "dma_channel_2"
+/// ```
+///
+/// Only one bool option of a group can be `true`, options of other types can't have the same
+/// value (e.g. a pin number).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Conflict {
+    pub first: String,
+    pub second: String,
+}
+
+pub(crate) fn conflicts(config: &Map<String, ConfigOption>, active: &ActiveSet) -> Vec<Conflict> {
+    let mut claimed = HashMap::new();
+    let mut conflicts = Vec::new();
+    collect_conflicts(&mut claimed, &mut conflicts, config, active, "");
+    conflicts
+}
+
+fn collect_conflicts(
+    claimed: &mut HashMap<(String, String), String>,
+    conflicts: &mut Vec<Conflict>,
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+    prefix: &str,
+) {
+    for (name, option) in config {
+        let key = format!("{}{}", prefix, name);
+        if !active.contains(&key) {
+            continue;
+        }
+
+        if let Some(options) = option.options.as_ref() {
+            collect_conflicts(claimed, conflicts, options, active, &format!("{}.", key));
+        }
+
+        let Some(group) = option.exclusive_group.as_ref() else {
+            continue;
+        };
+        let claim = match option.__value.as_ref().or(option.default_value.as_ref()) {
+            Some(Value::Bool(false)) | None => continue,
+            Some(Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+        };
+        match claimed.get(&(group.clone(), claim.clone())) {
+            Some(first) => conflicts.push(Conflict {
+                first: first.clone(),
+                second: key,
+            }),
+            None => {
+                claimed.insert((group.clone(), claim), key);
+            }
+        }
+    }
+}
+
+/// The first conflict makes the config invalid, see [conflicts]
+pub(crate) fn check_exclusive(
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+) -> Result<(), Error> {
+    match conflicts(config, active).into_iter().next() {
+        Some(conflict) => Err(Error::InvalidConfigurationValue(conflict.second)),
+        None => Ok(()),
+    }
+}
+
+/// Two available, enabled bool options one of them declares to be incompatible with
+///
+/// ```toml
+/// [psram.options.octal]
+/// description = "Octal mode"
+/// type = "bool"
+/// conflicts = ["dma.burst"]
+/// ```
+///
+/// The option declaring the conflict is `first`, each pair is only reported once.
+pub(crate) fn declared_conflicts(
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+) -> Vec<Conflict> {
+    let index = OptionIndex::new(config);
+    let is_enabled = |key: &str| {
+        active.contains(key)
+            && index.get(key).is_some_and(|option| {
+                option.__value.as_ref().or(option.default_value.as_ref())
+                    == Some(&Value::Bool(true))
+            })
+    };
+
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    for (key, option) in index.iter() {
+        if !is_enabled(key) {
+            continue;
+        }
+        for other in option.conflicts.iter().flatten() {
+            let reported = conflicts
+                .iter()
+                .any(|conflict| conflict.first == *other && conflict.second == key);
+            if is_enabled(other) && !reported {
+                conflicts.push(Conflict {
+                    first: key.to_string(),
+                    second: other.clone(),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// The first declared conflict makes the config invalid, see [declared_conflicts]
+pub(crate) fn check_declared_conflicts(
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+) -> Result<(), Error> {
+    match declared_conflicts(config, active).into_iter().next() {
+        Some(conflict) => Err(Error::ConflictingOptions(conflict.first, conflict.second)),
+        None => Ok(()),
+    }
+}
+
+/// Only bool options can conflict, with other bool options of the definition
+pub(crate) fn check_conflicts_definition(config: &Map<String, ConfigOption>) -> Result<(), Error> {
+    let index = OptionIndex::new(config);
+    let is_bool = |option: &ConfigOption| option.value_type == Some(ValueType::Bool);
+    for (key, option) in index.iter() {
+        let Some(conflicts) = option.conflicts.as_ref() else {
+            continue;
+        };
+        if !is_bool(option)
+            || !conflicts
+                .iter()
+                .all(|other| other != key && index.get(other).is_some_and(is_bool))
+        {
+            return Err(Error::InvalidDefinition(format!("{}.conflicts", key)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Only options with a type can be in a group
+pub(crate) fn check_exclusive_group(key: &str, option: &ConfigOption) -> Result<(), Error> {
+    if option.exclusive_group.is_some() && option.value_type.is_none() {
+        return Err(Error::InvalidDefinition(format!("{}.exclusive_group", key)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_config_str, evaluate_config, parse_definition_str, ConfigProblemKind};
+
+    const DEFINITION: &str = r#"
+    [spi]
+    description = "SPI"
+
+    [spi.options.dma]
+    description = "Use DMA channel 2"
+    type = "bool"
+    default = true
+    exclusive_group = "dma_channel_2"
+
+    [spi.options.pin]
+    description = "Clock pin"
+    type = "u32"
+    default = 4
+    exclusive_group = "pins"
+
+    [i2s]
+    description = "I2S"
+
+    [i2s.options.enable]
+    description = "Enable I2S"
+    type = "bool"
+    default = false
+
+    [i2s.options.dma]
+    description = "Use DMA channel 2"
+    depends = 'enabled("i2s.enable")'
+    type = "bool"
+    default = true
+    exclusive_group = "dma_channel_2"
+
+    [i2s.options.pin]
+    description = "Clock pin"
+    type = "u32"
+    default = 5
+    exclusive_group = "pins"
+    "#;
+
+    #[test]
+    fn exclusive_groups() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        // `i2s.dma` isn't available
+        assert!(evaluate_config("", "mycrate", definition.clone(), vec![]).is_ok());
+
+        for (user_config, conflicting) in [
+            ("i2s.enable = true", "i2s.dma"),
+            ("i2s.pin = 4", "i2s.pin"),
+            ("spi.pin = 5", "i2s.pin"),
+        ] {
+            let user_config = format!("[mycrate]\n{}\n", user_config);
+            match evaluate_config(&user_config, "mycrate", definition.clone(), vec![]) {
+                Err(Error::InvalidConfigurationValue(key)) => assert_eq!(conflicting, key),
+                other => panic!("{}: {:?}", user_config, other.map(|_| ())),
+            }
+        }
+
+        let user_config = "[mycrate]\ni2s.enable = true\nspi.dma = false\ni2s.pin = 6\n";
+        assert!(evaluate_config(user_config, "mycrate", definition.clone(), vec![]).is_ok());
+
+        // the user's value is the problem
+        let problems =
+            check_config_str("[mycrate]\nspi.pin = 5\n", "mycrate", &definition, vec![]).unwrap();
+        assert_eq!(1, problems.len());
+        assert_eq!("spi.pin", problems[0].key);
+        assert_eq!(ConfigProblemKind::InvalidValue, problems[0].kind);
+
+        let menu = parse_definition_str(
+            "[spi]\ndescription = \"SPI\"\nexclusive_group = \"dma\"\n\n[spi.options.dma]\ndescription = \"DMA\"\ntype = \"bool\"\n",
+        )
+        .unwrap();
+        assert!(check_exclusive_group("spi", &menu["spi"]).is_err());
+    }
+
+    const CONFLICTS: &str = r#"
+    [psram]
+    description = "PSRAM"
+
+    [psram.options.octal]
+    description = "Octal mode"
+    type = "bool"
+    default = false
+    conflicts = ["dma.burst"]
+
+    [dma]
+    description = "DMA"
+
+    [dma.options.burst]
+    description = "Burst transfers"
+    type = "bool"
+    default = true
+    conflicts = ["psram.octal"]
+    "#;
+
+    #[test]
+    fn declared_conflicts() {
+        let definition = parse_definition_str(CONFLICTS).unwrap();
+        assert!(check_conflicts_definition(&definition).is_ok());
+        assert!(evaluate_config("", "mycrate", definition.clone(), vec![]).is_ok());
+
+        let user_config = "[mycrate]\npsram.octal = true\n";
+        let err = evaluate_config(user_config, "mycrate", definition.clone(), vec![])
+            .map(|_| ())
+            .unwrap_err();
+        match &err {
+            Error::ConflictingOptions(first, second) => {
+                assert_eq!("psram.octal", first);
+                assert_eq!("dma.burst", second);
+            }
+            other => panic!("{:?}", other),
+        }
+        let message = crate::compile_error_message("mycrate", &err);
+        assert!(message.contains("`mycrate.psram.octal`"));
+        assert!(message.contains("`mycrate.dma.burst`"));
+
+        let problems = check_config_str(user_config, "mycrate", &definition, vec![]).unwrap();
+        assert_eq!(1, problems.len());
+        assert_eq!("psram.octal", problems[0].key);
+
+        let user_config = "[mycrate]\npsram.octal = true\ndma.burst = false\n";
+        assert!(evaluate_config(user_config, "mycrate", definition.clone(), vec![]).is_ok());
+
+        let invalid = parse_definition_str(&CONFLICTS.replace("[\"dma.burst\"]", "[\"dma\"]"));
+        match check_conflicts_definition(&invalid.unwrap()) {
+            Err(Error::InvalidDefinition(key)) => assert_eq!("psram.octal.conflicts", key),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,528 @@
+//! The built-in evaluator of `depends` and `valid` expressions, used unless the `rhai` feature
+//! enables full Rhai scripting
+//!
+//! It covers the subset of Rhai config-definitions use: `feature("...")`, `enabled("...")`,
+//! `target("...")`, `value_of("...")`, `value`, bool/integer/float/string literals, `!`, `&&`, `||`,
+//! comparisons and arithmetic.
+//! Anything else is rejected when parsing, the same way Rhai rejects an invalid script.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::Value;
+
+/// Deeper nesting is rejected - the parser is recursive
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    Literal(Scalar),
+    /// The value to validate
+    Value,
+    Feature(Box<Expr>),
+    Enabled(Box<Expr>),
+    Target(Box<Expr>),
+    ValueOf(Box<Expr>),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Scalar {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Scalar {
+    fn from_json(value: &Value) -> Self {
+        match value {
+            Value::Bool(b) => Scalar::Bool(*b),
+            Value::Number(n) => match n.as_i64() {
+                Some(n) => Scalar::Int(n),
+                None => Scalar::Float(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => Scalar::Str(s.clone()),
+            _ => Scalar::Bool(false),
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Scalar::Int(n) => Some(*n as f64),
+            Scalar::Float(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// What the functions and `value` of an expression refer to
+pub(crate) struct Env<'a> {
+    pub(crate) features: &'a [String],
+    pub(crate) enabled: &'a HashSet<String>,
+    pub(crate) value: Option<&'a Value>,
+    /// The target being built for, if known
+    pub(crate) target: Option<&'a str>,
+    /// What `value_of("...")` returns, only known for `[constraints]`
+    pub(crate) values: &'a HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Ident(String),
+    Open,
+    Close,
+    Not,
+    Op(Op),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let mut literal = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                    literal.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(number(&literal)?);
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    ident.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Ident(ident));
+            continue;
+        }
+
+        chars.next();
+        let token = match c {
+            '"' | '`' => {
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') if c == '"' => match chars.next() {
+                            Some('n') => literal.push('\n'),
+                            Some('t') => literal.push('\t'),
+                            Some(escaped @ ('"' | '\\')) => literal.push(escaped),
+                            _ => return Err("Invalid escape sequence".to_string()),
+                        },
+                        Some(c) => literal.push(c),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                Token::Str(literal)
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '+' => Token::Op(Op::Add),
+            '-' => Token::Op(Op::Sub),
+            '*' => Token::Op(Op::Mul),
+            '/' => Token::Op(Op::Div),
+            '%' => Token::Op(Op::Rem),
+            '&' | '|' | '=' => {
+                if chars.next() != Some(c) {
+                    return Err(format!("Unexpected `{}`", c));
+                }
+                Token::Op(match c {
+                    '&' => Op::And,
+                    '|' => Op::Or,
+                    _ => Op::Eq,
+                })
+            }
+            '!' | '<' | '>' => {
+                let with_eq = chars.next_if_eq(&'=').is_some();
+                match (c, with_eq) {
+                    ('!', false) => Token::Not,
+                    ('!', true) => Token::Op(Op::Ne),
+                    ('<', false) => Token::Op(Op::Lt),
+                    ('<', true) => Token::Op(Op::Le),
+                    ('>', false) => Token::Op(Op::Gt),
+                    _ => Token::Op(Op::Ge),
+                }
+            }
+            _ => return Err(format!("Unexpected `{}`", c)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn number(literal: &str) -> Result<Token, String> {
+    let invalid = || format!("Invalid number `{}`", literal);
+    let digits = literal.replace('_', "");
+    let radix = match digits.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+
+    if radix != 10 {
+        return i64::from_str_radix(&digits[2..], radix)
+            .map(Token::Int)
+            .map_err(|_| invalid());
+    }
+    if digits.contains('.') {
+        return digits.parse().map(Token::Float).map_err(|_| invalid());
+    }
+    digits.parse().map(Token::Int).map_err(|_| invalid())
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+/// Operators by precedence, lowest first
+const PRECEDENCE: &[&[Op]] = &[
+    &[Op::Or],
+    &[Op::And],
+    &[Op::Eq, Op::Ne],
+    &[Op::Lt, Op::Le, Op::Gt, Op::Ge],
+    &[Op::Add, Op::Sub],
+    &[Op::Mul, Op::Div, Op::Rem],
+];
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {:?}, found {:?}", expected, token)),
+            None => Err(format!("Expected {:?}", expected)),
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            if !PRECEDENCE[level].contains(&op) {
+                break;
+            }
+            self.next();
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("Expression nested too deep".to_string());
+        }
+
+        let expr = match self.next() {
+            Some(Token::Not) => Expr::Not(Box::new(self.unary()?)),
+            Some(Token::Op(Op::Sub)) => Expr::Neg(Box::new(self.unary()?)),
+            Some(Token::Open) => {
+                let expr = self.binary(0)?;
+                self.expect(Token::Close)?;
+                expr
+            }
+            Some(Token::Int(n)) => Expr::Literal(Scalar::Int(n)),
+            Some(Token::Float(n)) => Expr::Literal(Scalar::Float(n)),
+            Some(Token::Str(s)) => Expr::Literal(Scalar::Str(s)),
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" => Expr::Literal(Scalar::Bool(true)),
+                "false" => Expr::Literal(Scalar::Bool(false)),
+                "value" => Expr::Value,
+                "feature" | "enabled" | "target" | "value_of" => {
+                    self.expect(Token::Open)?;
+                    let argument = Box::new(self.binary(0)?);
+                    self.expect(Token::Close)?;
+                    match ident.as_str() {
+                        "feature" => Expr::Feature(argument),
+                        "enabled" => Expr::Enabled(argument),
+                        "target" => Expr::Target(argument),
+                        _ => Expr::ValueOf(argument),
+                    }
+                }
+                _ => return Err(format!("Unknown `{}`", ident)),
+            },
+            Some(token) => return Err(format!("Unexpected {:?}", token)),
+            None => return Err("Unexpected end of the expression".to_string()),
+        };
+
+        self.depth -= 1;
+        Ok(expr)
+    }
+}
+
+pub(crate) fn parse(input: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+        depth: 0,
+    };
+    let expr = parser.binary(0)?;
+    match parser.next() {
+        None => Ok(expr),
+        Some(token) => Err(format!("Unexpected {:?}", token)),
+    }
+}
+
+impl Expr {
+    pub(crate) fn eval(&self, env: &Env) -> Result<Scalar, String> {
+        Ok(match self {
+            Expr::Literal(scalar) => scalar.clone(),
+            Expr::Value => Scalar::from_json(env.value.ok_or("Variable not found: value")?),
+            Expr::Feature(argument) => {
+                let feature = argument.eval_str(env)?;
+                Scalar::Bool(env.features.contains(&feature))
+            }
+            Expr::Enabled(argument) => Scalar::Bool(env.enabled.contains(&argument.eval_str(env)?)),
+            Expr::Target(argument) => {
+                let target = argument.eval_str(env)?;
+                Scalar::Bool(env.target == Some(target.as_str()))
+            }
+            Expr::ValueOf(argument) => {
+                let key = argument.eval_str(env)?;
+                Scalar::from_json(
+                    env.values
+                        .get(&key)
+                        .ok_or_else(|| format!("No value for `{}`", key))?,
+                )
+            }
+            Expr::Not(expr) => Scalar::Bool(!expr.eval_bool(env)?),
+            Expr::Neg(expr) => match expr.eval(env)? {
+                Scalar::Int(n) => Scalar::Int(n.checked_neg().ok_or("Integer overflow")?),
+                Scalar::Float(n) => Scalar::Float(-n),
+                _ => return Err("Only numbers can be negated".to_string()),
+            },
+            // both sides need to be bools, the right one is only evaluated if needed
+            Expr::Binary(Op::And, left, right) => {
+                Scalar::Bool(left.eval_bool(env)? && right.eval_bool(env)?)
+            }
+            Expr::Binary(Op::Or, left, right) => {
+                Scalar::Bool(left.eval_bool(env)? || right.eval_bool(env)?)
+            }
+            Expr::Binary(op, left, right) => binary(*op, left.eval(env)?, right.eval(env)?)?,
+        })
+    }
+
+    pub(crate) fn eval_bool(&self, env: &Env) -> Result<bool, String> {
+        match self.eval(env)? {
+            Scalar::Bool(b) => Ok(b),
+            other => Err(format!("Expected a bool, got {:?}", other)),
+        }
+    }
+
+    fn eval_str(&self, env: &Env) -> Result<String, String> {
+        match self.eval(env)? {
+            Scalar::Str(s) => Ok(s),
+            other => Err(format!("Expected a string, got {:?}", other)),
+        }
+    }
+}
+
+fn binary(op: Op, left: Scalar, right: Scalar) -> Result<Scalar, String> {
+    let overflow = || "Integer overflow or division by zero".to_string();
+    match (op, &left, &right) {
+        (Op::Add, Scalar::Str(a), Scalar::Str(b)) => Ok(Scalar::Str(format!("{}{}", a, b))),
+        (Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem, Scalar::Int(a), Scalar::Int(b)) => {
+            match op {
+                Op::Add => a.checked_add(*b),
+                Op::Sub => a.checked_sub(*b),
+                Op::Mul => a.checked_mul(*b),
+                Op::Div => a.checked_div(*b),
+                _ => a.checked_rem(*b),
+            }
+            .map(Scalar::Int)
+            .ok_or_else(overflow)
+        }
+        (Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem, _, _) => {
+            let (Some(a), Some(b)) = (left.as_f64(), right.as_f64()) else {
+                return Err(format!(
+                    "Can't apply {:?} to {:?} and {:?}",
+                    op, left, right
+                ));
+            };
+            Ok(Scalar::Float(match op {
+                Op::Add => a + b,
+                Op::Sub => a - b,
+                Op::Mul => a * b,
+                Op::Div => a / b,
+                _ => a % b,
+            }))
+        }
+        _ => Ok(Scalar::Bool(compare(op, &left, &right))),
+    }
+}
+
+/// Like Rhai, values of different types are never equal and can't be ordered
+fn compare(op: Op, left: &Scalar, right: &Scalar) -> bool {
+    let ordering = match (left, right) {
+        (Scalar::Bool(a), Scalar::Bool(b)) if matches!(op, Op::Eq | Op::Ne) => a.partial_cmp(b),
+        (Scalar::Str(a), Scalar::Str(b)) => a.partial_cmp(b),
+        _ => match (left.as_f64(), right.as_f64()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => None,
+        },
+    };
+
+    match ordering {
+        Some(ordering) => match op {
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Gt => ordering.is_gt(),
+            _ => ordering.is_ge(),
+        },
+        None => op == Op::Ne,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str, value: Option<Value>) -> Result<bool, String> {
+        let enabled = HashSet::from(["psram.enable".to_string()]);
+        let features = ["esp32".to_string()];
+        let env = Env {
+            features: &features,
+            enabled: &enabled,
+            value: value.as_ref(),
+            target: Some("riscv32imac-unknown-none-elf"),
+            values: &HashMap::from([("wifi.rx_buffers".to_string(), Value::from(10))]),
+        };
+        parse(expression)?.eval_bool(&env)
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(Ok(true), eval(r#"feature("esp32")"#, None));
+        assert_eq!(
+            Ok(false),
+            eval(r#"feature("esp32") && !enabled("psram.enable")"#, None)
+        );
+        assert_eq!(
+            Ok(true),
+            eval(r#"feature("esp32s3") || (enabled("psram.enable"))"#, None)
+        );
+        assert_eq!(
+            Ok(true),
+            eval("value >= 0 && value <= 80000", Some(Value::from(4096)))
+        );
+        assert_eq!(
+            Ok(false),
+            eval("value >= 0 && value <= 80000", Some(Value::from(90000)))
+        );
+        assert_eq!(
+            Ok(true),
+            eval(
+                "value % 4 == 0 && value < 0x1_0000",
+                Some(Value::from(1024))
+            )
+        );
+        assert_eq!(Ok(true), eval("value > -1.5", Some(Value::from(-1))));
+        assert_eq!(
+            Ok(true),
+            eval(
+                r#"value == "quad" || value == "octal""#,
+                Some(Value::from("quad"))
+            )
+        );
+        // values of different types are never equal
+        assert_eq!(Ok(false), eval(r#"value == "1""#, Some(Value::from(1))));
+        assert_eq!(Ok(true), eval(r#"value != "1""#, Some(Value::from(1))));
+        assert_eq!(Ok(true), eval("1 + 2 * 3 == 7", None));
+        assert_eq!(
+            Ok(true),
+            eval(r#"target("riscv32imac-unknown-none-elf")"#, None)
+        );
+        assert_eq!(Ok(false), eval(r#"target("xtensa-esp32-none-elf")"#, None));
+        assert_eq!(
+            Ok(true),
+            eval(r#"value_of("wifi.rx_buffers") + 15 <= 25"#, None)
+        );
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        for expression in [
+            "",
+            "feature(",
+            r#"feature("esp32""#,
+            "loop {}",
+            "x == 1",
+            "value = 1",
+            "1 2",
+            "(true",
+            "0xZZ",
+            &"(".repeat(1000),
+        ] {
+            assert!(parse(expression).is_err(), "{}", expression);
+        }
+
+        // parse but can't be evaluated
+        for expression in [
+            "1 + 1",
+            "value > 1",
+            "true && 1",
+            "1 / 0",
+            r#"feature(1)"#,
+            r#"value_of("wifi.tx_buffers") > 1"#,
+        ] {
+            assert!(eval(expression, None).is_err(), "{}", expression);
+        }
+    }
+}
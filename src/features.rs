@@ -0,0 +1,143 @@
+use crate::definition_file::DefinitionFile;
+use crate::{default_table, expression_arguments, ConfigOption, Error, Map};
+
+/// The features each option checks via `feature("...")` in its `depends`, `valid` and `emit_if`
+/// expressions and its default table or conditional defaults - options which don't check any are
+/// left out
+pub fn features_by_option(config: &Map<String, ConfigOption>) -> Vec<(String, Vec<String>)> {
+    let mut result = Vec::new();
+    collect_features_by_option(&mut result, config, "");
+    result
+}
+
+fn collect_features_by_option(
+    result: &mut Vec<(String, Vec<String>)>,
+    config: &Map<String, ConfigOption>,
+    prefix: &str,
+) {
+    for (name, item) in config {
+        let key = format!("{}{}", prefix, name);
+
+        let mut features: Vec<String> = Vec::new();
+        let checked = [&item.depends, &item.valid, &item.emit_if]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .chain(default_table::default_conditions(item))
+            .flat_map(|expression| expression_arguments(expression, "feature"));
+        // the features of a default table
+        let keyed = default_table::default_table(item)
+            .into_iter()
+            .flat_map(|table| table.keys().map(String::as_str))
+            .filter(|feature| *feature != default_table::OTHER);
+        for feature in checked.chain(keyed) {
+            if !features.iter().any(|f| f == feature) {
+                features.push(feature.to_string());
+            }
+        }
+        if !features.is_empty() {
+            result.push((key.clone(), features));
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            collect_features_by_option(result, options, &format!("{}.", key));
+        }
+    }
+}
+
+/// The features a config-definition declares - `None` if it doesn't have `features`
+///
+/// ```toml
+/// features = ["esp32", "esp32s3"]
+/// ```
+pub fn parse_declared_features_str(definition: &str) -> Result<Option<Vec<String>>, Error> {
+    Ok(DefinitionFile::parse(definition)?.features)
+}
+
+/// The `(key, feature)` of every feature an option checks which isn't declared, e.g. a typo
+pub fn unknown_features(
+    config: &Map<String, ConfigOption>,
+    declared: &[String],
+) -> Vec<(String, String)> {
+    features_by_option(config)
+        .into_iter()
+        .flat_map(|(key, features)| {
+            features
+                .into_iter()
+                .filter(|feature| !declared.contains(feature))
+                .map(move |feature| (key.clone(), feature))
+        })
+        .collect()
+}
+
+/// The warnings about unknown features of a definition declaring its `features`
+pub(crate) fn unknown_feature_warnings(definition: &DefinitionFile) -> Vec<String> {
+    let Some(declared) = definition.features.as_ref() else {
+        return Vec::new();
+    };
+
+    unknown_features(&definition.options, declared)
+        .into_iter()
+        .map(|(key, feature)| {
+            format!(
+                "`{}` checks the feature `{}` which isn't one of the definition's `features`",
+                key, feature
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_definition_str, resolve, EvalContext};
+
+    const DEFINITION: &str = r#"
+    features = ["esp32", "esp32s3"]
+
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32") || feature("esp32s33")'
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    type = "u32"
+    default = { esp32s3 = 4, other = 2 }
+    "#;
+
+    #[test]
+    fn declared_features() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        assert_eq!(
+            vec![
+                (
+                    "psram".to_string(),
+                    vec!["esp32".to_string(), "esp32s33".to_string()]
+                ),
+                ("psram.size".to_string(), vec!["esp32s3".to_string()]),
+            ],
+            features_by_option(&definition)
+        );
+        assert_eq!(
+            Some(vec!["esp32".to_string(), "esp32s3".to_string()]),
+            parse_declared_features_str(DEFINITION).unwrap()
+        );
+        assert_eq!(
+            None,
+            parse_declared_features_str("[psram]\ndescription = \"PSRAM\"").unwrap()
+        );
+
+        let ctx = EvalContext::new(&[]).with_crate_name("mycrate");
+        assert_eq!(
+            vec![
+                "`psram` checks the feature `esp32s33` which isn't one of the definition's `features`"
+            ],
+            resolve(DEFINITION, "", &ctx).unwrap().warnings()
+        );
+        let undeclared = DEFINITION.replace("features = [\"esp32\", \"esp32s3\"]", "");
+        assert!(resolve(&undeclared, "", &ctx)
+            .unwrap()
+            .warnings()
+            .is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use serde::de::DeserializeOwned;
+
+use crate::{parse_toml, Error};
+
+/// The format of a config-definition, see [crate::parse_definition_str_as]
+///
+/// All formats have the structure of the TOML definition, e.g. in JSON
+///
+/// ```json
+/// {
+///     "psram": {
+///         "description": "PSRAM",
+///         "options": {
+///             "size": { "description": "PSRAM size", "type": "u32", "default": 2 }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub enum DefinitionFormat {
+    Toml,
+    /// E.g. generated from SVD or board description files
+    Json,
+    /// Converted to JSON by the function before it's parsed, e.g. YAML via `serde_yaml` - the
+    /// conversion has to keep the order of the keys
+    Converted(fn(&str) -> Result<String, String>),
+}
+
+impl DefinitionFormat {
+    /// JSON if the definition starts with `{` (a TOML document can't), otherwise TOML
+    pub fn detect(definition: &str) -> Self {
+        if definition.trim_start().starts_with('{') {
+            DefinitionFormat::Json
+        } else {
+            DefinitionFormat::Toml
+        }
+    }
+
+    pub(crate) fn parse<T: DeserializeOwned>(self, definition: &str) -> Result<T, Error> {
+        match self {
+            DefinitionFormat::Toml => parse_toml(definition),
+            DefinitionFormat::Json => {
+                serde_json::from_str(definition).map_err(|err| Error::InvalidToml(err.to_string()))
+            }
+            DefinitionFormat::Converted(convert) => {
+                DefinitionFormat::Json.parse(&convert(definition).map_err(Error::InvalidToml)?)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config, parse_definition_str, parse_definition_str_as};
+
+    const TOML: &str = r#"
+    [psram]
+    description = "PSRAM"
+
+    [psram.options.size]
+    description = "PSRAM size"
+    type = "u32"
+    default = 2
+
+    [heap]
+    description = "Heap"
+    type = "u32"
+    default = 1024
+    "#;
+
+    const JSON: &str = r#"
+    {
+        "psram": {
+            "description": "PSRAM",
+            "options": {
+                "size": { "description": "PSRAM size", "type": "u32", "default": 2 }
+            }
+        },
+        "heap": { "description": "Heap", "type": "u32", "default": 1024 }
+    }
+    "#;
+
+    #[test]
+    fn formats() {
+        let from_toml = parse_definition_str(TOML).unwrap();
+        let from_json = parse_definition_str(JSON).unwrap();
+        assert_eq!(
+            from_toml.keys().collect::<Vec<_>>(),
+            from_json.keys().collect::<Vec<_>>()
+        );
+
+        let effective_config =
+            evaluate_config("[mycrate]\nheap = 2048\n", "mycrate", from_json, vec![]).unwrap();
+        assert_eq!(Some(2), effective_config.get_u64("psram.size"));
+        assert_eq!(Some(2048), effective_config.get_u64("heap"));
+
+        // a stand-in for e.g. YAML
+        let single_quoted = JSON.replace('"', "'");
+        let converted = parse_definition_str_as(
+            &single_quoted,
+            DefinitionFormat::Converted(|definition| Ok(definition.replace('\'', "\""))),
+        )
+        .unwrap();
+        assert_eq!(vec!["psram", "heap"], converted.keys().collect::<Vec<_>>());
+
+        assert!(parse_definition_str_as(TOML, DefinitionFormat::Json).is_err());
+        assert!(parse_definition_str_as(
+            TOML,
+            DefinitionFormat::Converted(|_| Err("not YAML".to_string()))
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::active::{check_syntax, Expressions};
+use crate::definition_file::DefinitionFile;
+use crate::{EffectiveConfig, Error, Map};
+
+/// An expression over all options of the crate which has to be true, listed by name in the
+/// `[constraints]` table of the config-definition
+///
+/// ```toml
+/// [constraints]
+/// rx_tx_budget = 'value_of("wifi.rx_buffers") + value_of("wifi.tx_buffers") <= 25'
+/// heap = { expression = 'value_of("heap.size") >= 1024', message = "The heap needs 1 KiB" }
+/// ```
+///
+/// `value_of("...")` is the value of an available option and fails for any other. A constraint
+/// which can't be evaluated isn't met - check `enabled("...")` or `feature("...")` first if the
+/// option isn't always available.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum GlobalConstraint {
+    Expression(String),
+    WithMessage { expression: String, message: String },
+}
+
+impl GlobalConstraint {
+    pub fn expression(&self) -> &str {
+        match self {
+            GlobalConstraint::Expression(expression) => expression,
+            GlobalConstraint::WithMessage { expression, .. } => expression,
+        }
+    }
+
+    /// What to tell the user if the constraint isn't met
+    pub fn message(&self) -> String {
+        match self {
+            GlobalConstraint::Expression(expression) => format!("`{}` doesn't hold", expression),
+            GlobalConstraint::WithMessage { message, .. } => message.clone(),
+        }
+    }
+}
+
+/// A constraint the config doesn't meet
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnmetConstraint {
+    pub name: String,
+    pub message: String,
+}
+
+/// The `[constraints]` of a config-definition
+pub fn parse_constraints_str(definition: &str) -> Result<Map<String, GlobalConstraint>, Error> {
+    Ok(DefinitionFile::parse(definition)?.constraints)
+}
+
+/// The constraints the evaluated config doesn't meet, in the order of the definition
+pub fn unmet_constraints(
+    constraints: &Map<String, GlobalConstraint>,
+    cfg: &EffectiveConfig,
+    features: &[&str],
+) -> Vec<UnmetConstraint> {
+    if constraints.is_empty() {
+        return Vec::new();
+    }
+
+    let values: HashMap<_, _> = cfg
+        .iter()
+        .map(|entry| (entry.key.clone(), entry.value.clone()))
+        .collect();
+    let expressions = Expressions::new(&Map::new(), features).with_values(values);
+    constraints
+        .iter()
+        .filter(|(_, constraint)| {
+            !expressions
+                .depends(Some(constraint.expression()))
+                .unwrap_or(false)
+        })
+        .map(|(name, constraint)| UnmetConstraint {
+            name: name.clone(),
+            message: constraint.message(),
+        })
+        .collect()
+}
+
+/// Fail with the first constraint the evaluated config doesn't meet
+pub(crate) fn check_global_constraints(
+    constraints: &Map<String, GlobalConstraint>,
+    cfg: &EffectiveConfig,
+    features: &[&str],
+) -> Result<(), Error> {
+    match unmet_constraints(constraints, cfg, features)
+        .into_iter()
+        .next()
+    {
+        Some(unmet) => Err(Error::UnmetConstraint(unmet.name, unmet.message)),
+        None => Ok(()),
+    }
+}
+
+/// Make sure the expressions of the constraints at least parse
+pub(crate) fn check_constraints_syntax(
+    constraints: &Map<String, GlobalConstraint>,
+) -> Result<(), Error> {
+    for (name, constraint) in constraints {
+        if check_syntax(constraint.expression()).is_err() {
+            return Err(Error::InvalidExpression(format!("constraints.{}", name)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compile_error_message, evaluate_config, parse_definition_str, resolve, EvalContext,
+    };
+
+    const DEFINITION: &str = r#"
+    [constraints]
+    rx_tx_budget = 'value_of("wifi.rx_buffers") + value_of("wifi.tx_buffers") <= 25'
+    coex = { expression = '!enabled("wifi.coex") || value_of("wifi.rx_buffers") >= 8', message = "Coexistence needs at least 8 RX buffers" }
+
+    [wifi]
+    description = "WiFi"
+
+    [wifi.options.rx_buffers]
+    description = "RX buffers"
+    type = "u32"
+    default = 10
+
+    [wifi.options.tx_buffers]
+    description = "TX buffers"
+    type = "u32"
+    default = 10
+
+    [wifi.options.coex]
+    description = "Coexistence with BLE"
+    type = "bool"
+    default = false
+    "#;
+
+    #[test]
+    fn global_constraints() {
+        let ctx = EvalContext::new(&[]).with_crate_name("mycrate");
+        assert!(resolve(DEFINITION, "", &ctx).is_ok());
+
+        let budget =
+            "`value_of(\"wifi.rx_buffers\") + value_of(\"wifi.tx_buffers\") <= 25` doesn't hold";
+        let err = resolve(DEFINITION, "[mycrate]\nwifi.tx_buffers = 16\n", &ctx)
+            .map(|_| ())
+            .unwrap_err();
+        match &err {
+            Error::UnmetConstraint(name, message) => {
+                assert_eq!("rx_tx_budget", name);
+                assert_eq!(budget, message);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(compile_error_message("mycrate", &err).contains("`rx_tx_budget`"));
+
+        // all of them
+        let cfg = evaluate_config(
+            "[mycrate]\nwifi.coex = true\nwifi.rx_buffers = 4\nwifi.tx_buffers = 30\n",
+            "mycrate",
+            parse_definition_str(DEFINITION).unwrap(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                UnmetConstraint {
+                    name: "rx_tx_budget".to_string(),
+                    message: budget.to_string(),
+                },
+                UnmetConstraint {
+                    name: "coex".to_string(),
+                    message: "Coexistence needs at least 8 RX buffers".to_string(),
+                },
+            ],
+            unmet_constraints(&parse_constraints_str(DEFINITION).unwrap(), &cfg, &[])
+        );
+
+        match resolve(&DEFINITION.replace("<= 25'", "<= '"), "", &ctx) {
+            Err(Error::InvalidExpression(key)) => assert_eq!("constraints.rx_tx_budget", key),
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{expression_arguments, ConfigOption, Map};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    /// Graphviz
+    Dot,
+    Mermaid,
+}
+
+enum Node {
+    Option(String),
+    Feature(String),
+}
+
+/// Create a graph of the `depends` of a config-definition
+///
+/// There is an edge from every option and feature checked via `enabled("...")` or `feature("...")`
+/// to the option or menu depending on it. Options without any relations are left out.
+pub fn dependency_graph(definition: &Map<String, ConfigOption>, format: GraphFormat) -> String {
+    let mut edges = Vec::new();
+    collect_edges(&mut edges, definition, "");
+
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut index = |node: Node| -> usize {
+        let position = nodes.iter().position(|existing| match (existing, &node) {
+            (Node::Option(a), Node::Option(b)) | (Node::Feature(a), Node::Feature(b)) => a == b,
+            _ => false,
+        });
+        position.unwrap_or_else(|| {
+            nodes.push(node);
+            nodes.len() - 1
+        })
+    };
+    let edges: Vec<(usize, usize)> = edges
+        .into_iter()
+        .map(|(from, to)| (index(from), index(Node::Option(to))))
+        .collect();
+
+    match format {
+        GraphFormat::Dot => {
+            let mut out = String::from("digraph rconfig {\n");
+            for node in &nodes {
+                match node {
+                    Node::Option(key) => out.push_str(&format!("    \"{}\";\n", key)),
+                    Node::Feature(feature) => out.push_str(&format!(
+                        "    \"feature:{}\" [label=\"{}\", shape=box];\n",
+                        feature, feature
+                    )),
+                }
+            }
+            for (from, to) in edges {
+                out.push_str(&format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    dot_id(&nodes[from]),
+                    dot_id(&nodes[to])
+                ));
+            }
+            out.push_str("}\n");
+            out
+        }
+        GraphFormat::Mermaid => {
+            // node ids are restricted - the keys are used as labels
+            let mut out = String::from("graph LR\n");
+            for (i, node) in nodes.iter().enumerate() {
+                match node {
+                    Node::Option(key) => out.push_str(&format!("    n{}[\"{}\"]\n", i, key)),
+                    Node::Feature(feature) => {
+                        out.push_str(&format!("    n{}([\"{}\"])\n", i, feature))
+                    }
+                }
+            }
+            for (from, to) in edges {
+                out.push_str(&format!("    n{} --> n{}\n", from, to));
+            }
+            out
+        }
+    }
+}
+
+fn dot_id(node: &Node) -> String {
+    match node {
+        Node::Option(key) => key.clone(),
+        Node::Feature(feature) => format!("feature:{}", feature),
+    }
+}
+
+fn collect_edges(
+    edges: &mut Vec<(Node, String)>,
+    config: &Map<String, ConfigOption>,
+    prefix: &str,
+) {
+    for (name, item) in config {
+        let key = format!("{}{}", prefix, name);
+
+        if let Some(depends) = &item.depends {
+            for feature in expression_arguments(depends, "feature") {
+                edges.push((Node::Feature(feature.to_string()), key.clone()));
+            }
+            for option in expression_arguments(depends, "enabled") {
+                edges.push((Node::Option(option.to_string()), key.clone()));
+            }
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            collect_edges(edges, options, &format!("{}.", key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32") || feature("esp32s3")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+
+    [psram.options.type]
+    description = "PSRAM Type"
+    depends = 'feature("esp32s3") && enabled("psram.enable")'
+    type = "u32"
+    "#;
+
+    #[test]
+    fn dot() {
+        assert_eq!(
+            r#"digraph rconfig {
+    "feature:esp32" [label="esp32", shape=box];
+    "psram";
+    "feature:esp32s3" [label="esp32s3", shape=box];
+    "psram.type";
+    "psram.enable";
+    "feature:esp32" -> "psram";
+    "feature:esp32s3" -> "psram";
+    "feature:esp32s3" -> "psram.type";
+    "psram.enable" -> "psram.type";
+}
+"#,
+            dependency_graph(&parse_definition_str(DEFINITION).unwrap(), GraphFormat::Dot)
+        );
+    }
+
+    #[test]
+    fn mermaid() {
+        assert_eq!(
+            r#"graph LR
+    n0(["esp32"])
+    n1["psram"]
+    n2(["esp32s3"])
+    n3["psram.type"]
+    n4["psram.enable"]
+    n0 --> n1
+    n2 --> n1
+    n2 --> n3
+    n4 --> n3
+"#,
+            dependency_graph(
+                &parse_definition_str(DEFINITION).unwrap(),
+                GraphFormat::Mermaid
+            )
+        );
+    }
+}
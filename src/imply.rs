@@ -0,0 +1,267 @@
+use crate::active::{ActiveSet, Expressions};
+use crate::{ConfigOption, Error, Map, OptionIndex, Value, ValueType};
+
+/// Make the options an enabled option implies default to `true` - options the user set keep
+/// their value
+///
+/// ```toml
+/// [wifi.options.enable]
+/// description = "Enable WiFi"
+/// type = "bool"
+/// imply = ["wifi.coex"]
+/// ```
+///
+/// Unlike `selects` the implied option can still be disabled and doesn't need to be available.
+/// An implied option can imply others in turn. The options of `selects` default to `true` the
+/// same way, [check_selects] makes sure the user didn't disable them.
+pub(crate) fn apply_implies(config: &mut Map<String, ConfigOption>, features: &[&str]) {
+    // every round turns at least one default to `true`, so this terminates
+    loop {
+        let expressions = Expressions::new(config, features);
+        let active = ActiveSet::new(config, &expressions);
+        let implied: Vec<String> = OptionIndex::new(config)
+            .iter()
+            .filter(|(key, option)| active.contains(key) && is_enabled(option))
+            .flat_map(|(_, option)| {
+                option
+                    .imply
+                    .iter()
+                    .chain(option.selects.iter())
+                    .flatten()
+                    .cloned()
+            })
+            .collect();
+
+        let mut changed = false;
+        for key in implied {
+            changed |= imply_by_key(config, &key);
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+fn is_enabled(option: &ConfigOption) -> bool {
+    option.__value.as_ref().or(option.default_value.as_ref()) == Some(&Value::Bool(true))
+}
+
+/// Default the option to `true` if the user didn't set it - whether that changed anything
+fn imply_by_key(config: &mut Map<String, ConfigOption>, key: &str) -> bool {
+    let mut current = Some(config);
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let Some(item) = current.and_then(|level| level.get_mut(part)) else {
+            return false;
+        };
+        if parts.peek().is_none() {
+            if item.__value.is_some() || item.default_value == Some(Value::Bool(true)) {
+                return false;
+            }
+            item.default_value = Some(Value::Bool(true));
+            return true;
+        }
+        current = item.options.as_mut();
+    }
+    false
+}
+
+/// The options selected by an available, enabled option which the user disabled
+///
+/// ```toml
+/// [wifi.options.enable]
+/// description = "Enable WiFi"
+/// type = "bool"
+/// selects = ["radio.enable"]
+/// ```
+///
+/// A selected option doesn't need to be available.
+pub(crate) fn unmet_selects(config: &Map<String, ConfigOption>, active: &ActiveSet) -> Vec<String> {
+    let index = OptionIndex::new(config);
+    index
+        .iter()
+        .filter(|(key, option)| active.contains(key) && is_enabled(option))
+        .flat_map(|(_, option)| option.selects.iter().flatten())
+        .filter(|selected| {
+            active.contains(selected)
+                && index
+                    .get(selected)
+                    .is_some_and(|option| !is_enabled(option))
+        })
+        .cloned()
+        .collect()
+}
+
+/// The first unmet select makes the config invalid, see [unmet_selects]
+pub(crate) fn check_selects(
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+) -> Result<(), Error> {
+    match unmet_selects(config, active).into_iter().next() {
+        Some(key) => Err(Error::InvalidConfigurationValue(key)),
+        None => Ok(()),
+    }
+}
+
+/// Make sure only bool options imply or select and only bool options of the definition are
+/// implied or selected
+pub(crate) fn check_implies(config: &Map<String, ConfigOption>) -> Result<(), Error> {
+    let index = OptionIndex::new(config);
+    for (key, option) in index.iter() {
+        for (field, targets) in [("imply", &option.imply), ("selects", &option.selects)] {
+            let Some(targets) = targets.as_ref() else {
+                continue;
+            };
+
+            let is_bool = |option: &ConfigOption| option.value_type == Some(ValueType::Bool);
+            if !is_bool(option)
+                || !targets
+                    .iter()
+                    .all(|target| index.get(target).is_some_and(is_bool))
+            {
+                return Err(Error::InvalidDefinition(format!("{}.{}", key, field)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_config_str, evaluate_config, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    [wifi]
+    description = "WiFi"
+
+    [wifi.options.enable]
+    description = "Enable WiFi"
+    type = "bool"
+    default = false
+    imply = ["wifi.coex"]
+
+    [wifi.options.coex]
+    description = "Coexistence with BLE"
+    type = "bool"
+    default = false
+    imply = ["wifi.coex_log"]
+
+    [wifi.options.coex_log]
+    description = "Log coexistence events"
+    type = "bool"
+    default = false
+    depends = 'enabled("wifi.coex")'
+    "#;
+
+    #[test]
+    fn imply() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+
+        let effective_config = evaluate_config("", "mycrate", definition.clone(), vec![]).unwrap();
+        assert_eq!(Some(false), effective_config.get_bool("wifi.coex"));
+
+        let effective_config = evaluate_config(
+            "[mycrate]\nwifi.enable = true\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(true), effective_config.get_bool("wifi.coex"));
+        // implied options imply in turn
+        assert_eq!(Some(true), effective_config.get_bool("wifi.coex_log"));
+
+        // the user's value wins
+        let effective_config = evaluate_config(
+            "[mycrate]\nwifi.enable = true\nwifi.coex = false\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(false), effective_config.get_bool("wifi.coex"));
+        assert_eq!(None, effective_config.get_bool("wifi.coex_log"));
+
+        assert!(check_implies(&definition).is_ok());
+        for invalid in [
+            DEFINITION.replacen("[\"wifi.coex\"]", "[\"wifi.missing\"]", 1),
+            DEFINITION.replacen(
+                "type = \"bool\"\n    default = false\n    imply",
+                "type = \"u32\"\n    default = 0\n    imply",
+                1,
+            ),
+        ] {
+            let definition = parse_definition_str(&invalid).unwrap();
+            match check_implies(&definition) {
+                Err(Error::InvalidDefinition(key)) => assert_eq!("wifi.enable.imply", key),
+                other => panic!("{}: {:?}", invalid, other),
+            }
+        }
+    }
+
+    const SELECTS: &str = r#"
+    [wifi]
+    description = "WiFi"
+
+    [wifi.options.enable]
+    description = "Enable WiFi"
+    type = "bool"
+    default = false
+    selects = ["radio.enable"]
+    implies = ["wifi.coex"]
+
+    [wifi.options.coex]
+    description = "Coexistence with BLE"
+    type = "bool"
+    default = false
+
+    [radio]
+    description = "Radio"
+
+    [radio.options.enable]
+    description = "Enable the radio"
+    type = "bool"
+    default = false
+    "#;
+
+    #[test]
+    fn selects() {
+        let definition = parse_definition_str(SELECTS).unwrap();
+        assert!(check_implies(&definition).is_ok());
+
+        let effective_config = evaluate_config(
+            "[mycrate]\nwifi.enable = true\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(true), effective_config.get_bool("radio.enable"));
+        assert_eq!(Some(true), effective_config.get_bool("wifi.coex"));
+
+        // only the implied option can be disabled
+        assert!(evaluate_config(
+            "[mycrate]\nwifi.enable = true\nwifi.coex = false\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .is_ok());
+        let user_config = "[mycrate]\nwifi.enable = true\nradio.enable = false\n";
+        match evaluate_config(user_config, "mycrate", definition.clone(), vec![]) {
+            Err(Error::InvalidConfigurationValue(key)) => assert_eq!("radio.enable", key),
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+        let problems = check_config_str(user_config, "mycrate", &definition, vec![]).unwrap();
+        assert_eq!(1, problems.len());
+        assert_eq!("radio.enable", problems[0].key);
+
+        let invalid = SELECTS.replacen("[\"radio.enable\"]", "[\"radio\"]", 1);
+        match check_implies(&parse_definition_str(&invalid).unwrap()) {
+            Err(Error::InvalidDefinition(key)) => assert_eq!("wifi.enable.selects", key),
+            other => panic!("{:?}", other),
+        }
+    }
+}
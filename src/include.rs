@@ -0,0 +1,310 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::active::Expressions;
+use crate::format::DefinitionFormat;
+use crate::{parse_toml, Error, Map, Value};
+
+/// An entry of `include` in the user's config
+///
+/// ```toml
+/// include = { path = "config-esp32s3.toml", if = 'target("xtensa-esp32s3-none-elf")' }
+/// # or more than one
+/// include = [
+///     { path = "config-common.toml" },
+///     { path = "config-psram.toml", if = 'feature("psram")' },
+/// ]
+/// ```
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Include {
+    /// Relative to the directory of `config.toml`
+    path: String,
+    /// Only included if the expression is true - `feature("...")` and `target("...")` can be used
+    #[serde(rename = "if")]
+    condition: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+enum Includes {
+    One(Include),
+    Many(Vec<Include>),
+}
+
+/// Merge the files the `include` of the user's config (the content of `config.toml`) refers to
+/// into it - the user's config is returned as it is if there is no `include`
+///
+/// Entries of the user's config win over the ones of the included files, included files can't
+/// include other files. Returns the merged config and all files `include` refers to, whether
+/// their condition is true or not.
+pub fn resolve_includes(
+    user_config: &str,
+    base_dir: &Path,
+    features: &[&str],
+    target: Option<&str>,
+) -> Result<(String, Vec<PathBuf>), Error> {
+    let mut config = match parse_toml::<Value>(user_config)? {
+        Value::Object(config) => config,
+        _ => return Ok((user_config.to_string(), Vec::new())),
+    };
+    let Some(includes) = config.remove("include") else {
+        return Ok((user_config.to_string(), Vec::new()));
+    };
+    let includes = match serde_json::from_value(includes) {
+        Ok(Includes::One(include)) => vec![include],
+        Ok(Includes::Many(includes)) => includes,
+        Err(_) => return Err(Error::InvalidConfiguration("include".to_string())),
+    };
+
+    let expressions = Expressions::new(&Map::new(), features).with_target(target);
+    let mut paths = Vec::new();
+    for include in includes {
+        let path = base_dir.join(&include.path);
+        paths.push(path.clone());
+
+        let included = expressions
+            .depends(include.condition.as_deref())
+            .map_err(|_| Error::InvalidExpression(format!("include.{}", include.path)))?;
+        if !included {
+            continue;
+        }
+
+        let fragment = std::fs::read_to_string(&path)
+            .map_err(|_| Error::InvalidConfiguration(include.path.clone()))?;
+        let Value::Object(fragment) = parse_toml::<Value>(&fragment)? else {
+            continue;
+        };
+        if fragment.contains_key("include") {
+            return Err(Error::InvalidConfiguration(format!(
+                "{}.include",
+                include.path
+            )));
+        }
+        merge(&mut config, fragment);
+    }
+
+    let merged = basic_toml::to_string(&Value::Object(config))
+        .map_err(|err| Error::InvalidToml(err.to_string()))?;
+    Ok((merged, paths))
+}
+
+/// Add what the fragment has and the config doesn't
+fn merge(config: &mut serde_json::Map<String, Value>, fragment: serde_json::Map<String, Value>) {
+    for (name, value) in fragment {
+        match (config.get_mut(&name), value) {
+            (Some(Value::Object(table)), Value::Object(value)) => merge(table, value),
+            (Some(_), _) => (),
+            (None, value) => {
+                config.insert(name, value);
+            }
+        }
+    }
+}
+
+/// The `include` of a config-definition - the other entries are the options
+#[derive(Deserialize, Default)]
+struct DefinitionIncludes {
+    #[serde(default)]
+    include: Vec<String>,
+}
+
+/// The config-definition followed by the files its `include` refers to, e.g. one per peripheral
+///
+/// ```toml
+/// include = ["psram.toml", "wifi.toml"]
+/// ```
+///
+/// Included files are relative to the directory of the file including them and can include
+/// other files, every file is listed once. The files are merged like [crate::parse_definitions_str]
+/// does.
+pub fn definition_files(definition: &Path) -> Result<Vec<PathBuf>, Error> {
+    Ok(read_with_includes(vec![definition.to_path_buf()])?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// The contents of the files of a config-definition and the files they include (each once), see
+/// [definition_files] - for a `Cargo.toml` the definition in it, see
+/// [crate::parse_manifest_definition_str]
+pub fn read_definitions(definitions: &[PathBuf]) -> Result<Vec<String>, Error> {
+    Ok(read_with_includes(definitions.to_vec())?
+        .into_iter()
+        .map(|(_, content)| content)
+        .collect())
+}
+
+/// The paths and contents of the files and the files they include
+pub(crate) fn read_with_includes(files: Vec<PathBuf>) -> Result<Vec<(PathBuf, String)>, Error> {
+    let mut pending: std::collections::VecDeque<PathBuf> = files.into();
+    let mut seen = Vec::new();
+    let mut read = Vec::new();
+    while let Some(path) = pending.pop_front() {
+        // `psram.toml` and `../config/psram.toml` are the same file
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+
+        let mut content = std::fs::read_to_string(&path)
+            .map_err(|_| Error::InvalidDefinition(format!("include.{}", path.display())))?;
+        if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+            content = crate::parse_manifest_definition_str(&content)?.ok_or_else(|| {
+                Error::InvalidDefinition("package.metadata.rconfig.definition".to_string())
+            })?;
+        }
+        let base_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        for include in DefinitionFormat::detect(&content)
+            .parse::<DefinitionIncludes>(&content)?
+            .include
+        {
+            pending.push_back(base_dir.join(include));
+        }
+        read.push((path, content));
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn includes() {
+        let dir = std::env::temp_dir().join(format!("rconfig-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("config-esp32s3.toml"),
+            "[mycrate]\npsram.enable = true\nheap.size = 1024\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("config-psram.toml"), "[mycrate]\npsram.size = 4\n").unwrap();
+
+        let user_config = r#"
+include = [
+    { path = "config-esp32s3.toml", if = 'target("xtensa-esp32s3-none-elf")' },
+    { path = "config-psram.toml", if = 'feature("psram")' },
+]
+
+[mycrate]
+heap.size = 2048
+"#;
+        let (merged, paths) =
+            resolve_includes(user_config, &dir, &[], Some("xtensa-esp32s3-none-elf")).unwrap();
+        let merged = parse_toml::<Value>(&merged).unwrap();
+        assert_eq!(
+            serde_json::json!({ "mycrate": { "heap": { "size": 2048 }, "psram": { "enable": true } } }),
+            merged
+        );
+        assert_eq!(
+            vec![
+                dir.join("config-esp32s3.toml"),
+                dir.join("config-psram.toml")
+            ],
+            paths
+        );
+
+        let (merged, _) = resolve_includes(user_config, &dir, &["psram"], None).unwrap();
+        assert_eq!(
+            serde_json::json!({ "mycrate": { "heap": { "size": 2048 }, "psram": { "size": 4 } } }),
+            parse_toml::<Value>(&merged).unwrap()
+        );
+
+        // kept as it is without includes
+        let plain = "[mycrate]\nheap.size = 0x800\n";
+        assert_eq!(
+            (plain.to_string(), vec![]),
+            resolve_includes(plain, &dir, &[], None).unwrap()
+        );
+
+        for invalid in [
+            "include = { path = \"missing.toml\" }",
+            "include = { path = \"config-psram.toml\", if = \"target(\" }",
+            "include = { file = \"config-psram.toml\" }",
+        ] {
+            assert!(
+                resolve_includes(invalid, &dir, &[], None).is_err(),
+                "{}",
+                invalid
+            );
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn definition_includes() {
+        let dir =
+            std::env::temp_dir().join(format!("rconfig-definition-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("peripherals")).unwrap();
+        std::fs::write(
+            dir.join("rconfig.toml"),
+            r#"
+include = ["peripherals/psram.toml", "peripherals/wifi.toml"]
+
+[heap]
+description = "Heap"
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("peripherals/psram.toml"),
+            r#"
+include = ["wifi.toml"]
+
+[psram]
+description = "PSRAM"
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("peripherals/wifi.toml"),
+            r#"
+# included twice, listed once
+include = ["../rconfig.toml"]
+
+[wifi]
+description = "WiFi"
+"#,
+        )
+        .unwrap();
+
+        let files = definition_files(&dir.join("rconfig.toml")).unwrap();
+        assert_eq!(
+            vec![
+                dir.join("rconfig.toml"),
+                dir.join("peripherals/psram.toml"),
+                dir.join("peripherals/wifi.toml"),
+            ],
+            files
+        );
+
+        let definition = format!(
+            "include = [\"{}\"]\n\n[board]\ndescription = \"Board\"\n",
+            dir.join("peripherals/psram.toml").display()
+        );
+        let parsed = crate::parse_definition_str(&definition).unwrap();
+        assert_eq!(
+            vec!["board", "psram", "wifi", "heap"],
+            parsed.keys().collect::<Vec<_>>()
+        );
+
+        std::fs::write(
+            dir.join("peripherals/wifi.toml"),
+            "include = [\"ble.toml\"]",
+        )
+        .unwrap();
+        match definition_files(&dir.join("rconfig.toml")) {
+            Err(Error::InvalidDefinition(key)) => assert_eq!(
+                format!("include.{}", dir.join("peripherals/ble.toml").display()),
+                key
+            ),
+            other => panic!("{:?}", other),
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::active::{is_set, prune, ActiveSet, Expressions};
+use crate::choice::apply_choices;
+use crate::imply::apply_implies;
+use crate::{
+    expression_arguments, fuse_config_str, select_defaults, ConfigOption, Error, Map, OptionIndex,
+    Value,
+};
+
+/// The config of a crate as [crate::evaluate_config_str_to_cfg] creates it, kept up to date while
+/// single values change - e.g. in a configuration UI
+///
+/// Whether an option is available only depends on the features and the values its `depends`
+/// checks via `enabled("...")`, not on whether those options are available. Changing a value
+/// therefore only re-evaluates the `depends` referencing it and the menus and options below them.
+pub struct IncrementalConfig {
+    // the definition with the user's values fused in, including what isn't available
+    config: Map<String, ConfigOption>,
+    expressions: Expressions,
+    active: ActiveSet,
+    // dotted key -> the menus and options with a `depends` checking it, in definition order
+    dependents: HashMap<String, Vec<String>>,
+    // the `depends` calling `enabled` with something else than a string literal - they are
+    // re-evaluated on every change
+    dynamic: Vec<String>,
+    evaluated: Rc<Map<String, ConfigOption>>,
+    // the config before applying `imply`, `selects` and `choice` and the features if the definition uses
+    // them - a changed value can change the defaults of other options then, everything is
+    // evaluated again
+    implying: Option<(Map<String, ConfigOption>, Vec<String>)>,
+}
+
+impl IncrementalConfig {
+    pub fn new(
+        input: &str,
+        crate_name: &str,
+        config: Map<String, ConfigOption>,
+        features: Vec<&str>,
+    ) -> Result<Self, Error> {
+        let mut config = fuse_config_str(input, crate_name, config)?;
+        select_defaults(&mut config, &features);
+        Ok(Self::from_fused(config, &features))
+    }
+
+    fn from_fused(mut config: Map<String, ConfigOption>, features: &[&str]) -> Self {
+        let implying = OptionIndex::new(&config)
+            .iter()
+            .any(|(_, option)| option.imply.is_some() || option.selects.is_some() || option.choice)
+            .then(|| {
+                (
+                    config.clone(),
+                    features.iter().map(|feature| feature.to_string()).collect(),
+                )
+            });
+        apply_implies(&mut config, features);
+        apply_choices(&mut config, features);
+
+        let expressions = Expressions::new(&config, features);
+        let active = ActiveSet::new(&config, &expressions);
+        let mut evaluated = config.clone();
+        prune(&mut evaluated, &active, "");
+
+        let mut incremental = Self {
+            config,
+            expressions,
+            active,
+            dependents: HashMap::new(),
+            dynamic: Vec::new(),
+            evaluated: Rc::new(evaluated),
+            implying,
+        };
+        collect_dependents(
+            &mut incremental.dependents,
+            &mut incremental.dynamic,
+            &incremental.config,
+            "",
+        );
+        incremental
+    }
+
+    /// The config without the menus and options which are not available
+    pub fn config(&self) -> Rc<Map<String, ConfigOption>> {
+        self.evaluated.clone()
+    }
+
+    /// Set or remove (`None`) the user's value of the option with the dotted key
+    pub fn set_value(&mut self, key: &str, value: Option<Value>) -> Result<(), Error> {
+        let option = get_mut(&mut self.config, key).ok_or(Error::InvalidKey)?;
+        if option.value_type.is_none() {
+            return Err(Error::InvalidKey);
+        }
+        if let Some((mut fused, features)) = self.implying.take() {
+            if let Some(option) = get_mut(&mut fused, key) {
+                option.__value = value;
+            }
+            let features: Vec<&str> = features.iter().map(String::as_str).collect();
+            *self = Self::from_fused(fused, &features);
+            return Ok(());
+        }
+        option.__value = value.clone();
+        let enabled = option
+            .__value
+            .as_ref()
+            .or(option.default_value.as_ref())
+            .is_some_and(is_set);
+
+        if let Some(option) = get_mut(Rc::make_mut(&mut self.evaluated), key) {
+            option.__value = value;
+        }
+
+        if enabled == self.expressions.is_enabled(key) {
+            return Ok(());
+        }
+        self.expressions.set_enabled(key, enabled);
+
+        let affected: Vec<String> = self
+            .dependents
+            .get(key)
+            .into_iter()
+            .flatten()
+            .chain(&self.dynamic)
+            .cloned()
+            .collect();
+        for key in affected {
+            self.update_active(&key);
+        }
+
+        Ok(())
+    }
+
+    fn update_active(&mut self, key: &str) {
+        let (parent, name) = match key.rsplit_once('.') {
+            Some((parent, name)) => (Some(parent), name),
+            None => (None, key),
+        };
+        // a menu which isn't available hides everything in it anyways
+        if parent.is_some_and(|parent| !self.active.contains(parent)) {
+            return;
+        }
+
+        let level = match parent {
+            Some(parent) => get(&self.config, parent).and_then(|menu| menu.options.as_ref()),
+            None => Some(&self.config),
+        };
+        let Some(level) = level else {
+            return;
+        };
+        let option = &level[name];
+
+        let active = self
+            .expressions
+            .depends(option.depends.as_deref())
+            .unwrap_or(false);
+        if active == self.active.contains(key) {
+            return;
+        }
+
+        let evaluated = Rc::make_mut(&mut self.evaluated);
+        let evaluated_level = match parent {
+            Some(parent) => get_mut(evaluated, parent).and_then(|menu| menu.options.as_mut()),
+            None => Some(evaluated),
+        };
+        let Some(evaluated_level) = evaluated_level else {
+            return;
+        };
+
+        if !active {
+            self.active.remove(key, option);
+            evaluated_level.remove(name);
+            return;
+        }
+
+        self.active.insert(key, option, &self.expressions);
+
+        // keep the definition order - the other entries of the menu are moved, not re-evaluated
+        let mut rebuilt = Map::new();
+        for (entry_name, entry) in level {
+            if entry_name.as_str() == name {
+                let mut entry = entry.clone();
+                if let Some(options) = entry.options.as_mut() {
+                    prune(options, &self.active, &format!("{}.", key));
+                }
+                rebuilt.insert(entry_name.clone(), entry);
+            } else if let Some(entry) = evaluated_level.remove(entry_name) {
+                rebuilt.insert(entry_name.clone(), entry);
+            }
+        }
+        *evaluated_level = rebuilt;
+    }
+}
+
+fn collect_dependents(
+    dependents: &mut HashMap<String, Vec<String>>,
+    dynamic: &mut Vec<String>,
+    config: &Map<String, ConfigOption>,
+    prefix: &str,
+) {
+    for (name, item) in config {
+        let key = format!("{}{}", prefix, name);
+        if let Some(depends) = item.depends.as_ref() {
+            let referenced = expression_arguments(depends, "enabled");
+            if referenced.len() != depends.matches("enabled(").count() {
+                dynamic.push(key.clone());
+            }
+            for referenced in referenced {
+                dependents
+                    .entry(referenced.to_string())
+                    .or_default()
+                    .push(key.clone());
+            }
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            collect_dependents(dependents, dynamic, options, &format!("{}.", key));
+        }
+    }
+}
+
+fn get<'a>(config: &'a Map<String, ConfigOption>, key: &str) -> Option<&'a ConfigOption> {
+    let mut parts = key.split('.');
+    let mut option = config.get(parts.next()?)?;
+    for part in parts {
+        option = option.options.as_ref()?.get(part)?;
+    }
+    Some(option)
+}
+
+fn get_mut<'a>(
+    config: &'a mut Map<String, ConfigOption>,
+    key: &str,
+) -> Option<&'a mut ConfigOption> {
+    let mut parts = key.split('.');
+    let mut option = config.get_mut(parts.next()?)?;
+    for part in parts {
+        option = option.options.as_mut()?.get_mut(part)?;
+    }
+    Some(option)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config_str_to_cfg, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [psram.options.type]
+    description = "PSRAM Type"
+    depends = 'enabled("psram.enable")'
+
+    [psram.options.type.options.type]
+    description = "PSRAM Type"
+    type = "enum"
+    values = [
+        { description = "Quad", value = "quad" },
+        { description = "Octal", value = "octal" },
+    ]
+    default = "quad"
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    depends = 'enabled("psram.enable")'
+    type = "u32"
+    default = 2
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    "#;
+
+    fn to_json(config: &Map<String, ConfigOption>) -> Value {
+        serde_json::to_value(config).unwrap()
+    }
+
+    #[test]
+    fn incremental_config() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let mut config =
+            IncrementalConfig::new("[mycrate]\n", "mycrate", definition.clone(), vec!["esp32"])
+                .unwrap();
+        assert!(get(&config.config(), "psram.size").is_none());
+
+        let steps = [
+            (
+                "psram.enable",
+                Some(Value::Bool(true)),
+                "psram.enable = true",
+            ),
+            (
+                "psram.type.type",
+                Some(Value::from("octal")),
+                "psram.enable = true\npsram.type.type = \"octal\"",
+            ),
+            (
+                "heap.size",
+                Some(Value::from(1024)),
+                "psram.enable = true\npsram.type.type = \"octal\"\nheap.size = 1024",
+            ),
+            (
+                "psram.enable",
+                None,
+                "psram.type.type = \"octal\"\nheap.size = 1024",
+            ),
+            (
+                "psram.enable",
+                Some(Value::Bool(true)),
+                "psram.enable = true\npsram.type.type = \"octal\"\nheap.size = 1024",
+            ),
+        ];
+        for (key, value, cfg) in steps {
+            config.set_value(key, value).unwrap();
+            let expected = evaluate_config_str_to_cfg(
+                &format!("[mycrate]\n{}\n", cfg),
+                "mycrate",
+                definition.clone(),
+                vec!["esp32"],
+            )
+            .unwrap();
+            assert_eq!(to_json(&expected), to_json(&config.config()), "{}", key);
+        }
+
+        assert_eq!(
+            vec!["enable", "type", "size"],
+            config.config()["psram"]
+                .options
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>()
+        );
+
+        assert!(config.set_value("psram.speed", None).is_err());
+        assert!(config.set_value("psram", None).is_err());
+    }
+
+    #[test]
+    fn incremental_imply() {
+        let definition = parse_definition_str(&format!(
+            "{}\n[heap.options.enable]\ndescription = \"Enable\"\ntype = \"bool\"\ndefault = false\n",
+            DEFINITION.replace("default = false", "default = false\nimply = [\"heap.enable\"]")
+        ))
+        .unwrap();
+        let mut config = IncrementalConfig::new("", "mycrate", definition, vec!["esp32"]).unwrap();
+        // implied defaults change with the value
+        for (value, implied) in [(Some(true), true), (Some(false), false), (None, false)] {
+            config
+                .set_value("psram.enable", value.map(Value::Bool))
+                .unwrap();
+            assert_eq!(
+                Some(&Value::Bool(implied)),
+                config.config()["heap"].options.as_ref().unwrap()["enable"]
+                    .default_value
+                    .as_ref(),
+                "{:?}",
+                value
+            );
+        }
+        assert!(config.set_value("psram.speed", None).is_err());
+    }
+}
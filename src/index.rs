@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use crate::{ConfigOption, Map};
+
+/// A menu or option in an [OptionIndex]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone)]
+struct Node<'a> {
+    key: String,
+    option: &'a ConfigOption,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+/// All menus and options of a config by their dotted key, e.g. `psram.size`
+///
+/// Built in a single pass, looking up a key doesn't descend the menus anymore. Use it when
+/// looking up many keys of the same config.
+#[derive(Debug, Clone)]
+pub struct OptionIndex<'a> {
+    // in definition order, a menu before its options
+    nodes: Vec<Node<'a>>,
+    by_key: HashMap<String, NodeId>,
+    roots: Vec<NodeId>,
+}
+
+impl<'a> OptionIndex<'a> {
+    pub fn new(config: &'a Map<String, ConfigOption>) -> Self {
+        let mut index = Self {
+            nodes: Vec::new(),
+            by_key: HashMap::new(),
+            roots: Vec::new(),
+        };
+        index.roots = index.add_level(config, None, "");
+        index
+    }
+
+    fn add_level(
+        &mut self,
+        config: &'a Map<String, ConfigOption>,
+        parent: Option<NodeId>,
+        prefix: &str,
+    ) -> Vec<NodeId> {
+        let mut ids = Vec::new();
+        for (name, option) in config {
+            let id = NodeId(self.nodes.len());
+            let key = format!("{}{}", prefix, name);
+            self.nodes.push(Node {
+                key: key.clone(),
+                option,
+                parent,
+                children: Vec::new(),
+            });
+            self.by_key.insert(key.clone(), id);
+
+            if let Some(options) = option.options.as_ref() {
+                let children = self.add_level(options, Some(id), &format!("{}.", key));
+                self.nodes[id.0].children = children;
+            }
+            ids.push(id);
+        }
+        ids
+    }
+
+    pub fn id(&self, key: &str) -> Option<NodeId> {
+        self.by_key.get(key).copied()
+    }
+
+    /// The menu or option with the dotted key
+    pub fn get(&self, key: &str) -> Option<&'a ConfigOption> {
+        self.id(key).map(|id| self.option(id))
+    }
+
+    pub fn option(&self, id: NodeId) -> &'a ConfigOption {
+        self.nodes[id.0].option
+    }
+
+    pub fn key(&self, id: NodeId) -> &str {
+        &self.nodes[id.0].key
+    }
+
+    /// The menu containing it - `None` for top-level entries
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    /// The options and menus of a menu
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.0].children
+    }
+
+    /// The top-level menus and options
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// The menus containing it, starting with the top-level menu, and the entry itself
+    pub fn path(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = vec![id];
+        let mut current = id;
+        while let Some(parent) = self.parent(current) {
+            path.push(parent);
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// All entries in definition order, menus before their options
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &'a ConfigOption)> + '_ {
+        self.nodes
+            .iter()
+            .map(|node| (node.key.as_str(), node.option))
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+
+    [psram.options.type]
+    description = "PSRAM Type"
+
+    [psram.options.type.options.type]
+    description = "PSRAM Type"
+    type = "enum"
+    values = [
+        { description = "Quad", value = "quad" },
+    ]
+
+    [heap]
+    description = "Heapsize"
+    "#;
+
+    #[test]
+    fn index() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let index = OptionIndex::new(&definition);
+
+        assert_eq!(5, index.len());
+        assert_eq!(
+            vec![
+                "psram",
+                "psram.enable",
+                "psram.type",
+                "psram.type.type",
+                "heap"
+            ],
+            index.iter().map(|(key, _)| key).collect::<Vec<_>>()
+        );
+
+        let id = index.id("psram.type.type").unwrap();
+        assert_eq!("PSRAM Type", index.option(id).description);
+        assert_eq!(
+            vec!["psram", "psram.type", "psram.type.type"],
+            index
+                .path(id)
+                .into_iter()
+                .map(|id| index.key(id))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, index.children(index.id("psram").unwrap()).len());
+        assert_eq!(2, index.roots().len());
+
+        assert!(index.get("psram.speed").is_none());
+        assert!(index.get("type").is_none());
+    }
+}
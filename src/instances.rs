@@ -0,0 +1,335 @@
+use std::io::Write;
+
+use crate::{
+    to_rust_literal, to_rust_type, to_variant_name, value_as_string, ConfigOption, EffectiveConfig,
+    Error, Map, OptionIndex, Value, ValueItem, ValueType,
+};
+
+/// Expand the menus with `instances = <n>` - their options are the same for every instance
+///
+/// ```toml
+/// [uart]
+/// description = "UART"
+/// instances = 3
+///
+/// [uart.options.baud]
+/// description = "Baudrate"
+/// type = "u32"
+/// ```
+/// becomes the menus `uart.0`, `uart.1` and `uart.2`, each with an option `baud`.
+///
+/// With `foreach = "<key of an enum option>"` instead there is an instance per value of the enum,
+/// named after the value - e.g. `uart.uart0` and `uart.uart1`.
+pub(crate) fn expand_instances(options: &mut Map<String, ConfigOption>) -> Result<(), Error> {
+    // the enums can be anywhere in the definition
+    let enums: Map<String, Vec<ValueItem>> = OptionIndex::new(options)
+        .iter()
+        .filter(|(_, option)| option.value_type == Some(ValueType::Enum))
+        .filter_map(|(key, option)| Some((key.to_string(), option.values.clone()?)))
+        .collect();
+    expand_level(options, "", &enums)
+}
+
+fn expand_level(
+    options: &mut Map<String, ConfigOption>,
+    prefix: &str,
+    enums: &Map<String, Vec<ValueItem>>,
+) -> Result<(), Error> {
+    for (name, option) in options.iter_mut() {
+        let key = format!("{}{}", prefix, name);
+
+        if let Some(children) = option.options.as_mut() {
+            expand_level(children, &format!("{}.", key), enums)?;
+        }
+
+        // (name, description) of the instances
+        let instances: Vec<(String, String)> = match (option.instances, &option.foreach) {
+            (None, None) => continue,
+            (Some(instances), None) => (0..instances)
+                .map(|index| (index.to_string(), index.to_string()))
+                .collect(),
+            (None, Some(foreach)) => match enums.get(foreach) {
+                Some(values) => values
+                    .iter()
+                    .map(|value| (value.value.clone(), value.description.clone()))
+                    .collect(),
+                None => return Err(Error::InvalidDefinition(format!("{}.foreach", key))),
+            },
+            (Some(_), Some(_)) => return Err(Error::InvalidDefinition(format!("{}.foreach", key))),
+        };
+        let Some(template) = option.options.take().filter(|_| !instances.is_empty()) else {
+            return Err(Error::InvalidDefinition(format!("{}.instances", key)));
+        };
+
+        let expanded = instances
+            .into_iter()
+            .map(|(name, description)| {
+                let instance = ConfigOption {
+                    description: format!("{} {}", option.description, description),
+                    options: Some(template.clone()),
+                    ..Default::default()
+                };
+                (name, instance)
+            })
+            .collect();
+        option.options = Some(expanded);
+    }
+
+    Ok(())
+}
+
+/// The options of a menu with `instances` (or `foreach`) as they are written in the definition
+pub(crate) fn template(option: &ConfigOption) -> Option<&Map<String, ConfigOption>> {
+    if option.instances.is_none() && option.foreach.is_none() {
+        return None;
+    }
+    option.options.as_ref()?.values().next()?.options.as_ref()
+}
+
+/// The name of the enum type of the enum option - the instances share it, e.g. `UartParity` for
+/// `uart.0.parity` and `uart.1.parity`
+pub(crate) fn enum_type_name(definition: &Map<String, ConfigOption>, key: &str) -> String {
+    let mut parts = Vec::new();
+    let mut level = Some(definition);
+    let mut is_instance = false;
+    for part in key.split('.') {
+        if !is_instance {
+            parts.push(part);
+        }
+        let option = level.and_then(|level| level.get(part));
+        is_instance = option.is_some_and(|option| template(option).is_some());
+        level = option.and_then(|option| option.options.as_ref());
+    }
+    to_variant_name(&parts.join("_"))
+}
+
+/// Write an array per option of the instances, e.g. `UART_BAUD: [u32; 3]` for `uart.0.baud`,
+/// `uart.1.baud` and `uart.2.baud` - `UART_PARITY: [UartParity; 3]` for enums
+///
+/// Only if every instance has a value.
+pub(crate) fn write_instance_arrays(
+    out: &mut impl Write,
+    definition: &Map<String, ConfigOption>,
+    cfg: &EffectiveConfig,
+) -> std::io::Result<()> {
+    write_arrays(out, definition, definition, cfg, "")
+}
+
+fn write_arrays(
+    out: &mut impl Write,
+    definition: &Map<String, ConfigOption>,
+    options: &Map<String, ConfigOption>,
+    cfg: &EffectiveConfig,
+    prefix: &str,
+) -> std::io::Result<()> {
+    for (name, option) in options {
+        let key = format!("{}{}", prefix, name);
+
+        if let Some(template) = template(option) {
+            let instances: Vec<&String> = option
+                .options
+                .iter()
+                .flatten()
+                .map(|(name, _)| name)
+                .collect();
+            let mut leaves = Vec::new();
+            collect_leaves(&mut leaves, template, "");
+            for (suffix, value_type) in leaves {
+                let values: Option<Vec<&Value>> = instances
+                    .iter()
+                    .map(|instance| cfg.get_value(&format!("{}.{}.{}", key, instance, suffix)))
+                    .collect();
+                let Some(values) = values else {
+                    continue;
+                };
+
+                let (rust_type, literals) = if *value_type == ValueType::Enum {
+                    let type_name =
+                        enum_type_name(definition, &format!("{}.{}.{}", key, instances[0], suffix));
+                    let literals = values
+                        .into_iter()
+                        .map(|value| {
+                            format!(
+                                "{}::{}",
+                                type_name,
+                                to_variant_name(&value_as_string(value))
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    (type_name, literals)
+                } else {
+                    let literals = values
+                        .into_iter()
+                        .map(|value| to_rust_literal(value_type, value))
+                        .collect();
+                    (to_rust_type(value_type), literals)
+                };
+                writeln!(
+                    out,
+                    "pub const {}: [{}; {}] = [{}];",
+                    format!("{}.{}", key, suffix)
+                        .replace('.', "_")
+                        .to_uppercase(),
+                    rust_type,
+                    instances.len(),
+                    literals.join(", ")
+                )?;
+            }
+        }
+
+        if let Some(children) = option.options.as_ref() {
+            write_arrays(out, definition, children, cfg, &format!("{}.", key))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_leaves<'a>(
+    leaves: &mut Vec<(String, &'a ValueType)>,
+    options: &'a Map<String, ConfigOption>,
+    prefix: &str,
+) {
+    for (name, option) in options {
+        let key = format!("{}{}", prefix, name);
+        match (&option.value_type, option.options.as_ref()) {
+            (Some(value_type), _) => leaves.push((key, value_type)),
+            (None, Some(children)) => collect_leaves(leaves, children, &format!("{}.", key)),
+            (None, None) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        definition_to_toml_string, evaluate_config, generate_config, parse_definition_str,
+    };
+
+    const DEFINITION: &str = r#"
+    [uart]
+    description = "UART"
+    instances = 2
+
+    [uart.options.baud]
+    description = "Baudrate"
+    type = "u32"
+    default = 115200
+
+    [uart.options.parity]
+    description = "Parity"
+    type = "enum"
+    values = [
+        { description = "None", value = "none" },
+        { description = "Even", value = "even" },
+    ]
+    default = "none"
+    "#;
+
+    #[test]
+    fn instances() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let uart = definition["uart"].options.as_ref().unwrap();
+        assert_eq!(vec!["0", "1"], uart.keys().collect::<Vec<_>>());
+        assert_eq!("UART 1", uart["1"].description);
+
+        let effective_config = evaluate_config(
+            "[mycrate]\nuart.1.baud = 9600\nuart.1.parity = \"even\"\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(115200), effective_config.get_u32("uart.0.baud"));
+        assert_eq!(Some(9600), effective_config.get_u32("uart.1.baud"));
+
+        let config_rs = generate_config(&definition, &effective_config).config_rs;
+        assert!(config_rs.contains("pub const UART_1_BAUD: u32 = 9600;\n"));
+        assert!(config_rs.contains("pub const UART_1_PARITY: UartParity = UartParity::Even;\n"));
+        assert!(config_rs.contains("pub const UART_BAUD: [u32; 2] = [115200, 9600];\n"));
+        assert!(config_rs.contains(
+            "pub const UART_PARITY: [UartParity; 2] = [UartParity::None, UartParity::Even];\n"
+        ));
+        assert_eq!(1, config_rs.matches("pub enum ").count());
+
+        // written as it was defined
+        let toml = definition_to_toml_string(&definition);
+        assert!(toml.contains("instances = 2\n"));
+        assert!(toml.contains("[uart.options.baud]\n"));
+        assert!(!toml.contains("uart.options.0"));
+        assert_eq!(
+            toml,
+            definition_to_toml_string(&parse_definition_str(&toml).unwrap())
+        );
+    }
+
+    const FOREACH: &str = r#"
+    [uart]
+    description = "UART"
+    foreach = "console.port"
+
+    [uart.options.baud]
+    description = "Baudrate"
+    type = "u32"
+    default = 115200
+
+    [console]
+    description = "Console"
+
+    [console.options.port]
+    description = "Port"
+    type = "enum"
+    values = [
+        { description = "UART0", value = "uart0" },
+        { description = "UART1", value = "uart1" },
+    ]
+    default = "uart0"
+    "#;
+
+    #[test]
+    fn foreach() {
+        let definition = parse_definition_str(FOREACH).unwrap();
+        let uart = definition["uart"].options.as_ref().unwrap();
+        assert_eq!(vec!["uart0", "uart1"], uart.keys().collect::<Vec<_>>());
+        assert_eq!("UART UART1", uart["uart1"].description);
+
+        let effective_config = evaluate_config(
+            "[mycrate]\nuart.uart1.baud = 9600\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        let config_rs = generate_config(&definition, &effective_config).config_rs;
+        assert!(config_rs.contains("pub const UART_UART1_BAUD: u32 = 9600;\n"));
+        assert!(config_rs.contains("pub const UART_BAUD: [u32; 2] = [115200, 9600];\n"));
+
+        let toml = definition_to_toml_string(&definition);
+        assert!(toml.contains("foreach = \"console.port\"\n"));
+        assert!(toml.contains("[uart.options.baud]\n"));
+        assert_eq!(
+            toml,
+            definition_to_toml_string(&parse_definition_str(&toml).unwrap())
+        );
+
+        for invalid in [
+            FOREACH.replace("foreach = \"console.port\"", "foreach = \"console\""),
+            FOREACH.replace(
+                "foreach = \"console.port\"",
+                "foreach = \"console.port\"\ninstances = 2",
+            ),
+        ] {
+            assert!(parse_definition_str(&invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn invalid_instances() {
+        for definition in [
+            "[uart]\ndescription = \"UART\"\ninstances = 2\n",
+            "[uart]\ndescription = \"UART\"\ninstances = 0\n\n[uart.options.baud]\ndescription = \"Baudrate\"\ntype = \"u32\"\n",
+        ] {
+            assert!(parse_definition_str(definition).is_err(), "{}", definition);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::active::{ActiveSet, Expressions};
+use crate::{constraints, ConfigOption, Error, Map, Value, ValueType};
+
+/// Expand the `${other.key}` placeholders in the values of the available string options with
+/// the value of the other option, `$${` is a literal `${`
+///
+/// ```toml
+/// [device.options.path]
+/// description = "Device path"
+/// type = "string"
+/// default = "/dev/${device.name}"
+/// ```
+///
+/// Placeholders in the value of the other option are expanded as well. A placeholder of an option
+/// which isn't available or has no value - or which refers back to the option - makes the value
+/// invalid. The expanded values have to meet the constraints and pass the validation.
+pub(crate) fn expand_placeholders(
+    config: &mut Map<String, ConfigOption>,
+    active: &ActiveSet,
+    expressions: &Expressions,
+) -> Result<(), Error> {
+    let mut texts = Map::new();
+    let mut expanded = Map::new();
+    collect_values(&mut texts, &mut expanded, config, active, "");
+
+    let mut expander = Expander {
+        texts: &texts,
+        expanded,
+        stack: Vec::new(),
+    };
+    for (key, text) in &texts {
+        if text.contains('$') {
+            expander.expand(key)?;
+        }
+    }
+
+    replace_values(config, &expander.expanded, expressions, "")
+}
+
+/// The values of the available options as text - the ones of string options still to expand
+/// in `texts`, the others in `expanded`
+fn collect_values(
+    texts: &mut Map<String, String>,
+    expanded: &mut Map<String, String>,
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+    prefix: &str,
+) {
+    for (name, option) in config {
+        let key = format!("{}{}", prefix, name);
+        if !active.contains(&key) {
+            continue;
+        }
+
+        if let Some(options) = option.options.as_ref() {
+            collect_values(texts, expanded, options, active, &format!("{}.", key));
+        } else if let Some(value) = option.__value.as_ref().or(option.default_value.as_ref()) {
+            match (value, &option.value_type) {
+                (Value::String(text), Some(ValueType::String)) => texts.insert(key, text.clone()),
+                (Value::String(text), _) => expanded.insert(key, text.clone()),
+                (value, _) => expanded.insert(key, value.to_string()),
+            };
+        }
+    }
+}
+
+struct Expander<'a> {
+    texts: &'a Map<String, String>,
+    expanded: Map<String, String>,
+    // the keys being expanded, to detect cycles
+    stack: Vec<String>,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, key: &str) -> Result<String, Error> {
+        if let Some(expanded) = self.expanded.get(key) {
+            return Ok(expanded.clone());
+        }
+
+        let Some(text) = self.texts.get(key) else {
+            return Err(Error::InvalidConfigurationValue(key.to_string()));
+        };
+
+        self.stack.push(key.to_string());
+        let mut expanded = String::new();
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find('$') {
+            expanded.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if let Some(escaped) = rest.strip_prefix("$${") {
+                expanded.push_str("${");
+                rest = escaped;
+            } else if let Some(placeholder) = rest.strip_prefix("${") {
+                let Some((reference, after)) = placeholder.split_once('}') else {
+                    return Err(Error::InvalidConfigurationValue(key.to_string()));
+                };
+                let reference = reference.trim();
+                if self.stack.iter().any(|key| key == reference)
+                    || !(self.texts.contains_key(reference)
+                        || self.expanded.contains_key(reference))
+                {
+                    return Err(Error::InvalidConfigurationValue(key.to_string()));
+                }
+                expanded.push_str(&self.expand(reference)?);
+                rest = after;
+            } else {
+                expanded.push('$');
+                rest = &rest[1..];
+            }
+        }
+        expanded.push_str(rest);
+        self.stack.pop();
+
+        self.expanded.insert(key.to_string(), expanded.clone());
+        Ok(expanded)
+    }
+}
+
+/// Replace the user's value or the default of string options with the expanded value
+fn replace_values(
+    config: &mut Map<String, ConfigOption>,
+    expanded: &Map<String, String>,
+    expressions: &Expressions,
+    prefix: &str,
+) -> Result<(), Error> {
+    for (name, option) in config.iter_mut() {
+        let key = format!("{}{}", prefix, name);
+
+        if let Some(options) = option.options.as_mut() {
+            replace_values(options, expanded, expressions, &format!("{}.", key))?;
+            continue;
+        }
+
+        let Some(text) = expanded.get(&key) else {
+            continue;
+        };
+        if option.value_type != Some(ValueType::String) {
+            continue;
+        }
+
+        let value = Value::from(text.as_str());
+        if !constraints::meets_constraints(option, &value)
+            || !expressions
+                .valid(option.valid.as_deref(), &value)
+                .map_err(|_| Error::InvalidExpression(key.clone()))?
+        {
+            return Err(Error::InvalidConfigurationValue(key));
+        }
+
+        match option.__value.as_mut() {
+            Some(user_value) => *user_value = value,
+            None => option.default_value = Some(value),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{evaluate_config, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    [device]
+    description = "Device"
+
+    [device.options.name]
+    description = "Name"
+    type = "string"
+    default = "uart"
+
+    [device.options.index]
+    description = "Index"
+    type = "u32"
+    default = 0
+
+    [device.options.path]
+    description = "Path"
+    type = "string"
+    default = "/dev/${device.name}${device.index}"
+
+    [device.options.label]
+    description = "Label"
+    type = "string"
+    default = "${device.path} ($${ and $ are kept)"
+    "#;
+
+    #[test]
+    fn placeholders() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let effective_config =
+            evaluate_config("[mycrate]\n", "mycrate", definition.clone(), vec![]).unwrap();
+        assert_eq!(Some("/dev/uart0"), effective_config.get_str("device.path"));
+        assert_eq!(
+            Some("/dev/uart0 (${ and $ are kept)"),
+            effective_config.get_str("device.label")
+        );
+
+        let effective_config = evaluate_config(
+            "[mycrate]\ndevice.name = \"spi\"\ndevice.index = 2\n",
+            "mycrate",
+            definition,
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some("/dev/spi2"), effective_config.get_str("device.path"));
+    }
+
+    #[test]
+    fn invalid_placeholders() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        for user_config in [
+            // a cycle
+            "device.name = \"${device.path}\"",
+            "device.name = \"${device.name}\"",
+            "device.name = \"${device.missing}\"",
+            "device.name = \"${device.index\"",
+        ] {
+            assert!(
+                evaluate_config(
+                    &format!("[mycrate]\n{}\n", user_config),
+                    "mycrate",
+                    definition.clone(),
+                    vec![]
+                )
+                .is_err(),
+                "{}",
+                user_config
+            );
+        }
+    }
+}
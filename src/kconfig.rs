@@ -0,0 +1,998 @@
+use crate::{default_table, ConfigOption, DisplayHint, Error, Map, Value, ValueType};
+
+/// Export a definition to Kconfig syntax.
+///
+/// Menu items become `menu` blocks, enums become `choice` groups and features referenced in
+/// `depends` expressions become promptless `FEATURE_*` symbols which are expected to be set by
+/// the surrounding build.
+pub fn definition_to_kconfig(definition: &Map<String, ConfigOption>) -> String {
+    let mut features = Vec::new();
+    collect_features(definition, &mut features);
+
+    let mut out = String::new();
+    out.push_str("# generated by rconfig\n\n");
+
+    for feature in &features {
+        out.push_str(&format!("config {}\n", feature_symbol(feature)));
+        out.push_str("    bool\n\n");
+    }
+
+    export_options(&mut out, definition, "");
+
+    out
+}
+
+fn collect_features(config: &Map<String, ConfigOption>, features: &mut Vec<String>) {
+    for (_, item) in config {
+        if let Some(depends) = &item.depends {
+            for token in tokenize_rhai(depends).unwrap_or_default() {
+                if let RhaiToken::Feature(feature) = token {
+                    if !features.contains(&feature) {
+                        features.push(feature);
+                    }
+                }
+            }
+        }
+        for feature in default_table::default_table(item)
+            .into_iter()
+            .flat_map(|table| table.keys())
+        {
+            if feature != default_table::OTHER && !features.contains(feature) {
+                features.push(feature.clone());
+            }
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            collect_features(options, features);
+        }
+    }
+}
+
+fn export_options(out: &mut String, config: &Map<String, ConfigOption>, prefix: &str) {
+    for (name, item) in config {
+        let path = format!("{}{}", prefix, name);
+        let symbol = path_symbol(&path);
+
+        match &item.value_type {
+            None if item.options.is_none() => {
+                out.push_str(&format!("comment {}\n", quote(&item.description)));
+                export_depends(out, item);
+                out.push('\n');
+            }
+            None => {
+                out.push_str(&format!("menu {}\n", quote(&item.description)));
+                export_depends(out, item);
+                out.push('\n');
+                if let Some(options) = item.options.as_ref() {
+                    export_options(out, options, &format!("{}.", path));
+                }
+                out.push_str("endmenu\n\n");
+            }
+            Some(ValueType::Enum) => {
+                let values = item.values.clone().unwrap_or_default();
+
+                out.push_str(&format!("choice {}\n", symbol));
+                out.push_str(&format!("    prompt {}\n", quote(&item.description)));
+                export_depends(out, item);
+                for (default, condition) in defaults(item) {
+                    if let Value::String(default) = default {
+                        out.push_str(&format!(
+                            "    default {}_{}{}\n",
+                            symbol,
+                            path_symbol(default),
+                            condition
+                        ));
+                    }
+                }
+                out.push('\n');
+
+                for value in values {
+                    out.push_str(&format!(
+                        "config {}_{}\n",
+                        symbol,
+                        path_symbol(&value.value)
+                    ));
+                    out.push_str(&format!("    bool {}\n\n", quote(&value.description)));
+                }
+                out.push_str("endchoice\n\n");
+            }
+            Some(value_type) => {
+                let hex = item.display == Some(DisplayHint::Hex);
+                let kconfig_type = match (value_type, value_type.integer_range()) {
+                    (ValueType::Bool, _) => "bool",
+                    // unsigned only
+                    (_, Some((0, _))) if hex => "hex",
+                    (_, Some(_)) => "int",
+                    _ => "string",
+                };
+
+                out.push_str(&format!("config {}\n", symbol));
+                out.push_str(&format!(
+                    "    {} {}\n",
+                    kconfig_type,
+                    quote(&item.description)
+                ));
+                export_depends(out, item);
+
+                for (default, condition) in defaults(item) {
+                    match default {
+                        Value::Bool(true) => out.push_str(&format!("    default y{}\n", condition)),
+                        Value::Bool(false) => {
+                            out.push_str(&format!("    default n{}\n", condition))
+                        }
+                        Value::Number(n) if hex => out.push_str(&format!(
+                            "    default {:#x}{}\n",
+                            n.as_u64().unwrap_or(0),
+                            condition
+                        )),
+                        Value::Number(n) => {
+                            out.push_str(&format!("    default {}{}\n", n, condition))
+                        }
+                        Value::String(s) => {
+                            out.push_str(&format!("    default {}{}\n", quote(s), condition))
+                        }
+                        _ => (),
+                    }
+                }
+
+                if let Some(valid) = &item.valid {
+                    if let Some((min, max)) = parse_simple_range(valid) {
+                        out.push_str(&format!("    range {} {}\n", min, max));
+                    } else {
+                        out.push_str(&format!("    # valid (not translated): {}\n", valid));
+                    }
+                }
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// The defaults with their ` if FEATURE_*` condition - Kconfig uses the first one which applies,
+/// like the entries of a default table and conditional defaults
+fn defaults(item: &ConfigOption) -> Vec<(&Value, String)> {
+    match &item.default_value {
+        Some(Value::Object(table)) => {
+            let mut defaults: Vec<_> = table
+                .iter()
+                .filter(|(feature, _)| *feature != default_table::OTHER)
+                .map(|(feature, value)| (value, format!(" if {}", feature_symbol(feature))))
+                .collect();
+            defaults.extend(
+                table
+                    .get(default_table::OTHER)
+                    .map(|value| (value, String::new())),
+            );
+            defaults
+        }
+        // conditions which can't be translated are left out
+        Some(Value::Array(entries)) => entries
+            .iter()
+            .filter_map(|entry| {
+                let value = entry.get("value")?;
+                match entry.get("if").and_then(Value::as_str) {
+                    Some(condition) => rhai_to_kconfig_expr(condition)
+                        .map(|condition| (value, format!(" if {}", condition))),
+                    None => Some((value, String::new())),
+                }
+            })
+            .collect(),
+        Some(value) => vec![(value, String::new())],
+        None => Vec::new(),
+    }
+}
+
+fn export_depends(out: &mut String, item: &ConfigOption) {
+    if let Some(depends) = &item.depends {
+        match rhai_to_kconfig_expr(depends) {
+            Some(expr) => out.push_str(&format!("    depends on {}\n", expr)),
+            None => out.push_str(&format!("    # depends (not translated): {}\n", depends)),
+        }
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn path_symbol(path: &str) -> String {
+    path.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn feature_symbol(feature: &str) -> String {
+    format!("FEATURE_{}", path_symbol(feature))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RhaiToken {
+    Feature(String),
+    Enabled(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+/// Tokenize the subset of Rhai used in typical `depends` expressions.
+///
+/// Returns `None` if the expression uses anything else.
+fn tokenize_rhai(expr: &str) -> Option<Vec<RhaiToken>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim();
+
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix("&&") {
+            tokens.push(RhaiToken::And);
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("||") {
+            tokens.push(RhaiToken::Or);
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('!') {
+            tokens.push(RhaiToken::Not);
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('(') {
+            tokens.push(RhaiToken::Open);
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix(')') {
+            tokens.push(RhaiToken::Close);
+            rest = r;
+        } else {
+            let (func, r) = if let Some(r) = rest.strip_prefix("feature") {
+                ("feature", r)
+            } else if let Some(r) = rest.strip_prefix("enabled") {
+                ("enabled", r)
+            } else {
+                return None;
+            };
+
+            let r = r.trim_start().strip_prefix('(')?.trim_start();
+            let r = r.strip_prefix('"')?;
+            let end = r.find('"')?;
+            let arg = r[..end].to_string();
+            let r = r[end + 1..].trim_start().strip_prefix(')')?;
+
+            tokens.push(if func == "feature" {
+                RhaiToken::Feature(arg)
+            } else {
+                RhaiToken::Enabled(arg)
+            });
+            rest = r;
+        }
+        rest = rest.trim_start();
+    }
+
+    Some(tokens)
+}
+
+fn rhai_to_kconfig_expr(expr: &str) -> Option<String> {
+    let tokens = tokenize_rhai(expr)?;
+    let mut out = String::new();
+    for token in tokens {
+        if !out.is_empty() && !out.ends_with('(') && !out.ends_with('!') {
+            out.push(' ');
+        }
+
+        match token {
+            RhaiToken::Feature(feature) => out.push_str(&feature_symbol(&feature)),
+            RhaiToken::Enabled(path) => out.push_str(&path_symbol(&path)),
+            RhaiToken::And => out.push_str("&&"),
+            RhaiToken::Or => out.push_str("||"),
+            RhaiToken::Not => out.push('!'),
+            RhaiToken::Open => out.push('('),
+            RhaiToken::Close => {
+                out.pop();
+                out.push(')');
+            }
+        }
+    }
+    Some(out)
+}
+
+/// Recognize `value >= MIN && value <= MAX`.
+fn parse_simple_range(valid: &str) -> Option<(i64, i64)> {
+    let (lower, upper) = valid.split_once("&&")?;
+    let min = lower
+        .trim()
+        .strip_prefix("value")?
+        .trim()
+        .strip_prefix(">=")?;
+    let max = upper
+        .trim()
+        .strip_prefix("value")?
+        .trim()
+        .strip_prefix("<=")?;
+    Some((min.trim().parse().ok()?, max.trim().parse().ok()?))
+}
+
+#[derive(Debug, Default)]
+struct KconfigEntry {
+    key: String,
+    symbol: Option<String>,
+    kind: KconfigKind,
+    prompt: Option<String>,
+    depends: Vec<String>,
+    default: Option<String>,
+    range: Option<(String, String)>,
+    children: Vec<KconfigEntry>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+enum KconfigKind {
+    #[default]
+    Menu,
+    Choice,
+    Bool,
+    Int,
+    Hex,
+    String,
+}
+
+const BLOCK_KEYWORDS: &[&str] = &[
+    "config",
+    "menuconfig",
+    "menu",
+    "endmenu",
+    "choice",
+    "endchoice",
+    "if",
+    "endif",
+    "comment",
+    "source",
+    "mainmenu",
+];
+
+/// Import a Kconfig file, producing the equivalent rconfig definition as TOML.
+///
+/// Symbols named `FEATURE_*` are translated into `feature("...")` checks, all other symbols
+/// referenced in `depends on` become `enabled("...")` checks of the imported options.
+pub fn kconfig_to_definition(input: &str) -> Result<String, Error> {
+    let lines = join_continuations(input);
+
+    // stack of open menus/choices, the bottom is the implicit root menu
+    let mut stack: Vec<KconfigEntry> = vec![KconfigEntry::default()];
+    let mut if_stack: Vec<String> = Vec::new();
+    let mut current: Option<KconfigEntry> = None;
+
+    let mut lines = lines.iter().peekable();
+    while let Some(line) = lines.next() {
+        let indent = line.len() - line.trim_start().len();
+        let line = strip_comment(line.trim());
+        if line.is_empty() {
+            continue;
+        }
+
+        let (keyword, rest) = match line.split_once(char::is_whitespace) {
+            Some((keyword, rest)) => (keyword, rest.trim()),
+            None => (line, ""),
+        };
+
+        if BLOCK_KEYWORDS.contains(&keyword) {
+            if let Some(entry) = current.take() {
+                push_entry(&mut stack, entry);
+            }
+        }
+
+        match keyword {
+            "mainmenu" | "comment" => (),
+            "source" => {
+                return Err(Error::InvalidKconfig(format!(
+                    "`source` is not supported: {}",
+                    rest
+                )))
+            }
+            "menu" => {
+                stack.push(KconfigEntry {
+                    prompt: Some(unquote(rest)),
+                    depends: if_stack.clone(),
+                    ..Default::default()
+                });
+            }
+            "endmenu" | "endchoice" => {
+                if stack.len() < 2 {
+                    return Err(Error::InvalidKconfig(format!("unbalanced `{}`", keyword)));
+                }
+                let entry = stack.pop().unwrap();
+                push_entry(&mut stack, entry);
+            }
+            "choice" => {
+                stack.push(KconfigEntry {
+                    symbol: (!rest.is_empty()).then(|| rest.to_string()),
+                    kind: KconfigKind::Choice,
+                    depends: if_stack.clone(),
+                    ..Default::default()
+                });
+            }
+            "config" | "menuconfig" => {
+                current = Some(KconfigEntry {
+                    symbol: Some(rest.to_string()),
+                    kind: KconfigKind::Bool,
+                    depends: if_stack.clone(),
+                    ..Default::default()
+                });
+            }
+            "if" => if_stack.push(rest.to_string()),
+            "endif" => {
+                if if_stack.pop().is_none() {
+                    return Err(Error::InvalidKconfig("unbalanced `endif`".to_string()));
+                }
+            }
+            "help" | "---help---" => {
+                // skip the help text
+                while let Some(next) = lines.peek() {
+                    let next_indent = next.len() - next.trim_start().len();
+                    if next.trim().is_empty() || next_indent > indent {
+                        lines.next();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                let entry = match current.as_mut() {
+                    Some(entry) => entry,
+                    None => stack.last_mut().unwrap(),
+                };
+                apply_attribute(entry, keyword, rest)?;
+            }
+        }
+    }
+
+    if let Some(entry) = current.take() {
+        push_entry(&mut stack, entry);
+    }
+
+    if stack.len() != 1 {
+        return Err(Error::InvalidKconfig(
+            "unterminated `menu` or `choice`".to_string(),
+        ));
+    }
+    let mut root = stack.pop().unwrap();
+    resolve_keys(&mut root.children, "");
+
+    let mut symbols = Map::new();
+    collect_symbols(&root.children, "", &mut symbols);
+
+    let mut out = String::new();
+    write_definition(&mut out, &root.children, "", &symbols)?;
+    Ok(out)
+}
+
+fn join_continuations(input: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut pending = String::new();
+    for line in input.lines() {
+        if let Some(line) = line.strip_suffix('\\') {
+            pending.push_str(line);
+            pending.push(' ');
+        } else {
+            pending.push_str(line);
+            lines.push(std::mem::take(&mut pending));
+        }
+    }
+    if !pending.is_empty() {
+        lines.push(pending);
+    }
+    lines
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return line[..index].trim_end(),
+            _ => (),
+        }
+    }
+    line
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    if let Some(inner) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        inner.replace("\\\"", "\"").replace("\\\\", "\\")
+    } else {
+        s.to_string()
+    }
+}
+
+fn slug(title: &str) -> String {
+    let slug: String = title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    slug.trim_matches('_').to_string()
+}
+
+/// Drop a trailing `if <expr>` clause from an attribute value.
+fn strip_condition(rest: &str) -> &str {
+    let mut in_string = false;
+    for (index, c) in rest.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ' ' if !in_string && rest[index..].starts_with(" if ") => {
+                return rest[..index].trim();
+            }
+            _ => (),
+        }
+    }
+    rest
+}
+
+fn apply_attribute(entry: &mut KconfigEntry, keyword: &str, rest: &str) -> Result<(), Error> {
+    let value = strip_condition(rest);
+
+    match keyword {
+        "bool" | "tristate" | "int" | "hex" | "string" => {
+            entry.kind = match keyword {
+                "int" => KconfigKind::Int,
+                "hex" => KconfigKind::Hex,
+                "string" => KconfigKind::String,
+                _ => KconfigKind::Bool,
+            };
+            if !value.is_empty() {
+                entry.prompt = Some(unquote(value));
+            }
+        }
+        "def_bool" | "def_tristate" => {
+            entry.kind = KconfigKind::Bool;
+            entry.default.get_or_insert_with(|| value.to_string());
+        }
+        "prompt" => entry.prompt = Some(unquote(value)),
+        // only the first default applies for conditional defaults, the condition is dropped
+        "default" => {
+            entry.default.get_or_insert_with(|| value.to_string());
+        }
+        "depends" => {
+            let expr = value.strip_prefix("on").unwrap_or(value).trim();
+            entry.depends.push(expr.to_string());
+        }
+        "range" => {
+            let mut parts = value.split_whitespace();
+            if let (Some(min), Some(max)) = (parts.next(), parts.next()) {
+                entry.range = Some((min.to_string(), max.to_string()));
+            }
+        }
+        "select" | "imply" | "visible" | "option" | "modules" | "optional" => (),
+        _ => {
+            return Err(Error::InvalidKconfig(format!(
+                "unsupported attribute `{}`",
+                keyword
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+fn push_entry(stack: &mut [KconfigEntry], entry: KconfigEntry) {
+    // promptless feature symbols are provided by the build, not configurable options
+    let is_feature = entry.prompt.is_none()
+        && entry
+            .symbol
+            .as_ref()
+            .is_some_and(|symbol| symbol.starts_with("FEATURE_"));
+
+    if !is_feature {
+        stack.last_mut().unwrap().children.push(entry);
+    }
+}
+
+/// Derive the option keys from the symbol names.
+///
+/// Kconfig menus don't have a symbol, so a menu's key is the common prefix of the symbols it
+/// contains, e.g. `PSRAM_ENABLE` inside a menu together with `PSRAM_SIZE` becomes `psram.enable`.
+fn resolve_keys(entries: &mut [KconfigEntry], prefix: &str) {
+    for (index, entry) in entries.iter_mut().enumerate() {
+        if entry.kind == KconfigKind::Menu {
+            let locals: Vec<Vec<&str>> = entry
+                .children
+                .iter()
+                .filter_map(|child| child.symbol.as_ref())
+                .filter_map(|symbol| symbol.strip_prefix(prefix))
+                .map(|symbol| symbol.split('_').collect())
+                .collect();
+
+            let mut common: Vec<&str> = match locals.first() {
+                Some(first) => first[..first.len() - 1].to_vec(),
+                None => Vec::new(),
+            };
+            for local in &locals[1.min(locals.len())..] {
+                let shared = common
+                    .iter()
+                    .zip(local.iter())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                common.truncate(shared.min(local.len() - 1));
+            }
+
+            let common = common.join("_");
+            if common.is_empty() {
+                entry.key = slug(entry.prompt.as_deref().unwrap_or_default());
+                resolve_keys(&mut entry.children, prefix);
+            } else {
+                entry.key = common.to_lowercase();
+                resolve_keys(&mut entry.children, &format!("{}{}_", prefix, common));
+            }
+        } else if let Some(symbol) = &entry.symbol {
+            entry.key = symbol.strip_prefix(prefix).unwrap_or(symbol).to_lowercase();
+        } else {
+            entry.key = slug(entry.prompt.as_deref().unwrap_or_default());
+        }
+
+        if entry.key.is_empty() {
+            entry.key = format!("option{}", index);
+        }
+    }
+}
+
+fn collect_symbols(entries: &[KconfigEntry], prefix: &str, symbols: &mut Map<String, String>) {
+    for entry in entries {
+        let path = format!("{}{}", prefix, entry.key);
+        if let Some(symbol) = &entry.symbol {
+            symbols.insert(symbol.clone(), path.clone());
+        }
+        if entry.kind == KconfigKind::Menu {
+            collect_symbols(&entry.children, &format!("{}.", path), symbols);
+        }
+    }
+}
+
+fn kconfig_expr_to_rhai(expr: &str, symbols: &Map<String, String>) -> Result<String, Error> {
+    let mut out = String::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => (),
+            '(' | ')' | '!' => out.push(c),
+            '&' | '|' => {
+                if chars.next() != Some(c) {
+                    return Err(Error::InvalidKconfig(format!(
+                        "unsupported expression `{}`",
+                        expr
+                    )));
+                }
+                out.push_str(&format!(" {}{} ", c, c));
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut symbol = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        symbol.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                if let Some(feature) = symbol.strip_prefix("FEATURE_") {
+                    out.push_str(&format!(
+                        "feature(\"{}\")",
+                        feature.to_lowercase().replace('_', "-")
+                    ));
+                } else if let Some(path) = symbols.get(&symbol) {
+                    out.push_str(&format!("enabled(\"{}\")", path));
+                } else {
+                    return Err(Error::InvalidKconfig(format!(
+                        "unknown symbol `{}`",
+                        symbol
+                    )));
+                }
+            }
+            _ => {
+                return Err(Error::InvalidKconfig(format!(
+                    "unsupported expression `{}`",
+                    expr
+                )))
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn toml_string(s: &str) -> String {
+    Value::String(s.to_string()).to_string()
+}
+
+fn parse_int(s: &str) -> Option<u64> {
+    let s = unquote(s);
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+fn write_definition(
+    out: &mut String,
+    entries: &[KconfigEntry],
+    prefix: &str,
+    symbols: &Map<String, String>,
+) -> Result<(), Error> {
+    for entry in entries {
+        let path = format!("{}{}", prefix, entry.key);
+
+        out.push_str(&format!("[{}]\n", path));
+        let description = entry
+            .prompt
+            .clone()
+            .or_else(|| entry.symbol.clone())
+            .unwrap_or_else(|| entry.key.clone());
+        out.push_str(&format!("description = {}\n", toml_string(&description)));
+
+        if !entry.depends.is_empty() {
+            let depends = entry
+                .depends
+                .iter()
+                .map(|d| kconfig_expr_to_rhai(d, symbols))
+                .collect::<Result<Vec<_>, _>>()?;
+            let depends = if depends.len() == 1 {
+                depends[0].clone()
+            } else {
+                depends
+                    .iter()
+                    .map(|d| format!("({})", d))
+                    .collect::<Vec<_>>()
+                    .join(" && ")
+            };
+            out.push_str(&format!("depends = {}\n", toml_string(&depends)));
+        }
+
+        match entry.kind {
+            KconfigKind::Menu => {
+                out.push('\n');
+                write_definition(out, &entry.children, &format!("{}.options.", path), symbols)?;
+                continue;
+            }
+            KconfigKind::Choice => {
+                let symbol_prefix = entry
+                    .symbol
+                    .as_ref()
+                    .map(|s| format!("{}_", s))
+                    .unwrap_or_default();
+                let value_of = |member: &KconfigEntry| {
+                    let symbol = member.symbol.clone().unwrap_or_default();
+                    symbol
+                        .strip_prefix(&symbol_prefix)
+                        .unwrap_or(&symbol)
+                        .to_lowercase()
+                };
+
+                out.push_str("type = \"enum\"\n");
+                out.push_str("values = [\n");
+                for member in &entry.children {
+                    let description = member.prompt.clone().unwrap_or_else(|| value_of(member));
+                    out.push_str(&format!(
+                        "    {{ description = {}, value = {} }},\n",
+                        toml_string(&description),
+                        toml_string(&value_of(member))
+                    ));
+                }
+                out.push_str("]\n");
+
+                let default = entry
+                    .default
+                    .as_ref()
+                    .and_then(|d| entry.children.iter().find(|m| m.symbol.as_ref() == Some(d)))
+                    .or(entry.children.first());
+                if let Some(default) = default {
+                    out.push_str(&format!("default = {}\n", toml_string(&value_of(default))));
+                }
+            }
+            KconfigKind::Bool => {
+                out.push_str("type = \"bool\"\n");
+                out.push_str(&format!(
+                    "default = {}\n",
+                    matches!(entry.default.as_deref(), Some("y") | Some("m"))
+                ));
+            }
+            KconfigKind::Int | KconfigKind::Hex => {
+                out.push_str("type = \"u32\"\n");
+                if entry.kind == KconfigKind::Hex {
+                    out.push_str("display = \"hex\"\n");
+                }
+                if let Some(default) = entry.default.as_deref().and_then(parse_int) {
+                    out.push_str(&format!("default = {}\n", default));
+                }
+                if let Some((min, max)) = &entry.range {
+                    if let (Some(min), Some(max)) = (parse_int(min), parse_int(max)) {
+                        out.push_str(&format!(
+                            "valid = {}\n",
+                            toml_string(&format!("value >= {} && value <= {}", min, max))
+                        ));
+                    }
+                }
+            }
+            KconfigKind::String => {
+                out.push_str("type = \"string\"\n");
+                if let Some(default) = &entry.default {
+                    out.push_str(&format!("default = {}\n", toml_string(&unquote(default))));
+                }
+            }
+        }
+        out.push('\n');
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32") || feature("esp32s3")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    depends = 'enabled("psram.enable")'
+    type = "enum"
+    values = [
+        { description = "1MB", value = "1" },
+        { description = "2MB", value = "2" },
+    ]
+    default = "2"
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    default = 1024
+    valid = "value >= 0 && value <= 80000"
+    "#;
+
+    #[test]
+    fn export_kconfig() {
+        let kconfig = definition_to_kconfig(&parse_definition_str(DEFINITION).unwrap());
+
+        assert!(kconfig.contains("config FEATURE_ESP32S3\n    bool\n"));
+        assert!(
+            kconfig.contains("menu \"PSRAM\"\n    depends on FEATURE_ESP32 || FEATURE_ESP32S3\n")
+        );
+        assert!(kconfig.contains("config PSRAM_ENABLE\n    bool \"Enable PSRAM\"\n    default n\n"));
+        assert!(kconfig.contains("choice PSRAM_SIZE\n    prompt \"PSRAM Size\"\n    depends on PSRAM_ENABLE\n    default PSRAM_SIZE_2\n"));
+        assert!(kconfig.contains("config PSRAM_SIZE_1\n    bool \"1MB\"\n"));
+        assert!(kconfig.contains("config HEAP_SIZE\n    int \"Bytes to allocate\"\n    default 1024\n    range 0 80000\n"));
+
+        // default tables
+        let definition = DEFINITION.replace(
+            "default = 1024",
+            "default = { other = 1024, esp32c6 = 4096 }",
+        );
+        let kconfig = definition_to_kconfig(&parse_definition_str(&definition).unwrap());
+        assert!(kconfig.contains("config FEATURE_ESP32C6\n    bool\n"));
+        assert!(kconfig.contains("    default 4096 if FEATURE_ESP32C6\n    default 1024\n"));
+    }
+
+    #[test]
+    fn import_kconfig() {
+        let kconfig = r#"
+mainmenu "Test"
+
+menu "PSRAM"
+    depends on FEATURE_ESP32
+
+config PSRAM_ENABLE
+    bool "Enable PSRAM"
+    default y
+    help
+      Enables the external RAM.
+
+choice PSRAM_SIZE
+    prompt "PSRAM Size"
+    depends on PSRAM_ENABLE
+    default PSRAM_SIZE_2
+
+config PSRAM_SIZE_1
+    bool "1MB"
+
+config PSRAM_SIZE_2
+    bool "2MB"
+
+endchoice
+
+endmenu
+
+menu "Heapsize"
+
+config HEAP_SIZE
+    hex "Bytes to allocate"
+    default 0x400
+    range 0 0x13880
+
+endmenu
+"#;
+
+        let definition = kconfig_to_definition(kconfig).unwrap();
+        let parsed = parse_definition_str(&definition).unwrap();
+
+        let psram = &parsed["psram"];
+        assert_eq!(psram.description, "PSRAM");
+        assert_eq!(psram.depends.as_deref(), Some("feature(\"esp32\")"));
+
+        let options = psram.options.as_ref().unwrap();
+        assert_eq!(options["enable"].value_type, Some(ValueType::Bool));
+        assert_eq!(options["enable"].default_value, Some(Value::Bool(true)));
+        assert_eq!(options["size"].value_type, Some(ValueType::Enum));
+        assert_eq!(
+            options["size"].depends.as_deref(),
+            Some("enabled(\"psram.enable\")")
+        );
+        assert_eq!(
+            options["size"].default_value,
+            Some(Value::String("2".to_string()))
+        );
+        assert_eq!(
+            options["size"].values.as_ref().unwrap()[0].description,
+            "1MB"
+        );
+
+        let heap = &parsed["heap"].options.as_ref().unwrap()["size"];
+        assert_eq!(heap.value_type, Some(ValueType::U32));
+        assert_eq!(heap.default_value, Some(Value::Number(1024.into())));
+        assert_eq!(heap.valid.as_deref(), Some("value >= 0 && value <= 80000"));
+        assert_eq!(heap.display, Some(DisplayHint::Hex));
+    }
+
+    #[test]
+    fn kconfig_round_trip() {
+        let kconfig = definition_to_kconfig(&parse_definition_str(DEFINITION).unwrap());
+        let definition = kconfig_to_definition(&kconfig).unwrap();
+        let parsed = parse_definition_str(&definition).unwrap();
+
+        assert_eq!(parsed.keys().collect::<Vec<_>>(), vec!["psram", "heap"]);
+        assert_eq!(
+            parsed["psram"].depends.as_deref(),
+            Some("feature(\"esp32\") || feature(\"esp32s3\")")
+        );
+
+        let psram = parsed["psram"].options.as_ref().unwrap();
+        assert_eq!(psram.keys().collect::<Vec<_>>(), vec!["enable", "size"]);
+        assert_eq!(
+            psram["size"].depends.as_deref(),
+            Some("enabled(\"psram.enable\")")
+        );
+
+        let heap = parsed["heap"].options.as_ref().unwrap();
+        assert_eq!(
+            heap["size"].valid.as_deref(),
+            Some("value >= 0 && value <= 80000")
+        );
+    }
+
+    #[test]
+    fn import_unknown_symbol() {
+        let kconfig = "config A\n    bool \"A\"\n    depends on B\n";
+        assert!(matches!(
+            kconfig_to_definition(kconfig),
+            Err(Error::InvalidKconfig(_))
+        ));
+    }
+}
@@ -1,68 +1,382 @@
-use convert_case::Casing;
+// the `json!` of the metadata test is deep
+#![recursion_limit = "256"]
+
 use linked_hash_map::LinkedHashMap as Map;
-use rhai::Engine;
-use rhai::Scope;
-use serde::Deserialize;
-pub use serde_json::Map as JsonMap;
+use serde::{Deserialize, Serialize};
 pub use serde_json::Value;
 use std::io::Write;
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+mod active;
+mod address;
+mod build_info;
+mod builder;
+mod cache;
+mod cfgs;
+mod choice;
+mod compile_error;
+mod constraints;
+mod default_table;
+mod definition;
+mod definition_file;
+mod deprecated;
+mod effective;
+mod emit;
+mod enumerate;
+mod exclusive;
+#[cfg(not(feature = "rhai"))]
+mod expression;
+mod features;
+mod format;
+mod global_constraints;
+#[cfg(feature = "export")]
+mod graph;
+mod imply;
+mod include;
+mod incremental;
+mod index;
+mod instances;
+mod interpolate;
+#[cfg(feature = "export")]
+pub mod kconfig;
+mod literal;
+mod manifest;
+mod merge;
+#[cfg(feature = "export")]
+mod metadata;
+mod migrate;
+mod payload;
+mod query;
+mod required;
+#[cfg(feature = "export")]
+mod schema;
+mod secret;
+mod section;
+mod size;
+mod templates;
+pub mod testing;
+mod to_toml;
+mod unless;
+mod value;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use active::{is_set, prune, ActiveSet, Expressions};
+pub use address::parse_address;
+pub use build_info::{config_fingerprint, write_build_info, BuildInfo};
+pub use builder::DefinitionBuilder;
+pub use cfgs::{
+    generate_cfgs_with, generate_check_cfgs, parse_cfg_names_str, write_cfg_macros, CfgNames,
+};
+pub use compile_error::{compile_error_message, write_compile_error};
+pub use constraints::{describe_constraints, numeric_range, Charset};
+pub use default_table::select_defaults;
+pub use definition::{Definition, OptionNode};
+pub use effective::{ConfigEntry, EffectiveConfig, Provenance};
+pub use emit::emitted_config;
+pub use enumerate::{config_to_toml_string, enumerate_configs};
+pub use features::{features_by_option, parse_declared_features_str, unknown_features};
+pub use format::DefinitionFormat;
+pub use global_constraints::{
+    parse_constraints_str, unmet_constraints, GlobalConstraint, UnmetConstraint,
+};
+#[cfg(feature = "export")]
+pub use graph::{dependency_graph, GraphFormat};
+pub use include::{definition_files, read_definitions, resolve_includes};
+pub use incremental::IncrementalConfig;
+pub use index::{NodeId, OptionIndex};
+pub use literal::{
+    integer_radixes, integer_value, parse_integer_literal, parse_signed_integer_literal, Radix,
+};
+pub use manifest::parse_manifest_definition_str;
+pub use merge::{merge_definitions, parse_definitions_str, MergePolicy};
+#[cfg(feature = "export")]
+pub use metadata::export_metadata;
+pub use migrate::{migrate_user_config, parse_migrations_str, Migration, MigrationApplied};
+pub use query::{get_option_info, EvalContext, OptionInfo};
+#[cfg(feature = "export")]
+pub use schema::generate_json_schema;
+#[cfg(feature = "export")]
+pub use secret::redact_config_str;
+pub use secret::{env_vars, REDACTED};
+pub use section::{parse_section_name_str, unknown_sections};
+pub use size::parse_size;
+pub use to_toml::definition_to_toml_string;
+pub use value::ConfigValue;
+
+#[deprecated(
+    note = "use `serde_json::Map` - or `Definition` and `ConfigValue` which don't expose it"
+)]
+pub type JsonMap<K, V> = serde_json::Map<K, V>;
 
 #[derive(Deserialize, Debug)]
 pub enum Error {
     InvalidKey,
     InvalidConfiguration(String),
     InvalidConfigurationValue(String),
+    InvalidKconfig(String),
+    /// The key is defined differently by definitions which are merged
+    DefinitionConflict(String),
+    /// The config-definition or the user's config isn't valid TOML (or JSON, see
+    /// [DefinitionFormat]) or doesn't have the expected structure
+    InvalidToml(String),
+    /// A `depends` or `valid` expression which can't be evaluated, with the key of the option
+    InvalidExpression(String),
+    /// Something in the config-definition which can't be used, with its key - e.g. a payload of
+    /// an enum value which isn't a valid identifier
+    InvalidDefinition(String),
+    /// The config doesn't meet a `[constraints]` entry of the config-definition, with its name and
+    /// message
+    UnmetConstraint(String, String),
+    /// Available `required` options the user's config doesn't set, with their keys
+    MissingRequired(Vec<String>),
+    /// Two enabled options declared as incompatible via `conflicts`, with their keys
+    ConflictingOptions(String, String),
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct ConfigOption {
     pub description: String,
-    #[serde(rename(deserialize = "type"))]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub value_type: Option<ValueType>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub depends: Option<String>,
+    /// The option isn't available if the expression is true - folded into `depends` when the
+    /// definition is parsed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unless: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub valid: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub values: Option<Vec<ValueItem>>,
 
-    #[serde(rename(deserialize = "default"))]
+    #[serde(rename = "default", skip_serializing_if = "Option::is_none")]
     pub default_value: Option<Value>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<Map<String, ConfigOption>>,
-
+    /// A menu with the same options per instance - `options` has a menu `0`, `1`, ... for each
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instances: Option<u32>,
+    /// Like `instances` but with an instance per value of the enum option with this key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub foreach: Option<String>,
+
+    /// The minimum number of characters of a string option
+    #[serde(alias = "min_len", skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    /// The maximum number of characters of a string option
+    #[serde(alias = "max_len", skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    /// The characters a string option accepts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset: Option<Charset>,
+    /// Values of a numeric option have to be a multiple of it, e.g. aligned buffer sizes
+    #[serde(alias = "multiple_of", skip_serializing_if = "Option::is_none")]
+    pub step: Option<u64>,
+    /// The smallest value of an integer option
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<i64>,
+    /// The largest value of an integer option
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<i64>,
+
+    /// Tooling doesn't show the value, e.g. of credentials - it's still in the generated config
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+    /// The environment variable of the build the value comes from, it can't be set in
+    /// `config.toml`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+
+    /// Options of the same group can't claim the same resource - only one bool option of the
+    /// group can be `true`, other options can't have the same value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclusive_group: Option<String>,
+
+    /// The bool options of the menu are mutually exclusive - exactly one of them is `true`, the
+    /// one the user enabled or else the first available one defaulting to `true`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub choice: bool,
+
+    /// The user has to set the value if the option is available, it can't have a default
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+
+    /// Setting the option in `config.toml` makes the build warn, it's going away
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+
+    /// The key the option had before - the user's config can still use it, see
+    /// [parse_migrations_str]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_from: Option<String>,
+
+    /// Bool options defaulting to `true` while the option is enabled - the user can still disable
+    /// them, unlike a hard `select`
+    #[serde(alias = "implies", skip_serializing_if = "Option::is_none")]
+    pub imply: Option<Vec<String>>,
+
+    /// Bool options forced to `true` while the option is enabled - the user disabling them makes
+    /// the config invalid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selects: Option<Vec<String>>,
+
+    /// Bool options which can't be enabled together with the option - the config is invalid if
+    /// both are, see [Error::ConflictingOptions]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflicts: Option<Vec<String>>,
+
+    /// Only generate code for the option (and its sub-options) if the expression is true - it can
+    /// only use `feature("...")` and `target("...")`, see [emitted_config]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emit_if: Option<String>,
+
+    /// How tooling should show numeric values
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayHint>,
+
+    /// Tooling shows options with a lower weight first, the definition's order is kept otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<i64>,
+
+    /// An internal option - evaluated and generated as usual, but tooling only shows it in expert
+    /// mode
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub __value: Option<Value>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct ValueItem {
     pub description: String,
     pub value: String,
+    /// The discriminant of the variant, the generated enum is `#[repr(u32)]` if the values have one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repr: Option<u32>,
+    /// Extra named values (e.g. `freq_hz = 80000000`), the generated enum gets a `const fn` for each
+    #[serde(flatten)]
+    pub payload: Map<String, Value>,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub enum ValueType {
-    #[serde(rename(deserialize = "bool"))]
+    #[serde(rename = "bool")]
     Bool,
-    #[serde(rename(deserialize = "u32"))]
+    #[serde(rename = "u8")]
+    U8,
+    #[serde(rename = "u16")]
+    U16,
+    #[serde(rename = "u32")]
     U32,
-    #[serde(rename(deserialize = "enum"))]
+    #[serde(rename = "u64")]
+    U64,
+    /// Validated against the range of `u32` - the value has to fit on 32-bit targets
+    #[serde(rename = "usize")]
+    Usize,
+    #[serde(rename = "i8")]
+    I8,
+    #[serde(rename = "i16")]
+    I16,
+    #[serde(rename = "i32")]
+    I32,
+    #[serde(rename = "i64")]
+    I64,
+    /// A number of bytes, `config.toml` can also have sizes like `"32KB"` - the const is a `usize`
+    #[serde(rename = "size")]
+    Size,
+    /// An address like `192.168.0.1`, the const is a `[u8; 4]`
+    #[serde(rename = "ipv4")]
+    Ipv4,
+    /// An address like `fe80::1`, the const is a `[u8; 16]`
+    #[serde(rename = "ipv6")]
+    Ipv6,
+    /// An address like `02:00:00:00:00:01`, the const is a `[u8; 6]`
+    #[serde(rename = "mac")]
+    Mac,
+    #[serde(rename = "enum")]
     Enum,
-    #[serde(rename(deserialize = "string"))]
+    #[serde(rename = "string")]
     String,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum DisplayHint {
+    #[serde(rename = "decimal")]
+    Decimal,
+    #[serde(rename = "hex")]
+    Hex,
+    #[serde(rename = "binary")]
+    Binary,
+}
+
+impl DisplayHint {
+    /// The radix to write the value in
+    pub fn radix(self) -> Radix {
+        match self {
+            DisplayHint::Decimal => Radix::Decimal,
+            DisplayHint::Hex => Radix::Hex,
+            DisplayHint::Binary => Radix::Binary,
+        }
+    }
+}
+
 impl std::fmt::Display for ValueType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ValueType::Bool => write!(f, "bool"),
+            ValueType::U8 => write!(f, "u8"),
+            ValueType::U16 => write!(f, "u16"),
             ValueType::U32 => write!(f, "u32"),
+            ValueType::U64 => write!(f, "u64"),
+            ValueType::Usize => write!(f, "usize"),
+            ValueType::I8 => write!(f, "i8"),
+            ValueType::I16 => write!(f, "i16"),
+            ValueType::I32 => write!(f, "i32"),
+            ValueType::I64 => write!(f, "i64"),
+            ValueType::Size => write!(f, "size"),
+            ValueType::Ipv4 => write!(f, "ipv4"),
+            ValueType::Ipv6 => write!(f, "ipv6"),
+            ValueType::Mac => write!(f, "mac"),
             ValueType::Enum => write!(f, "enum"),
             ValueType::String => write!(f, "string"),
         }
     }
 }
 
+impl ValueType {
+    /// The smallest and the largest value of an integer type, `None` for the other types
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        match self {
+            ValueType::U8 => Some((0, u8::MAX.into())),
+            ValueType::U16 => Some((0, u16::MAX.into())),
+            ValueType::U32 | ValueType::Usize | ValueType::Size => Some((0, u32::MAX.into())),
+            ValueType::U64 => Some((0, u64::MAX.into())),
+            ValueType::I8 => Some((i8::MIN.into(), i8::MAX.into())),
+            ValueType::I16 => Some((i16::MIN.into(), i16::MAX.into())),
+            ValueType::I32 => Some((i32::MIN.into(), i32::MAX.into())),
+            ValueType::I64 => Some((i64::MIN.into(), i64::MAX.into())),
+            ValueType::Bool
+            | ValueType::Ipv4
+            | ValueType::Ipv6
+            | ValueType::Mac
+            | ValueType::Enum
+            | ValueType::String => None,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.integer_range().is_some()
+    }
+}
+
 #[cfg(not(host_os = "windows"))]
 #[macro_export]
 macro_rules! include_config {
@@ -79,314 +393,857 @@ macro_rules! include_config {
     };
 }
 
-pub fn parse_definition_str(input: &str) -> Map<String, ConfigOption> {
-    basic_toml::from_str(input).unwrap()
+/// Assert a relationship between consts of the generated configs at compile time - the build
+/// fails with the condition as the message if it doesn't hold
+///
+/// ```
+/// mod esp_hal {
+///     pub const HEAP_SIZE: u32 = 4096;
+/// }
+/// mod esp_wifi {
+///     pub const RX_BUFFER: u32 = 1024;
+///     pub const TX_BUFFER: u32 = 1024;
+/// }
+///
+/// rconfig::assert_cfg!(esp_wifi::RX_BUFFER + esp_wifi::TX_BUFFER <= esp_hal::HEAP_SIZE);
+/// rconfig::assert_cfg!(
+///     esp_wifi::RX_BUFFER >= 512,
+///     "esp-wifi needs at least 512 bytes of RX buffer"
+/// );
+/// ```
+///
+/// ```compile_fail
+/// const HEAP_SIZE: u32 = 1024;
+/// const RX_BUFFER: u32 = 2048;
+///
+/// rconfig::assert_cfg!(RX_BUFFER <= HEAP_SIZE);
+/// ```
+#[macro_export]
+macro_rules! assert_cfg {
+    ($condition:expr $(,)?) => {
+        const _: () = if !($condition) {
+            ::core::panic!(
+                "{}",
+                ::core::concat!(
+                    "configuration invariant `",
+                    ::core::stringify!($condition),
+                    "` doesn't hold"
+                )
+            )
+        };
+    };
+    ($condition:expr, $message:literal $(,)?) => {
+        const _: () = if !($condition) {
+            ::core::panic!("{}", $message)
+        };
+    };
 }
 
-pub fn evaluate_config_str_to_cfg(
+/// Assert at compile time that the crates were built with the same `config.toml` - catches a
+/// crate compiled against an outdated config, needs `build_info = true` in the definitions
+///
+/// ```
+/// # mod esp_hal { pub mod rconfig_build_info { pub const USER_CONFIG_DIGEST: u64 = 0x2c7e; } }
+/// # mod esp_wifi { pub mod rconfig_build_info { pub const USER_CONFIG_DIGEST: u64 = 0x2c7e; } }
+/// rconfig::assert_consistent_config!(esp_hal::rconfig_build_info, esp_wifi::rconfig_build_info);
+/// ```
+///
+/// The crates have to make their `rconfig_build_info` module public.
+#[macro_export]
+macro_rules! assert_consistent_config {
+    ($first:path $(, $other:path)+ $(,)?) => {
+        $(
+            const _: () = {
+                use $first as first;
+                use $other as other;
+                if first::USER_CONFIG_DIGEST != other::USER_CONFIG_DIGEST {
+                    ::core::panic!(
+                        "{}",
+                        ::core::concat!(
+                            "`",
+                            ::core::stringify!($other),
+                            "` was built with another config.toml than `",
+                            ::core::stringify!($first),
+                            "`"
+                        )
+                    )
+                }
+            };
+        )+
+    };
+}
+
+/// Parse a config-definition - the files its `include` refers to are relative to the current
+/// directory (the package's directory for build scripts), see [definition_files]
+///
+/// A JSON definition is detected, see [DefinitionFormat::detect].
+pub fn parse_definition_str(input: &str) -> Result<Map<String, ConfigOption>, Error> {
+    parse_definition_str_as(input, DefinitionFormat::detect(input))
+}
+
+/// [parse_definition_str] for a definition in the given format, e.g. YAML converted to JSON
+pub fn parse_definition_str_as(
+    input: &str,
+    format: DefinitionFormat,
+) -> Result<Map<String, ConfigOption>, Error> {
+    let parsed = definition_file::DefinitionFile::parse_as(input, format)?;
+    if parsed.include.is_empty() {
+        return Ok(parsed.options);
+    }
+
+    let included = include::read_with_includes(parsed.include.iter().map(PathBuf::from).collect())?;
+    let mut files = vec![parsed];
+    for (_, definition) in included {
+        files.push(definition_file::DefinitionFile::parse(&definition)?);
+    }
+    Ok(merge::merge_definition_files(files)?.options)
+}
+
+fn parse_toml<T: serde::de::DeserializeOwned>(input: &str) -> Result<T, Error> {
+    basic_toml::from_str::<T>(input).map_err(|err| Error::InvalidToml(err.to_string()))
+}
+
+/// The crate's section of the user's config - `None` if there is none
+fn crate_section(
+    input: &str,
+    crate_name: &str,
+) -> Result<Option<serde_json::Map<String, Value>>, Error> {
+    match parse_toml::<Value>(input)?.get(crate_name) {
+        None => Ok(None),
+        Some(Value::Object(section)) => Ok(Some(section.clone())),
+        Some(_) => Err(Error::InvalidToml(format!(
+            "`{}` is not a table",
+            crate_name
+        ))),
+    }
+}
+
+/// Split a TOML document into its top-level sections
+///
+/// Tables like `[crate.psram]` belong to the section `crate`, everything before the first table
+/// is returned as the section with an empty name.
+pub fn split_sections(toml: &str) -> Vec<(String, String)> {
+    let mut sections: Vec<(String, String)> = vec![(String::new(), String::new())];
+
+    for line in toml.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && !trimmed.starts_with("[[") {
+            let header = trimmed.trim_start_matches('[');
+            let header = header.split(']').next().unwrap_or_default();
+            let name = if let Some(quoted) = header.trim().strip_prefix('"') {
+                quoted.split('"').next().unwrap_or_default()
+            } else {
+                header.split('.').next().unwrap_or_default().trim()
+            };
+
+            if sections.last().map(|(last, _)| last.as_str()) != Some(name) {
+                sections.push((name.to_string(), String::new()));
+            }
+        }
+
+        let text = &mut sections.last_mut().unwrap().1;
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    sections
+}
+
+/// Fuse the user's config into the definition without removing options which are not applicable
+pub fn fuse_config_str(
     input: &str,
     crate_name: &str,
     mut config: Map<String, ConfigOption>,
-    features: Vec<&str>,
 ) -> Result<Map<String, ConfigOption>, Error> {
-    let input = basic_toml::from_str::<Value>(input).unwrap();
+    if let Some(input) = crate_section(input, crate_name)? {
+        // fuse the user changed configs into the config
+        fuse(input, &mut config)?;
+    }
 
-    let input = input.as_object().unwrap().get(crate_name).unwrap();
+    Ok(config)
+}
 
-    // fuse the user changed configs into the config
-    fuse(input.clone(), &mut config)?;
+pub fn evaluate_config_str_to_cfg(
+    input: &str,
+    crate_name: &str,
+    config: Map<String, ConfigOption>,
+    features: Vec<&str>,
+) -> Result<Map<String, ConfigOption>, Error> {
+    let mut config = fuse_config_str(input, crate_name, config)?;
 
     // don't validate - might run into issue while editing and we'll remove things in the next step anyways
 
-    let config = remove_non_applicable(&config, &config, &features, Map::new())?;
+    default_table::select_defaults(&mut config, &features);
+    imply::apply_implies(&mut config, &features);
+    choice::apply_choices(&mut config, &features);
+    let expressions = Expressions::new(&config, &features);
+    let active = ActiveSet::new(&config, &expressions);
+    prune(&mut config, &active, "");
 
     Ok(config)
 }
 
+/// Like [evaluate_config] but with stringified values
 pub fn evaluate_config_str(
     input: &str,
     crate_name: &str,
-    mut config: Map<String, ConfigOption>,
+    config: Map<String, ConfigOption>,
     features: Vec<&str>,
 ) -> Result<Vec<(String, String, ValueType)>, Error> {
-    let input = basic_toml::from_str::<Value>(input).unwrap();
-    let no_input = basic_toml::from_str::<Value>("").unwrap();
+    evaluate_config(input, crate_name, config, features).map(|config| config.to_tuples())
+}
 
-    let input = input
-        .as_object()
-        .unwrap()
-        .get(crate_name)
-        .unwrap_or_else(|| &no_input);
+/// Evaluate the user's config (the content of `config.toml`) for the crate
+pub fn evaluate_config(
+    input: &str,
+    crate_name: &str,
+    config: Map<String, ConfigOption>,
+    features: Vec<&str>,
+) -> Result<EffectiveConfig, Error> {
+    check_definition(&config, "")?;
+    imply::check_implies(&config)?;
+    exclusive::check_conflicts_definition(&config)?;
+    evaluate_checked_config(input, crate_name, config, features)
+}
 
-    // fuse the user changed configs into the config
-    fuse(input.clone(), &mut config)?;
+/// [evaluate_config] for a definition with expressions known to parse
+fn evaluate_checked_config(
+    input: &str,
+    crate_name: &str,
+    config: Map<String, ConfigOption>,
+    features: Vec<&str>,
+) -> Result<EffectiveConfig, Error> {
+    let mut result = Vec::new();
+    visit_checked_config(
+        input,
+        crate_name,
+        config,
+        features,
+        |key, value, option, provenance| {
+            result.push(ConfigEntry {
+                key: key.to_string(),
+                value: value.clone(),
+                value_type: option.value_type.clone().unwrap(),
+                provenance,
+                secret: option.secret,
+            })
+        },
+    )?;
 
-    validate(&config, &config, &features, true)?;
+    Ok(EffectiveConfig::new(result))
+}
 
-    let config = remove_non_applicable(&config, &config, &features, Map::new())?;
+fn visit_checked_config(
+    input: &str,
+    crate_name: &str,
+    mut config: Map<String, ConfigOption>,
+    features: Vec<&str>,
+    mut visitor: impl FnMut(&str, &Value, &ConfigOption, Provenance),
+) -> Result<(), Error> {
+    if let Some(input) = crate_section(input, crate_name)? {
+        // fuse the user changed configs into the config
+        fuse(input, &mut config)?;
+    }
 
-    // create result
-    let mut result = Vec::new();
-    create_result(&mut result, &config, &config, &features, "".to_string());
+    default_table::select_defaults(&mut config, &features);
+    imply::apply_implies(&mut config, &features);
+    choice::apply_choices(&mut config, &features);
+    let expressions = Expressions::new(&config, &features);
+    validate(&config, &expressions, true, "")?;
+
+    let active = ActiveSet::new(&config, &expressions);
+    interpolate::expand_placeholders(&mut config, &active, &expressions)?;
+    exclusive::check_exclusive(&config, &active)?;
+    choice::check_choices(&config, &active)?;
+    imply::check_selects(&config, &active)?;
+    exclusive::check_declared_conflicts(&config, &active)?;
+    required::check_required(&config, &active)?;
+    visit_result(&config, &active, &mut String::new(), &mut visitor);
 
-    Ok(result)
+    Ok(())
 }
 
-pub fn current_config_values(
+/// Like [evaluate_config] but the entries are handed to the visitor (key, value, type and
+/// provenance) instead of being collected, e.g. to write them out directly
+pub fn visit_config(
+    input: &str,
+    crate_name: &str,
     config: Map<String, ConfigOption>,
     features: Vec<&str>,
-) -> Result<Vec<(String, String)>, Error> {
-    let config = remove_non_applicable(&config, &config, &features, Map::new())?;
+    mut visitor: impl FnMut(&str, &Value, &ValueType, Provenance),
+) -> Result<(), Error> {
+    check_definition(&config, "")?;
+    imply::check_implies(&config)?;
+    exclusive::check_conflicts_definition(&config)?;
+    visit_checked_config(
+        input,
+        crate_name,
+        config,
+        features,
+        |key, value, option, provenance| {
+            visitor(key, value, option.value_type.as_ref().unwrap(), provenance)
+        },
+    )
+}
 
-    // create result
+/// The values set by the user which are applicable with the features
+pub fn current_config_values(
+    config: Map<String, ConfigOption>,
+    features: Vec<&str>,
+) -> Result<Vec<(String, Value)>, Error> {
     let mut result = Vec::new();
-    create_current_config_result(&mut result, &config, &config, &features, "".to_string());
+    visit_current_config_values(&config, &features, |key, value| {
+        result.push((key.to_string(), value.clone()))
+    });
 
     Ok(result)
 }
 
-fn create_current_config_result(
-    result: &mut Vec<(String, String)>,
+/// Like [current_config_values] but the entries are handed to the visitor instead of being
+/// collected
+pub fn visit_current_config_values(
     config: &Map<String, ConfigOption>,
-    all_config: &Map<String, ConfigOption>,
-    features: &Vec<&str>,
-    prefix: String,
+    features: &[&str],
+    mut visitor: impl FnMut(&str, &Value),
 ) {
-    for (name, item) in config {
-        if let Some(value) = &item.__value {
-            result.push((format!("{}{}", prefix, name), value.to_string()));
-        } else if let Some(options) = item.options.as_ref() {
-            create_current_config_result(
-                result,
-                options,
-                all_config,
-                features,
-                format!("{}{}.", prefix, name),
-            );
+    let config = default_table::with_defaults(config, features);
+    let active = ActiveSet::new(&config, &Expressions::new(&config, features));
+    visit_current_values(&config, &active, &mut String::new(), &mut visitor);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigProblemKind {
+    /// The key doesn't exist in the definition
+    UnknownKey,
+    /// The option isn't available with the current features and values
+    NotApplicable,
+    /// The value has the wrong type, doesn't pass the validation or can only be set by an
+    /// environment variable
+    InvalidValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    pub key: String,
+    pub value: Value,
+    pub kind: ConfigProblemKind,
+}
+
+/// Find all entries of the user's config which would make the evaluation fail
+pub fn check_config_str(
+    input: &str,
+    crate_name: &str,
+    config: &Map<String, ConfigOption>,
+    features: Vec<&str>,
+) -> Result<Vec<ConfigProblem>, Error> {
+    let Some(input) = crate_section(input, crate_name)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut problems = Vec::new();
+    let mut values = Vec::new();
+    collect_known_values(
+        &Value::Object(input),
+        config,
+        "",
+        &mut problems,
+        &mut values,
+    );
+
+    // fuse what is known to check the dependencies and validations
+    let mut fused = config.clone();
+    for (key, value) in &values {
+        set_value_by_key(&mut fused, key, value.clone());
+    }
+    default_table::select_defaults(&mut fused, &features);
+    imply::apply_implies(&mut fused, &features);
+    choice::apply_choices(&mut fused, &features);
+
+    let expressions = Expressions::new(&fused, &features);
+    let active = ActiveSet::new(&fused, &expressions);
+    let index = OptionIndex::new(&fused);
+    for (key, value) in &values {
+        // all keys were collected from the definition
+        let Some(option) = index.get(key) else {
+            continue;
+        };
+
+        let kind = if !active.contains(key) {
+            Some(ConfigProblemKind::NotApplicable)
+        } else if option.env.is_some()
+            || !is_value_of_type(option, value)
+            || !constraints::meets_constraints(option, value)
+            || !expressions
+                .valid(option.valid.as_deref(), value)
+                .unwrap_or(false)
+        {
+            Some(ConfigProblemKind::InvalidValue)
+        } else {
+            None
+        };
+
+        if let Some(kind) = kind {
+            problems.push(ConfigProblem {
+                key: key.clone(),
+                value: value.clone(),
+                kind,
+            });
+        }
+    }
+
+    // the user's value claiming what another option already claims is the problem
+    for conflict in exclusive::conflicts(&fused, &active)
+        .into_iter()
+        .chain(exclusive::declared_conflicts(&fused, &active))
+    {
+        let conflicting = [conflict.second, conflict.first]
+            .into_iter()
+            .find_map(|key| {
+                let (_, value) = values.iter().find(|(value_key, _)| *value_key == key)?;
+                Some((key, value.clone()))
+            });
+        if let Some((key, value)) = conflicting {
+            if !problems.iter().any(|problem| problem.key == key) {
+                problems.push(ConfigProblem {
+                    key,
+                    value,
+                    kind: ConfigProblemKind::InvalidValue,
+                });
+            }
+        }
+    }
+
+    for key in choice::clashes(&fused, &active)
+        .into_iter()
+        .chain(imply::unmet_selects(&fused, &active))
+    {
+        let Some((_, value)) = values.iter().find(|(value_key, _)| *value_key == key) else {
+            continue;
+        };
+        if !problems.iter().any(|problem| problem.key == key) {
+            problems.push(ConfigProblem {
+                key,
+                value: value.clone(),
+                kind: ConfigProblemKind::InvalidValue,
+            });
         }
     }
+
+    Ok(problems)
 }
 
-fn remove_non_applicable(
-    config_part: &Map<String, ConfigOption>,
-    all_config: &Map<String, ConfigOption>,
-    features: &Vec<&str>,
-    mut building: Map<String, ConfigOption>,
-) -> Result<Map<String, ConfigOption>, Error> {
-    for (name, item) in config_part {
-        let mut item = item.clone();
-        let take = is_valid_depends(item.depends.clone(), all_config, features);
+fn collect_known_values(
+    input: &Value,
+    config: &Map<String, ConfigOption>,
+    prefix: &str,
+    problems: &mut Vec<ConfigProblem>,
+    values: &mut Vec<(String, Value)>,
+) {
+    let Some(input) = input.as_object() else {
+        return;
+    };
 
-        if let Some(options) = item.options.as_ref() {
-            let options = remove_non_applicable(options, all_config, features, Map::new())?;
-            item.options = Some(options);
+    for (name, value) in input {
+        let key = format!("{}{}", prefix, name);
+        match config.get(name) {
+            Some(ConfigOption {
+                options: Some(options),
+                ..
+            }) if value.is_object() => {
+                collect_known_values(value, options, &format!("{}.", key), problems, values)
+            }
+            Some(item) if item.options.is_none() && item.value_type.is_some() => {
+                values.push((key, user_value(item, value.clone())))
+            }
+            _ => collect_unknown_values(value, key, problems),
+        }
+    }
+}
+
+/// The value of the user's config to use for the option - integers can also be written as strings,
+/// e.g. `"0x8000"`
+fn user_value(option: &ConfigOption, value: Value) -> Value {
+    match &option.value_type {
+        Some(ValueType::Size) => size::size_from_string(value),
+        Some(value_type) if value_type.is_integer() => literal::integer_from_string(value),
+        _ => value,
+    }
+}
+
+fn collect_unknown_values(input: &Value, key: String, problems: &mut Vec<ConfigProblem>) {
+    match input.as_object() {
+        Some(input) if !input.is_empty() => {
+            for (name, value) in input {
+                collect_unknown_values(value, format!("{}.{}", key, name), problems);
+            }
         }
+        _ => problems.push(ConfigProblem {
+            key,
+            value: input.clone(),
+            kind: ConfigProblemKind::UnknownKey,
+        }),
+    }
+}
 
-        if take {
-            building.insert(name.clone(), item);
+fn set_value_by_key(config: &mut Map<String, ConfigOption>, key: &str, value: Value) {
+    let mut current = Some(config);
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let Some(item) = current.and_then(|level| level.get_mut(part)) else {
+            return;
+        };
+        if parts.peek().is_none() {
+            item.__value = Some(value);
+            return;
         }
+        current = item.options.as_mut();
     }
+}
 
-    Ok(building)
+/// Values of integer options have to be in the range of the type and values of address options
+/// valid addresses - the generated consts wouldn't compile otherwise
+fn fits_value_type(option: &ConfigOption, value: &Value) -> bool {
+    !option.value_type.as_ref().is_some_and(|value_type| {
+        value_type.is_integer() || address::address_len(value_type).is_some()
+    }) || is_value_of_type(option, value)
 }
 
-fn validate(
-    config_part: &Map<String, ConfigOption>,
+pub(crate) fn is_value_of_type(option: &ConfigOption, value: &Value) -> bool {
+    match &option.value_type {
+        Some(ValueType::Bool) => value.is_boolean(),
+        Some(ValueType::String) => value.is_string(),
+        Some(address @ (ValueType::Ipv4 | ValueType::Ipv6 | ValueType::Mac)) => value
+            .as_str()
+            .is_some_and(|text| address::parse_address(address, text).is_some()),
+        Some(ValueType::Enum) => option.values.as_ref().is_some_and(|values| {
+            values
+                .iter()
+                .any(|v| Some(v.value.as_str()) == value.as_str())
+        }),
+        Some(integer) => match (integer.integer_range(), literal::json_integer(value)) {
+            (Some((min, max)), Some(number)) => (min..=max).contains(&number),
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+/// All features checked via `feature("...")` in `depends` and `valid` expressions and the ones of
+/// default tables.
+pub fn referenced_features(config: &Map<String, ConfigOption>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for feature in features_by_option(config)
+        .into_iter()
+        .flat_map(|(_, features)| features)
+    {
+        if !result.contains(&feature) {
+            result.push(feature);
+        }
+    }
+    result
+}
+
+/// The string arguments of all calls to `function` in the expression
+pub(crate) fn expression_arguments<'a>(expression: &'a str, function: &str) -> Vec<&'a str> {
+    let mut result = Vec::new();
+    let call = format!("{}(", function);
+    let mut rest = expression;
+    while let Some(index) = rest.find(&call) {
+        rest = rest[index + call.len()..].trim_start();
+        let quote = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => continue,
+        };
+        rest = &rest[1..];
+        if let Some(end) = rest.find(quote) {
+            result.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+    result
+}
+
+/// A `depends` which isn't met
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnmetDepends {
+    /// The option or menu the `depends` belongs to
+    pub key: String,
+    pub depends: String,
+    /// Features checked by the expression which are not active
+    pub missing_features: Vec<String>,
+    /// Options checked by the expression which are not enabled
+    pub disabled_options: Vec<String>,
+}
+
+/// Explain why an option isn't available - every menu on the way to the option and the option itself
+/// can have a `depends` which isn't met
+///
+/// `all_config` is the config with the user's values fused in (see [fuse_config_str])
+pub fn explain_unavailable(
+    key: &str,
     all_config: &Map<String, ConfigOption>,
     features: &Vec<&str>,
+) -> Vec<UnmetDepends> {
+    let all_config = &*default_table::with_defaults(all_config, features);
+    let expressions = Expressions::new(all_config, features);
+    let mut result = Vec::new();
+    let mut current = Some(all_config);
+    let mut path = Vec::new();
+    for part in key.split('.') {
+        let Some(item) = current.and_then(|level| level.get(part)) else {
+            break;
+        };
+        path.push(part);
+
+        let unmet = item
+            .depends
+            .clone()
+            .filter(|depends| !expressions.depends(Some(depends)).unwrap_or(false));
+        if let Some(depends) = unmet {
+            result.push(UnmetDepends {
+                key: path.join("."),
+                missing_features: expression_arguments(&depends, "feature")
+                    .into_iter()
+                    .filter(|feature| !features.contains(feature))
+                    .map(|feature| feature.to_string())
+                    .collect(),
+                disabled_options: expression_arguments(&depends, "enabled")
+                    .into_iter()
+                    .filter(|option| !is_value_resolves_to_set(option, all_config))
+                    .map(|option| option.to_string())
+                    .collect(),
+                depends,
+            });
+        }
+
+        current = item.options.as_ref();
+    }
+    result
+}
+
+// `key` is a buffer for the dotted keys, it's left as it was
+fn visit_current_values(
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+    key: &mut String,
+    visitor: &mut impl FnMut(&str, &Value),
+) {
+    for (name, item) in config {
+        let len = key.len();
+        key.push_str(name);
+
+        if active.contains(key) {
+            if let Some(value) = &item.__value {
+                visitor(key, value);
+            } else if let Some(options) = item.options.as_ref() {
+                key.push('.');
+                visit_current_values(options, active, key, visitor);
+            }
+        }
+
+        key.truncate(len);
+    }
+}
+
+fn validate(
+    config_part: &Map<String, ConfigOption>,
+    expressions: &Expressions,
     take: bool,
+    prefix: &str,
 ) -> Result<(), Error> {
     for (name, item) in config_part {
-        let take = take && is_valid_depends(item.depends.clone(), all_config, features);
+        let key = format!("{}{}", prefix, name);
+        let take = take
+            && expressions
+                .depends(item.depends.as_deref())
+                .map_err(|_| Error::InvalidExpression(key.clone()))?;
 
         if let Some(_value) = &item.__value {
             if !take {
-                return Err(Error::InvalidConfiguration(name.to_string()));
+                // the environment variables are there whether the option is used or not
+                if item.env.is_none() {
+                    return Err(Error::InvalidConfiguration(key));
+                }
+            } else if !fits_value_type(item, _value)
+                || !constraints::meets_constraints(item, _value)
+                || !expressions
+                    .valid(item.valid.as_deref(), _value)
+                    .map_err(|_| Error::InvalidExpression(key.clone()))?
+            {
+                return Err(Error::InvalidConfigurationValue(key));
             }
+        }
+
+        if let Some(options) = item.options.as_ref() {
+            validate(options, expressions, take, &format!("{}.", key))?;
+        }
+    }
 
-            if !is_value_valid(item.valid.clone(), _value, all_config, features) {
-                return Err(Error::InvalidConfigurationValue(name.to_string()));
+    Ok(())
+}
+
+/// Make sure all `depends`, `valid` and `emit_if` expressions at least parse and the code
+/// generated for the definition compiles - the keys of options and menus become parts of const
+/// names, enum defaults have to be one of the `values`
+fn check_definition(config: &Map<String, ConfigOption>, prefix: &str) -> Result<(), Error> {
+    for (name, item) in config {
+        let key = format!("{}{}", prefix, name);
+        // entries without a type and options are just comments
+        let generates_code = item.value_type.is_some() || item.options.is_some();
+        if generates_code && !is_key_identifier(name, prefix.is_empty()) {
+            return Err(Error::InvalidDefinition(key));
+        }
+        for expression in [&item.depends, &item.valid, &item.emit_if]
+            .into_iter()
+            .flatten()
+        {
+            if active::check_syntax(expression).is_err() {
+                return Err(Error::InvalidExpression(key));
             }
         }
 
+        if let Some(values) = item.values.as_ref() {
+            payload::payloads(&key, values)?;
+            payload::check_reprs(&key, values)?;
+        }
+        constraints::check_constraints(&key, item)?;
+        secret::check_secret(&key, item)?;
+        required::check_required_option(&key, item)?;
+        default_table::check_default_table(&key, item)?;
+        if let Some(default) = item.default_value.as_ref() {
+            if default_table::default_table(item).is_none()
+                && default_table::conditional_defaults(item).is_none()
+                && (!fits_value_type(item, default)
+                    || item.value_type == Some(ValueType::Enum) && !is_value_of_type(item, default))
+            {
+                return Err(Error::InvalidDefinition(format!("{}.default", key)));
+            }
+        }
+        exclusive::check_exclusive_group(&key, item)?;
+        choice::check_choice(&key, item)?;
+
         if let Some(options) = item.options.as_ref() {
-            validate(options, all_config, features, take)?;
+            check_definition(options, &format!("{}.", key))?;
         }
     }
 
     Ok(())
 }
 
+/// Whether the key can be part of a const name - below the top-level (e.g. instances like `uart.0`)
+/// it can start with a digit
+fn is_key_identifier(name: &str, top_level: bool) -> bool {
+    let first = name.chars().next();
+    first.is_some_and(|c| !top_level || !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether the value passes the validation - a validation which can't be evaluated fails
 pub fn is_value_valid(
     validation: Option<String>,
     value: &Value,
     all_config: &Map<String, ConfigOption>,
     features: &Vec<&str>,
 ) -> bool {
-    if let Some(validation) = validation {
-        // is this expensive? should we reuse the Engine?
-        let mut engine = Engine::new();
-
-        let script_features: Vec<String> = features.iter().map(|s| s.to_string()).collect();
-
-        let f = move |what: String| script_features.contains(&what);
-        engine.register_fn("feature", f);
-
-        let all_config = all_config.clone();
-        let f = move |what: &str| is_value_resolves_to_set(what, &all_config);
-        engine.register_fn("enabled", f);
-
-        let mut scope = Scope::new();
-        match value {
-            Value::Bool(b) => scope.push("value", *b),
-            Value::Number(n) => scope.push("value", n.as_u64().unwrap() as i64),
-            Value::String(s) => scope.push("value", s.as_str().to_string()),
-            _ => scope.push("value", false),
-        };
+    Expressions::new(
+        &default_table::with_defaults(all_config, features),
+        features,
+    )
+    .valid(validation.as_deref(), value)
+    .unwrap_or(false)
+}
 
-        engine
-            .eval_with_scope::<bool>(&mut scope, &validation)
-            .unwrap()
-    } else {
-        true
-    }
+/// Whether the value fits the option's type (the range of integer types, the format of addresses),
+/// meets its `min_length`, `max_length`, `charset` and `step` and passes its validation
+pub fn is_option_value_valid(
+    option: &ConfigOption,
+    value: &Value,
+    all_config: &Map<String, ConfigOption>,
+    features: &Vec<&str>,
+) -> bool {
+    fits_value_type(option, value)
+        && constraints::meets_constraints(option, value)
+        && is_value_valid(option.valid.clone(), value, all_config, features)
 }
 
-fn is_valid_depends(
+/// Whether the `depends` is met - a `depends` which can't be evaluated isn't
+pub fn is_valid_depends(
     depends: Option<String>,
     all_config: &Map<String, ConfigOption>,
     features: &Vec<&str>,
 ) -> bool {
-    if let Some(depends) = depends {
-        // is this expensive? should we reuse the Engine?
-        let mut engine = Engine::new();
-
-        let script_features: Vec<String> = features.iter().map(|s| s.to_string()).collect();
-
-        let f = move |what: String| script_features.contains(&what);
-        engine.register_fn("feature", f);
-
-        let all_config = all_config.clone();
-        let f = move |what: &str| is_value_resolves_to_set(what, &all_config);
-        engine.register_fn("enabled", f);
-
-        engine.eval::<bool>(&depends).unwrap()
-    } else {
-        true
-    }
+    Expressions::new(
+        &default_table::with_defaults(all_config, features),
+        features,
+    )
+    .depends(depends.as_deref())
+    .unwrap_or(false)
 }
 
 fn is_value_resolves_to_set(option: &str, all_config: &Map<String, ConfigOption>) -> bool {
-    let value = get_value(option, all_config);
-    match value {
-        None => false,
-        Some(value) => match value {
-            Value::Null => false,
-            Value::Bool(value) => value,
-            Value::Number(value) => value != serde_json::Number::from_f64(0f64).unwrap(),
-            Value::String(value) => !value.is_empty(),
-            Value::Array(_) => false,
-            Value::Object(_) => false,
-        },
-    }
+    get_value(option, all_config).is_some_and(|value| is_set(&value))
 }
 
 fn get_value(option: &str, all_config: &Map<String, ConfigOption>) -> Option<serde_json::Value> {
-    let path = option.split(".");
-    let mut current = all_config;
-
-    let parts: Vec<&str> = path.collect();
-    for part in &parts[..parts.len() - 1] {
-        if let Some(next) = &current.get(*part) {
-            if let Some(next) = next.options.as_ref() {
-                current = next;
-            } else {
-                return None;
-            }
-        } else {
-            return None;
-        }
+    let mut parts = option.split('.');
+    let mut item = all_config.get(parts.next()?)?;
+    for part in parts {
+        item = item.options.as_ref()?.get(part)?;
     }
 
-    if current.get(*parts.last().unwrap()).as_ref().is_some() {
-        let value = current
-            .get(*parts.last().unwrap())
-            .as_ref()
-            .unwrap()
-            .__value
-            .clone();
-        let def_value = current
-            .get(*parts.last().unwrap())
-            .as_ref()
-            .unwrap()
-            .default_value
-            .clone();
-
-        let value = if value.is_some() { value } else { def_value };
-        value
-    } else {
-        None
-    }
+    item.__value.clone().or_else(|| item.default_value.clone())
 }
 
-fn create_result(
-    result: &mut Vec<(String, String, ValueType)>,
+// `key` is a buffer for the dotted keys, it's left as it was - the visitor only gets options
+// with a type
+fn visit_result(
     config: &Map<String, ConfigOption>,
-    all_config: &Map<String, ConfigOption>,
-    features: &Vec<&str>,
-    prefix: String,
+    active: &ActiveSet,
+    key: &mut String,
+    visitor: &mut impl FnMut(&str, &Value, &ConfigOption, Provenance),
 ) {
     for (name, item) in config {
-        if let Some(value) = &item.__value {
-            result.push((
-                format!("{}{}", prefix, name),
-                value.to_string(),
-                item.value_type.as_ref().unwrap().clone(),
-            ));
-        } else {
-            if let Some(value) = &item.default_value {
-                if is_valid_depends(item.depends.clone(), &all_config, features) {
-                    result.push((
-                        format!("{}{}", prefix, name),
-                        value.to_string(),
-                        item.value_type.as_ref().unwrap().clone(),
-                    ));
+        let len = key.len();
+        key.push_str(name);
+
+        if active.contains(key) {
+            if let Some(value) = &item.__value {
+                if item.value_type.is_some() {
+                    visitor(key, value, item, secret::value_provenance(item));
                 }
-            } else {
-                if let Some(options) = item.options.as_ref() {
-                    create_result(
-                        result,
-                        options,
-                        all_config,
-                        features,
-                        format!("{}{}.", prefix, name),
-                    );
+            } else if let Some(value) = &item.default_value {
+                if item.value_type.is_some() {
+                    visitor(key, value, item, Provenance::Default);
                 }
+            } else if let Some(options) = item.options.as_ref() {
+                key.push('.');
+                visit_result(options, active, key, visitor);
             }
         }
+
+        key.truncate(len);
     }
 }
 
-fn fuse(value: Value, config: &mut Map<String, ConfigOption>) -> Result<(), Error> {
-    match value {
-        Value::Null => (),
-        Value::Bool(_) => todo!(),
-        Value::Number(_) => todo!(),
-        Value::String(_) => todo!(),
-        Value::Array(_) => todo!(),
-        Value::Object(item) => {
-            for (name, value) in item {
-                if !config.contains_key(&name) {
-                    return Err(Error::InvalidKey);
-                }
-                let c = config.get_mut(&name).unwrap();
+fn fuse(
+    input: serde_json::Map<String, Value>,
+    config: &mut Map<String, ConfigOption>,
+) -> Result<(), Error> {
+    for (name, value) in input {
+        let Some(c) = config.get_mut(&name) else {
+            return Err(Error::InvalidKey);
+        };
 
-                if let Some(options) = c.options.as_mut() {
-                    fuse(value, options)?;
-                } else {
-                    c.__value = Some(value);
-                }
+        match (c.options.as_mut(), value) {
+            (Some(options), Value::Object(value)) => fuse(value, options)?,
+            // only set from the environment
+            (None, _) if c.env.is_some() => return Err(Error::InvalidConfiguration(name)),
+            (None, value) if c.value_type.is_some() && !value.is_object() => {
+                c.__value = Some(user_value(c, value))
             }
+            // comments can't have a value
+            (None, _) if c.value_type.is_none() => return Err(Error::InvalidKey),
+            (_, _) => return Err(Error::InvalidConfigurationValue(name)),
         }
     }
 
@@ -397,12 +1254,15 @@ fn fuse(value: Value, config: &mut Map<String, ConfigOption>) -> Result<(), Erro
 struct EnumDefinition {
     name: String,
     variant_names: Vec<String>,
+    /// The discriminants of the variants
+    reprs: Option<Vec<u32>>,
+    payloads: Vec<payload::Payload>,
 }
 
-fn extract_all_enum_definitions(config: Map<String, ConfigOption>) -> Vec<EnumDefinition> {
+fn extract_all_enum_definitions(config: &Map<String, ConfigOption>) -> Vec<EnumDefinition> {
     let mut result = Vec::new();
 
-    extract_all_enum_definitions_recusive(&mut result, &config, "".to_string());
+    extract_all_enum_definitions_recusive(&mut result, config, "".to_string());
 
     result
 }
@@ -415,146 +1275,469 @@ fn extract_all_enum_definitions_recusive(
     for (name, item) in config {
         if let Some(ValueType::Enum) = item.value_type {
             let mut variant_names = Vec::new();
-            for variant in item.values.as_ref().unwrap() {
+            for variant in item.values.iter().flatten() {
                 variant_names.push(to_variant_name(&variant.value));
             }
 
             let item = EnumDefinition {
-                name: format!(
-                    "{}{}",
-                    prefix.to_case(convert_case::Case::Pascal),
-                    name.to_case(convert_case::Case::Pascal)
-                ),
+                name: format!("{}{}", pascal_case(&prefix), pascal_case(name)),
                 variant_names,
+                reprs: item
+                    .values
+                    .iter()
+                    .flatten()
+                    .map(|variant| variant.repr)
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|reprs| !reprs.is_empty()),
+                // checked when evaluating the config
+                payloads: payload::payloads(name, item.values.as_deref().unwrap_or_default())
+                    .unwrap_or_default(),
             };
             result.push(item);
-        } else {
-            if let Some(options) = item.options.as_ref() {
-                extract_all_enum_definitions_recusive(
-                    result,
-                    options,
-                    format!(
-                        "{}{}",
-                        prefix.to_case(convert_case::Case::Pascal),
-                        name.to_case(convert_case::Case::Pascal)
-                    ),
-                );
+        } else if let Some(template) = instances::template(item) {
+            // the instances share the enums, see [instances::enum_type_name]
+            extract_all_enum_definitions_recusive(
+                result,
+                template,
+                format!("{}{}", pascal_case(&prefix), pascal_case(name)),
+            );
+        } else if let Some(options) = item.options.as_ref() {
+            extract_all_enum_definitions_recusive(
+                result,
+                options,
+                format!("{}{}", pascal_case(&prefix), pascal_case(name)),
+            );
+        }
+    }
+}
+
+pub fn to_variant_name(str: &str) -> String {
+    let str = if str.chars().next().is_some_and(char::is_numeric) {
+        format!("Variant{}", str)
+    } else {
+        str.to_string()
+    };
+
+    pascal_case(&str)
+}
+
+/// The name in PascalCase - words are separated by `-`, `_` and spaces and where lower case, upper
+/// case and digits change, e.g. `ESP32s3` becomes `Esp32S3`
+fn pascal_case(name: &str) -> String {
+    fn is_upper(c: char) -> bool {
+        c.to_lowercase().ne(c.to_uppercase()) && c.to_uppercase().eq([c])
+    }
+    fn is_lower(c: char) -> bool {
+        c.to_lowercase().ne(c.to_uppercase()) && c.to_lowercase().eq([c])
+    }
+
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if matches!(c, '-' | '_' | ' ') {
+            words.push(std::mem::take(&mut word));
+            continue;
+        }
+
+        if let Some(&previous) = i.checked_sub(1).and_then(|i| chars.get(i)) {
+            let digit = c.is_ascii_digit();
+            let previous_digit = previous.is_ascii_digit();
+            let boundary = (is_lower(previous) && (is_upper(c) || digit))
+                || (is_upper(previous) && digit)
+                || (previous_digit && (is_upper(c) || is_lower(c)))
+                // the last letter of an acronym starts the next word, e.g. `SPIFlash`
+                || (is_upper(previous)
+                    && is_upper(c)
+                    && chars.get(i + 1).is_some_and(|&next| is_lower(next)));
+            if boundary {
+                words.push(std::mem::take(&mut word));
             }
         }
+        word.push(c);
+    }
+    words.push(word);
+
+    words
+        .iter()
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().into_iter().flat_map(char::to_uppercase);
+            first
+                .chain(chars.as_str().to_lowercase().chars())
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// Generate `config.rs` in `OUT_DIR` from the config-definition and the user's `config.toml`
+///
+/// The definition can also be inline in the crate's `Cargo.toml`, see
+/// [parse_manifest_definition_str] - `rconfig::apply_config(&"Cargo.toml".into())` uses it.
+///
+/// Only to be called from a build-script, panics (failing the build) if the definition can't be
+/// read or the user's config is invalid. With `compile_errors = true` in the definition an invalid
+/// user's config creates a `config.rs` with a `compile_error!` instead.
+pub fn apply_config(definition: &PathBuf) {
+    apply_config_with(definition, |_, _| Ok(()));
+}
+
+/// [apply_config] for a config-definition split into several files, e.g. one per subsystem -
+/// they are merged like [parse_definitions_str] does
+///
+/// ```no_run
+/// rconfig::apply_config_many(&[
+///     "config/wifi.toml".into(),
+///     "config/ble.toml".into(),
+///     "config/power.toml".into(),
+/// ]);
+/// ```
+pub fn apply_config_many(definitions: &[PathBuf]) {
+    apply_config_many_with(definitions, |_, _| Ok(()));
+}
+
+/// [apply_config] with additional code generated by the hook, e.g. lookup tables from the
+/// resolved values - what the hook writes ends up at the end of `config.rs`
+///
+/// ```no_run
+/// use std::io::Write;
+///
+/// rconfig::apply_config_with(&"config/rconfig.toml".into(), |cfg, out| {
+///     let size = cfg.get_u32("heap.size").unwrap_or_default();
+///     writeln!(out, "pub static HEAP: [u8; {}] = [0; {}];", size, size)
+/// });
+/// ```
+///
+/// The hook isn't called if the user's config is invalid.
+pub fn apply_config_with(
+    definition: &PathBuf,
+    hook: impl FnOnce(&EffectiveConfig, &mut dyn Write) -> std::io::Result<()>,
+) {
+    apply_config_many_with(std::slice::from_ref(definition), hook);
+}
+
+/// [apply_config_many] with a hook, see [apply_config_with]
+pub fn apply_config_many_with(
+    definitions: &[PathBuf],
+    hook: impl FnOnce(&EffectiveConfig, &mut dyn Write) -> std::io::Result<()>,
+) {
+    assert!(!definitions.is_empty(), "No config-definition given");
+
+    let files = include::read_with_includes(definitions.to_vec()).unwrap_or_else(|err| {
+        match definitions.iter().find(|definition| !definition.is_file()) {
+            Some(definition) => panic!("Can't find {}", definition.display()),
+            None => panic!("Invalid config-definition: {:?}", err),
+        }
+    });
+    for (path, _) in files.iter().filter(|(path, _)| !definitions.contains(path)) {
+        println!("cargo::rerun-if-changed={}", path.display());
+    }
+    let (definitions, definitions_content): (Vec<PathBuf>, Vec<String>) = files.into_iter().unzip();
+
+    // for tooling - the included files, too - more than one are separated like in `PATH`
+    let paths: Vec<String> = definitions
+        .iter()
+        .map(|definition| {
+            definition
+                .canonicalize()
+                .unwrap_or_else(|err| panic!("Can't find {}: {}", definition.display(), err))
+                .display()
+                .to_string()
+                .trim_start_matches("\\\\?\\")
+                .to_string()
+        })
+        .collect();
+    println!(
+        "cargo::rustc-env=__RCONFIG={}",
+        env::join_paths(&paths)
+            .unwrap_or_else(|err| panic!("Can't pass the config-definitions to tooling: {}", err))
+            .to_string_lossy()
+    );
+
+    let crate_name = env::var("CARGO_PKG_NAME").expect("CARGO_PKG_NAME is set for build-scripts");
+
+    let definitions = definitions_content;
+    let definition = definitions.join("\n");
+
+    let compiled = merge::merge_definition_files(
+        definitions
+            .iter()
+            .map(|definition| load_compiled_definition(definition))
+            .collect(),
+    )
+    .unwrap_or_else(|err| panic!("Invalid config-definition: {:?}", err));
+    // tooling edits the section the crate reads
+    println!(
+        "cargo::rustc-env=__RCONFIG_CRATE={}",
+        compiled.section_name.as_deref().unwrap_or(&crate_name)
+    );
+    let names = CfgNames::of(&compiled, &crate_name);
+    for check_cfg in compiled_check_cfgs(&compiled, &names) {
+        println!("cargo::rustc-check-cfg={check_cfg}");
     }
-}
 
-pub fn to_variant_name(str: &str) -> String {
-    let str = if str.chars().next().unwrap().is_numeric() {
-        format!("Variant{}", str)
-    } else {
-        str.to_string()
+    let out = &PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set for build-scripts"));
+    let cfg = match try_load_effective_compiled_config(&compiled, &crate_name) {
+        Ok(cfg) => cfg,
+        Err(err) if compiled.compile_errors => {
+            let section = compiled.section_name.as_deref().unwrap_or(&crate_name);
+            std::fs::File::create(out.join("config.rs"))
+                .and_then(|mut config_rs| {
+                    write_compile_error(&mut config_rs, &compile_error_message(section, &err))
+                })
+                .unwrap_or_else(|err| panic!("Can't write config.rs: {}", err));
+            return;
+        }
+        Err(err) => panic!("Invalid configuration: {:?}", err),
     };
+    for entry in &cfg {
+        eprintln!("{}", entry.key);
+    }
 
-    str.to_case(convert_case::Case::Pascal)
+    let features = build_script_features();
+    let feature_names: Vec<&str> = features.iter().map(|v| v.as_str()).collect();
+    let target = env::var("TARGET").ok();
+    let (options, emitted) =
+        emitted_config(&compiled.options, &cfg, &feature_names, target.as_deref())
+            .unwrap_or_else(|err| panic!("Invalid config-definition: {:?}", err));
+    for cfg in compiled_cfgs(&compiled, &emitted, &names) {
+        println!("cargo::rustc-cfg={cfg}");
+    }
+
+    std::fs::File::create(out.join("config.rs"))
+        .and_then(|config_rs| {
+            let mut config_rs = std::io::BufWriter::new(config_rs);
+            write_compiled_config_rs(
+                &mut config_rs,
+                &compiled,
+                &names,
+                &options,
+                &emitted,
+                || {
+                    BuildInfo::new(&definition, &cfg, &features)
+                        .with_user_config(&build_script_user_config().unwrap_or_default())
+                        .with_resolved_at(build_info::resolved_at())
+                },
+            )?;
+            hook(&cfg, &mut config_rs)?;
+            config_rs.flush()
+        })
+        .unwrap_or_else(|err| panic!("Can't write config.rs: {}", err));
 }
 
-pub fn apply_config(definition: &PathBuf) {
-    // for tooling
-    println!(
-        "cargo::rustc-env=__RCONFIG={}",
-        definition
-            .canonicalize()
-            .unwrap()
-            .display()
-            .to_string()
-            .trim_start_matches("\\\\?\\")
-    );
+/// The cfgs [apply_config] sets for the emitted config (see [emitted_config]) - none with
+/// `consts_only`
+fn compiled_cfgs(
+    compiled: &definition_file::DefinitionFile,
+    emitted: &EffectiveConfig,
+    names: &CfgNames,
+) -> Vec<String> {
+    if compiled.consts_only {
+        return Vec::new();
+    }
+    generate_cfgs_with(emitted, names).collect()
+}
 
-    let crate_name = env::var("CARGO_PKG_NAME").unwrap();
-    println!("cargo::rustc-env=__RCONFIG_CRATE={}", crate_name);
+/// The cfgs [apply_config] declares for `cargo::rustc-check-cfg` - none with `consts_only`
+fn compiled_check_cfgs(
+    compiled: &definition_file::DefinitionFile,
+    names: &CfgNames,
+) -> Vec<String> {
+    if compiled.consts_only {
+        return Vec::new();
+    }
+    generate_check_cfgs(&compiled.options, names)
+}
 
-    let definition = std::fs::read_to_string(definition).unwrap();
+/// The `config.rs` [apply_config] writes for the emitted config, without what the hook adds -
+/// [testing::generate] uses it, too
+fn write_compiled_config_rs(
+    out: &mut impl Write,
+    compiled: &definition_file::DefinitionFile,
+    names: &CfgNames,
+    options: &Map<String, ConfigOption>,
+    emitted: &EffectiveConfig,
+    build_info: impl FnOnce() -> BuildInfo,
+) -> std::io::Result<()> {
+    write_config_rs(out, options, emitted)?;
+    if compiled.build_info {
+        write_build_info(out, &build_info())?;
+    }
+    if compiled.cfg_macros && !compiled.consts_only {
+        write_cfg_macros(out, options, names)?;
+    }
+    Ok(())
+}
 
-    let cfg = load_config(&definition, &crate_name);
+/// What [apply_config] creates for the effective config
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedConfig {
+    /// The content of `config.rs` - enums and consts
+    pub config_rs: String,
+    /// The cfgs to set, e.g. `has_psram_size` and `psram_enable`
+    pub cfgs: Vec<String>,
+    /// The cfgs the options can set, as `cfg(has_psram_size, psram_size)` for
+    /// `cargo::rustc-check-cfg`
+    pub check_cfgs: Vec<String>,
+}
 
-    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
-    let mut config_rs = std::fs::File::create(out.join("config.rs")).unwrap();
+pub fn generate_config(
+    definition: &Map<String, ConfigOption>,
+    cfg: &EffectiveConfig,
+) -> GeneratedConfig {
+    generate_config_with(definition, cfg, &CfgNames::default())
+}
 
-    let enums = extract_all_enum_definitions(parse_definition_str(&definition));
-    for e in enums {
-        config_rs
-            .write("#[derive(Debug,Clone,Copy)]\n".as_bytes())
-            .unwrap();
-        config_rs
-            .write(format!("pub enum {} {{\n", &e.name).as_bytes())
-            .unwrap();
-        for v in e.variant_names {
-            config_rs.write(format!("{},\n", &v).as_bytes()).unwrap();
-        }
-        config_rs.write("}\n".as_bytes()).unwrap();
+/// [generate_config] for a definition with a `cfg_namespace`, see [parse_cfg_names_str]
+pub fn generate_config_with(
+    definition: &Map<String, ConfigOption>,
+    cfg: &EffectiveConfig,
+    names: &CfgNames,
+) -> GeneratedConfig {
+    let mut config_rs = Vec::new();
+    write_config_rs(&mut config_rs, definition, cfg).expect("Writing to a Vec doesn't fail");
+
+    GeneratedConfig {
+        config_rs: String::from_utf8(config_rs).expect("config.rs is valid UTF-8"),
+        cfgs: generate_cfgs_with(cfg, names).collect(),
+        check_cfgs: generate_check_cfgs(definition, names),
     }
+}
+
+/// The cfgs to set for the effective config without a `cfg_namespace`, see
+/// [GeneratedConfig::cfgs] and [generate_cfgs_with]
+pub fn generate_cfgs(cfg: &EffectiveConfig) -> impl Iterator<Item = String> + '_ {
+    static PLAIN: CfgNames = CfgNames::plain();
+    generate_cfgs_with(cfg, &PLAIN)
+}
 
-    for (name, value, value_type) in cfg {
-        eprintln!("{name}");
-        let name = name.replace(".", "_");
-        println!("cargo::rustc-cfg=has_{name}");
-        if value != "0" && value != "false" {
-            println!("cargo::rustc-cfg={name}");
+/// Write the content of `config.rs` (see [GeneratedConfig::config_rs]) without building it in
+/// memory first
+pub fn write_config_rs(
+    out: &mut impl Write,
+    definition: &Map<String, ConfigOption>,
+    cfg: &EffectiveConfig,
+) -> std::io::Result<()> {
+    for e in extract_all_enum_definitions(definition) {
+        writeln!(out, "#[derive(Debug,Clone,Copy)]")?;
+        match &e.reprs {
+            Some(reprs) => {
+                writeln!(out, "#[repr(u32)]")?;
+                writeln!(out, "pub enum {} {{", &e.name)?;
+                for (v, repr) in e.variant_names.iter().zip(reprs) {
+                    writeln!(out, "{} = {},", v, repr)?;
+                }
+            }
+            None => {
+                writeln!(out, "pub enum {} {{", &e.name)?;
+                for v in &e.variant_names {
+                    writeln!(out, "{},", v)?;
+                }
+            }
         }
+        writeln!(out, "}}")?;
+        payload::write_payload_impl(out, &e.name, &e.variant_names, &e.payloads)?;
+    }
 
-        if value_type != ValueType::Enum {
-            config_rs
-                .write(
-                    format!(
-                        "pub const {}: {} = {};\n",
-                        name.to_uppercase(),
-                        value_type.to_string(),
-                        value
-                    )
-                    .as_bytes(),
-                )
-                .unwrap();
+    let index = OptionIndex::new(definition);
+    for entry in cfg {
+        let name = entry.key.replace(".", "_");
+        if entry.value_type != ValueType::Enum {
+            let display = index.get(&entry.key).and_then(|option| option.display);
+            writeln!(
+                out,
+                "pub const {}: {} = {};",
+                name.to_uppercase(),
+                to_rust_type(&entry.value_type),
+                match (display, entry.value.as_u64()) {
+                    (Some(display), Some(number)) => display.radix().format(number),
+                    _ => to_rust_literal(&entry.value_type, &entry.value),
+                }
+            )?;
         } else {
-            config_rs
-                .write(
-                    format!(
-                        "pub const {}: {} = {}::{};\n",
-                        name.to_uppercase(),
-                        to_variant_name(&name),
-                        to_variant_name(&name),
-                        to_variant_name(&value.replace("\"", "")),
-                    )
-                    .as_bytes(),
-                )
-                .unwrap();
+            let type_name = instances::enum_type_name(definition, &entry.key);
+            writeln!(
+                out,
+                "pub const {}: {} = {}::{};",
+                name.to_uppercase(),
+                type_name,
+                type_name,
+                to_variant_name(&value_as_string(&entry.value)),
+            )?;
         }
     }
-}
 
-pub fn load_config(definition: &str, crate_name: &str) -> Vec<(String, String, ValueType)> {
-    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    instances::write_instance_arrays(out, definition, cfg)
+}
 
-    let root_path = find_root_path(&out_dir);
-    let cfg_path = root_path.clone();
+/// A cfg for the option itself is only set if the value isn't `false` or `0`
+fn is_enabled_value(value: &Value) -> bool {
+    match value {
+        Value::Bool(value) => *value,
+        Value::Number(value) => value.as_f64() != Some(0.0),
+        _ => true,
+    }
+}
 
-    let cfg_path = cfg_path.as_ref().and_then(|c| {
-        let mut x = c.to_owned();
-        x.push("config.toml");
-        Some(x)
-    });
+fn value_as_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
 
-    let cfg_path = cfg_path.unwrap();
-    let config = if let Ok(metadata) = std::fs::metadata(&cfg_path) {
-        if metadata.is_file() {
-            std::fs::read_to_string(&cfg_path).unwrap()
-        } else {
-            "".to_string()
+fn to_rust_type(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::String => "&str".to_string(),
+        ValueType::Size => "usize".to_string(),
+        address @ (ValueType::Ipv4 | ValueType::Ipv6 | ValueType::Mac) => {
+            format!(
+                "[u8; {}]",
+                address::address_len(address).unwrap_or_default()
+            )
         }
-    } else {
-        "".to_string()
-    };
+        value_type => value_type.to_string(),
+    }
+}
+
+fn to_rust_literal(value_type: &ValueType, value: &Value) -> String {
+    let address = value
+        .as_str()
+        .and_then(|text| address::parse_address(value_type, text));
+    if let Some(bytes) = address {
+        let bytes: Vec<String> = bytes
+            .into_iter()
+            .map(|byte| match value_type {
+                ValueType::Mac => format!("{:#04x}", byte),
+                _ => byte.to_string(),
+            })
+            .collect();
+        return format!("[{}]", bytes.join(", "));
+    }
+
+    match value {
+        Value::String(value) => format!("{:?}", value),
+        value => value.to_string(),
+    }
+}
 
-    println!("cargo::rerun-if-changed={}", cfg_path.to_str().unwrap());
+/// Like [load_effective_config] but with stringified values
+pub fn load_config(definition: &str, crate_name: &str) -> Vec<(String, String, ValueType)> {
+    load_effective_config(definition, crate_name).to_tuples()
+}
 
-    let parsed_definition = parse_definition_str(definition);
+/// Evaluate the `config.toml` of the project being built with the enabled features of the crate
+///
+/// The crate's section is `crate_name` unless the definition has a `section_name`. Only to be
+/// called from a build-script, panics (failing the build) if the definition or the user's config
+/// is invalid. Use [resolve] to get an [Error] instead.
+pub fn load_effective_config(definition: &str, crate_name: &str) -> EffectiveConfig {
+    load_effective_compiled_config(&load_compiled_definition(definition), crate_name)
+}
 
-    // collect features
+/// The enabled features of the crate being built
+fn build_script_features() -> Vec<String> {
     let vars = env::vars();
     let mut features = Vec::new();
     for (var, _) in vars {
@@ -567,19 +1750,198 @@ pub fn load_config(definition: &str, crate_name: &str) -> Vec<(String, String, V
             features.push(var);
         }
     }
+    features
+}
+
+/// The project's directory - `config.toml` is next to the target directory
+///
+/// `None` if `OUT_DIR` isn't inside a target directory, e.g. for doctests of downstream crates or
+/// when rust-analyzer replays the build script.
+fn build_script_root_path() -> Option<PathBuf> {
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set for build-scripts"));
+    find_root_path(&out_dir)
+}
+
+/// Compile the definition or reuse what another build script compiled, see
+/// [cache::compile_definition_cached]
+fn load_compiled_definition(definition: &str) -> definition_file::DefinitionFile {
+    match build_script_root_path() {
+        Some(root) => {
+            cache::compile_definition_cached(definition, &root.join("target").join("rconfig"))
+        }
+        None => cache::compile_definition(definition),
+    }
+    .unwrap_or_else(|err| panic!("Invalid config-definition: {:?}", err))
+}
+
+fn load_effective_compiled_config(
+    compiled: &definition_file::DefinitionFile,
+    crate_name: &str,
+) -> EffectiveConfig {
+    try_load_effective_compiled_config(compiled, crate_name)
+        .unwrap_or_else(|err| panic!("Invalid configuration: {:?}", err))
+}
+
+/// The content of the project's `config.toml` - `None` if there is none
+fn build_script_user_config() -> Option<String> {
+    std::fs::read_to_string(build_script_root_path()?.join("config.toml")).ok()
+}
+
+/// Evaluate the `config.toml` of the project being built, only a definition which can't be used
+/// panics
+///
+/// Without a project directory (see [build_script_root_path]) the defaults are used - unless
+/// `RCONFIG_STRICT` is set, which makes it panic.
+fn try_load_effective_compiled_config(
+    compiled: &definition_file::DefinitionFile,
+    crate_name: &str,
+) -> Result<EffectiveConfig, Error> {
+    println!("cargo::rerun-if-env-changed=RCONFIG_STRICT");
+    let mut warnings = Vec::new();
+    let cfg_path = build_script_root_path().map(|root| root.join("config.toml"));
+    let config = match &cfg_path {
+        Some(cfg_path) => {
+            println!("cargo::rerun-if-changed={}", cfg_path.display());
+            match std::fs::metadata(cfg_path) {
+                Ok(metadata) if metadata.is_file() => std::fs::read_to_string(cfg_path)
+                    .unwrap_or_else(|err| panic!("Can't read {}: {}", cfg_path.display(), err)),
+                _ => "".to_string(),
+            }
+        }
+        None if env::var_os("RCONFIG_STRICT").is_some() => {
+            panic!("Can't find config.toml, OUT_DIR isn't inside a target directory")
+        }
+        None => {
+            warnings.push(
+                "Can't find config.toml (OUT_DIR isn't inside a target directory), using the \
+                 defaults"
+                    .to_string(),
+            );
+            "".to_string()
+        }
+    };
+
+    let features = build_script_features();
 
     // for tooling
     println!("cargo::rustc-env=__RCONFIG_FEATURES={}", features.join(","));
 
-    let effective_config = evaluate_config_str(
+    let feature_names: Vec<&str> = features.iter().map(|v| v.as_str()).collect();
+    let target = env::var("TARGET").ok();
+    let (config, included) = resolve_includes(
         &config,
-        crate_name,
-        parsed_definition,
-        features.iter().map(|v| v.as_str()).collect(),
-    )
-    .unwrap();
+        cfg_path
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or(Path::new("")),
+        &feature_names,
+        target.as_deref(),
+    )?;
+    for path in included {
+        println!("cargo::rerun-if-changed={}", path.display());
+    }
+
+    // tooling needs to learn about the crate even if the config is invalid
+    println!("cargo::rerun-if-env-changed=RCONFIG_TOOLING");
+
+    let mut env_values = Map::new();
+    for var in env_vars(&compiled.options) {
+        println!("cargo::rerun-if-env-changed={}", var);
+        if let Ok(value) = env::var(&var) {
+            env_values.insert(var, value);
+        }
+    }
+
+    // the user might not know the section was renamed
+    let section = compiled.section_name.as_deref().unwrap_or(crate_name);
+    if section != crate_name
+        && section::has_section(&config, crate_name)
+        && !section::has_section(&config, section)
+    {
+        println!(
+            "cargo::warning=`[{}]` in `config.toml` isn't used, the crate's section is `[{}]`",
+            crate_name, section
+        );
+    }
+
+    let ctx = EvalContext {
+        crate_name: section.to_string(),
+        features,
+        fallback_to_defaults: env::var_os("RCONFIG_TOOLING").is_some(),
+        env: env_values,
+    };
+
+    let effective_config = resolve_compiled(compiled, &config, &ctx)?;
+    warnings.extend_from_slice(effective_config.warnings());
+    for warning in &warnings {
+        println!("cargo::warning={}", warning);
+    }
+    Ok(effective_config.with_warnings(warnings))
+}
+
+/// Evaluate the user's config (the content of `config.toml`) - everything a build script does
+/// without depending on its environment
+///
+/// Applies the definition's migrations first. Both that and falling back to the defaults
+/// (see [EvalContext::fallback_to_defaults]) are reported as [EffectiveConfig::warnings].
+pub fn resolve(
+    definition: &str,
+    user_config: &str,
+    ctx: &EvalContext,
+) -> Result<EffectiveConfig, Error> {
+    resolve_compiled(&cache::compile_definition(definition)?, user_config, ctx)
+}
+
+fn resolve_compiled(
+    compiled: &definition_file::DefinitionFile,
+    user_config: &str,
+    ctx: &EvalContext,
+) -> Result<EffectiveConfig, Error> {
+    let mut warnings = features::unknown_feature_warnings(compiled);
+
+    let (user_config, migrations) =
+        migrate_user_config(user_config, &ctx.crate_name, &compiled.migrations)?;
+    for migration in migrations {
+        warnings.push(match migration.to {
+            Some(to) => format!(
+                "`{}` in `config.toml` is outdated, use `{}` instead",
+                migration.from, to
+            ),
+            None => format!("`{}` in `config.toml` isn't used anymore", migration.from),
+        });
+    }
+
+    warnings.extend(deprecated::deprecated_warnings(
+        &compiled.options,
+        &user_config,
+        &ctx.crate_name,
+    )?);
+
+    let features: Vec<&str> = ctx.features.iter().map(|v| v.as_str()).collect();
+    let effective_config = match secret::apply_env(compiled.options.clone(), &ctx.env)
+        .and_then(|options| {
+            evaluate_checked_config(&user_config, &ctx.crate_name, options, features.clone())
+        })
+        .and_then(|effective_config| {
+            global_constraints::check_global_constraints(
+                &compiled.constraints,
+                &effective_config,
+                &features,
+            )?;
+            Ok(effective_config)
+        }) {
+        Ok(effective_config) => effective_config,
+        Err(err) if ctx.fallback_to_defaults => {
+            warnings.push(format!(
+                "Invalid configuration ({:?}), using the defaults",
+                err
+            ));
+            evaluate_checked_config("", &ctx.crate_name, compiled.options.clone(), features)?
+        }
+        Err(err) => return Err(err),
+    };
 
-    effective_config
+    Ok(effective_config.with_warnings(warnings))
 }
 
 fn find_root_path(out_dir: &PathBuf) -> Option<PathBuf> {
@@ -660,9 +2022,9 @@ mod tests {
         heap.size = 30000
         "#;
 
-        let parsed_definition = parse_definition_str(DEFINITION);
+        let parsed_definition = parse_definition_str(DEFINITION).unwrap();
         let effective_config = evaluate_config_str(
-            &cfg,
+            cfg,
             "mycrate",
             parsed_definition,
             vec!["esp32c6", "flip-link"],
@@ -688,9 +2050,9 @@ mod tests {
         heap.size = 30000
         "#;
 
-        let parsed_definition = parse_definition_str(DEFINITION);
+        let parsed_definition = parse_definition_str(DEFINITION).unwrap();
         let effective_config =
-            evaluate_config_str(&cfg, "mycrate", parsed_definition, vec!["esp32s3"]).unwrap();
+            evaluate_config_str(cfg, "mycrate", parsed_definition, vec!["esp32s3"]).unwrap();
 
         println!("{:#?}", effective_config);
 
@@ -720,9 +2082,9 @@ mod tests {
         heap.size = 30000
         "#;
 
-        let parsed_definition = parse_definition_str(DEFINITION);
+        let parsed_definition = parse_definition_str(DEFINITION).unwrap();
         let effective_config =
-            evaluate_config_str(&cfg, "mycrate", parsed_definition, vec!["esp32s3"]).unwrap();
+            evaluate_config_str(cfg, "mycrate", parsed_definition, vec!["esp32s3"]).unwrap();
 
         println!("{:#?}", effective_config);
 
@@ -749,9 +2111,9 @@ mod tests {
         heap.size = 30000
         "#;
 
-        let parsed_definition = parse_definition_str(DEFINITION);
+        let parsed_definition = parse_definition_str(DEFINITION).unwrap();
         let effective_config =
-            evaluate_config_str(&cfg, "mycrate", parsed_definition, vec!["esp32"]).unwrap();
+            evaluate_config_str(cfg, "mycrate", parsed_definition, vec!["esp32"]).unwrap();
 
         println!("{:#?}", effective_config);
 
@@ -769,6 +2131,155 @@ mod tests {
         );
     }
 
+    #[test]
+    fn resolve_without_build_environment() {
+        let definition = format!(
+            "{}{}",
+            r#"
+            [[migrations]]
+            from = "heap.bytes"
+            to = "heap.size"
+            "#,
+            DEFINITION
+        );
+        let ctx = EvalContext::new(&["esp32"]).with_crate_name("mycrate");
+
+        let effective_config =
+            resolve(&definition, "[mycrate]\nheap.bytes = 30000\n", &ctx).unwrap();
+        assert_eq!(Some(30000), effective_config.get_u32("heap.size"));
+        assert_eq!(
+            vec!["`heap.bytes` in `config.toml` is outdated, use `heap.size` instead".to_string()],
+            effective_config.warnings()
+        );
+
+        let invalid = "[mycrate]\nheap.size = 90000\n";
+        assert!(resolve(&definition, invalid, &ctx).is_err());
+
+        let ctx = EvalContext {
+            fallback_to_defaults: true,
+            ..ctx
+        };
+        let effective_config = resolve(&definition, invalid, &ctx).unwrap();
+        assert_eq!(None, effective_config.get_u32("heap.size"));
+        assert_eq!(1, effective_config.warnings().len());
+    }
+
+    #[test]
+    fn effective_config() {
+        let cfg = r#"
+        [mycrate]
+        psram.enable = true
+        heap.size = 30000
+        "#;
+
+        let parsed_definition = parse_definition_str(DEFINITION).unwrap();
+        let effective_config =
+            evaluate_config(cfg, "mycrate", parsed_definition, vec!["esp32"]).unwrap();
+
+        assert_eq!(Some(true), effective_config.get_bool("psram.enable"));
+        assert_eq!(Some("2"), effective_config.get_str("psram.size"));
+        assert_eq!(Some(30000), effective_config.get_u32("heap.size"));
+        assert_eq!(None, effective_config.get_u32("psram.type.type"));
+
+        assert_eq!(
+            Provenance::User,
+            effective_config.get("heap.size").unwrap().provenance
+        );
+        assert_eq!(
+            Provenance::Default,
+            effective_config.get("psram.size").unwrap().provenance
+        );
+    }
+
+    #[test]
+    fn check_config() {
+        let cfg = r#"
+        [mycrate]
+        psram.enable = true
+        psram.size = "8"
+        psram.type.type = "octal"
+        psram.speed = 80
+        heap.size = 90000
+        wifi.enable = true
+        "#;
+
+        let parsed_definition = parse_definition_str(DEFINITION).unwrap();
+        let problems = check_config_str(cfg, "mycrate", &parsed_definition, vec!["esp32"]).unwrap();
+
+        assert_eq!(
+            vec![
+                ConfigProblem {
+                    key: "psram.speed".to_string(),
+                    value: Value::Number(80.into()),
+                    kind: ConfigProblemKind::UnknownKey
+                },
+                ConfigProblem {
+                    key: "wifi.enable".to_string(),
+                    value: Value::Bool(true),
+                    kind: ConfigProblemKind::UnknownKey
+                },
+                ConfigProblem {
+                    key: "heap.size".to_string(),
+                    value: Value::Number(90000.into()),
+                    kind: ConfigProblemKind::InvalidValue
+                },
+                ConfigProblem {
+                    key: "psram.size".to_string(),
+                    value: Value::String("8".to_string()),
+                    kind: ConfigProblemKind::InvalidValue
+                },
+                ConfigProblem {
+                    key: "psram.type.type".to_string(),
+                    value: Value::String("octal".to_string()),
+                    kind: ConfigProblemKind::NotApplicable
+                },
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn explain_unavailable_option() {
+        let cfg = r#"
+        [mycrate]
+        psram.enable = false
+        "#;
+
+        let parsed_definition = parse_definition_str(DEFINITION).unwrap();
+        let config = fuse_config_str(cfg, "mycrate", parsed_definition).unwrap();
+        let unmet = explain_unavailable("psram.type.type", &config, &vec!["esp32"]);
+
+        assert_eq!(
+            vec![
+                UnmetDepends {
+                    key: "psram.type".to_string(),
+                    depends: "feature(\"esp32s3\") && enabled(\"psram.enable\")".to_string(),
+                    missing_features: vec!["esp32s3".to_string()],
+                    disabled_options: vec!["psram.enable".to_string()],
+                },
+                UnmetDepends {
+                    key: "psram.type.type".to_string(),
+                    depends: "feature(\"esp32s3\")".to_string(),
+                    missing_features: vec!["esp32s3".to_string()],
+                    disabled_options: vec![],
+                },
+            ],
+            unmet
+        );
+
+        assert!(explain_unavailable("psram.enable", &config, &vec!["esp32"]).is_empty());
+    }
+
+    #[test]
+    fn referenced_features_in_expressions() {
+        let parsed_definition = parse_definition_str(DEFINITION).unwrap();
+
+        assert_eq!(
+            vec!["esp32", "esp32s2", "esp32s3"],
+            referenced_features(&parsed_definition)
+        );
+    }
+
     #[test]
     fn current_config_result() {
         let cfg = r#"# something without a type is just a menu item
@@ -820,19 +2331,307 @@ mod tests {
         __value = 4949
         "#;
 
-        let parsed_definition = parse_definition_str(cfg);
+        let parsed_definition = parse_definition_str(cfg).unwrap();
         let effective_config = current_config_values(parsed_definition, vec!["esp32s3"]).unwrap();
 
         println!("{:#?}", effective_config);
 
         assert_eq!(
             vec![
-                ("psram.enable".to_string(), "true".to_string()),
-                ("psram.size".to_string(), "\"4\"".to_string()),
-                ("psram.type.type".to_string(), "\"octal\"".to_string()),
-                ("heap.size".to_string(), "4949".to_string()),
+                ("psram.enable".to_string(), Value::Bool(true)),
+                ("psram.size".to_string(), Value::String("4".to_string())),
+                (
+                    "psram.type.type".to_string(),
+                    Value::String("octal".to_string())
+                ),
+                ("heap.size".to_string(), Value::from(4949)),
             ],
             effective_config
         );
     }
+
+    #[test]
+    fn visitors() {
+        let cfg = "[mycrate]\npsram.enable = true\nheap.size = 30000\n";
+
+        let mut visited = Vec::new();
+        visit_config(
+            cfg,
+            "mycrate",
+            parse_definition_str(DEFINITION).unwrap(),
+            vec!["esp32"],
+            |key, value, _, provenance| visited.push((key.to_string(), value.clone(), provenance)),
+        )
+        .unwrap();
+        let effective_config = evaluate_config(
+            cfg,
+            "mycrate",
+            parse_definition_str(DEFINITION).unwrap(),
+            vec!["esp32"],
+        )
+        .unwrap();
+        assert_eq!(
+            effective_config
+                .iter()
+                .map(|entry| (entry.key.clone(), entry.value.clone(), entry.provenance))
+                .collect::<Vec<_>>(),
+            visited
+        );
+
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let generated = generate_config(&definition, &effective_config);
+        let mut config_rs = Vec::new();
+        write_config_rs(&mut config_rs, &definition, &effective_config).unwrap();
+        assert_eq!(generated.config_rs.as_bytes(), config_rs.as_slice());
+        assert!(generated
+            .config_rs
+            .contains("pub const HEAP_SIZE: u32 = 30000;\n"));
+        assert_eq!(
+            generated.cfgs,
+            generate_cfgs(&effective_config).collect::<Vec<_>>()
+        );
+        assert!(generated.cfgs.contains(&"psram_enable".to_string()));
+
+        let fused = fuse_config_str(cfg, "mycrate", definition).unwrap();
+        let mut current = Vec::new();
+        visit_current_config_values(&fused, &["esp32"], |key, _| current.push(key.to_string()));
+        assert_eq!(vec!["psram.enable", "heap.size"], current);
+    }
+
+    const SIGNED_DEFINITION: &str = r#"
+    [temperature]
+    description = "Throttle below this temperature"
+    type = "i8"
+    default = -40
+
+    [offset]
+    description = "Clock offset"
+    type = "i64"
+    default = 0
+    "#;
+
+    #[test]
+    fn signed_integers() {
+        let definition = parse_definition_str(SIGNED_DEFINITION).unwrap();
+
+        let effective_config = evaluate_config(
+            "[mycrate]\noffset = \"-5_000\"\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(-40), effective_config.get_i64("temperature"));
+        assert_eq!(Some(-5000), effective_config.get_i64("offset"));
+        assert!(generate_config(&definition, &effective_config)
+            .config_rs
+            .contains("pub const TEMPERATURE: i8 = -40;\n"));
+
+        let out_of_range = SIGNED_DEFINITION.replace("-40", "-200");
+        match check_definition(&parse_definition_str(&out_of_range).unwrap(), "") {
+            Err(Error::InvalidDefinition(key)) => assert_eq!("temperature.default", key),
+            other => panic!("{:?}", other),
+        }
+
+        for invalid in ["temperature = -129", "temperature = 128", "offset = true"] {
+            assert!(
+                evaluate_config(
+                    &format!("[mycrate]\n{}\n", invalid),
+                    "mycrate",
+                    definition.clone(),
+                    vec![],
+                )
+                .is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn definitions_generating_invalid_code() {
+        let check = |definition: &str| check_definition(&parse_definition_str(definition)?, "");
+
+        let enum_definition = r#"
+        [mode]
+        description = "Mode"
+        type = "enum"
+        values = [{ value = "fast", description = "Fast" }, { value = "slow", description = "Slow" }]
+        default = "fast"
+        "#;
+        assert!(check(enum_definition).is_ok());
+        match check(&enum_definition.replace("default = \"fast\"", "default = \"medium\"")) {
+            Err(Error::InvalidDefinition(key)) => assert_eq!("mode.default", key),
+            other => panic!("{:?}", other),
+        }
+
+        for key in ["rx-buffer", "2nd", "rx.buffer"] {
+            let definition = format!(
+                "[\"{}\"]\ndescription = \"Buffer\"\ntype = \"u32\"\ndefault = 1\n",
+                key
+            );
+            assert!(matches!(
+                check(&definition),
+                Err(Error::InvalidDefinition(_))
+            ));
+        }
+        // instances are below the menu, comments don't generate code
+        assert!(check(
+            r#"
+            [uart]
+            description = "UART"
+            instances = 2
+
+            [uart.options.baud]
+            description = "Baud rate"
+            type = "u32"
+            default = 115200
+
+            ["a note"]
+            description = "A note"
+            "#
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn unsigned_integers() {
+        let definition = parse_definition_str(
+            r#"
+            [retries]
+            description = "Retries"
+            type = "u8"
+            default = 3
+
+            [buffer]
+            description = "Buffer size"
+            type = "usize"
+            default = 1024
+
+            [timeout]
+            description = "Timeout in nanoseconds"
+            type = "u64"
+            default = 0
+            "#,
+        )
+        .unwrap();
+
+        let effective_config = evaluate_config(
+            "[mycrate]\ntimeout = 10_000_000_000\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(10_000_000_000), effective_config.get_u64("timeout"));
+        let config_rs = generate_config(&definition, &effective_config).config_rs;
+        assert!(config_rs.contains("pub const RETRIES: u8 = 3;\n"));
+        assert!(config_rs.contains("pub const BUFFER: usize = 1024;\n"));
+        assert!(config_rs.contains("pub const TIMEOUT: u64 = 10000000000;\n"));
+
+        for invalid in ["retries = 256", "retries = -1", "buffer = 0x1_0000_0000"] {
+            assert!(
+                evaluate_config(
+                    &format!("[mycrate]\n{}\n", invalid),
+                    "mycrate",
+                    definition.clone(),
+                    vec![],
+                )
+                .is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn display_hints() {
+        let definition = parse_definition_str(
+            r#"
+            [mask]
+            description = "Interrupt mask"
+            type = "u32"
+            default = 0b1010
+            display = "binary"
+
+            [base]
+            description = "Base address"
+            type = "u32"
+            default = 0x4000_0000
+            display = "hex"
+            "#,
+        )
+        .unwrap();
+
+        let effective_config = evaluate_config(
+            "[mycrate]\nmask = \"0b1111_0000\"\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(0b1111_0000), effective_config.get_u32("mask"));
+        let config_rs = generate_config(&definition, &effective_config).config_rs;
+        assert!(config_rs.contains("pub const MASK: u32 = 0b11110000;\n"));
+        assert!(config_rs.contains("pub const BASE: u32 = 0x40000000;\n"));
+    }
+
+    #[test]
+    fn malformed_input() {
+        let ctx = EvalContext::new(&["esp32"]).with_crate_name("mycrate");
+
+        for definition in [
+            "[psram",
+            "psram = 1",
+            "[psram]\ndescription = 1",
+            "[heap.options.size]\ndescription = \"\"\ntype = \"u64\"",
+        ] {
+            assert!(matches!(
+                parse_definition_str(definition),
+                Err(Error::InvalidToml(_))
+            ));
+            assert!(resolve(definition, "", &ctx).is_err());
+        }
+
+        for depends in ["feature(", "enabled(\"psram\") &&", "}", "loop {}", "1 + 1"] {
+            let definition = DEFINITION.replace(
+                "depends = \"enabled(\\\"psram.enable\\\")\"",
+                &format!("depends = {:?}", depends),
+            );
+            assert!(matches!(
+                resolve(&definition, "", &ctx),
+                Err(Error::InvalidExpression(key)) if key == "psram.size" || key == "size"
+            ));
+        }
+
+        for cfg in [
+            "[mycrate",
+            "mycrate = 1",
+            "[mycrate]\npsram = 1",
+            "[mycrate]\nheap.size = \"big\"",
+            "[mycrate]\nheap.size = -1",
+            "[mycrate]\nheap.size.value = 1",
+            "[mycrate]\npsram.enable.value = true",
+        ] {
+            assert!(resolve(DEFINITION, cfg, &ctx).is_err(), "{}", cfg);
+            let definition = parse_definition_str(DEFINITION).unwrap();
+            let _ = check_config_str(cfg, "mycrate", &definition, vec!["esp32"]);
+            let _ = fuse_config_str(cfg, "mycrate", definition);
+        }
+
+        assert_eq!("", to_variant_name(""));
+    }
+
+    #[test]
+    fn variant_names() {
+        for (value, name) in [
+            ("quad-spi", "QuadSpi"),
+            ("ESP32s3", "Esp32S3"),
+            ("SPIFlash", "SpiFlash"),
+            ("80MHz", "Variant80MHz"),
+            ("console_port", "ConsolePort"),
+        ] {
+            assert_eq!(name, to_variant_name(value));
+        }
+    }
 }
@@ -0,0 +1,231 @@
+use crate::{split_sections, Map, Value};
+
+/// How an integer is written in `config.toml`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Decimal,
+    /// `0x8000`
+    Hex,
+    /// `0o100000`
+    Octal,
+    /// `0b1000`
+    Binary,
+}
+
+impl Radix {
+    /// The radix of an integer literal, `None` if it isn't one
+    pub fn of(literal: &str) -> Option<Self> {
+        parse_integer_literal(literal).map(|(_, radix)| radix)
+    }
+
+    /// Write the value as TOML integer literal
+    pub fn format(self, value: u64) -> String {
+        match self {
+            Radix::Decimal => value.to_string(),
+            Radix::Hex => format!("{:#x}", value),
+            Radix::Octal => format!("{:#o}", value),
+            Radix::Binary => format!("{:#b}", value),
+        }
+    }
+}
+
+/// Parse an integer the way TOML writes it - decimal, `0x`, `0o` or `0b` prefixed and with `_`
+/// between digits, e.g. `0x8000` or `65_536`
+pub fn parse_integer_literal(literal: &str) -> Option<(u64, Radix)> {
+    let literal = literal.trim();
+    let (digits, radix) = if let Some(hex) = literal.strip_prefix("0x") {
+        (hex, Radix::Hex)
+    } else if let Some(octal) = literal.strip_prefix("0o") {
+        (octal, Radix::Octal)
+    } else if let Some(binary) = literal.strip_prefix("0b") {
+        (binary, Radix::Binary)
+    } else {
+        (literal, Radix::Decimal)
+    };
+
+    if digits.is_empty()
+        || digits.starts_with('_')
+        || digits.ends_with('_')
+        || digits.contains("__")
+    {
+        return None;
+    }
+    let base = match radix {
+        Radix::Decimal => 10,
+        Radix::Hex => 16,
+        Radix::Octal => 8,
+        Radix::Binary => 2,
+    };
+    let value = u64::from_str_radix(&digits.replace('_', ""), base).ok()?;
+    Some((value, radix))
+}
+
+/// Like [parse_integer_literal] but also negative decimal integers, e.g. `-40` - TOML doesn't have
+/// negative literals in the other radixes
+pub fn parse_signed_integer_literal(literal: &str) -> Option<(i128, Radix)> {
+    let literal = literal.trim();
+    match literal.strip_prefix('-') {
+        Some(digits) => match parse_integer_literal(digits)? {
+            (number, Radix::Decimal) if digits.starts_with(|c: char| c.is_ascii_digit()) => {
+                Some((-i128::from(number), Radix::Decimal))
+            }
+            _ => None,
+        },
+        None => parse_integer_literal(literal).map(|(number, radix)| (number.into(), radix)),
+    }
+}
+
+/// The integer as JSON number, `Null` if it doesn't fit into an `i64` or `u64`
+pub fn integer_value(number: i128) -> Value {
+    i64::try_from(number)
+        .map(Value::from)
+        .or_else(|_| u64::try_from(number).map(Value::from))
+        .unwrap_or(Value::Null)
+}
+
+/// The JSON number as integer, `None` if it isn't an integer
+pub(crate) fn json_integer(value: &Value) -> Option<i128> {
+    value
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| value.as_u64().map(i128::from))
+}
+
+/// A string which is an integer literal becomes the number, e.g. `"0x8000"` for an integer option
+pub(crate) fn integer_from_string(value: Value) -> Value {
+    match value.as_str().and_then(parse_signed_integer_literal) {
+        Some((number, _)) => integer_value(number),
+        None => value,
+    }
+}
+
+/// The dotted keys of the crate's section of the user's config (the content of `config.toml`)
+/// which have an integer written in another radix than decimal
+///
+/// TOML parsers don't keep how a number was written - use it to write the values back the same
+/// way.
+pub fn integer_radixes(input: &str, crate_name: &str) -> Map<String, Radix> {
+    let mut radixes = Map::new();
+    let Some((_, section)) = split_sections(input)
+        .into_iter()
+        .find(|(name, _)| name == crate_name)
+    else {
+        return radixes;
+    };
+
+    let mut prefix = String::new();
+    for line in section.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(header) = line.strip_prefix('[') {
+            // `[crate]` or `[crate.menu]`
+            let header = header.trim_end_matches(']').trim();
+            prefix = header
+                .split_once('.')
+                .map(|(_, menu)| format!("{}.", unquote(menu)))
+                .unwrap_or_default();
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match Radix::of(value) {
+            Some(Radix::Decimal) | None => (),
+            Some(radix) => {
+                radixes.insert(format!("{}{}", prefix, unquote(key.trim())), radix);
+            }
+        }
+    }
+    radixes
+}
+
+fn unquote(key: &str) -> String {
+    key.split('.')
+        .map(|part| part.trim().trim_matches('"'))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_literals() {
+        assert_eq!(Some((0x8000, Radix::Hex)), parse_integer_literal("0x8000"));
+        assert_eq!(
+            Some((65536, Radix::Decimal)),
+            parse_integer_literal("65_536")
+        );
+        assert_eq!(Some((0o17, Radix::Octal)), parse_integer_literal("0o17"));
+        assert_eq!(Some((5, Radix::Binary)), parse_integer_literal("0b1_01"));
+        for invalid in ["", "0x", "_1", "1_", "1__0", "0xg", "-1", "1.5", "abc"] {
+            assert_eq!(None, parse_integer_literal(invalid), "{}", invalid);
+        }
+
+        for radix in [Radix::Decimal, Radix::Hex, Radix::Octal, Radix::Binary] {
+            assert_eq!(
+                Some((1234, radix)),
+                parse_integer_literal(&radix.format(1234))
+            );
+        }
+
+        assert_eq!(
+            Some((-40, Radix::Decimal)),
+            parse_signed_integer_literal("-40")
+        );
+        assert_eq!(
+            Some((0x10, Radix::Hex)),
+            parse_signed_integer_literal("0x10")
+        );
+        for invalid in ["-", "-0x10", "--1", "-+1", "- 1"] {
+            assert_eq!(None, parse_signed_integer_literal(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn integer_values() {
+        let definition = crate::parse_definition_str(
+            "[heap]\ndescription = \"Heap\"\ntype = \"u32\"\n\n[name]\ndescription = \"Name\"\ntype = \"string\"\n",
+        )
+        .unwrap();
+        for input in ["heap = 0x8000", "heap = 32_768", "heap = \"0x8000\""] {
+            let effective_config = crate::evaluate_config(
+                &format!("[mycrate]\n{}\nname = \"0x10\"\n", input),
+                "mycrate",
+                definition.clone(),
+                vec![],
+            )
+            .unwrap();
+            assert_eq!(Some(0x8000), effective_config.get_u32("heap"), "{}", input);
+            assert_eq!(Some("0x10"), effective_config.get_str("name"));
+        }
+    }
+
+    #[test]
+    fn radixes() {
+        let input = r#"
+[mycrate]
+heap.size = 0x8000 # hex
+psram.size = 65_536
+"psram".mask = 0b1010
+
+[mycrate.dma]
+buffer = 0o700
+
+[other]
+heap.size = 0x10
+"#;
+        assert_eq!(
+            vec![
+                ("heap.size".to_string(), Radix::Hex),
+                ("psram.mask".to_string(), Radix::Binary),
+                ("dma.buffer".to_string(), Radix::Octal),
+            ],
+            integer_radixes(input, "mycrate")
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert!(integer_radixes(input, "missing").is_empty());
+    }
+}
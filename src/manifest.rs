@@ -0,0 +1,129 @@
+use crate::{parse_toml, Error, Value};
+
+const DEFINITION: &str = "package.metadata.rconfig.definition";
+
+/// The config-definition inline in the crate's `Cargo.toml` (the content of it) - `None` if the
+/// manifest only tells the file of the definition, e.g. `definition = "config/rconfig.toml"`
+///
+/// ```toml
+/// [package.metadata.rconfig.definition]
+/// section_name = "hal"
+///
+/// [package.metadata.rconfig.definition.psram]
+/// description = "PSRAM"
+///
+/// [package.metadata.rconfig.definition.psram.options.enable]
+/// description = "Enable PSRAM"
+/// type = "bool"
+/// ```
+///
+/// The definition is returned as if it was a file of its own. It has to be written with tables
+/// like above - their order is the order of the options.
+pub fn parse_manifest_definition_str(manifest: &str) -> Result<Option<String>, Error> {
+    let inline =
+        match &parse_toml::<Value>(manifest)?["package"]["metadata"]["rconfig"]["definition"] {
+            Value::Object(inline) => Value::Object(inline.clone()),
+            _ => return Ok(None),
+        };
+
+    let mut definition = String::new();
+    let mut within = false;
+    for line in manifest.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            let array = trimmed.starts_with("[[");
+            let header = trimmed.trim_start_matches('[');
+            let header = header.split(']').next().unwrap_or_default().trim();
+            within = header == DEFINITION
+                || header
+                    .strip_prefix(DEFINITION)
+                    .is_some_and(|rest| rest.starts_with('.'));
+            if within && header != DEFINITION {
+                let table = &header[DEFINITION.len() + 1..];
+                if array {
+                    definition.push_str(&format!("[[{}]]\n", table));
+                } else {
+                    definition.push_str(&format!("[{}]\n", table));
+                }
+            }
+            continue;
+        }
+
+        if within {
+            definition.push_str(line);
+            definition.push('\n');
+        }
+    }
+
+    // e.g. dotted keys or inline tables can't be taken over line by line
+    if parse_toml::<Value>(&definition)? != inline {
+        return Err(Error::InvalidDefinition(DEFINITION.to_string()));
+    }
+
+    Ok(Some(definition))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "fake-hal"
+version = "0.1.0"
+
+[package.metadata.rconfig.definition]
+section_name = "hal"
+
+[package.metadata.rconfig.definition.psram]
+description = "PSRAM"
+
+[package.metadata.rconfig.definition.psram.options.enable]
+description = "Enable PSRAM"
+type = "bool"
+
+[package.metadata.rconfig.definition.heap]
+description = "Heap"
+
+[[package.metadata.rconfig.definition.migrations]]
+from = "psram.on"
+to = "psram.enable"
+
+[dependencies]
+"#;
+
+    #[test]
+    fn manifest_definition() {
+        let definition = parse_manifest_definition_str(MANIFEST).unwrap().unwrap();
+        assert_eq!(
+            vec!["psram", "heap"],
+            parse_definition_str(&definition)
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "hal",
+            crate::parse_section_name_str(&definition, "fake-hal").unwrap()
+        );
+        assert_eq!(1, crate::parse_migrations_str(&definition).unwrap().len());
+
+        let with_file = "[package.metadata.rconfig]\ndefinition = \"config/rconfig.toml\"\n";
+        assert_eq!(None, parse_manifest_definition_str(with_file).unwrap());
+
+        let inline =
+            "[package.metadata.rconfig]\ndefinition = { heap = { description = \"Heap\" } }\n";
+        assert!(parse_manifest_definition_str(inline).is_err());
+
+        // what the build script and tooling read for a `Cargo.toml`
+        let dir = std::env::temp_dir().join(format!("rconfig-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), MANIFEST).unwrap();
+        assert_eq!(
+            vec![definition],
+            crate::read_definitions(&[dir.join("Cargo.toml")]).unwrap()
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
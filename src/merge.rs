@@ -0,0 +1,355 @@
+use crate::definition_file::DefinitionFile;
+use crate::{ConfigOption, Error, Map};
+
+/// How [merge_definitions] handles options defined in both definitions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergePolicy {
+    /// The overlay can only add options, defining an existing option again is an error
+    Additive,
+    /// What the overlay sets for an existing option (e.g. `default`, `description`) replaces the base's
+    Override,
+}
+
+/// Merge two config-definitions, e.g. a board-support crate extending a HAL's definition
+///
+/// Menus defined in both are merged, options of the overlay which are not in the base are added
+/// after the base's options. A key which is a menu in one and an option in the other or options
+/// with different types are errors regardless of the policy.
+pub fn merge_definitions(
+    base: Map<String, ConfigOption>,
+    overlay: Map<String, ConfigOption>,
+    policy: MergePolicy,
+) -> Result<Map<String, ConfigOption>, Error> {
+    merge_level(base, overlay, policy, "")
+}
+
+/// Parse and merge the config-definitions of a crate split into several files, see
+/// [crate::apply_config_many] - an option defined in more than one file is an error
+pub fn parse_definitions_str(definitions: &[&str]) -> Result<Map<String, ConfigOption>, Error> {
+    let files = definitions
+        .iter()
+        .map(|definition| DefinitionFile::parse(definition))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(merge_definition_files(files)?.options)
+}
+
+/// Merge parsed config-definitions - the options via [MergePolicy::Additive], the migrations of
+/// all files are kept and the first file setting e.g. `section_name` wins
+pub(crate) fn merge_definition_files(files: Vec<DefinitionFile>) -> Result<DefinitionFile, Error> {
+    let mut merged = DefinitionFile::default();
+    for file in files {
+        let DefinitionFile {
+            include: _,
+            migrations,
+            cfg_namespace,
+            section_name,
+            negated_cfgs,
+            build_info,
+            compile_errors,
+            consts_only,
+            cfg_macros,
+            features,
+            constraints,
+            templates: _,
+            options,
+        } = file;
+
+        merged.options = merge_definitions(merged.options, options, MergePolicy::Additive)?;
+        merged.migrations.extend(migrations);
+        merged.cfg_namespace = merged.cfg_namespace.or(cfg_namespace);
+        merged.section_name = merged.section_name.or(section_name);
+        merged.negated_cfgs |= negated_cfgs;
+        merged.build_info |= build_info;
+        merged.compile_errors |= compile_errors;
+        merged.consts_only |= consts_only;
+        merged.cfg_macros |= cfg_macros;
+        merged.features = match (merged.features, features) {
+            (Some(mut merged_features), Some(features)) => {
+                merged_features.extend(features);
+                Some(merged_features)
+            }
+            (merged_features, features) => merged_features.or(features),
+        };
+        for (name, constraint) in constraints {
+            if merged.constraints.contains_key(&name) {
+                return Err(Error::DefinitionConflict(format!("constraints.{}", name)));
+            }
+            merged.constraints.insert(name, constraint);
+        }
+    }
+
+    Ok(merged)
+}
+
+fn merge_level(
+    mut base: Map<String, ConfigOption>,
+    overlay: Map<String, ConfigOption>,
+    policy: MergePolicy,
+    prefix: &str,
+) -> Result<Map<String, ConfigOption>, Error> {
+    for (name, overlay_option) in overlay {
+        let key = format!("{}{}", prefix, name);
+        let Some(base_option) = base.get_mut(&name) else {
+            base.insert(name, overlay_option);
+            continue;
+        };
+
+        match (base_option.options.take(), overlay_option.options.clone()) {
+            (Some(base_children), Some(overlay_children)) => {
+                base_option.options = Some(merge_level(
+                    base_children,
+                    overlay_children,
+                    policy,
+                    &format!("{}.", key),
+                )?);
+                if policy == MergePolicy::Override {
+                    override_fields(base_option, overlay_option);
+                }
+            }
+            (None, None) => {
+                if policy == MergePolicy::Additive {
+                    return Err(Error::DefinitionConflict(key));
+                }
+                if overlay_option.value_type.is_some()
+                    && overlay_option.value_type != base_option.value_type
+                {
+                    return Err(Error::DefinitionConflict(key));
+                }
+                override_fields(base_option, overlay_option);
+            }
+            _ => return Err(Error::DefinitionConflict(key)),
+        }
+    }
+
+    Ok(base)
+}
+
+fn override_fields(base: &mut ConfigOption, overlay: ConfigOption) {
+    if !overlay.description.is_empty() {
+        base.description = overlay.description;
+    }
+    if overlay.depends.is_some() {
+        base.depends = overlay.depends;
+    }
+    if overlay.valid.is_some() {
+        base.valid = overlay.valid;
+    }
+    if overlay.values.is_some() {
+        base.values = overlay.values;
+    }
+    if overlay.default_value.is_some() {
+        base.default_value = overlay.default_value;
+    }
+    if overlay.min_length.is_some() {
+        base.min_length = overlay.min_length;
+    }
+    if overlay.max_length.is_some() {
+        base.max_length = overlay.max_length;
+    }
+    if overlay.charset.is_some() {
+        base.charset = overlay.charset;
+    }
+    if overlay.step.is_some() {
+        base.step = overlay.step;
+    }
+    if overlay.min.is_some() {
+        base.min = overlay.min;
+    }
+    if overlay.max.is_some() {
+        base.max = overlay.max;
+    }
+    if overlay.emit_if.is_some() {
+        base.emit_if = overlay.emit_if;
+    }
+    if overlay.exclusive_group.is_some() {
+        base.exclusive_group = overlay.exclusive_group;
+    }
+    if overlay.choice {
+        base.choice = true;
+    }
+    if overlay.imply.is_some() {
+        base.imply = overlay.imply;
+    }
+    if overlay.selects.is_some() {
+        base.selects = overlay.selects;
+    }
+    if overlay.conflicts.is_some() {
+        base.conflicts = overlay.conflicts;
+    }
+    if overlay.required {
+        base.required = true;
+    }
+    if overlay.deprecated {
+        base.deprecated = true;
+    }
+    if overlay.renamed_from.is_some() {
+        base.renamed_from = overlay.renamed_from;
+    }
+    if overlay.secret {
+        base.secret = true;
+    }
+    if overlay.env.is_some() {
+        base.env = overlay.env;
+    }
+    if overlay.display.is_some() {
+        base.display = overlay.display;
+    }
+    if overlay.weight.is_some() {
+        base.weight = overlay.weight;
+    }
+    if overlay.hidden {
+        base.hidden = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_definition_str, Value};
+
+    const BASE: &str = r#"
+    [psram]
+    description = "PSRAM"
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    default = 4096
+    "#;
+
+    #[test]
+    fn additive() {
+        let overlay = parse_definition_str(
+            r#"
+            [psram]
+            description = "PSRAM"
+
+            [psram.options.speed]
+            description = "PSRAM Speed"
+            type = "u32"
+
+            [board]
+            description = "Board"
+            "#,
+        )
+        .unwrap();
+
+        let merged = merge_definitions(
+            parse_definition_str(BASE).unwrap(),
+            overlay,
+            MergePolicy::Additive,
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["psram", "heap", "board"],
+            merged.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["enable", "speed"],
+            merged["psram"]
+                .options
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn override_defaults() {
+        let overlay = parse_definition_str(
+            r#"
+            [heap]
+            description = ""
+
+            [heap.options.size]
+            description = ""
+            default = 8192
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            merge_definitions(parse_definition_str(BASE).unwrap(), overlay.clone(), MergePolicy::Additive),
+            Err(Error::DefinitionConflict(key)) if key == "heap.size"
+        ));
+
+        let merged = merge_definitions(
+            parse_definition_str(BASE).unwrap(),
+            overlay,
+            MergePolicy::Override,
+        )
+        .unwrap();
+        let size = &merged["heap"].options.as_ref().unwrap()["size"];
+        assert_eq!(Some(Value::from(8192)), size.default_value);
+        assert_eq!("Bytes to allocate", size.description);
+        assert_eq!("Heapsize", merged["heap"].description);
+    }
+
+    #[test]
+    fn definition_files() {
+        let merged = parse_definitions_str(&[
+            BASE,
+            "section_name = \"hal\"\n\n[wifi]\ndescription = \"WiFi\"\n",
+        ])
+        .unwrap();
+        assert_eq!(
+            vec!["psram", "heap", "wifi"],
+            merged.keys().collect::<Vec<_>>()
+        );
+
+        let files = [BASE, "section_name = \"hal\"", "section_name = \"other\""]
+            .iter()
+            .map(|definition| DefinitionFile::parse(definition).unwrap())
+            .collect();
+        assert_eq!(
+            Some("hal".to_string()),
+            merge_definition_files(files).unwrap().section_name
+        );
+
+        assert!(matches!(
+            parse_definitions_str(&[BASE, BASE]),
+            Err(Error::DefinitionConflict(key)) if key == "psram.enable"
+        ));
+    }
+
+    #[test]
+    fn conflicts() {
+        let overlay = parse_definition_str(
+            r#"
+            [heap]
+            description = "Heapsize"
+
+            [heap.options.size]
+            description = "Bytes to allocate"
+            type = "bool"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            merge_definitions(parse_definition_str(BASE).unwrap(), overlay, MergePolicy::Override),
+            Err(Error::DefinitionConflict(key)) if key == "heap.size"
+        ));
+
+        let overlay = parse_definition_str(
+            r#"
+            [heap]
+            description = "Heapsize"
+            type = "u32"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            merge_definitions(parse_definition_str(BASE).unwrap(), overlay, MergePolicy::Override),
+            Err(Error::DefinitionConflict(key)) if key == "heap"
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{referenced_features, ConfigOption, Map, Value};
+
+/// Describe a config-definition as JSON, e.g. for IDE plugins or documentation generators
+///
+/// ```json
+/// {
+///   "features": ["esp32"],
+///   "options": [
+///     { "key": "psram", "kind": "menu", "description": "PSRAM", "depends": "feature(\"esp32\")", "options": [..] },
+///     ..
+///   ]
+/// }
+/// ```
+///
+/// Every entry has `key` (the dotted path), `kind` (`menu`, `option` or `comment`) and
+/// `description`. `type`, `default`, `values`, `depends`, `valid`, `step`, `min`, `max`, `display`,
+/// `weight` and `env` are `null` if not set in the definition (`default` also for `secret` options), `options` is
+/// `null` for everything but menus.
+pub fn export_metadata(definition: &Map<String, ConfigOption>) -> Value {
+    serde_json::json!({
+        "features": referenced_features(definition),
+        "options": export_options(definition, ""),
+    })
+}
+
+fn export_options(options: &Map<String, ConfigOption>, prefix: &str) -> Value {
+    Value::Array(
+        options
+            .iter()
+            .map(|(name, option)| {
+                let key = format!("{}{}", prefix, name);
+                let kind = if option.options.is_some() {
+                    "menu"
+                } else if option.value_type.is_some() {
+                    "option"
+                } else {
+                    "comment"
+                };
+                let children = option
+                    .options
+                    .as_ref()
+                    .map(|children| export_options(children, &format!("{}.", key)));
+
+                serde_json::json!({
+                    "key": key,
+                    "kind": kind,
+                    "description": option.description,
+                    "type": option.value_type,
+                    "default": option.default_value.as_ref().filter(|_| !option.secret),
+                    "values": option.values,
+                    "depends": option.depends,
+                    "valid": option.valid,
+                    "step": option.step,
+                    "min": option.min,
+                    "max": option.max,
+                    "exclusive_group": option.exclusive_group,
+                    "choice": option.choice,
+                    "display": option.display,
+                    "weight": option.weight,
+                    "hidden": option.hidden,
+                    "secret": option.secret,
+                    "required": option.required,
+                    "deprecated": option.deprecated,
+                    "env": option.env,
+                    "options": children,
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    #[test]
+    fn metadata() {
+        let definition = parse_definition_str(
+            r#"
+            [psram]
+            description = "PSRAM"
+            depends = 'feature("esp32")'
+
+            [psram.options.note]
+            description = "Needs an external chip"
+
+            [psram.options.size]
+            description = "PSRAM Size"
+            type = "enum"
+            values = [{ description = "1MB", value = "1" }]
+            default = "1"
+            display = "hex"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "features": ["esp32"],
+                "options": [{
+                    "key": "psram",
+                    "kind": "menu",
+                    "description": "PSRAM",
+                    "type": null,
+                    "default": null,
+                    "values": null,
+                    "depends": "feature(\"esp32\")",
+                    "valid": null,
+                    "step": null,
+                    "min": null,
+                    "max": null,
+                    "exclusive_group": null,
+                    "choice": false,
+                    "display": null,
+                    "weight": null,
+                    "hidden": false,
+                    "secret": false,
+                    "required": false,
+                    "deprecated": false,
+                    "env": null,
+                    "options": [{
+                        "key": "psram.note",
+                        "kind": "comment",
+                        "description": "Needs an external chip",
+                        "type": null,
+                        "default": null,
+                        "values": null,
+                        "depends": null,
+                        "valid": null,
+                        "step": null,
+                        "min": null,
+                        "max": null,
+                        "exclusive_group": null,
+                        "choice": false,
+                        "display": null,
+                        "weight": null,
+                        "hidden": false,
+                        "secret": false,
+                        "required": false,
+                        "deprecated": false,
+                        "env": null,
+                        "options": null,
+                    }, {
+                        "key": "psram.size",
+                        "kind": "option",
+                        "description": "PSRAM Size",
+                        "type": "enum",
+                        "default": "1",
+                        "values": [{ "description": "1MB", "value": "1" }],
+                        "depends": null,
+                        "valid": null,
+                        "step": null,
+                        "min": null,
+                        "max": null,
+                        "exclusive_group": null,
+                        "choice": false,
+                        "display": "hex",
+                        "weight": null,
+                        "hidden": false,
+                        "secret": false,
+                        "required": false,
+                        "deprecated": false,
+                        "env": null,
+                        "options": null,
+                    }],
+                }],
+            }),
+            export_metadata(&definition)
+        );
+    }
+}
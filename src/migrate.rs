@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+
+use crate::definition_file::DefinitionFile;
+use crate::{parse_toml, split_sections, value_as_string, Error, Map, Value};
+
+/// A change of the config-definition which needs the user's config to be updated
+///
+/// Listed as `[[migrations]]` in the config-definition, they are applied in order.
+/// ```toml
+/// [[migrations]]
+/// from = "psram.mode"
+/// to = "psram.type.type"
+/// values = { "0" = "quad", "1" = "octal" }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Migration {
+    /// The key used before
+    pub from: String,
+    /// The key to use instead - without it the entry gets removed
+    pub to: Option<String>,
+    /// Old values (as text, e.g. `"1"` or `"true"`) mapped to the new values, other values are kept
+    pub values: Option<Map<String, Value>>,
+}
+
+/// A migration which changed an entry of the user's config
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationApplied {
+    pub from: String,
+    /// `None` if the entry was removed
+    pub to: Option<String>,
+    pub old_value: Value,
+    pub new_value: Option<Value>,
+}
+
+/// The migrations of a config-definition
+pub fn parse_migrations_str(definition: &str) -> Result<Vec<Migration>, Error> {
+    Ok(DefinitionFile::parse(definition)?.migrations)
+}
+
+/// Apply the migrations to the crate's section of the user's config (the content of `config.toml`)
+///
+/// Returns the input unchanged if no migration applies. Otherwise the crate's section is rewritten
+/// with one `key=value` line per entry, the other sections are kept as they are.
+pub fn migrate_user_config(
+    input: &str,
+    crate_name: &str,
+    migrations: &[Migration],
+) -> Result<(String, Vec<MigrationApplied>), Error> {
+    let parsed = parse_toml::<Value>(input)?;
+    let mut entries = Vec::new();
+    if let Some(section) = parsed.get(crate_name) {
+        collect_entries(&mut entries, section, "");
+    }
+
+    let mut applied = Vec::new();
+    for migration in migrations {
+        let Some(index) = entries.iter().position(|(key, _)| *key == migration.from) else {
+            continue;
+        };
+        let (_, old_value) = entries.remove(index);
+
+        let new_value = migration.to.as_ref().map(|_| {
+            migration
+                .values
+                .as_ref()
+                .and_then(|values| values.get(&value_as_string(&old_value)))
+                .cloned()
+                .unwrap_or_else(|| old_value.clone())
+        });
+
+        if let (Some(to), Some(new_value)) = (&migration.to, &new_value) {
+            // a value already set for the new key wins
+            if !entries.iter().any(|(key, _)| key == to) {
+                entries.insert(index.min(entries.len()), (to.clone(), new_value.clone()));
+            }
+        }
+
+        applied.push(MigrationApplied {
+            from: migration.from.clone(),
+            to: migration.to.clone(),
+            old_value,
+            new_value,
+        });
+    }
+
+    if applied.is_empty() {
+        return Ok((input.to_string(), applied));
+    }
+
+    let mut section = format!("[{}]\n", crate_name);
+    for (key, value) in &entries {
+        section.push_str(&format!("{}={}\n", key, value));
+    }
+
+    let mut out = String::new();
+    let mut written = false;
+    for (name, text) in split_sections(input) {
+        if name == crate_name {
+            if !written {
+                out.push_str(&section);
+                written = true;
+            }
+        } else {
+            out.push_str(&text);
+        }
+    }
+    if !written {
+        out.push_str(&section);
+    }
+
+    Ok((out, applied))
+}
+
+pub(crate) fn collect_entries(entries: &mut Vec<(String, Value)>, value: &Value, prefix: &str) {
+    match value {
+        Value::Object(table) => {
+            for (name, value) in table {
+                collect_entries(entries, value, &format!("{}{}.", prefix, name));
+            }
+        }
+        value => entries.push((prefix.trim_end_matches('.').to_string(), value.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    const DEFINITION: &str = r#"
+    [[migrations]]
+    from = "psram.mode"
+    to = "psram.type"
+    values = { "0" = "quad", "1" = "octal" }
+
+    [[migrations]]
+    from = "psram.legacy"
+
+    [psram]
+    description = "PSRAM"
+
+    [psram.options.type]
+    description = "PSRAM Type"
+    type = "enum"
+    values = [
+        { description = "Quad", value = "quad" },
+        { description = "Octal", value = "octal" },
+    ]
+    "#;
+
+    #[test]
+    fn migrations_in_definition() {
+        assert_eq!(2, parse_migrations_str(DEFINITION).unwrap().len());
+        assert!(parse_definition_str(DEFINITION)
+            .unwrap()
+            .contains_key("psram"));
+        assert!(!parse_definition_str(DEFINITION)
+            .unwrap()
+            .contains_key("migrations"));
+    }
+
+    #[test]
+    fn migrate() {
+        let cfg = r#"# keep me
+[other]
+psram.mode = 1
+
+[mycrate]
+psram.mode = 1
+psram.legacy = true
+heap.size = 30000
+"#;
+
+        let (migrated, applied) =
+            migrate_user_config(cfg, "mycrate", &parse_migrations_str(DEFINITION).unwrap())
+                .unwrap();
+
+        assert_eq!(
+            r#"# keep me
+[other]
+psram.mode = 1
+
+[mycrate]
+heap.size=30000
+psram.type="octal"
+"#,
+            migrated
+        );
+        assert_eq!(
+            vec![
+                MigrationApplied {
+                    from: "psram.mode".to_string(),
+                    to: Some("psram.type".to_string()),
+                    old_value: Value::from(1),
+                    new_value: Some(Value::from("octal")),
+                },
+                MigrationApplied {
+                    from: "psram.legacy".to_string(),
+                    to: None,
+                    old_value: Value::Bool(true),
+                    new_value: None,
+                },
+            ],
+            applied
+        );
+
+        let (unchanged, applied) = migrate_user_config(&migrated, "mycrate", &[]).unwrap();
+        assert_eq!(migrated, unchanged);
+        assert!(applied.is_empty());
+    }
+}
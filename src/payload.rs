@@ -0,0 +1,326 @@
+use std::io::Write;
+
+use crate::{Error, Value, ValueItem};
+
+/// Identifiers which can't be the name of a payload's const fn
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PayloadType {
+    Bool,
+    U32,
+    I64,
+    U64,
+    F64,
+    Str,
+}
+
+impl PayloadType {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(_) => Some(PayloadType::Bool),
+            Value::Number(n) if n.as_u64().is_some_and(|n| n <= u32::MAX as u64) => {
+                Some(PayloadType::U32)
+            }
+            Value::Number(n) if n.is_i64() => Some(PayloadType::I64),
+            Value::Number(n) if n.is_u64() => Some(PayloadType::U64),
+            Value::Number(_) => Some(PayloadType::F64),
+            Value::String(_) => Some(PayloadType::Str),
+            _ => None,
+        }
+    }
+
+    /// The type which can hold values of both types
+    fn widen(self, other: Self) -> Option<Self> {
+        use PayloadType::*;
+        match (self, other) {
+            (a, b) if a == b => Some(a),
+            (U32, I64) | (I64, U32) => Some(I64),
+            (U32, U64) | (U64, U32) => Some(U64),
+            (U32 | I64 | U64 | F64, U32 | I64 | U64 | F64) => Some(F64),
+            _ => None,
+        }
+    }
+
+    fn rust_type(self) -> &'static str {
+        match self {
+            PayloadType::Bool => "bool",
+            PayloadType::U32 => "u32",
+            PayloadType::I64 => "i64",
+            PayloadType::U64 => "u64",
+            PayloadType::F64 => "f64",
+            PayloadType::Str => "&'static str",
+        }
+    }
+
+    fn literal(self, value: &Value) -> String {
+        match (self, value) {
+            (PayloadType::F64, Value::Number(n)) => format!("{:?}", n.as_f64().unwrap_or_default()),
+            (_, Value::String(s)) => format!("{:?}", s),
+            (_, value) => value.to_string(),
+        }
+    }
+}
+
+/// The payloads of the variants of an enum option (e.g. `{ value = "80mhz", freq_hz = 80000000 }`)
+/// become `const fn`s of the generated enum
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Payload {
+    name: String,
+    payload_type: PayloadType,
+    /// Per variant - if some variants don't have it the fn returns an `Option`
+    values: Vec<Option<Value>>,
+}
+
+/// The payloads of an enum option's values, in the order they first appear
+pub(crate) fn payloads(key: &str, values: &[ValueItem]) -> Result<Vec<Payload>, Error> {
+    let mut result: Vec<Payload> = Vec::new();
+    for item in values {
+        for name in item.payload.keys() {
+            if !result.iter().any(|payload| payload.name == *name) {
+                result.push(payload(key, name, values)?);
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn payload(key: &str, name: &str, values: &[ValueItem]) -> Result<Payload, Error> {
+    let invalid = || Error::InvalidDefinition(format!("{}.values.{}", key, name));
+
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier || KEYWORDS.contains(&name) {
+        return Err(invalid());
+    }
+
+    let values: Vec<Option<Value>> = values
+        .iter()
+        .map(|item| item.payload.get(name).cloned())
+        .collect();
+    let mut payload_type = None;
+    for value in values.iter().flatten() {
+        let of = PayloadType::of(value).ok_or_else(invalid)?;
+        payload_type = Some(match payload_type {
+            None => of,
+            Some(payload_type) => PayloadType::widen(payload_type, of).ok_or_else(invalid)?,
+        });
+    }
+
+    Ok(Payload {
+        name: name.to_string(),
+        payload_type: payload_type.ok_or_else(invalid)?,
+        values,
+    })
+}
+
+/// Either all values of an enum option have a `repr` or none and no two have the same
+pub(crate) fn check_reprs(key: &str, values: &[ValueItem]) -> Result<(), Error> {
+    let reprs: Vec<u32> = values.iter().filter_map(|item| item.repr).collect();
+    let unique = reprs
+        .iter()
+        .enumerate()
+        .all(|(index, repr)| !reprs[..index].contains(repr));
+    if !reprs.is_empty() && (reprs.len() != values.len() || !unique) {
+        return Err(Error::InvalidDefinition(format!("{}.values.repr", key)));
+    }
+
+    Ok(())
+}
+
+/// Write the `impl` with a `const fn` per payload
+pub(crate) fn write_payload_impl(
+    out: &mut impl Write,
+    enum_name: &str,
+    variant_names: &[String],
+    payloads: &[Payload],
+) -> std::io::Result<()> {
+    if payloads.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "impl {} {{", enum_name)?;
+    for payload in payloads {
+        let optional = payload.values.iter().any(Option::is_none);
+        let rust_type = payload.payload_type.rust_type();
+        if optional {
+            writeln!(
+                out,
+                "pub const fn {}(self) -> Option<{}> {{",
+                payload.name, rust_type
+            )?;
+        } else {
+            writeln!(
+                out,
+                "pub const fn {}(self) -> {} {{",
+                payload.name, rust_type
+            )?;
+        }
+
+        writeln!(out, "match self {{")?;
+        for (variant, value) in variant_names.iter().zip(&payload.values) {
+            let literal = value
+                .as_ref()
+                .map(|value| payload.payload_type.literal(value));
+            match (optional, literal) {
+                (false, Some(literal)) => {
+                    writeln!(out, "{}::{} => {},", enum_name, variant, literal)?
+                }
+                (true, Some(literal)) => {
+                    writeln!(out, "{}::{} => Some({}),", enum_name, variant, literal)?
+                }
+                (_, None) => writeln!(out, "{}::{} => None,", enum_name, variant)?,
+            }
+        }
+        writeln!(out, "}}")?;
+        writeln!(out, "}}")?;
+    }
+    writeln!(out, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    const DEFINITION: &str = r#"
+    [flash]
+    description = "Flash"
+
+    [flash.options.speed]
+    description = "Flash speed"
+    type = "enum"
+    values = [
+        { description = "40MHz", value = "40mhz", freq_hz = 40000000, label = "slow" },
+        { description = "80MHz", value = "80mhz", freq_hz = 80000000, divider = 1.5 },
+    ]
+    "#;
+
+    #[test]
+    fn enum_payloads() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let values = definition["flash"].options.as_ref().unwrap()["speed"]
+            .values
+            .clone()
+            .unwrap();
+        let payloads = payloads("flash.speed", &values).unwrap();
+        assert_eq!(
+            vec!["freq_hz", "label", "divider"],
+            payloads
+                .iter()
+                .map(|payload| payload.name.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let mut out = Vec::new();
+        write_payload_impl(
+            &mut out,
+            "FlashSpeed",
+            &["V40mhz".to_string(), "V80mhz".to_string()],
+            &payloads,
+        )
+        .unwrap();
+        assert_eq!(
+            "impl FlashSpeed {
+pub const fn freq_hz(self) -> u32 {
+match self {
+FlashSpeed::V40mhz => 40000000,
+FlashSpeed::V80mhz => 80000000,
+}
+}
+pub const fn label(self) -> Option<&'static str> {
+match self {
+FlashSpeed::V40mhz => Some(\"slow\"),
+FlashSpeed::V80mhz => None,
+}
+}
+pub const fn divider(self) -> Option<f64> {
+match self {
+FlashSpeed::V40mhz => None,
+FlashSpeed::V80mhz => Some(1.5),
+}
+}
+}
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_payloads() {
+        for payload in ["type = 1", "freq-hz = 1", "list = [1]"] {
+            let definition = parse_definition_str(&format!(
+                "[speed]\ndescription = \"Speed\"\ntype = \"enum\"\nvalues = [{{ description = \"Fast\", value = \"fast\", {} }}]\n",
+                payload
+            ))
+            .unwrap();
+            assert!(
+                payloads("speed", definition["speed"].values.as_ref().unwrap()).is_err(),
+                "{}",
+                payload
+            );
+        }
+
+        // mixed types
+        let definition = parse_definition_str(
+            "[speed]\ndescription = \"Speed\"\ntype = \"enum\"\nvalues = [{ description = \"Fast\", value = \"fast\", x = 1 }, { description = \"Slow\", value = \"slow\", x = \"1\" }]\n",
+        )
+        .unwrap();
+        assert!(payloads("speed", definition["speed"].values.as_ref().unwrap()).is_err());
+    }
+
+    #[test]
+    fn discriminants() {
+        let definition = r#"
+        [psram]
+        description = "PSRAM"
+
+        [psram.options.size]
+        description = "PSRAM Size"
+        type = "enum"
+        values = [
+            { description = "2MB", value = "2", repr = 2097152 },
+            { description = "4MB", value = "4", repr = 4194304 },
+        ]
+        default = "2"
+        "#;
+        let parsed = parse_definition_str(definition).unwrap();
+        let values = parsed["psram"].options.as_ref().unwrap()["size"]
+            .values
+            .clone()
+            .unwrap();
+        assert_eq!(Some(2097152), values[0].repr);
+        // not a payload
+        assert!(payloads("psram.size", &values).unwrap().is_empty());
+
+        let effective_config =
+            crate::evaluate_config("", "mycrate", parsed.clone(), vec![]).unwrap();
+        let config_rs = crate::generate_config(&parsed, &effective_config).config_rs;
+        assert!(config_rs.contains(
+            "#[repr(u32)]\npub enum PsramSize {\nVariant2 = 2097152,\nVariant4 = 4194304,\n}\n"
+        ));
+
+        for invalid in [
+            definition.replace(", repr = 4194304", ""),
+            definition.replace("4194304", "2097152"),
+        ] {
+            let parsed = parse_definition_str(&invalid).unwrap();
+            let values = parsed["psram"].options.as_ref().unwrap()["size"]
+                .values
+                .clone()
+                .unwrap();
+            match check_reprs("psram.size", &values) {
+                Err(Error::InvalidDefinition(key)) => assert_eq!("psram.size.values.repr", key),
+                other => panic!("{}: {:?}", invalid, other),
+            }
+        }
+    }
+}
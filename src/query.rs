@@ -0,0 +1,148 @@
+use crate::{explain_unavailable, ConfigOption, Map, Provenance, UnmetDepends, Value};
+
+/// What an evaluation depends on besides the config-definition and the user's config
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalContext {
+    /// The crate's section in `config.toml`
+    pub crate_name: String,
+    /// The active features of the crate
+    pub features: Vec<String>,
+    /// Use the defaults if the user's config is invalid instead of failing - tooling needs to
+    /// learn about the crate anyways
+    pub fallback_to_defaults: bool,
+    /// The environment variables options with `env` read, see [crate::env_vars]
+    pub env: Map<String, String>,
+}
+
+impl EvalContext {
+    pub fn new(features: &[&str]) -> Self {
+        Self {
+            features: features.iter().map(|feature| feature.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_crate_name(mut self, crate_name: &str) -> Self {
+        self.crate_name = crate_name.to_string();
+        self
+    }
+
+    pub fn with_env(mut self, env: Map<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+}
+
+/// Everything about a single option or menu
+#[derive(Debug, Clone)]
+pub struct OptionInfo {
+    /// The dotted key, e.g. `psram.size`
+    pub key: String,
+    /// The definition of the option with the user's value
+    pub option: ConfigOption,
+    /// Whether the option is available with the features and the other values
+    pub active: bool,
+    /// The user's value or the default - `None` if the option isn't active or has neither
+    pub value: Option<Value>,
+    pub provenance: Option<Provenance>,
+    /// Why the option isn't active
+    pub unmet: Vec<UnmetDepends>,
+}
+
+/// Look up a single option of a definition the user's config is fused into (see
+/// [crate::fuse_config_str])
+pub fn get_option_info(
+    config: &Map<String, ConfigOption>,
+    key: &str,
+    ctx: &EvalContext,
+) -> Option<OptionInfo> {
+    let features: Vec<&str> = ctx
+        .features
+        .iter()
+        .map(|feature| feature.as_str())
+        .collect();
+    let config = &*crate::default_table::with_defaults(config, &features);
+
+    let mut parts = key.split('.');
+    let mut option = config.get(parts.next()?)?;
+    for part in parts {
+        option = option.options.as_ref()?.get(part)?;
+    }
+
+    let unmet = explain_unavailable(key, config, &features);
+    let active = unmet.is_empty();
+
+    let (value, provenance) = match (&option.__value, &option.default_value) {
+        _ if !active => (None, None),
+        (Some(value), _) => (
+            Some(value.clone()),
+            Some(crate::secret::value_provenance(option)),
+        ),
+        (None, Some(default)) => (Some(default.clone()), Some(Provenance::Default)),
+        (None, None) => (None, None),
+    };
+
+    Some(OptionInfo {
+        key: key.to_string(),
+        option: option.clone(),
+        active,
+        value,
+        provenance,
+        unmet,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fuse_config_str, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    depends = 'enabled("psram.enable")'
+    type = "enum"
+    values = [
+        { description = "1MB", value = "1" },
+        { description = "2MB", value = "2" },
+    ]
+    default = "2"
+    "#;
+
+    #[test]
+    fn option_info() {
+        let cfg = r#"
+        [mycrate]
+        psram.enable = true
+        "#;
+        let config =
+            fuse_config_str(cfg, "mycrate", parse_definition_str(DEFINITION).unwrap()).unwrap();
+
+        let info = get_option_info(&config, "psram.enable", &EvalContext::new(&["esp32"])).unwrap();
+        assert!(info.active);
+        assert_eq!(Some(Value::Bool(true)), info.value);
+        assert_eq!(Some(Provenance::User), info.provenance);
+
+        let info = get_option_info(&config, "psram.size", &EvalContext::new(&["esp32"])).unwrap();
+        assert!(info.active);
+        assert_eq!(Some(Value::from("2")), info.value);
+        assert_eq!(Some(Provenance::Default), info.provenance);
+        assert_eq!("PSRAM Size", info.option.description);
+
+        let info = get_option_info(&config, "psram.size", &EvalContext::new(&[])).unwrap();
+        assert!(!info.active);
+        assert_eq!(None, info.value);
+        assert_eq!(vec!["esp32".to_string()], info.unmet[0].missing_features);
+
+        assert!(get_option_info(&config, "psram.speed", &EvalContext::default()).is_none());
+    }
+}
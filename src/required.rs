@@ -0,0 +1,116 @@
+use crate::active::ActiveSet;
+use crate::{ConfigOption, Error, Map, OptionIndex};
+
+/// The keys of the available `required` options the user didn't set, in the order of the
+/// definition
+///
+/// ```toml
+/// [wifi.options.ssid]
+/// description = "SSID"
+/// type = "string"
+/// required = true
+/// ```
+pub(crate) fn missing_required(
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+) -> Vec<String> {
+    OptionIndex::new(config)
+        .iter()
+        .filter(|(key, option)| option.required && option.__value.is_none() && active.contains(key))
+        .map(|(key, _)| key.to_string())
+        .collect()
+}
+
+/// Fail with all missing required options
+pub(crate) fn check_required(
+    config: &Map<String, ConfigOption>,
+    active: &ActiveSet,
+) -> Result<(), Error> {
+    let missing = missing_required(config, active);
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::MissingRequired(missing))
+    }
+}
+
+/// Only options with a type and without a default can be required
+pub(crate) fn check_required_option(key: &str, option: &ConfigOption) -> Result<(), Error> {
+    if option.required && (option.value_type.is_none() || option.default_value.is_some()) {
+        return Err(Error::InvalidDefinition(format!("{}.required", key)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compile_error_message, evaluate_config, evaluate_config_str, parse_definition_str,
+    };
+
+    const DEFINITION: &str = r#"
+    [wifi]
+    description = "WiFi"
+
+    [wifi.options.enable]
+    description = "Enable WiFi"
+    type = "bool"
+    default = true
+
+    [wifi.options.ssid]
+    description = "SSID"
+    type = "string"
+    depends = 'enabled("wifi.enable")'
+    required = true
+
+    [wifi.options.password]
+    description = "Password"
+    type = "string"
+    depends = 'enabled("wifi.enable")'
+    required = true
+    "#;
+
+    #[test]
+    fn required() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+
+        let err = evaluate_config_str("", "mycrate", definition.clone(), vec![]).unwrap_err();
+        match &err {
+            Error::MissingRequired(keys) => assert_eq!(vec!["wifi.ssid", "wifi.password"], *keys),
+            other => panic!("{:?}", other),
+        }
+        assert!(compile_error_message("mycrate", &err).contains("`mycrate.wifi.ssid`"));
+
+        let effective_config = evaluate_config(
+            "[mycrate]\nwifi.ssid = \"home\"\nwifi.password = \"secret\"\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some("home"), effective_config.get_str("wifi.ssid"));
+
+        // not available, not required
+        assert!(evaluate_config_str(
+            "[mycrate]\nwifi.enable = false\n",
+            "mycrate",
+            definition,
+            vec![]
+        )
+        .is_ok());
+
+        for invalid in [
+            DEFINITION.replacen("required = true", "required = true\n    default = \"x\"", 1),
+            DEFINITION.replacen("type = \"string\"\n    depends", "depends", 1),
+        ] {
+            let definition = parse_definition_str(&invalid).unwrap();
+            let ssid = &definition["wifi"].options.as_ref().unwrap()["ssid"];
+            match check_required_option("wifi.ssid", ssid) {
+                Err(Error::InvalidDefinition(key)) => assert_eq!("wifi.ssid.required", key),
+                other => panic!("{}: {:?}", invalid, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{Charset, ConfigOption, Map, Value, ValueType};
+
+// the integer literals `config.toml` can have as strings, see [crate::parse_integer_literal]
+const DECIMAL: &str = "[0-9](_?[0-9])*";
+const HEX: &str = "0x[0-9A-Fa-f](_?[0-9A-Fa-f])*";
+const OCTAL: &str = "0o[0-7](_?[0-7])*";
+const BINARY: &str = "0b[01](_?[01])*";
+
+/// Create a JSON Schema for the user's `config.toml`
+///
+/// `definitions` are the crate names with their config-definitions. Each crate gets a table,
+/// menus are nested tables. Only the structure, the types, the enum values and the string
+/// constraints are checked - `depends` and `valid` can't be expressed in the schema.
+pub fn generate_json_schema(definitions: &[(&str, &Map<String, ConfigOption>)]) -> Value {
+    let crates: serde_json::Map<String, Value> = definitions
+        .iter()
+        .map(|(crate_name, definition)| (crate_name.to_string(), table_schema(definition)))
+        .collect();
+
+    serde_json::json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "rconfig configuration",
+        "type": "object",
+        "properties": crates,
+    })
+}
+
+fn table_schema(options: &Map<String, ConfigOption>) -> Value {
+    let properties: serde_json::Map<String, Value> = options
+        .iter()
+        .filter_map(|(name, option)| Some((name.clone(), option_schema(option)?)))
+        .collect();
+
+    serde_json::json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+fn option_schema(option: &ConfigOption) -> Option<Value> {
+    // can't be set in `config.toml`
+    if option.env.is_some() {
+        return None;
+    }
+
+    let mut schema = if let Some(options) = &option.options {
+        table_schema(options)
+    } else {
+        match option.value_type.as_ref()? {
+            ValueType::Bool => serde_json::json!({ "type": "boolean" }),
+            ValueType::String => {
+                let mut schema = serde_json::json!({ "type": "string" });
+                if let Some(min_length) = option.min_length {
+                    schema["minLength"] = Value::from(min_length);
+                }
+                if let Some(max_length) = option.max_length {
+                    schema["maxLength"] = Value::from(max_length);
+                }
+                if let Some(charset) = option.charset {
+                    schema["pattern"] = Value::from(match charset {
+                        Charset::Ascii => "^[\\x00-\\x7f]*$",
+                        Charset::Printable => "^[\\x20-\\x7e]*$",
+                        Charset::Alphanumeric => "^[A-Za-z0-9]*$",
+                        Charset::Ident => "^([A-Za-z_][A-Za-z0-9_]*)?$",
+                    });
+                }
+                schema
+            }
+            ValueType::Enum => {
+                let values: Vec<&str> = option
+                    .values
+                    .iter()
+                    .flatten()
+                    .map(|item| item.value.as_str())
+                    .collect();
+                serde_json::json!({ "type": "string", "enum": values })
+            }
+            ValueType::Ipv4 => serde_json::json!({ "type": "string", "format": "ipv4" }),
+            ValueType::Ipv6 => serde_json::json!({ "type": "string", "format": "ipv6" }),
+            ValueType::Mac => serde_json::json!({
+                "type": "string",
+                "pattern": "^[0-9A-Fa-f]{2}([:-][0-9A-Fa-f]{2}){5}$",
+            }),
+            // see [crate::parse_size]
+            ValueType::Size => serde_json::json!({
+                "anyOf": [
+                    { "type": "integer", "minimum": 0, "maximum": u32::MAX },
+                    {
+                        "type": "string",
+                        "pattern": format!(
+                            "^(({DECIMAL}|{OCTAL}) *(B|[Kk]B?|KiB|[MG](i?B)?)?|{HEX}|{BINARY})$"
+                        ),
+                    },
+                ],
+            }),
+            // the integer types, also written as string, e.g. `"0x8000"`
+            _ => {
+                let (min, max) = crate::numeric_range(option);
+                let mut integer = serde_json::json!({
+                    "type": "integer",
+                    "minimum": crate::integer_value(min),
+                    "maximum": crate::integer_value(max),
+                });
+                if let Some(step) = option.step {
+                    integer["multipleOf"] = Value::from(step);
+                }
+                let sign = if min < 0 { "-?" } else { "" };
+                serde_json::json!({
+                    "anyOf": [
+                        integer,
+                        {
+                            "type": "string",
+                            "pattern": format!("^({sign}{DECIMAL}|{HEX}|{OCTAL}|{BINARY})$"),
+                        },
+                    ],
+                })
+            }
+        }
+    };
+
+    schema["description"] = Value::from(option.description.clone());
+    // a default table and conditional defaults depend on the features
+    if let Some(default) = option
+        .default_value
+        .as_ref()
+        .filter(|value| !value.is_object() && !value.is_array())
+    {
+        schema["default"] = default.clone();
+    }
+    Some(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_definition_str;
+
+    #[test]
+    fn json_schema() {
+        let definition = parse_definition_str(
+            r#"
+            [psram]
+            description = "PSRAM"
+
+            [psram.options.note]
+            description = "Comments don't appear in the schema"
+
+            [psram.options.enable]
+            description = "Enable PSRAM"
+            type = "bool"
+            default = false
+
+            [psram.options.size]
+            description = "PSRAM Size"
+            type = "enum"
+            values = [{ description = "1MB", value = "1" }, { description = "2MB", value = "2" }]
+
+            [heap]
+            description = "Heapsize"
+
+            [heap.options.size]
+            description = "Bytes to allocate"
+            type = "u32"
+
+            [heap.options.start]
+            description = "Start address"
+            type = "u32"
+            default = "0x10"
+
+            [heap.options.stack]
+            description = "Stack size"
+            type = "size"
+            default = "0x1000"
+            "#,
+        )
+        .unwrap();
+
+        let schema = generate_json_schema(&[("mycrate", &definition)]);
+        let psram = &schema["properties"]["mycrate"]["properties"]["psram"];
+
+        assert_eq!(Value::from(false), psram["additionalProperties"]);
+        assert_eq!(Value::Null, psram["properties"]["note"]);
+        assert_eq!(
+            serde_json::json!({ "type": "boolean", "description": "Enable PSRAM", "default": false }),
+            psram["properties"]["enable"]
+        );
+        assert_eq!(
+            serde_json::json!(["1", "2"]),
+            psram["properties"]["size"]["enum"]
+        );
+        let heap_size =
+            &schema["properties"]["mycrate"]["properties"]["heap"]["properties"]["size"];
+        assert_eq!(Value::from(u32::MAX), heap_size["anyOf"][0]["maximum"]);
+        assert_eq!(
+            format!("^({DECIMAL}|{HEX}|{OCTAL}|{BINARY})$"),
+            heap_size["anyOf"][1]["pattern"]
+        );
+        assert_eq!(Value::from("Bytes to allocate"), heap_size["description"]);
+
+        let heap = &schema["properties"]["mycrate"]["properties"]["heap"]["properties"];
+        assert_eq!(Value::from(16), heap["start"]["default"]);
+        assert_eq!(Value::from(4096), heap["stack"]["default"]);
+        assert!(heap["stack"]["anyOf"][1]["pattern"]
+            .as_str()
+            .unwrap()
+            .contains(HEX));
+    }
+}
@@ -0,0 +1,281 @@
+#[cfg(feature = "export")]
+use crate::OptionIndex;
+use crate::{
+    integer_value, parse_signed_integer_literal, ConfigOption, Error, Map, Provenance, Value,
+    ValueType,
+};
+
+/// What the TUI and the other tooling show instead of the value of a `secret` option
+pub const REDACTED: &str = "********";
+
+/// Set the options with `env` to the value of their environment variable - `env` are the variables
+/// of the build, an option keeps its default if its variable isn't set
+///
+/// ```toml
+/// [wifi.options.password]
+/// description = "WiFi password"
+/// type = "string"
+/// secret = true
+/// env = "WIFI_PASSWORD"
+/// ```
+pub(crate) fn apply_env(
+    mut options: Map<String, ConfigOption>,
+    env: &Map<String, String>,
+) -> Result<Map<String, ConfigOption>, Error> {
+    apply(&mut options, env, "")?;
+    Ok(options)
+}
+
+fn apply(
+    options: &mut Map<String, ConfigOption>,
+    env: &Map<String, String>,
+    prefix: &str,
+) -> Result<(), Error> {
+    for (name, option) in options.iter_mut() {
+        let key = format!("{}{}", prefix, name);
+
+        if let Some(children) = option.options.as_mut() {
+            apply(children, env, &format!("{}.", key))?;
+        }
+
+        let Some(text) = option.env.as_ref().and_then(|var| env.get(var)) else {
+            continue;
+        };
+        let value = env_value(option, text).ok_or(Error::InvalidConfigurationValue(key))?;
+        option.__value = Some(value);
+    }
+
+    Ok(())
+}
+
+/// The text of the environment variable as value of the option's type
+fn env_value(option: &ConfigOption, text: &str) -> Option<Value> {
+    let value_type = option.value_type.as_ref()?;
+    if *value_type == ValueType::Size {
+        return crate::parse_size(text).map(Value::from);
+    }
+    if let Some((min, max)) = value_type.integer_range() {
+        return parse_signed_integer_literal(text)
+            .filter(|(number, _)| (min..=max).contains(number))
+            .map(|(number, _)| integer_value(number));
+    }
+    match value_type {
+        ValueType::Bool => text.trim().parse::<bool>().ok().map(Value::Bool),
+        _ => Some(Value::from(text)),
+    }
+}
+
+/// The environment variables the options of the definition read their values from
+pub fn env_vars(definition: &Map<String, ConfigOption>) -> Vec<String> {
+    let mut vars = Vec::new();
+    collect_env_vars(&mut vars, definition);
+    vars
+}
+
+fn collect_env_vars(vars: &mut Vec<String>, options: &Map<String, ConfigOption>) {
+    for option in options.values() {
+        if let Some(var) = &option.env {
+            if !vars.contains(var) {
+                vars.push(var.clone());
+            }
+        }
+
+        if let Some(children) = option.options.as_ref() {
+            collect_env_vars(vars, children);
+        }
+    }
+}
+
+/// The user's config (the content of `config.toml`) with the values of the crate's `secret` options
+/// replaced by [REDACTED] - e.g. to show the changes before saving them
+///
+/// Everything else is kept as it is, only secrets set in inline tables are written differently.
+#[cfg(feature = "export")]
+pub fn redact_config_str(
+    input: &str,
+    crate_name: &str,
+    definition: &Map<String, ConfigOption>,
+) -> Result<String, Error> {
+    let index = OptionIndex::new(definition);
+    let secrets: Vec<&str> = index
+        .iter()
+        .filter(|(_, option)| option.secret)
+        .map(|(key, _)| key)
+        .collect();
+    if secrets.is_empty() {
+        return Ok(input.to_string());
+    }
+
+    let mut document: toml_edit::DocumentMut = input
+        .parse()
+        .map_err(|err: toml_edit::TomlError| Error::InvalidToml(err.to_string()))?;
+    if let Some(section) = document.get_mut(crate_name) {
+        for key in secrets {
+            let item = key
+                .split('.')
+                .try_fold(&mut *section, |item, part| item.get_mut(part));
+            if let Some(value) = item.and_then(|item| item.as_value_mut()) {
+                let decor = value.decor().clone();
+                *value = toml_edit::Value::from(REDACTED);
+                *value.decor_mut() = decor;
+            }
+        }
+    }
+    Ok(document.to_string())
+}
+
+/// Where the value an option has (`__value`) comes from - the user can't set options with `env`
+pub(crate) fn value_provenance(option: &ConfigOption) -> Provenance {
+    if option.env.is_some() {
+        Provenance::Env
+    } else {
+        Provenance::User
+    }
+}
+
+/// Only options with a type can be secret or read from the environment
+pub(crate) fn check_secret(key: &str, option: &ConfigOption) -> Result<(), Error> {
+    if option.value_type.is_none() {
+        if option.secret {
+            return Err(Error::InvalidDefinition(format!("{}.secret", key)));
+        }
+        if option.env.is_some() {
+            return Err(Error::InvalidDefinition(format!("{}.env", key)));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        check_config_str, generate_config, parse_definition_str, resolve, ConfigProblemKind,
+        EvalContext,
+    };
+
+    const DEFINITION: &str = r#"
+    [wifi]
+    description = "WiFi"
+
+    [wifi.options.ssid]
+    description = "SSID"
+    type = "string"
+    default = "rconfig"
+
+    [wifi.options.password]
+    description = "Password"
+    type = "string"
+    secret = true
+    env = "WIFI_PASSWORD"
+    default = ""
+
+    [wifi.options.channel]
+    description = "Channel"
+    type = "u32"
+    env = "WIFI_CHANNEL"
+    valid = "value <= 13"
+    "#;
+
+    fn ctx(env: &[(&str, &str)]) -> EvalContext {
+        EvalContext::new(&[]).with_crate_name("mycrate").with_env(
+            env.iter()
+                .map(|(var, value)| (var.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn env_values() {
+        let effective_config = resolve(
+            DEFINITION,
+            "",
+            &ctx(&[("WIFI_PASSWORD", "hunter2"), ("WIFI_CHANNEL", "0xb")]),
+        )
+        .unwrap();
+        assert_eq!(Some("hunter2"), effective_config.get_str("wifi.password"));
+        assert_eq!(Some(11), effective_config.get_u32("wifi.channel"));
+
+        let password = effective_config.get("wifi.password").unwrap();
+        assert_eq!(Provenance::Env, password.provenance);
+        assert!(password.secret);
+        assert!(!effective_config.get("wifi.ssid").unwrap().secret);
+
+        // still in the generated config
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let config_rs = generate_config(&definition, &effective_config).config_rs;
+        assert!(config_rs.contains("pub const WIFI_PASSWORD: &str = \"hunter2\";\n"));
+
+        let defaults = resolve(DEFINITION, "", &ctx(&[])).unwrap();
+        assert_eq!(Some(""), defaults.get_str("wifi.password"));
+        assert_eq!(None, defaults.get_u32("wifi.channel"));
+
+        assert_eq!(vec!["WIFI_PASSWORD", "WIFI_CHANNEL"], env_vars(&definition));
+    }
+
+    #[test]
+    fn invalid_env_values() {
+        for env in [("WIFI_CHANNEL", "14"), ("WIFI_CHANNEL", "eleven")] {
+            assert!(resolve(DEFINITION, "", &ctx(&[env])).is_err(), "{:?}", env);
+        }
+
+        // only from the environment
+        let user_config = "[mycrate]\nwifi.password = \"hunter2\"\n";
+        assert!(resolve(DEFINITION, user_config, &ctx(&[])).is_err());
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let problems = check_config_str(user_config, "mycrate", &definition, vec![]).unwrap();
+        assert_eq!(1, problems.len());
+        assert_eq!(ConfigProblemKind::InvalidValue, problems[0].kind);
+
+        let menu = parse_definition_str(
+            "[wifi]\ndescription = \"WiFi\"\nsecret = true\n\n[wifi.options.ssid]\ndescription = \"SSID\"\ntype = \"string\"\n",
+        )
+        .unwrap();
+        assert!(check_secret("wifi", &menu["wifi"]).is_err());
+    }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn redacted_config() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let redact = |input| redact_config_str(input, "mycrate", &definition).unwrap();
+
+        let input = r#"# secrets
+[mycrate]
+wifi.ssid = "home"
+wifi.password = "hunter2" # not really
+
+[other]
+wifi.password = "visible"
+"#;
+        assert_eq!(input.replace("\"hunter2\"", "\"********\""), redact(input),);
+
+        let input = "[mycrate.wifi]\n\"password\" = \"hunter2\"\nssid = \"home\"\n";
+        assert_eq!(input.replace("hunter2", "********"), redact(input));
+
+        let input = "[mycrate]\nwifi = { ssid = \"home\", password = \"hunter2\" }\n";
+        assert_eq!(input.replace("hunter2", "********"), redact(input));
+
+        assert!(redact_config_str("[mycrate", "mycrate", &definition).is_err());
+    }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn redacted_multi_line_secrets() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        for input in [
+            "[mycrate]\nwifi.password = \"\"\"\nhunter2\nhunter3\"\"\"\nwifi.ssid = \"home\"\n",
+            "[mycrate.wifi]\npassword = \'\'\'\nhunter2\nhunter3\'\'\'\nssid = \"home\"\n",
+        ] {
+            let redacted = redact_config_str(input, "mycrate", &definition).unwrap();
+            assert!(!redacted.contains("hunter"), "{}", redacted);
+            assert!(
+                redacted.contains("password = \"********\"\n"),
+                "{}",
+                redacted
+            );
+            assert!(redacted.contains("ssid = \"home\"\n"), "{}", redacted);
+        }
+    }
+}
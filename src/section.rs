@@ -0,0 +1,59 @@
+use crate::definition_file::DefinitionFile;
+use crate::{split_sections, Error};
+
+/// The crate's section in the user's config - the definition's `section_name` or the package name
+///
+/// ```toml
+/// # read `[hal]` instead of `[fake-hal]` of `config.toml`
+/// section_name = "hal"
+/// ```
+pub fn parse_section_name_str(definition: &str, package_name: &str) -> Result<String, Error> {
+    Ok(DefinitionFile::parse(definition)?
+        .section_name
+        .unwrap_or_else(|| package_name.to_string()))
+}
+
+/// The sections of the user's config (the content of `config.toml`) which don't belong to any of
+/// the crates, e.g. named after a renamed dependency instead of the package
+pub fn unknown_sections(user_config: &str, sections: &[&str]) -> Vec<String> {
+    split_sections(user_config)
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !name.is_empty() && !sections.contains(&name.as_str()))
+        .collect()
+}
+
+pub(crate) fn has_section(user_config: &str, section: &str) -> bool {
+    split_sections(user_config)
+        .iter()
+        .any(|(name, _)| name == section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections() {
+        assert_eq!(
+            "hal",
+            parse_section_name_str("section_name = \"hal\"\n", "fake-hal").unwrap()
+        );
+        assert_eq!(
+            "hal",
+            parse_section_name_str("section-name = \"hal\"\n", "fake-hal").unwrap()
+        );
+        assert_eq!(
+            "fake-hal",
+            parse_section_name_str("[heap]\ndescription = \"Heap\"\n", "fake-hal").unwrap()
+        );
+
+        let user_config = "include = { path = \"board.toml\" }\n\n[hal]\nheap.size = 1\n\n[my-hal.psram]\nenable = true\n\n[\"fake-wifi\"]\nble = true\n";
+        assert_eq!(
+            vec!["my-hal"],
+            unknown_sections(user_config, &["hal", "fake-wifi"])
+        );
+        assert!(has_section(user_config, "hal"));
+        assert!(!has_section(user_config, "fake-hal"));
+    }
+}
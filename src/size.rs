@@ -0,0 +1,164 @@
+use crate::literal::integer_from_string;
+use crate::{ConfigOption, Map, Value, ValueType};
+
+/// Parse a byte size - a number (any integer literal) optionally followed by a unit, e.g. `32KB`,
+/// `4 MiB` or `0x8000`
+///
+/// `K`, `KB` and `KiB` are all 1024 bytes (the same for `M` and `G`), sizes of memory are
+/// powers of two. `B` is a single byte.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(['K', 'k', 'M', 'G', 'B', ' '])
+        // `0b` and `0x` literals have letters
+        .filter(|_| !text.starts_with("0x") && !text.starts_with("0b"))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let (number, _) = crate::parse_integer_literal(number)?;
+
+    let factor: u64 = match unit.trim_start() {
+        "" | "B" => 1,
+        "K" | "k" | "KB" | "kB" | "KiB" => 1 << 10,
+        "M" | "MB" | "MiB" => 1 << 20,
+        "G" | "GB" | "GiB" => 1 << 30,
+        _ => return None,
+    };
+    number.checked_mul(factor)
+}
+
+/// A string which is a size becomes the number of bytes, e.g. `"32KB"` for a size option
+pub(crate) fn size_from_string(value: Value) -> Value {
+    match value.as_str().and_then(parse_size) {
+        Some(bytes) => Value::from(bytes),
+        None => value,
+    }
+}
+
+/// Turn the sizes of the `size` options' defaults (including the entries of default tables and
+/// conditional defaults) into bytes and the integer literals of the other integer options'
+/// defaults into numbers, e.g. `"0x10"`
+pub(crate) fn normalize_number_defaults(options: &mut Map<String, ConfigOption>) {
+    for (_, option) in options.iter_mut() {
+        let convert: Option<fn(Value) -> Value> = match &option.value_type {
+            Some(ValueType::Size) => Some(size_from_string),
+            Some(value_type) if value_type.is_integer() => Some(integer_from_string),
+            _ => None,
+        };
+        if let Some(convert) = convert {
+            option.default_value = option.default_value.take().map(|default| match default {
+                Value::Object(table) => Value::Object(
+                    table
+                        .into_iter()
+                        .map(|(feature, value)| (feature, convert(value)))
+                        .collect(),
+                ),
+                Value::Array(entries) => Value::Array(
+                    entries
+                        .into_iter()
+                        .map(|mut entry| {
+                            if let Some(value) = entry.get_mut("value") {
+                                *value = convert(value.take());
+                            }
+                            entry
+                        })
+                        .collect(),
+                ),
+                default => convert(default),
+            });
+        }
+
+        if let Some(children) = option.options.as_mut() {
+            normalize_number_defaults(children);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config, generate_config, parse_definition_str};
+
+    #[test]
+    fn sizes() {
+        for (text, bytes) in [
+            ("512", Some(512)),
+            ("32KB", Some(32 * 1024)),
+            ("32 KiB", Some(32 * 1024)),
+            ("4MiB", Some(4 * 1024 * 1024)),
+            ("1G", Some(1 << 30)),
+            ("64B", Some(64)),
+            ("0x8000", Some(0x8000)),
+            ("1_024 K", Some(1024 * 1024)),
+            ("KB", None),
+            ("4 TB", None),
+            ("1.5MB", None),
+        ] {
+            assert_eq!(bytes, parse_size(text), "{}", text);
+        }
+    }
+
+    const DEFINITION: &str = r#"
+    [heap]
+    description = "Heap"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "size"
+    default = "32KB"
+    valid = "value <= 0x50000"
+    "#;
+
+    #[test]
+    fn size_options() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let effective_config = evaluate_config("", "mycrate", definition.clone(), vec![]).unwrap();
+        assert_eq!(Some(32 * 1024), effective_config.get_u64("heap.size"));
+
+        let effective_config = evaluate_config(
+            "[mycrate]\nheap.size = \"64 KiB\"\n",
+            "mycrate",
+            definition.clone(),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(Some(64 * 1024), effective_config.get_u64("heap.size"));
+        assert!(generate_config(&definition, &effective_config)
+            .config_rs
+            .contains("pub const HEAP_SIZE: usize = 65536;\n"));
+
+        for invalid in ["\"1MB\"", "\"32 apples\""] {
+            assert!(evaluate_config(
+                &format!("[mycrate]\nheap.size = {}\n", invalid),
+                "mycrate",
+                definition.clone(),
+                vec![],
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn integer_literal_defaults() {
+        let definition = parse_definition_str(
+            r#"
+            [start]
+            description = "Start address"
+            type = "u32"
+            default = "0x10"
+
+            [offset]
+            description = "Offset"
+            type = "i8"
+            default = [{ if = 'feature("esp32")', value = "-0_8" }, { value = "0b11" }]
+            "#,
+        )
+        .unwrap();
+
+        let effective_config =
+            evaluate_config("", "mycrate", definition.clone(), vec!["esp32"]).unwrap();
+        assert_eq!(Some(16), effective_config.get_u64("start"));
+        assert_eq!(Some(-8), effective_config.get_i64("offset"));
+        let effective_config = evaluate_config("", "mycrate", definition, vec![]).unwrap();
+        assert_eq!(Some(3), effective_config.get_i64("offset"));
+    }
+}
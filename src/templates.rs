@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::definition_file::DefinitionFile;
+use crate::{ConfigOption, Error, Map, Value};
+
+const PREFIX: &str = "templates.";
+
+/// An option of a definition with `templates` before the templates are applied - the fields
+/// might be incomplete without the template
+#[derive(Deserialize, Serialize)]
+pub(crate) struct RawOption {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    options: Option<Map<String, RawOption>>,
+    #[serde(flatten)]
+    fields: serde_json::Map<String, Value>,
+}
+
+impl DefinitionFile<RawOption> {
+    /// Fill in what the options with `extends` don't define themselves from the template
+    ///
+    /// ```toml
+    /// [templates.buffer-size]
+    /// description = "Size of the buffer in bytes"
+    /// type = "u32"
+    /// valid = "value >= 64 && value <= 4096"
+    /// step = 64
+    ///
+    /// [uart.options.rx_buffer_size]
+    /// extends = "templates.buffer-size"
+    /// default = 256
+    /// ```
+    ///
+    /// Templates can't extend other templates.
+    pub(crate) fn expand_templates(self) -> Result<DefinitionFile, Error> {
+        for (name, template) in &self.templates {
+            if template.contains_key("extends") {
+                return Err(Error::InvalidDefinition(format!("{}{}", PREFIX, name)));
+            }
+        }
+
+        Ok(DefinitionFile {
+            options: expand(self.options, &self.templates, "")?,
+            include: self.include,
+            migrations: self.migrations,
+            cfg_namespace: self.cfg_namespace,
+            section_name: self.section_name,
+            negated_cfgs: self.negated_cfgs,
+            build_info: self.build_info,
+            compile_errors: self.compile_errors,
+            consts_only: self.consts_only,
+            cfg_macros: self.cfg_macros,
+            features: self.features,
+            constraints: self.constraints,
+            templates: Map::new(),
+        })
+    }
+}
+
+fn expand(
+    config: Map<String, RawOption>,
+    templates: &Map<String, serde_json::Map<String, Value>>,
+    prefix: &str,
+) -> Result<Map<String, ConfigOption>, Error> {
+    let mut expanded = Map::new();
+    for (name, option) in config {
+        let key = format!("{}{}", prefix, name);
+
+        let mut fields = option.fields;
+        if let Some(extends) = fields.remove("extends") {
+            let template = extends
+                .as_str()
+                .and_then(|extends| extends.strip_prefix(PREFIX))
+                .and_then(|name| templates.get(name))
+                .ok_or_else(|| Error::InvalidDefinition(format!("{}.extends", key)))?;
+            for (field, value) in template {
+                if !fields.contains_key(field) {
+                    fields.insert(field.clone(), value.clone());
+                }
+            }
+        }
+
+        let mut expanded_option: ConfigOption = serde_json::from_value(Value::Object(fields))
+            .map_err(|err| Error::InvalidToml(format!("{}: {}", key, err)))?;
+        if let Some(options) = option.options {
+            expanded_option.options = Some(expand(options, templates, &format!("{}.", key))?);
+        }
+        expanded.insert(name, expanded_option);
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{evaluate_config, parse_definition_str, Error};
+
+    const DEFINITION: &str = r#"
+    [templates.buffer-size]
+    description = "Size of the buffer in bytes"
+    type = "u32"
+    valid = "value >= 64"
+    default = 1024
+
+    [uart]
+    description = "UART"
+
+    [uart.options.rx_buffer_size]
+    extends = "templates.buffer-size"
+    default = 256
+
+    [uart.options.tx_buffer_size]
+    extends = "templates.buffer-size"
+    description = "Size of the TX buffer in bytes"
+    "#;
+
+    #[test]
+    fn templates() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        assert_eq!(vec!["uart"], definition.keys().collect::<Vec<_>>());
+        let options = definition["uart"].options.as_ref().unwrap();
+        assert_eq!(
+            vec!["rx_buffer_size", "tx_buffer_size"],
+            options.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "Size of the buffer in bytes",
+            options["rx_buffer_size"].description
+        );
+        assert_eq!(
+            "Size of the TX buffer in bytes",
+            options["tx_buffer_size"].description
+        );
+
+        let effective_config = evaluate_config("", "mycrate", definition.clone(), vec![]).unwrap();
+        assert_eq!(Some(256), effective_config.get_u32("uart.rx_buffer_size"));
+        assert_eq!(Some(1024), effective_config.get_u32("uart.tx_buffer_size"));
+
+        // the validation of the template applies
+        assert!(evaluate_config(
+            "[mycrate]\nuart.rx_buffer_size = 32\n",
+            "mycrate",
+            definition,
+            vec![]
+        )
+        .is_err());
+
+        for (invalid, key) in [
+            (
+                DEFINITION.replacen("\"templates.buffer-size\"", "\"templates.missing\"", 1),
+                "uart.rx_buffer_size.extends",
+            ),
+            (
+                DEFINITION.replacen("\"templates.buffer-size\"", "\"buffer-size\"", 1),
+                "uart.rx_buffer_size.extends",
+            ),
+            (
+                DEFINITION.replace("default = 1024", "extends = \"templates.other\""),
+                "templates.buffer-size",
+            ),
+        ] {
+            match parse_definition_str(&invalid) {
+                Err(Error::InvalidDefinition(invalid_key)) => assert_eq!(key, invalid_key),
+                other => panic!("{}: {:?}", invalid, other.map(|_| ())),
+            }
+        }
+    }
+}
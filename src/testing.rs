@@ -0,0 +1,215 @@
+//! Helpers for crates using rconfig to test their config-definition
+//!
+//! Everything here works without a build-script environment (no `OUT_DIR`, `CARGO_*`).
+//! `config` is always the content of the crate's section in `config.toml`, without the header.
+//!
+//! ```
+//! const DEFINITION: &str = r#"
+//! [heap]
+//! description = "Heapsize"
+//!
+//! [heap.options.size]
+//! description = "Bytes to allocate"
+//! type = "u32"
+//! default = 4096
+//! "#;
+//!
+//! rconfig::with_config!(DEFINITION, "heap.size = 1024", [], |config| {
+//!     assert_eq!(Some(1024), config.get_u32("heap.size"));
+//! });
+//! ```
+
+use crate::cache::compile_definition;
+use crate::{
+    check_config_str, compiled_cfgs, compiled_check_cfgs, emitted_config, migrate_user_config,
+    resolve, write_compiled_config_rs, BuildInfo, CfgNames, ConfigProblem, EffectiveConfig, Error,
+    EvalContext, GeneratedConfig,
+};
+
+const CRATE_NAME: &str = "crate-under-test";
+
+fn user_config(config: &str) -> String {
+    format!("[{}]\n{}\n", CRATE_NAME, config)
+}
+
+/// Evaluate the config with the given features like a build script does (see [resolve]) - with
+/// the definition's migrations and `[constraints]`
+pub fn evaluate(
+    definition: &str,
+    config: &str,
+    features: &[&str],
+) -> Result<EffectiveConfig, Error> {
+    resolve(
+        definition,
+        &user_config(config),
+        &EvalContext::new(features).with_crate_name(CRATE_NAME),
+    )
+}
+
+/// The problems of the config - unknown keys, options which are not available and invalid values
+///
+/// Keys the definition's migrations (and `renamed_from`) replace are checked by their new name.
+pub fn check(
+    definition: &str,
+    config: &str,
+    features: &[&str],
+) -> Result<Vec<ConfigProblem>, Error> {
+    let compiled = compile_definition(definition)?;
+    let (config, _) = migrate_user_config(&user_config(config), CRATE_NAME, &compiled.migrations)?;
+    check_config_str(&config, CRATE_NAME, &compiled.options, features.to_vec())
+}
+
+/// The `config.rs` and cfgs a build script would create
+///
+/// With `cfg_namespace = true` the crate is called `crate-under-test`, i.e. the cfgs start
+/// with `rcfg_crate_under_test_`. With `build_info = true` the `RESOLVED_AT` of the build info
+/// is always `0`, with `consts_only = true` there are no cfgs.
+pub fn generate(
+    definition: &str,
+    config: &str,
+    features: &[&str],
+) -> Result<GeneratedConfig, Error> {
+    let effective_config = evaluate(definition, config, features)?;
+    let compiled = compile_definition(definition)?;
+    let names = CfgNames::of(&compiled, CRATE_NAME);
+    let (options, emitted) = emitted_config(&compiled.options, &effective_config, features, None)?;
+
+    let mut config_rs = Vec::new();
+    write_compiled_config_rs(
+        &mut config_rs,
+        &compiled,
+        &names,
+        &options,
+        &emitted,
+        || {
+            let features: Vec<String> = features.iter().map(|v| v.to_string()).collect();
+            BuildInfo::new(definition, &effective_config, &features)
+                .with_user_config(&user_config(config))
+        },
+    )
+    .expect("Writing to a Vec doesn't fail");
+
+    Ok(GeneratedConfig {
+        config_rs: String::from_utf8(config_rs).expect("config.rs is valid UTF-8"),
+        cfgs: compiled_cfgs(&compiled, &emitted, &names),
+        check_cfgs: compiled_check_cfgs(&compiled, &names),
+    })
+}
+
+/// Run the body with the [EffectiveConfig] of a definition, a config and features
+///
+/// Panics if the config isn't valid.
+#[macro_export]
+macro_rules! with_config {
+    ($definition:expr, $config:expr, [$($feature:expr),* $(,)?], |$effective:ident| $body:expr) => {{
+        let $effective = $crate::testing::evaluate($definition, $config, &[$($feature),*])
+            .expect("invalid configuration");
+        $body
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConfigProblemKind;
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = 'feature("esp32")'
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = false
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    valid = "value <= 80000"
+    default = 4096
+    "#;
+
+    #[test]
+    fn evaluate_with_features() {
+        with_config!(DEFINITION, "psram.enable = true", ["esp32"], |config| {
+            assert_eq!(Some(true), config.get_bool("psram.enable"));
+            assert_eq!(Some(4096), config.get_u32("heap.size"));
+        });
+
+        with_config!(DEFINITION, "", [], |config| {
+            assert_eq!(None, config.get_bool("psram.enable"));
+        });
+    }
+
+    #[test]
+    fn unmet_constraints() {
+        let definition = format!(
+            "{}\n[constraints]\nheap = 'value_of(\"heap.size\") >= 1024'\n",
+            DEFINITION
+        );
+        assert!(evaluate(&definition, "heap.size = 2048", &[]).is_ok());
+        assert!(matches!(
+            evaluate(&definition, "heap.size = 512", &[]),
+            Err(Error::UnmetConstraint(name, _)) if name == "heap"
+        ));
+    }
+
+    #[test]
+    fn renamed_keys() {
+        let definition = DEFINITION.replace(
+            "[heap.options.size]\n",
+            "[heap.options.size]\n    renamed_from = \"heap.bytes\"\n",
+        );
+        with_config!(&definition, "heap.bytes = 1024", [], |config| {
+            assert_eq!(Some(1024), config.get_u32("heap.size"));
+            assert_eq!(1, config.warnings().len());
+        });
+        assert!(check(&definition, "heap.bytes = 1024", &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn problems() {
+        let problems = check(DEFINITION, "heap.size = 90000\npsram.enable = true", &[]).unwrap();
+        assert_eq!(
+            vec![
+                ConfigProblemKind::InvalidValue,
+                ConfigProblemKind::NotApplicable
+            ],
+            problems
+                .into_iter()
+                .map(|problem| problem.kind)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn generated() {
+        let generated = generate(DEFINITION, "psram.enable = true", &["esp32"]).unwrap();
+        assert_eq!(
+            vec![
+                "has_psram_enable",
+                "psram_enable",
+                "has_heap_size",
+                "heap_size"
+            ],
+            generated.cfgs
+        );
+        assert!(generated
+            .config_rs
+            .contains("pub const HEAP_SIZE: u32 = 4096;"));
+
+        let consts_only = format!("consts_only = true\n{}", DEFINITION);
+        let generated = generate(&consts_only, "psram.enable = true", &["esp32"]).unwrap();
+        assert!(generated.cfgs.is_empty());
+        assert!(generated.check_cfgs.is_empty());
+        assert!(generated
+            .config_rs
+            .contains("pub const HEAP_SIZE: u32 = 4096;"));
+    }
+}
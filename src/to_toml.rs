@@ -0,0 +1,285 @@
+use crate::instances;
+use crate::{Charset, ConfigOption, DisplayHint, Map, Value};
+
+/// Create the TOML of a config-definition
+///
+/// The output doesn't depend on how the definition was written: one table per option in the
+/// definition's order, the keys of an option always in the same order. The user's values fused
+/// into a definition are not part of the output.
+pub fn definition_to_toml_string(definition: &Map<String, ConfigOption>) -> String {
+    let mut out = String::new();
+    write_options(&mut out, definition, "");
+    out
+}
+
+fn write_options(out: &mut String, options: &Map<String, ConfigOption>, prefix: &str) {
+    for (name, option) in options {
+        let path = format!("{}{}", prefix, toml_key(name));
+
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("[{}]\n", path));
+        out.push_str(&format!(
+            "description = {}\n",
+            toml_string(&option.description)
+        ));
+
+        if let Some(depends) = &option.depends {
+            out.push_str(&format!("depends = {}\n", toml_string(depends)));
+        }
+        if let Some(value_type) = &option.value_type {
+            out.push_str(&format!("type = \"{}\"\n", value_type));
+        }
+        if let Some(values) = &option.values {
+            out.push_str("values = [\n");
+            for item in values {
+                out.push_str(&format!(
+                    "    {{ description = {}, value = {}",
+                    toml_string(&item.description),
+                    toml_string(&item.value)
+                ));
+                if let Some(repr) = item.repr {
+                    out.push_str(&format!(", repr = {}", repr));
+                }
+                for (name, value) in &item.payload {
+                    out.push_str(&format!(", {} = {}", name, toml_value(value)));
+                }
+                out.push_str(" },\n");
+            }
+            out.push_str("]\n");
+        }
+        if let Some(default) = &option.default_value {
+            out.push_str(&format!("default = {}\n", toml_value(default)));
+        }
+        if let Some(valid) = &option.valid {
+            out.push_str(&format!("valid = {}\n", toml_string(valid)));
+        }
+        if let Some(min_length) = option.min_length {
+            out.push_str(&format!("min_length = {}\n", min_length));
+        }
+        if let Some(max_length) = option.max_length {
+            out.push_str(&format!("max_length = {}\n", max_length));
+        }
+        if let Some(charset) = option.charset {
+            let charset = match charset {
+                Charset::Ascii => "ascii",
+                Charset::Printable => "printable",
+                Charset::Alphanumeric => "alphanumeric",
+                Charset::Ident => "ident",
+            };
+            out.push_str(&format!("charset = \"{}\"\n", charset));
+        }
+        if let Some(step) = option.step {
+            out.push_str(&format!("step = {}\n", step));
+        }
+        if let Some(min) = option.min {
+            out.push_str(&format!("min = {}\n", min));
+        }
+        if let Some(max) = option.max {
+            out.push_str(&format!("max = {}\n", max));
+        }
+        if let Some(emit_if) = &option.emit_if {
+            out.push_str(&format!("emit_if = {}\n", toml_string(emit_if)));
+        }
+        if option.required {
+            out.push_str("required = true\n");
+        }
+        if option.deprecated {
+            out.push_str("deprecated = true\n");
+        }
+        if let Some(from) = &option.renamed_from {
+            out.push_str(&format!("renamed_from = {}\n", toml_string(from)));
+        }
+        if let Some(group) = &option.exclusive_group {
+            out.push_str(&format!("exclusive_group = {}\n", toml_string(group)));
+        }
+        if option.choice {
+            out.push_str("choice = true\n");
+        }
+        if let Some(imply) = &option.imply {
+            let imply: Vec<String> = imply.iter().map(|key| toml_string(key)).collect();
+            out.push_str(&format!("imply = [{}]\n", imply.join(", ")));
+        }
+        if let Some(selects) = &option.selects {
+            let selects: Vec<String> = selects.iter().map(|key| toml_string(key)).collect();
+            out.push_str(&format!("selects = [{}]\n", selects.join(", ")));
+        }
+        if let Some(conflicts) = &option.conflicts {
+            let conflicts: Vec<String> = conflicts.iter().map(|key| toml_string(key)).collect();
+            out.push_str(&format!("conflicts = [{}]\n", conflicts.join(", ")));
+        }
+        if option.secret {
+            out.push_str("secret = true\n");
+        }
+        if let Some(env) = &option.env {
+            out.push_str(&format!("env = {}\n", toml_string(env)));
+        }
+        if let Some(display) = &option.display {
+            let display = match display {
+                DisplayHint::Decimal => "decimal",
+                DisplayHint::Hex => "hex",
+                DisplayHint::Binary => "binary",
+            };
+            out.push_str(&format!("display = \"{}\"\n", display));
+        }
+        if let Some(weight) = option.weight {
+            out.push_str(&format!("weight = {}\n", weight));
+        }
+        if option.hidden {
+            out.push_str("hidden = true\n");
+        }
+        if let Some(instances) = option.instances {
+            out.push_str(&format!("instances = {}\n", instances));
+        }
+        if let Some(foreach) = &option.foreach {
+            out.push_str(&format!("foreach = {}\n", toml_string(foreach)));
+        }
+
+        if let Some(children) = instances::template(option).or(option.options.as_ref()) {
+            write_options(out, children, &format!("{}.options.", path));
+        }
+    }
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        toml_string(key)
+    }
+}
+
+/// Expressions read better as literal strings, e.g. `'feature("esp32")'`
+fn toml_string(s: &str) -> String {
+    if (s.contains('"') || s.contains('\\')) && !s.contains('\'') && !s.contains('\n') {
+        format!("'{}'", s)
+    } else {
+        // JSON's escapes are valid in TOML's basic strings
+        serde_json::to_string(s).unwrap()
+    }
+}
+
+fn toml_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => toml_string(s),
+        // default tables
+        Value::Object(table) => format!(
+            "{{ {} }}",
+            table
+                .iter()
+                .map(|(key, value)| format!("{} = {}", toml_key(key), toml_value(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // conditional defaults
+        Value::Array(entries) => format!(
+            "[{}]",
+            entries
+                .iter()
+                .map(toml_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_config_str, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+    depends = "feature(\"esp32\") || feature(\"esp32s3\")"
+
+    [psram.options.enable]
+    type = "bool"
+    description = "Enable PSRAM"
+    default = false
+
+    [psram.options.size]
+    description = "PSRAM Size"
+    depends = 'enabled("psram.enable")'
+    type = "enum"
+    values = [{ value = "1", description = "1MB" }, { description = "2MB", value = "2" }]
+    default = "2"
+
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    display = "hex"
+    type = "u32"
+    default = { esp32 = 4096, "esp32-s3" = 8192, other = 1024 }
+    valid = "value >= 0 && value <= 80000"
+    weight = -1
+    "#;
+
+    const EXPECTED: &str = r#"[psram]
+description = "PSRAM"
+depends = 'feature("esp32") || feature("esp32s3")'
+
+[psram.options.enable]
+description = "Enable PSRAM"
+type = "bool"
+default = false
+
+[psram.options.size]
+description = "PSRAM Size"
+depends = 'enabled("psram.enable")'
+type = "enum"
+values = [
+    { description = "1MB", value = "1" },
+    { description = "2MB", value = "2" },
+]
+default = "2"
+
+[heap]
+description = "Heapsize"
+
+[heap.options.size]
+description = "Bytes to allocate"
+type = "u32"
+default = { esp32 = 4096, esp32-s3 = 8192, other = 1024 }
+valid = "value >= 0 && value <= 80000"
+display = "hex"
+weight = -1
+"#;
+
+    #[test]
+    fn canonical_output() {
+        let output = definition_to_toml_string(&parse_definition_str(DEFINITION).unwrap());
+        assert_eq!(EXPECTED, output);
+    }
+
+    #[test]
+    fn round_trip() {
+        let output = definition_to_toml_string(&parse_definition_str(DEFINITION).unwrap());
+        let reparsed = parse_definition_str(&output).unwrap();
+        assert_eq!(output, definition_to_toml_string(&reparsed));
+
+        let cfg = r#"
+        [mycrate]
+        psram.enable = true
+        heap.size = 4096
+        "#;
+        assert_eq!(
+            evaluate_config_str(
+                cfg,
+                "mycrate",
+                parse_definition_str(DEFINITION).unwrap(),
+                vec!["esp32"]
+            )
+            .unwrap(),
+            evaluate_config_str(cfg, "mycrate", reparsed, vec!["esp32"]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{ConfigOption, Map};
+
+/// Turn the `unless` of the options into their `depends`
+///
+/// ```toml
+/// [psram.options.heap]
+/// description = "Use PSRAM for the heap"
+/// type = "bool"
+/// depends = 'enabled("psram.enable")'
+/// unless = 'feature("esp32")'
+/// ```
+///
+/// is the same as `depends = '(enabled("psram.enable")) && !(feature("esp32"))'`.
+pub(crate) fn fold_unless(options: &mut Map<String, ConfigOption>) {
+    for (_, option) in options.iter_mut() {
+        if let Some(unless) = option.unless.take() {
+            option.depends = Some(combined_depends(option.depends.as_deref(), &unless));
+        }
+
+        if let Some(children) = option.options.as_mut() {
+            fold_unless(children);
+        }
+    }
+}
+
+/// The `depends` which is met if the `depends` is and the `unless` isn't
+fn combined_depends(depends: Option<&str>, unless: &str) -> String {
+    match depends {
+        Some(depends) => format!("({}) && !({})", depends, unless),
+        None => format!("!({})", unless),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{evaluate_config, parse_definition_str};
+
+    const DEFINITION: &str = r#"
+    [psram]
+    description = "PSRAM"
+
+    [psram.options.enable]
+    description = "Enable PSRAM"
+    type = "bool"
+    default = true
+
+    [psram.options.heap]
+    description = "Use PSRAM for the heap"
+    type = "bool"
+    default = true
+    depends = 'enabled("psram.enable")'
+    unless = 'feature("esp32")'
+
+    [psram.options.cache]
+    description = "Cache size"
+    type = "u32"
+    default = 16
+    unless = 'feature("esp32s2") || feature("esp32")'
+    "#;
+
+    #[test]
+    fn unless() {
+        let definition = parse_definition_str(DEFINITION).unwrap();
+        let options = definition["psram"].options.as_ref().unwrap();
+        assert_eq!(None, options["heap"].unless);
+        assert_eq!(
+            Some("(enabled(\"psram.enable\")) && !(feature(\"esp32\"))"),
+            options["heap"].depends.as_deref()
+        );
+
+        for (features, heap, cache) in [
+            (vec![], Some(true), Some(16)),
+            (vec!["esp32"], None, None),
+            (vec!["esp32s2"], Some(true), None),
+        ] {
+            let effective_config =
+                evaluate_config("", "mycrate", definition.clone(), features.clone()).unwrap();
+            assert_eq!(
+                heap,
+                effective_config.get_bool("psram.heap"),
+                "{:?}",
+                features
+            );
+            assert_eq!(cache, effective_config.get_u32("psram.cache"));
+        }
+
+        // composes with `depends`
+        let effective_config = evaluate_config(
+            "[mycrate]\npsram.enable = false\n",
+            "mycrate",
+            definition,
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(None, effective_config.get_bool("psram.heap"));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::Value;
+
+/// The value of an option
+///
+/// Unlike [serde_json::Value] (which the fields of [crate::ConfigOption] use) this only has the
+/// kinds of values an option can have.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Bool(bool),
+    /// Wide enough for all integer types
+    Integer(i128),
+    /// Values of string and enum options
+    String(String),
+}
+
+impl ConfigValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ConfigValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            ConfigValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ConfigValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_json(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(value) => Some(ConfigValue::Bool(*value)),
+            Value::Number(_) => crate::literal::json_integer(value).map(ConfigValue::Integer),
+            Value::String(value) => Some(ConfigValue::String(value.clone())),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        match self {
+            ConfigValue::Bool(value) => Value::Bool(*value),
+            ConfigValue::Integer(value) => crate::integer_value(*value),
+            ConfigValue::String(value) => Value::String(value.clone()),
+        }
+    }
+}
+
+/// Formatted as in `config.toml`
+impl std::fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigValue::Bool(value) => write!(f, "{}", value),
+            ConfigValue::Integer(value) => write!(f, "{}", value),
+            ConfigValue::String(value) => write!(f, "{}", Value::String(value.clone())),
+        }
+    }
+}
+
+impl From<bool> for ConfigValue {
+    fn from(value: bool) -> Self {
+        ConfigValue::Bool(value)
+    }
+}
+
+impl From<u32> for ConfigValue {
+    fn from(value: u32) -> Self {
+        ConfigValue::Integer(value.into())
+    }
+}
+
+impl From<i32> for ConfigValue {
+    fn from(value: i32) -> Self {
+        ConfigValue::Integer(value.into())
+    }
+}
+
+impl From<u64> for ConfigValue {
+    fn from(value: u64) -> Self {
+        ConfigValue::Integer(value.into())
+    }
+}
+
+impl From<i64> for ConfigValue {
+    fn from(value: i64) -> Self {
+        ConfigValue::Integer(value.into())
+    }
+}
+
+impl From<&str> for ConfigValue {
+    fn from(value: &str) -> Self {
+        ConfigValue::String(value.to_string())
+    }
+}
+
+impl From<String> for ConfigValue {
+    fn from(value: String) -> Self {
+        ConfigValue::String(value)
+    }
+}
@@ -0,0 +1,145 @@
+//! JavaScript bindings of the evaluation (feature `wasm`), e.g. for a browser-based configurator
+//! or documentation sites validating config snippets with the same logic as the build
+//!
+//! Build with `cargo build --target wasm32-unknown-unknown --features wasm` and generate the
+//! JavaScript glue with `wasm-bindgen`. All results are JSON strings.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    check_config_str, export_metadata, parse_definition_str, ConfigProblemKind, EffectiveConfig,
+    Error, EvalContext, Provenance, Value,
+};
+
+fn to_js_error(err: Error) -> JsError {
+    JsError::new(&format!("{:?}", err))
+}
+
+/// The effective config (as [crate::resolve] creates it) of the crate's section of the user's
+/// config
+///
+/// ```json
+/// {
+///   "entries": [{ "key": "psram.size", "value": "2", "type": "enum", "provenance": "default" }],
+///   "warnings": []
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn resolve(
+    definition: &str,
+    user_config: &str,
+    crate_name: &str,
+    features: Vec<String>,
+) -> Result<String, JsError> {
+    let ctx = EvalContext {
+        crate_name: crate_name.to_string(),
+        features,
+        fallback_to_defaults: false,
+        // there is no environment of a build
+        env: Default::default(),
+    };
+    let effective_config = crate::resolve(definition, user_config, &ctx).map_err(to_js_error)?;
+    Ok(effective_config_to_json(&effective_config).to_string())
+}
+
+/// The problems of the crate's section of the user's config, see [check_config_str]
+///
+/// ```json
+/// [{ "key": "heap.size", "value": 90000, "kind": "invalid-value" }]
+/// ```
+#[wasm_bindgen]
+pub fn check(
+    definition: &str,
+    user_config: &str,
+    crate_name: &str,
+    features: Vec<String>,
+) -> Result<String, JsError> {
+    let definition = parse_definition_str(definition).map_err(to_js_error)?;
+    let features = features.iter().map(|feature| feature.as_str()).collect();
+    let problems =
+        check_config_str(user_config, crate_name, &definition, features).map_err(to_js_error)?;
+
+    Ok(Value::Array(
+        problems
+            .into_iter()
+            .map(|problem| {
+                serde_json::json!({
+                    "key": problem.key,
+                    "value": problem.value,
+                    "kind": match problem.kind {
+                        ConfigProblemKind::UnknownKey => "unknown-key",
+                        ConfigProblemKind::NotApplicable => "not-applicable",
+                        ConfigProblemKind::InvalidValue => "invalid-value",
+                    },
+                })
+            })
+            .collect(),
+    )
+    .to_string())
+}
+
+/// The definition as [export_metadata] describes it
+#[wasm_bindgen]
+pub fn metadata(definition: &str) -> Result<String, JsError> {
+    let definition = parse_definition_str(definition).map_err(to_js_error)?;
+    Ok(export_metadata(&definition).to_string())
+}
+
+fn effective_config_to_json(effective_config: &EffectiveConfig) -> Value {
+    serde_json::json!({
+        "entries": effective_config
+            .iter()
+            .map(|entry| {
+                serde_json::json!({
+                    "key": entry.key,
+                    "value": if entry.secret { Value::Null } else { entry.value.clone() },
+                    "type": entry.value_type.to_string(),
+                    "provenance": match entry.provenance {
+                        Provenance::User => "user",
+                        Provenance::Default => "default",
+                        Provenance::Env => "env",
+                    },
+                    "secret": entry.secret,
+                })
+            })
+            .collect::<Vec<_>>(),
+        "warnings": effective_config.warnings(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"
+    [heap]
+    description = "Heapsize"
+
+    [heap.options.size]
+    description = "Bytes to allocate"
+    type = "u32"
+    valid = "value <= 80000"
+    default = 4096
+    "#;
+
+    #[test]
+    fn json_results() {
+        let effective_config = resolve(DEFINITION, "[mycrate]\n", "mycrate", vec![]).unwrap();
+        assert_eq!(
+            r#"{"entries":[{"key":"heap.size","provenance":"default","secret":false,"type":"u32","value":4096}],"warnings":[]}"#,
+            effective_config
+        );
+
+        let problems = check(
+            DEFINITION,
+            "[mycrate]\nheap.size = 90000\n",
+            "mycrate",
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(
+            r#"[{"key":"heap.size","kind":"invalid-value","value":90000}]"#,
+            problems
+        );
+    }
+}